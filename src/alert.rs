@@ -0,0 +1,67 @@
+use std::io::Write;
+
+use crate::config::AlertConfig;
+
+/// The first line of the answer, short enough for a notification
+fn summary(answer: &str) -> String {
+    let line = answer
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default()
+        .trim();
+
+    if line.chars().count() > 100 {
+        format!("{}…", line.chars().take(100).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(feature = "desktop-notification")]
+fn notify(title: String, body: String) -> Result<(), String> {
+    // It may wait for the notification daemon
+    tokio::task::spawn_blocking(move || {
+        let _ = notify_rust::Notification::new()
+            .appname("tenere")
+            .summary(&title)
+            .body(&body)
+            .show();
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "desktop-notification"))]
+fn notify(_title: String, _body: String) -> Result<(), String> {
+    Err(
+        "Desktop notifications are not available, build tenere with `--features desktop-notification`"
+            .to_string(),
+    )
+}
+
+/// Ring the bell or show a desktop notification for an answer that took a while
+pub fn answer_ended(
+    config: &AlertConfig,
+    terminal_focused: bool,
+    duration: f64,
+    model: &str,
+    answer: &str,
+) -> Result<(), String> {
+    if duration < config.min_duration || (config.unfocused_only && terminal_focused) {
+        return Ok(());
+    }
+
+    if config.bell {
+        let mut stderr = std::io::stderr();
+        let _ = stderr.write_all(b"\x07");
+        let _ = stderr.flush();
+    }
+
+    if config.desktop {
+        notify(
+            format!("{} answered in {:.0}s", model, duration),
+            summary(answer),
+        )?;
+    }
+
+    Ok(())
+}
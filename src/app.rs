@@ -1,17 +1,56 @@
+use crate::clipboard::Clipboard;
+use crate::cmdline::CommandLine;
+use crate::codeblock::CodeBlockPicker;
+use crate::compare::Compare;
+use crate::event::RequestId;
 use crate::history::History;
-use crate::prompt::Prompt;
+use crate::prompt::{Mode, Prompt};
 use crate::{chat::Chat, help::Help};
 use std;
+use std::collections::HashMap;
 use std::sync::atomic::AtomicBool;
 
-use crate::notification::Notification;
+use crate::completion::Completion;
+use crate::diagnostics::Diagnostics;
+use crate::diff::AnswerDiff;
+use crate::draft::Draft;
+use crate::info::ChatInfo;
+use crate::latency::{self, BackendStatus};
+use crate::llm::{FinishReason, LLMBackend};
+use crate::logging::LogViewer;
+use crate::notification::{Notification, NotificationLevel};
+use crate::paths;
+use crate::picker::FilePicker;
+use crate::pipeline::Pipeline;
+use crate::privacy::PrivacyReport;
+use crate::profile::ProfileSwitcher;
+use crate::queue;
+use crate::quit::{QuitConfirmation, QuitReason};
+use crate::rag::Index;
+use crate::reasoning::ReasoningDisplay;
+use crate::redaction::{self, Rule};
+use crate::registers::{RegisterViewer, Registers};
+use crate::scrubber::Scrubber;
+use crate::share::Share;
+use crate::shell::CommandRunner;
+use crate::spellcheck::{Corrections, Dictionary};
 use crate::spinner::Spinner;
-use crate::{config::Config, formatter::Formatter};
-use arboard::Clipboard;
-use crossterm::event::KeyCode;
+use crate::split::{ChatLayout, SplitView};
+use crate::state::State;
+use crate::tee::Tee;
+use crate::theme::Theme;
+use crate::undo::UndoStack;
+use crate::vault;
+#[cfg(feature = "voice")]
+use crate::voice::Recorder;
+use crate::{authors::Authors, config::Config, formatter::Formatter};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::text::Line;
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::{sync::oneshot, task::JoinHandle};
+use zeroize::Zeroizing;
 
 pub type AppResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -22,6 +61,22 @@ pub enum FocusedBlock {
     History,
     Preview,
     Help,
+    Payload,
+    Privacy,
+    Share,
+    Profiles,
+    Scrubber,
+    FilePicker,
+    CommandRunner,
+    CommandLine,
+    Log,
+    Info,
+    Diff,
+    Registers,
+    Corrections,
+    Quit,
+    CodeBlocks,
+    Diagnostics,
 }
 
 pub struct App<'a> {
@@ -33,43 +88,626 @@ pub struct App<'a> {
     pub notifications: Vec<Notification>,
     pub spinner: Spinner,
     pub terminate_response_signal: Arc<AtomicBool>,
-    pub clipboard: Option<Clipboard>,
+    pub clipboard: Clipboard,
     pub help: Help,
     pub previous_key: KeyCode,
+    /// When the previous key was pressed, to end the chords after `key_timeout`
+    pub previous_key_time: Instant,
     pub config: Arc<Config>,
-    pub formatter: &'a Formatter<'a>,
+    pub formatter: &'a Formatter,
+    pub last_activity: Instant,
+    pub locked: bool,
+    /// Typed on the lock screen when the history is encrypted, to unlock it
+    pub lock_passphrase: Zeroizing<String>,
+    pub lock_error: Option<String>,
+    pub theme: Theme,
+    pub state: State,
+    pub backend: LLMBackend,
+    pub backend_override: Option<LLMBackend>,
+    pub latencies: Vec<BackendStatus>,
+    pub branched: Option<Chat<'a>>,
+    /// The chats left for a thread of the history while their answer streams
+    pub detached: Vec<Chat<'a>>,
+    pub compare: Option<Compare<'a>>,
+    pub queued_prompt: Option<HashMap<String, String>>,
+    /// The pre-send hooks are changing the prompt in the background
+    pub pre_sending: bool,
+    /// The id of the last request sent
+    pub request_id: u64,
+    /// The task streaming the answer, aborted when the chat is left
+    pub answer_task: Option<JoinHandle<()>>,
+    /// Stops the command running in the chat
+    pub stop_command: Option<oneshot::Sender<()>>,
+    pub privacy_report: Option<PrivacyReport>,
+    /// Applied to the messages before they are sent
+    pub redaction_rules: Vec<Rule>,
+    pub share: Option<Share>,
+    pub profile_switcher: Option<ProfileSwitcher>,
+    pub scrubber: Option<Scrubber>,
+    pub log_viewer: Option<LogViewer>,
+    pub chat_info: Option<ChatInfo>,
+    pub diagnostics: Option<Diagnostics>,
+    pub answer_diff: Option<AnswerDiff>,
+    pub registers: Registers,
+    pub register_viewer: Option<RegisterViewer>,
+    /// The completions of the slash command or of the command line being typed
+    pub completion: Option<Completion>,
+    /// Loaded the first time the spellcheck is turned on
+    pub dictionary: Option<Dictionary>,
+    /// The prompt saved while it is typed, unless `save_draft` is off
+    pub draft: Option<Draft>,
+    pub spellcheck: bool,
+    pub corrections: Option<Corrections>,
+    pub quit_confirmation: Option<QuitConfirmation>,
+    /// The file the messages are appended to
+    pub tee: Option<Tee>,
+    pub file_picker: Option<FilePicker>,
+    pub command_runner: Option<CommandRunner>,
+    pub code_blocks: Option<CodeBlockPicker>,
+    /// The pipeline sending its steps in the chat
+    pub pipeline: Option<Pipeline>,
+    pub command_line: Option<CommandLine>,
+    pub undo: UndoStack<'a>,
+    pub index: Option<Arc<Index>>,
+    #[cfg(feature = "voice")]
+    pub recorder: Option<Recorder>,
+    pub transcribing: bool,
+    /// The prompt is to be edited in the external editor, which needs the terminal
+    pub external_editor: bool,
+    /// Assumed when the terminal does not report the focus changes
+    pub terminal_focused: bool,
+    pub layout: ChatLayout,
+    pub split: SplitView,
 }
 
 impl<'a> App<'a> {
-    pub fn new(config: Arc<Config>, formatter: &'a Formatter<'a>) -> Self {
+    pub fn new(config: Arc<Config>, formatter: &'a Formatter) -> Self {
+        paths::init(&config);
+        let state = State::load();
+
+        let theme = state
+            .theme
+            .as_deref()
+            .or(config.theme.name.as_deref())
+            .and_then(Theme::from_name)
+            .unwrap_or_default();
+        // Checked with the config
+        let theme = theme.with_overrides(&config.theme.colors).unwrap_or(theme);
+        formatter.set_theme(&theme);
+        formatter.set_math(config.render_math);
+        formatter.set_code_line_numbers(config.show_code_line_numbers);
+        formatter.set_reasoning(config.reasoning_display);
+        formatter.set_authors(Authors::new(&config.authors));
+
+        let mut prompt = Prompt {
+            theme,
+            max_height: config.prompt.max_height.clamp(10, 90),
+            ..Default::default()
+        };
+        prompt.update(&FocusedBlock::Prompt);
+
+        // Checked with the config
+        let redaction_rules = redaction::outgoing_rules(&config.redaction).unwrap_or_default();
+
+        let history = if config.history.persist {
+            History::load(formatter)
+        } else {
+            History::new()
+        };
+
+        let mut notifications = Vec::new();
+        if let Some(path) = &config.project {
+            notifications.push(Notification::new(
+                format!("Project settings: {}", path.display()),
+                NotificationLevel::Info,
+            ));
+        }
+        let dictionary = if config.spellcheck.enabled {
+            Dictionary::load(&config.spellcheck)
+                .map_err(|e| notifications.push(Notification::new(e, NotificationLevel::Warning)))
+                .ok()
+        } else {
+            None
+        };
+
+        let draft = config
+            .save_draft
+            .then(|| Draft::load(config.history.encrypt))
+            .flatten()
+            .map(|(draft, text)| {
+                if let Some(text) = text {
+                    prompt.set_text(&text);
+                    notifications.push(Notification::new(
+                        "Draft restored".to_string(),
+                        NotificationLevel::Info,
+                    ));
+                }
+                draft
+            });
+
+        let tee = config.tee.as_deref().and_then(|path| {
+            Tee::open(path)
+                .map_err(|e| notifications.push(Notification::new(e, NotificationLevel::Warning)))
+                .ok()
+        });
+
+        let backend = config.llm;
+        let layout = state.layout.unwrap_or(config.layout);
+        let index = config
+            .rag
+            .as_ref()
+            .and_then(|_| Index::load())
+            .map(Arc::new);
+
         Self {
             running: true,
-            prompt: Prompt::default(),
+            prompt,
             chat: Chat::new(),
             focused_block: FocusedBlock::Prompt,
-            history: History::new(),
-            notifications: Vec::new(),
+            history,
+            notifications,
             spinner: Spinner::default(),
             terminate_response_signal: Arc::new(AtomicBool::new(false)),
-            clipboard: Clipboard::new().ok(),
+            clipboard: Clipboard::new(&config.clipboard),
             help: Help::new(),
             previous_key: KeyCode::Null,
+            previous_key_time: Instant::now(),
             config,
             formatter,
+            last_activity: Instant::now(),
+            locked: false,
+            lock_passphrase: Zeroizing::new(String::with_capacity(256)),
+            lock_error: None,
+            theme,
+            state,
+            backend,
+            backend_override: None,
+            latencies: Vec::new(),
+            branched: None,
+            detached: Vec::new(),
+            compare: None,
+            queued_prompt: None,
+            pre_sending: false,
+            request_id: 0,
+            answer_task: None,
+            stop_command: None,
+            privacy_report: None,
+            redaction_rules,
+            share: None,
+            profile_switcher: None,
+            scrubber: None,
+            log_viewer: None,
+            chat_info: None,
+            diagnostics: None,
+            answer_diff: None,
+            registers: Registers::default(),
+            register_viewer: None,
+            completion: None,
+            spellcheck: dictionary.is_some(),
+            dictionary,
+            draft,
+            corrections: None,
+            quit_confirmation: None,
+            tee,
+            file_picker: None,
+            command_runner: None,
+            code_blocks: None,
+            pipeline: None,
+            command_line: None,
+            undo: UndoStack::default(),
+            index,
+            #[cfg(feature = "voice")]
+            recorder: None,
+            transcribing: false,
+            external_editor: false,
+            terminal_focused: true,
+            layout,
+            split: SplitView::default(),
+        }
+    }
+
+    /// The chat receiving the streamed answer, which is not the active one after a branch
+    /// or once another thread is loaded
+    /// The variables of the config and of the chat, the ones of the chat take precedence
+    pub fn variables(&self) -> HashMap<String, String> {
+        let mut variables = self.config.variables.clone();
+        variables.extend(self.chat.variables.clone());
+        variables
+    }
+
+    pub fn streaming_chat(&mut self) -> &mut Chat<'a> {
+        if let Some(chat) = self.detached.last_mut() {
+            return chat;
+        }
+        self.branched.as_mut().unwrap_or(&mut self.chat)
+    }
+
+    fn chats(&self) -> impl Iterator<Item = &Chat<'a>> {
+        std::iter::once(&self.chat)
+            .chain(self.branched.as_ref())
+            .chain(self.detached.iter())
+    }
+
+    /// The chat with this id, wherever it is kept while it streams
+    pub fn chat_by_id(&mut self, id: usize) -> Option<&mut Chat<'a>> {
+        std::iter::once(&mut self.chat)
+            .chain(self.branched.as_mut())
+            .chain(self.detached.iter_mut())
+            .find(|chat| chat.id == id)
+    }
+
+    /// Whether the chat that sent the request still waits for its answer
+    pub fn is_answering(&self, id: RequestId) -> bool {
+        self.chats()
+            .any(|chat| chat.id == id.chat && chat.request == Some(id.request))
+    }
+
+    /// Stop the stream once the answer is longer than `max_answer_chars`
+    pub fn check_answer_length(&mut self, id: RequestId) {
+        let Some(max_chars) = self.config.params.max_answer_chars else {
+            return;
+        };
+        let signal = self.terminate_response_signal.clone();
+
+        let Some(chat) = self.chat_by_id(id.chat) else {
+            return;
+        };
+        let answer = &mut chat.answer;
+        if answer.finish_reason.is_none() && answer.chars() > max_chars {
+            answer.finish_reason = Some(FinishReason::Truncated);
+            signal.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Checkpoint the partial answer in a new chat, the answer keeps streaming in the background
+    pub fn branch(&mut self) {
+        if self.branched.is_some() || self.chat.answer.plain_answer().is_empty() {
+            self.notifications.push(Notification::new(
+                "There is no answer being generated to branch from".to_string(),
+                NotificationLevel::Warning,
+            ));
+            return;
+        }
+
+        let branch = self.chat.checkpoint();
+        self.branched = Some(std::mem::replace(&mut self.chat, branch));
+
+        self.focused_block = FocusedBlock::Prompt;
+        self.prompt.mode = Mode::Insert;
+        self.prompt.update(&self.focused_block);
+    }
+
+    /// The backend to use for the next conversation
+    pub fn preferred_backend(&self) -> LLMBackend {
+        if let Some(backend) = self.backend_override {
+            return backend;
+        }
+
+        if self.config.auto_select.enabled {
+            if let Some(backend) = latency::fastest(&self.latencies) {
+                return backend;
+            }
+        }
+
+        self.config.llm
+    }
+
+    /// Go through the configured backends, then back to the automatic selection
+    pub fn cycle_backend_override(&mut self) {
+        let backends = self.config.backends();
+
+        self.backend_override = match self.backend_override {
+            None => backends.first().copied(),
+            Some(current) => backends
+                .iter()
+                .skip_while(|backend| **backend != current)
+                .nth(1)
+                .copied(),
+        };
+
+        let notif = match self.backend_override {
+            Some(backend) => format!("Backend: {} (manual)", backend),
+            None if self.config.auto_select.enabled => String::from("Backend: automatic"),
+            None => format!("Backend: {}", self.config.llm),
+        };
+        self.notifications
+            .push(Notification::new(notif, NotificationLevel::Info));
+    }
+
+    /// Shown on top of the prompt while recording or transcribing the voice input
+    pub fn voice_indicator(&self) -> Option<&'static str> {
+        #[cfg(feature = "voice")]
+        if self.recorder.is_some() {
+            return Some(" ● Recording ");
+        }
+
+        if self.transcribing {
+            Some(" Transcribing… ")
+        } else {
+            None
+        }
+    }
+
+    /// The active profile, shown on top of the chat
+    pub fn status_indicator(&self) -> Option<String> {
+        self.config
+            .profile
+            .as_ref()
+            .map(|profile| format!(" 👤 {} ", profile))
+    }
+
+    /// Fold or unfold the reasoning of the answers, unless the config hides it
+    pub fn toggle_reasoning(&mut self) {
+        let display = self.formatter.reasoning();
+        if display == ReasoningDisplay::Hidden {
+            self.notifications.push(Notification::new(
+                "The reasoning is hidden by `reasoning_display`".to_string(),
+                NotificationLevel::Info,
+            ));
+            return;
+        }
+        self.formatter.set_reasoning(display.toggle());
+
+        if !self.spinner.active && self.chat.answer.plain_answer().is_empty() {
+            self.chat.reformat(self.formatter);
+        }
+        self.split.invalidate();
+    }
+
+    /// Switch to the next theme and remember it for the next start
+    pub fn cycle_theme(&mut self) {
+        let theme = self.theme.next();
+        self.theme = theme
+            .with_overrides(&self.config.theme.colors)
+            .unwrap_or(theme);
+        self.formatter.set_theme(&self.theme);
+
+        self.prompt.theme = self.theme;
+        self.prompt.update(&self.focused_block);
+
+        if !self.spinner.active && self.chat.answer.plain_answer().is_empty() {
+            self.chat.reformat(self.formatter);
+        }
+        self.split.invalidate();
+
+        self.state.theme = Some(self.theme.name.to_string());
+        let notif = match self.state.save() {
+            Ok(_) => Notification::new(
+                format!("Theme: {}", self.theme.name),
+                NotificationLevel::Info,
+            ),
+            Err(e) => Notification::new(e.to_string(), NotificationLevel::Error),
+        };
+        self.notifications.push(notif);
+    }
+
+    /// Switch between the stacked and the split layouts
+    pub fn toggle_layout(&mut self) {
+        self.layout = self.layout.toggle();
+        self.split.move_to_bottom();
+
+        self.state.layout = Some(self.layout);
+        if let Err(e) = self.state.save() {
+            self.notifications
+                .push(Notification::new(e.to_string(), NotificationLevel::Error));
+        }
+    }
+
+    /// Write the history to disk, if enabled
+    pub fn save_history(&mut self) {
+        if !self.config.history.persist {
+            return;
+        }
+
+        if let Err(e) = self.history.persist() {
+            self.notifications.push(Notification::new(
+                format!("Can not save the history: {}", e),
+                NotificationLevel::Error,
+            ));
+        }
+    }
+
+    /// What quitting would lose, nothing when the confirmation is turned off
+    fn quit_reason(&self) -> Option<QuitReason> {
+        if !self.config.confirm_quit {
+            return None;
+        }
+
+        let streaming = self
+            .answer_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+            || !self.detached.is_empty()
+            || self.compare.as_ref().is_some_and(|c| c.is_streaming());
+        if streaming {
+            return Some(QuitReason::Streaming);
+        }
+
+        let unsaved = !self.config.history.persist
+            && (!self.chat.plain_chat.is_empty() || !self.history.threads.is_empty());
+        unsaved.then_some(QuitReason::UnsavedChat)
+    }
+
+    /// Stop the app, once confirmed if an answer or the chats would be lost
+    pub fn quit(&mut self) {
+        match self.quit_reason() {
+            Some(reason) => {
+                self.quit_confirmation =
+                    Some(QuitConfirmation::new(reason, self.focused_block.clone()));
+                self.focused_block = FocusedBlock::Quit;
+            }
+            None => self.running = false,
+        }
+    }
+
+    /// Move the current chat to the history if it is not empty
+    pub fn archive_chat(&mut self) {
+        if !self.chat.plain_chat.is_empty() {
+            self.history.push(self.chat.to_thread());
+            self.save_history();
+        }
+    }
+
+    /// Register user activity, returns true if the app was locked and the key is used by
+    /// the lock screen. Any key unlocks it, unless the history is encrypted: its passphrase
+    /// must be typed then.
+    pub fn unlock(&mut self, key_event: Option<KeyEvent>) -> bool {
+        self.last_activity = Instant::now();
+        if !self.locked {
+            return false;
+        }
+
+        let Some(vault) = vault::get().filter(|_| self.config.history.encrypt) else {
+            self.locked = false;
+            return true;
+        };
+        match key_event {
+            Some(KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }) => {
+                if vault.matches(&self.lock_passphrase) {
+                    self.locked = false;
+                    self.lock_error = None;
+                } else {
+                    self.lock_error = Some(String::from("Wrong passphrase"));
+                }
+                self.lock_passphrase.clear();
+            }
+            Some(KeyEvent {
+                code: KeyCode::Char(c),
+                modifiers,
+                ..
+            }) if !modifiers.contains(KeyModifiers::CONTROL) => self.lock_passphrase.push(c),
+            Some(KeyEvent {
+                code: KeyCode::Backspace,
+                ..
+            }) => {
+                self.lock_passphrase.pop();
+            }
+            Some(KeyEvent {
+                code: KeyCode::Esc, ..
+            }) => self.lock_passphrase.clear(),
+            _ => {}
+        }
+        true
+    }
+
+    pub fn waiting_line(&self) -> Line<'static> {
+        if let Some((_, remaining)) = queue::status() {
+            return Line::raw(format!(
+                "{} Rate limited, sending again in {}s… {}",
+                self.formatter.authors().assistant,
+                remaining.as_secs_f64().ceil(),
+                self.spinner.draw()
+            ));
+        }
+        Line::raw(format!(
+            "{} Waiting for {}… {}",
+            self.formatter.authors().assistant,
+            self.config.backend_model_name(&self.backend),
+            self.spinner.draw()
+        ))
+    }
+
+    /// Forget the first key of a chord once the second one is too late, true if it was forgotten
+    pub fn expire_previous_key(&mut self) -> bool {
+        let timeout = self.config.key_timeout;
+        if timeout == 0
+            || self.previous_key == KeyCode::Null
+            || self.previous_key_time.elapsed() < Duration::from_millis(timeout)
+        {
+            return false;
         }
+        self.previous_key = KeyCode::Null;
+        true
     }
 
-    pub fn tick(&mut self) {
-        self.notifications.retain(|n| n.ttl > 0);
-        self.notifications.iter_mut().for_each(|n| n.ttl -= 1);
+    /// Turn the spellcheck of the prompt on or off, the dictionary is read the first time
+    pub fn toggle_spellcheck(&mut self) {
+        if self.spellcheck {
+            self.spellcheck = false;
+            self.notifications.push(Notification::new(
+                "Spellcheck off".to_string(),
+                NotificationLevel::Info,
+            ));
+            return;
+        }
+
+        if self.dictionary.is_none() {
+            match Dictionary::load(&self.config.spellcheck) {
+                Ok(dictionary) => self.dictionary = Some(dictionary),
+                Err(e) => {
+                    self.notifications
+                        .push(Notification::new(e, NotificationLevel::Error));
+                    return;
+                }
+            }
+        }
+        self.spellcheck = true;
+        self.notifications.push(Notification::new(
+            "Spellcheck on, z= corrects the word under the cursor".to_string(),
+            NotificationLevel::Info,
+        ));
+    }
+
+    /// The dictionary, while the spellcheck is on
+    pub fn spellcheck_dictionary(&self) -> Option<&Dictionary> {
+        self.dictionary.as_ref().filter(|_| self.spellcheck)
+    }
+
+    /// Write the text of the prompt to the draft file, true if it failed and a warning is shown
+    pub fn save_draft(&mut self) -> bool {
+        let Some(draft) = self.draft.as_mut() else {
+            return false;
+        };
+        let text = self.prompt.editor.lines().join("\n");
+        match draft.save(&text) {
+            Ok(()) => false,
+            Err(e) => {
+                self.notifications
+                    .push(Notification::new(e, NotificationLevel::Warning));
+                true
+            }
+        }
+    }
+
+    /// Returns whether the screen needs to be drawn again
+    pub fn tick(&mut self) -> bool {
+        let notifications = self.notifications.len();
+        self.notifications.retain(|n| !n.expired());
+        let mut changed = self.notifications.len() != notifications;
+
+        // The pending chord leaves the status bar
+        changed |= self.expire_previous_key();
+
+        if let Some(timeout) = self.config.idle_lock_timeout {
+            if !self.locked
+                && self.last_activity.elapsed() >= Duration::from_secs(timeout.saturating_mul(60))
+            {
+                self.locked = true;
+                changed = true;
+            }
+        }
+
+        if self.draft.as_ref().is_some_and(Draft::is_due) {
+            changed |= self.save_draft();
+        }
+
+        if let Some(scrubber) = self.scrubber.as_mut() {
+            changed |= scrubber.tick(&self.chat.recordings);
+        }
 
         if self.spinner.active {
-            self.chat.formatted_chat.lines.pop();
-            self.chat
-                .formatted_chat
-                .lines
-                .push(Line::raw(format!("🤖: Waiting {}", self.spinner.draw())));
+            let line = self.waiting_line();
+            let chat = self.streaming_chat();
+            chat.formatted_chat.lines.pop();
+            chat.formatted_chat.lines.push(line);
             self.spinner.update();
+            changed = true;
         }
+
+        // The countdown of the rate limited requests in the status bar
+        changed || queue::status().is_some()
     }
 }
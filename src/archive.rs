@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::{attachment, authors, config::Config, history::Thread};
+
+/// Keep the titles short enough for a file name
+const MAX_TITLE_LENGTH: usize = 50;
+
+/// A title usable in a file name: lowercase, the words separated by `-`
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= MAX_TITLE_LENGTH {
+            break;
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        String::from("chat")
+    } else {
+        slug.to_string()
+    }
+}
+
+/// The title of the thread, or its first question
+fn title(thread: &Thread) -> String {
+    let name = thread.name();
+    let name = name.strip_prefix(authors::USER).unwrap_or(&name);
+    slug(name.lines().next().unwrap_or_default())
+}
+
+/// Replace the placeholders of the file name: `{date}`, `{time}` and `{title}`
+pub fn file_name(template: &str, thread: &Thread, now: DateTime<Local>) -> String {
+    template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+        .replace("{title}", &title(thread))
+}
+
+/// The path itself if it is free, the path with a number added before the extension otherwise
+fn unused(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    (2..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(path)
+}
+
+/// Where a chat is saved when no path is given: `archive_file_name` in `archive_dir`.
+/// An existing file is never overwritten, a number is added to the name instead.
+pub fn path(config: &Config, thread: &Thread, extension: Option<&str>) -> PathBuf {
+    let name = file_name(&config.archive_file_name, thread, Local::now());
+    let dir = config
+        .archive_dir
+        .as_deref()
+        .map(attachment::expand_home)
+        .unwrap_or_default();
+
+    let path = dir.join(attachment::expand_home(&name));
+    let path = match extension {
+        Some(extension) => path.with_extension(extension),
+        None => path,
+    };
+    unused(path)
+}
+
+/// Write the file, and the directories leading to it
+pub fn write(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, content)
+}
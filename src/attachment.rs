@@ -0,0 +1,406 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::context::estimate_tokens;
+
+// Vision APIs reject bigger images
+const MAX_IMAGE_SIZE: u64 = 20 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttachmentKind {
+    File,
+    Image,
+    Context,
+}
+
+/// An attachment sent along with the prompt.
+/// The content of an image is its base64 data url.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    pub name: String,
+    pub content: String,
+}
+
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// The language of the fenced block wrapping the content of a file
+fn language(name: &str) -> String {
+    let path = Path::new(split_range(name).0);
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+
+    let language = match extension.as_deref() {
+        Some("rs") => "rust",
+        Some("py") => "python",
+        Some("js" | "mjs" | "cjs") => "javascript",
+        Some("ts") => "typescript",
+        Some("rb") => "ruby",
+        Some("sh" | "bash" | "zsh") => "bash",
+        Some("md") => "markdown",
+        Some("yml") => "yaml",
+        Some("h") => "c",
+        Some("hpp" | "cc" | "cxx") => "cpp",
+        Some("kt") => "kotlin",
+        Some("cs") => "csharp",
+        Some(extension) => extension,
+        None => match path.file_name().and_then(|name| name.to_str()) {
+            Some("Dockerfile") => "dockerfile",
+            Some("Makefile") => "makefile",
+            _ => "",
+        },
+    };
+
+    language.to_string()
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| path.into()),
+        None => path.into(),
+    }
+}
+
+/// Keep the first `max_size` bytes of the text, returns true if it was longer
+pub fn truncate(text: &mut String, max_size: usize) -> bool {
+    if text.len() <= max_size {
+        return false;
+    }
+    let mut end = max_size;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    true
+}
+
+/// The path and the range of lines of `src/ui.rs:40-120`, `src/ui.rs:40` or `src/ui.rs:40-`
+fn split_range(spec: &str) -> (&str, Option<&str>) {
+    match spec.rsplit_once(':') {
+        Some((path, range))
+            if !path.is_empty()
+                && range.starts_with(|c: char| c.is_ascii_digit())
+                && range.chars().all(|c| c.is_ascii_digit() || c == '-') =>
+        {
+            (path, Some(range))
+        }
+        _ => (spec, None),
+    }
+}
+
+/// The first and the last lines of a range, counted from 1. The last one is open for `40-`.
+fn parse_range(range: &str) -> Result<(usize, Option<usize>), String> {
+    let invalid = || format!("`{}` is not a range of lines, like 40-120", range);
+
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let start: usize = start.parse().map_err(|_| invalid())?;
+    let end = match end {
+        "" => None,
+        end => Some(end.parse::<usize>().map_err(|_| invalid())?),
+    };
+
+    if start == 0 || end.is_some_and(|end| end < start) {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+/// Read some lines of a text file to attach, like `src/ui.rs:40-120`, or the whole file.
+/// Returns true as well if they were truncated to `max_file_size` bytes.
+pub fn load_lines(spec: &str, max_file_size: usize) -> Result<(Attachment, bool), String> {
+    let (path, range) = split_range(spec);
+    let Some(range) = range else {
+        return match load(path, max_file_size)? {
+            (attachment, _) if attachment.kind == AttachmentKind::Image => {
+                Err(format!("`{}` is not a text file", path))
+            }
+            loaded => Ok(loaded),
+        };
+    };
+    let (start, end) = parse_range(range)?;
+
+    let expanded = expand_home(path);
+    if image_mime_type(&expanded).is_some() {
+        return Err(format!("`{}` is not a text file", path));
+    }
+    let file = File::open(&expanded).map_err(|e| format!("{}: {}", path, e))?;
+
+    // Read line by line, up to the end of the range or to the size limit
+    let mut lines = Vec::new();
+    let mut size = 0;
+    let mut count = 0;
+    let mut truncated = false;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|_| format!("`{}` is not a text file", path))?;
+        count += 1;
+        if count < start {
+            continue;
+        }
+        if end.is_some_and(|end| count > end) {
+            break;
+        }
+        if size > max_file_size {
+            truncated = true;
+            break;
+        }
+        size += line.len() + 1;
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        return Err(format!("`{}` has {} lines", path, count));
+    }
+    let end = start + lines.len() - 1;
+
+    let mut content = lines.join("\n");
+    truncated |= truncate(&mut content, max_file_size);
+    if content.contains('\0') {
+        return Err(format!("`{}` is not a text file", path));
+    }
+
+    let attachment = Attachment {
+        kind: AttachmentKind::File,
+        name: format!("{}:{}-{}", path, start, end),
+        content,
+    };
+    Ok((attachment, truncated))
+}
+
+/// The first `limit` bytes of the file, and whether there are more
+fn read_at_most(path: &Path, limit: u64) -> std::io::Result<(Vec<u8>, bool)> {
+    let mut data = Vec::new();
+    File::open(path)?
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut data)?;
+
+    let more = data.len() as u64 > limit;
+    data.truncate(limit.min(data.len() as u64) as usize);
+    Ok((data, more))
+}
+
+/// Read the file to attach at the given path.
+/// Returns true as well if the file was truncated to `max_file_size` bytes.
+pub fn load(path: &str, max_file_size: usize) -> Result<(Attachment, bool), String> {
+    let expanded = expand_home(path);
+
+    let metadata = std::fs::metadata(&expanded).map_err(|e| format!("{}: {}", path, e))?;
+    if !metadata.is_file() {
+        return Err(format!("`{}` is not a file", path));
+    }
+
+    if let Some(mime_type) = image_mime_type(&expanded) {
+        if metadata.len() > MAX_IMAGE_SIZE {
+            return Err(format!("`{}` is bigger than 20MB", path));
+        }
+        let (data, more) =
+            read_at_most(&expanded, MAX_IMAGE_SIZE).map_err(|e| format!("{}: {}", path, e))?;
+        if more {
+            return Err(format!("`{}` is bigger than 20MB", path));
+        }
+
+        let attachment = Attachment {
+            kind: AttachmentKind::Image,
+            name: path.to_string(),
+            content: format!("data:{};base64,{}", mime_type, STANDARD.encode(data)),
+        };
+        return Ok((attachment, false));
+    }
+
+    let (data, truncated) =
+        read_at_most(&expanded, max_file_size as u64).map_err(|e| format!("{}: {}", path, e))?;
+
+    let content = match String::from_utf8(data) {
+        Ok(content) => content,
+        // The last character may be cut by the limit
+        Err(e) if truncated && e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut data = e.into_bytes();
+            data.truncate(valid);
+            String::from_utf8(data).unwrap_or_default()
+        }
+        Err(_) => return Err(format!("`{}` is not a text file", path)),
+    };
+    if content.contains('\0') {
+        return Err(format!("`{}` is not a text file", path));
+    }
+
+    let attachment = Attachment {
+        kind: AttachmentKind::File,
+        name: path.to_string(),
+        content,
+    };
+    Ok((attachment, truncated))
+}
+
+impl Attachment {
+    pub fn tokens(&self) -> usize {
+        match self.kind {
+            AttachmentKind::File | AttachmentKind::Context => estimate_tokens(&self.content),
+            // Vision models bill a fixed amount of tokens per image tile
+            AttachmentKind::Image => 765,
+        }
+    }
+
+    /// Placeholder displayed in the chat instead of the content
+    pub fn marker(&self) -> String {
+        match self.kind {
+            AttachmentKind::File => format!("[file: {}]", self.name),
+            AttachmentKind::Image => format!("[image: {}]", self.name),
+            AttachmentKind::Context => format!("[context: {}]", self.name),
+        }
+    }
+
+    /// The text sent to the model for this attachment
+    pub fn to_prompt(&self) -> String {
+        match self.kind {
+            AttachmentKind::File => format!(
+                "{}:\n```{}\n{}\n```",
+                self.name,
+                language(&self.name),
+                self.content.trim_end()
+            ),
+            AttachmentKind::Context => format!("{}:\n{}", self.name, self.content),
+            AttachmentKind::Image => format!("[image: {}]", self.name),
+        }
+    }
+}
+
+fn format_tokens(tokens: usize) -> String {
+    if tokens >= 1000 {
+        format!("~{:.1}k tokens", tokens as f32 / 1000.0)
+    } else {
+        format!("~{} tokens", tokens)
+    }
+}
+
+/// Short description of the attachments, like `+2 files, 1 image, ~3.4k tokens`
+pub fn summary(attachments: &[Attachment], prompt: &str) -> String {
+    let count = |kind: AttachmentKind| attachments.iter().filter(|a| a.kind == kind).count();
+
+    let mut parts = Vec::new();
+
+    match count(AttachmentKind::File) {
+        0 => {}
+        1 => parts.push(String::from("1 file")),
+        n => parts.push(format!("{} files", n)),
+    }
+
+    match count(AttachmentKind::Image) {
+        0 => {}
+        1 => parts.push(String::from("1 image")),
+        n => parts.push(format!("{} images", n)),
+    }
+
+    match count(AttachmentKind::Context) {
+        0 => {}
+        1 => parts.push(String::from("1 context")),
+        n => parts.push(format!("{} contexts", n)),
+    }
+
+    let tokens = estimate_tokens(prompt) + attachments.iter().map(|a| a.tokens()).sum::<usize>();
+    parts.push(format_tokens(tokens));
+
+    format!("+{}", parts.join(", "))
+}
+
+/// The text of a question of the chat, without the markers of its attachments
+pub fn strip_markers(question: &str) -> &str {
+    let mut text = question.trim_end();
+    while let Some((rest, line)) = text.rsplit_once('\n') {
+        let marker = ["[file: ", "[image: ", "[context: "]
+            .iter()
+            .any(|start| line.starts_with(start))
+            && line.ends_with(']');
+        if !marker {
+            break;
+        }
+        text = rest.trim_end();
+    }
+    text
+}
+
+/// The data urls of the attached images
+pub fn images(attachments: &[Attachment]) -> Vec<String> {
+    attachments
+        .iter()
+        .filter(|a| a.kind == AttachmentKind::Image)
+        .map(|a| a.content.clone())
+        .collect()
+}
+
+/// The whole message sent to the model: the prompt followed by the attachments
+pub fn payload(attachments: &[Attachment], prompt: &str) -> String {
+    let mut payload = prompt.to_string();
+    for attachment in attachments {
+        payload.push_str("\n\n");
+        payload.push_str(&attachment.to_prompt());
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            split_range("src/ui.rs:40-120"),
+            ("src/ui.rs", Some("40-120"))
+        );
+        assert_eq!(split_range("src/ui.rs"), ("src/ui.rs", None));
+        assert_eq!(split_range("C:\\src\\ui.rs"), ("C:\\src\\ui.rs", None));
+
+        assert_eq!(parse_range("40-120"), Ok((40, Some(120))));
+        assert_eq!(parse_range("40"), Ok((40, Some(40))));
+        assert_eq!(parse_range("40-"), Ok((40, None)));
+        assert!(parse_range("0-3").is_err());
+        assert!(parse_range("9-3").is_err());
+        assert!(parse_range("1-2-3").is_err());
+    }
+
+    #[test]
+    fn limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("attachment.txt");
+        std::fs::write(&path, "héllo\nworld\nagain\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        // The limit falls in the middle of `é`
+        let (attachment, truncated) = load(path, 2).unwrap();
+        assert_eq!((attachment.content.as_str(), truncated), ("h", true));
+        let (attachment, truncated) = load(path, 100).unwrap();
+        assert_eq!(
+            (attachment.content.as_str(), truncated),
+            ("héllo\nworld\nagain\n", false)
+        );
+
+        let (attachment, truncated) = load_lines(&format!("{}:2-", path), 100).unwrap();
+        assert_eq!(attachment.name, format!("{}:2-3", path));
+        assert_eq!(
+            (attachment.content.as_str(), truncated),
+            ("world\nagain", false)
+        );
+        let (attachment, truncated) = load_lines(&format!("{}:1-3", path), 3).unwrap();
+        assert_eq!((attachment.content.as_str(), truncated), ("hé", true));
+        assert!(load_lines(&format!("{}:9", path), 100).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use std::borrow::Cow;
+
+use crate::config::AuthorsConfig;
+
+/// The start of the questions in the chats and in the history, whatever the label shown
+pub const USER: &str = "👤: ";
+
+/// The start of the answers in the chats and in the history
+pub const ASSISTANT: &str = "🤖: ";
+
+/// The markers of the questions and the answers, before they were written alike
+const LEGACY_MARKERS: [(&str, &str); 2] = [("👤 : ", USER), ("🤖 : ", ASSISTANT)];
+
+/// The message starting with the current marker, for the ones saved with a legacy marker
+pub fn migrate(message: String) -> String {
+    for (legacy, marker) in LEGACY_MARKERS {
+        if let Some(text) = message.strip_prefix(legacy) {
+            return format!("{}{}", marker, text);
+        }
+    }
+    message
+}
+
+/// The labels shown in place of the markers starting the messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct Authors {
+    pub user: String,
+    pub assistant: String,
+}
+
+impl Default for Authors {
+    fn default() -> Self {
+        Self::new(&AuthorsConfig::default())
+    }
+}
+
+/// The prefix and the name, followed by a colon
+fn label(prefix: &str, name: Option<&str>, fallback: &str) -> String {
+    let label = [prefix, name.unwrap_or_default()]
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    if label.is_empty() {
+        format!("{}:", fallback)
+    } else {
+        format!("{}:", label)
+    }
+}
+
+impl Authors {
+    pub fn new(config: &AuthorsConfig) -> Self {
+        Self {
+            user: label(&config.user_prefix, config.user_name.as_deref(), "You"),
+            assistant: label(
+                &config.assistant_prefix,
+                config.assistant_name.as_deref(),
+                "Assistant",
+            ),
+        }
+    }
+
+    /// The message with the label of its author instead of its marker
+    pub fn display<'a>(&self, message: &'a str) -> Cow<'a, str> {
+        if let Some(question) = message.strip_prefix(USER) {
+            Cow::Owned(format!("{} {}", self.user, question))
+        } else if let Some(answer) = message.strip_prefix(ASSISTANT) {
+            Cow::Owned(format!("{} {}", self.assistant, answer))
+        } else {
+            Cow::Borrowed(message)
+        }
+    }
+
+    /// The messages of a chat as they are exported
+    pub fn display_chat(&self, plain_chat: &[String]) -> String {
+        plain_chat
+            .iter()
+            .map(|message| self.display(message))
+            .collect()
+    }
+
+    /// The label starting the line, true for the user
+    pub fn find(&self, line: &str) -> Option<(bool, usize)> {
+        if line.starts_with(&self.user) {
+            Some((true, self.user.len()))
+        } else if line.starts_with(&self.assistant) {
+            Some((false, self.assistant.len()))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_legacy_markers() {
+        assert_eq!(migrate(String::from("👤 : question\n")), "👤: question\n");
+        assert_eq!(migrate(String::from("🤖 : answer")), "🤖: answer");
+        assert_eq!(migrate(String::from("🤖: answer")), "🤖: answer");
+        assert_eq!(migrate(String::from("📝: summary")), "📝: summary");
+    }
+}
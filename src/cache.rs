@@ -0,0 +1,381 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+use crate::{
+    config::CacheConfig,
+    event::Event,
+    llm::{FinishReason, LLMAnswer, LLMError, LLMRole, LLM},
+    notification::{Notification, NotificationLevel},
+    vault::{self, Vault},
+};
+
+/// An answer kept in the cache
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// The hash of the conversation, the file is named after its start only
+    key: String,
+    time: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    reasoning: String,
+    answer: String,
+}
+
+/// SHA-256 in hexadecimal, stable from a version of Rust to the next unlike the hasher of
+/// the std
+fn hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Where the answers are kept
+fn dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("tenere").join("answers"))
+}
+
+/// Remove the expired answers, the ones of the conversations never sent again stay
+/// otherwise
+pub fn prune(config: &CacheConfig) {
+    if let Some(dir) = dir() {
+        prune_dir(&dir, config.ttl);
+    }
+}
+
+fn prune_dir(dir: &Path, ttl: u64) {
+    if ttl == 0 {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > Duration::from_secs(ttl));
+        if expired {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// The answers of a backend to the conversations already sent, the identical ones are
+/// answered from the cache instead of sending them again
+pub struct Cached {
+    llm: Box<dyn LLM>,
+    /// The backend, the model and the parameters of the requests
+    scope: String,
+    config: CacheConfig,
+    dir: Option<PathBuf>,
+    /// The answers are encrypted like the history
+    encrypt: bool,
+}
+
+impl Cached {
+    pub fn new(llm: Box<dyn LLM>, scope: String, config: CacheConfig, encrypt: bool) -> Self {
+        Self {
+            llm,
+            scope,
+            config,
+            dir: dir(),
+            encrypt,
+        }
+    }
+
+    /// The vault to encrypt the answers with. The cache is not used while it is locked.
+    fn vault(&self) -> Option<Option<&'static Vault>> {
+        match self.encrypt {
+            true => vault::get().map(Some),
+            false => Some(None),
+        }
+    }
+
+    /// The file of the answer and the hash of the conversation
+    fn path(&self, messages: &[HashMap<String, String>]) -> Option<(PathBuf, String)> {
+        // Sorted, the order of the keys of a map changes from a run to the next
+        let messages: Vec<BTreeMap<&String, &String>> = messages
+            .iter()
+            .map(|message| message.iter().collect())
+            .collect();
+        let key = hash(&format!(
+            "{}\n{}",
+            self.scope,
+            serde_json::to_string(&messages).ok()?
+        ));
+
+        let extension = if self.encrypt { "enc" } else { "json" };
+        let path = self
+            .dir
+            .as_ref()?
+            .join(format!("{}.{}", &key[..16], extension));
+        Some((path, key))
+    }
+
+    /// The answer in the cache, if it is the one of the conversation and it is not expired
+    fn get(&self, path: &Path, key: &str) -> Option<Entry> {
+        let content = std::fs::read(path).ok()?;
+        let content = match self.vault()? {
+            Some(vault) => vault.decrypt(&content).ok()?,
+            None => content,
+        };
+        let entry: Entry = serde_json::from_slice(&content).ok()?;
+        if entry.key != key {
+            return None;
+        }
+
+        let age = (Local::now() - entry.time).num_seconds();
+        if self.config.ttl > 0 && age > self.config.ttl as i64 {
+            let _ = std::fs::remove_file(path);
+            return None;
+        }
+        Some(entry)
+    }
+
+    fn put(&self, path: &Path, entry: &Entry) -> Result<(), String> {
+        let Some(vault) = self.vault() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let content = serde_json::to_vec(entry).map_err(|e| e.to_string())?;
+        let content = match vault {
+            Some(vault) => vault.encrypt(&content)?,
+            None => content,
+        };
+        std::fs::write(path, content).map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait]
+impl LLM for Cached {
+    async fn ask_with(
+        &self,
+        messages: Vec<HashMap<String, String>>,
+        sender: UnboundedSender<Event>,
+        terminate_response_signal: Arc<AtomicBool>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.path(&messages);
+
+        if let Some(entry) = path.as_ref().and_then(|(path, key)| self.get(path, key)) {
+            tracing::debug!("answered from the cache");
+            let _ = sender.send(Event::Notification(Notification::new(
+                String::from("Answered from the cache"),
+                NotificationLevel::Info,
+            )));
+            sender.send(Event::LLMEvent(LLMAnswer::StartAnswer))?;
+            if !entry.reasoning.is_empty() {
+                sender.send(Event::LLMEvent(LLMAnswer::Reasoning(entry.reasoning)))?;
+            }
+            sender.send(Event::LLMEvent(LLMAnswer::Answer(entry.answer)))?;
+            sender.send(Event::LLMEvent(LLMAnswer::FinishReason(FinishReason::Stop)))?;
+            sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
+            return Ok(());
+        }
+
+        let (answer_sender, mut receiver) = unbounded_channel();
+
+        // The answer is kept while it is forwarded
+        let forward = async {
+            let mut entry = Entry {
+                key: String::new(),
+                time: Local::now(),
+                reasoning: String::new(),
+                answer: String::new(),
+            };
+            let mut complete = true;
+            while let Some(event) = receiver.recv().await {
+                match &event {
+                    Event::LLMEvent(LLMAnswer::Answer(chunk)) => entry.answer.push_str(chunk),
+                    Event::LLMEvent(LLMAnswer::Reasoning(chunk)) => entry.reasoning.push_str(chunk),
+                    Event::LLMEvent(LLMAnswer::ToolCall(_)) => complete = false,
+                    Event::LLMEvent(LLMAnswer::FinishReason(reason)) => {
+                        complete &= *reason == FinishReason::Stop
+                    }
+                    _ => {}
+                }
+                let _ = sender.send(event);
+            }
+            (entry, complete)
+        };
+        let ask = async {
+            self.llm
+                .ask_with(messages, answer_sender, terminate_response_signal.clone())
+                .await
+                .map_err(LLMError::from)
+        };
+
+        let (res, (mut entry, complete)) = tokio::join!(ask, forward);
+        res?;
+
+        // Not the stopped or truncated answers
+        if complete
+            && !terminate_response_signal.load(Ordering::Relaxed)
+            && !entry.answer.is_empty()
+        {
+            if let Some((path, key)) = path {
+                entry.key = key;
+                if let Err(e) = self.put(&path, &entry) {
+                    tracing::warn!("can not save the answer in the cache: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn messages(&self) -> Vec<HashMap<String, String>> {
+        self.llm.messages()
+    }
+
+    fn set_messages(&mut self, messages: Vec<HashMap<String, String>>) {
+        self.llm.set_messages(messages);
+    }
+
+    fn append_chat_msg(&mut self, msg: String, role: LLMRole) {
+        self.llm.append_chat_msg(msg, role);
+    }
+
+    fn clear(&mut self) {
+        self.llm.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::MockConfig, mock::Mock};
+
+    fn cached(dir: &Path, ttl: u64) -> Cached {
+        let mock: MockConfig = toml::from_str("").unwrap();
+        Cached {
+            llm: Box::new(Mock::new(mock, Default::default())),
+            scope: String::from("mock\nmock"),
+            config: CacheConfig { enabled: true, ttl },
+            dir: Some(dir.to_path_buf()),
+            encrypt: false,
+        }
+    }
+
+    fn messages(content: &str) -> Vec<HashMap<String, String>> {
+        vec![HashMap::from([
+            (String::from("role"), String::from("user")),
+            (String::from("content"), content.to_string()),
+        ])]
+    }
+
+    fn entry(key: &str, age: i64) -> Entry {
+        Entry {
+            key: key.to_string(),
+            time: Local::now() - chrono::Duration::seconds(age),
+            reasoning: String::new(),
+            answer: String::from("An answer"),
+        }
+    }
+
+    #[test]
+    fn hash_of_the_conversation() {
+        assert_eq!(
+            hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(hash("tenere"), hash("tenere"));
+        assert_ne!(hash("tenere"), hash("tenere "));
+    }
+
+    #[test]
+    fn path_of_the_answer() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cached(dir.path(), 0);
+
+        let (path, key) = cache.path(&messages("Hello")).unwrap();
+        assert_eq!(path, dir.path().join(format!("{}.json", &key[..16])));
+        assert_eq!(key.len(), 64);
+
+        // Another map of the same message, its keys are iterated in another order
+        let reordered = vec![HashMap::from_iter(messages("Hello")[0].clone())];
+        assert_eq!(cache.path(&reordered).unwrap().1, key);
+        assert_ne!(cache.path(&messages("Hello!")).unwrap().1, key);
+
+        let other_model = Cached {
+            scope: String::from("mock\nother"),
+            ..cached(dir.path(), 0)
+        };
+        assert_ne!(other_model.path(&messages("Hello")).unwrap().1, key);
+    }
+
+    #[test]
+    fn answer_of_another_conversation() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cached(dir.path(), 0);
+        let (path, key) = cache.path(&messages("Hello")).unwrap();
+
+        // A file of the same name, for a conversation with the same start of hash
+        cache.put(&path, &entry(&hash("other"), 0)).unwrap();
+        assert!(cache.get(&path, &key).is_none());
+
+        cache.put(&path, &entry(&key, 0)).unwrap();
+        assert_eq!(cache.get(&path, &key).unwrap().answer, "An answer");
+    }
+
+    #[test]
+    fn expired_answers() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cached(dir.path(), 60);
+        let (path, key) = cache.path(&messages("Hello")).unwrap();
+
+        cache.put(&path, &entry(&key, 30)).unwrap();
+        assert!(cache.get(&path, &key).is_some());
+
+        cache.put(&path, &entry(&key, 90)).unwrap();
+        assert!(cache.get(&path, &key).is_none());
+        assert!(!path.exists());
+
+        // Kept forever without a ttl
+        let cache = cached(dir.path(), 0);
+        cache.put(&path, &entry(&key, 90)).unwrap();
+        assert!(cache.get(&path, &key).is_some());
+    }
+
+    #[test]
+    fn prune_the_expired_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.json");
+        let new = dir.path().join("new.json");
+        for path in [&old, &new] {
+            std::fs::write(path, "{}").unwrap();
+        }
+        let modified = SystemTime::now() - Duration::from_secs(120);
+        std::fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        prune_dir(dir.path(), 0);
+        assert!(old.exists());
+
+        prune_dir(dir.path(), 60);
+        assert!(!old.exists());
+        assert!(new.exists());
+    }
+}
@@ -1,122 +1,1018 @@
-use std::{rc::Rc, sync::atomic::AtomicBool};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ops::Range,
+    rc::Rc,
+    sync::atomic::{AtomicBool, AtomicUsize},
+    time::{Duration, Instant},
+};
 
 use ratatui::{
-    layout::Rect,
-    text::Text,
-    widgets::{Block, Paragraph, Wrap},
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{
+        block::{Position, Title},
+        Block, Paragraph, Wrap,
+    },
     Frame,
 };
 
-use crate::{formatter::Formatter, llm::LLMAnswer};
+use crate::{
+    attachment,
+    authors::{self, Authors},
+    context::{self, CharCount},
+    formatter::{Formatter, RenderProfile},
+    history::Thread,
+    i18n::{fill, t},
+    llm::{FinishReason, LLMAnswer, LLMError, ToolCallDelta, Usage},
+    reasoning::{self, Reasoning},
+    recording::Recording,
+    shell::{ShellOutput, MAX_OUTPUT_LINES},
+    streaming::StreamingRenderer,
+    theme::Theme,
+    timestamp::{self, Timestamp},
+    viewport::{clip, tail_heights, Viewport},
+};
+
+const ANSWER_PREFIX: &str = authors::ASSISTANT;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Answer<'a> {
-    pub plain_answer: String,
+    /// The answer after its author, the chunks are appended in place
+    message: String,
+    chars: CharCount,
     pub formatted_answer: Text<'a>,
+    pub tool_calls: Vec<ToolCallDelta>,
+    pub finish_reason: Option<FinishReason>,
+    /// The chain of thought of a reasoning model
+    pub reasoning: String,
+    pub recording: Recording,
+    /// When the question was sent
+    pub requested: Option<Instant>,
+    /// The time until the first token, answer or reasoning
+    pub first_token: Option<Duration>,
+    /// Reported by the backend, if it does
+    pub tokens: Option<u64>,
+    pub prompt_tokens: Option<u64>,
+    pub renderer: StreamingRenderer,
+}
+
+impl Default for Answer<'_> {
+    fn default() -> Self {
+        Self {
+            message: ANSWER_PREFIX.to_string(),
+            chars: CharCount::default(),
+            formatted_answer: Text::default(),
+            tool_calls: Vec::new(),
+            finish_reason: None,
+            reasoning: String::new(),
+            recording: Recording::default(),
+            requested: None,
+            first_token: None,
+            tokens: None,
+            prompt_tokens: None,
+            renderer: StreamingRenderer::default(),
+        }
+    }
+}
+
+impl Answer<'_> {
+    pub fn plain_answer(&self) -> &str {
+        &self.message[ANSWER_PREFIX.len()..]
+    }
+
+    /// The answer as it is added to the chat, with its author
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn push(&mut self, chunk: &str) {
+        self.received();
+        self.message.push_str(chunk);
+        self.chars.add(chunk);
+    }
+
+    /// Keep the time of the first token
+    fn received(&mut self) {
+        if self.first_token.is_none() {
+            self.first_token = self.requested.map(|requested| requested.elapsed());
+        }
+    }
+
+    pub fn chars(&self) -> usize {
+        self.chars.total()
+    }
+
+    pub fn estimated_tokens(&self) -> usize {
+        self.chars.tokens()
+    }
+}
+
+static CHAT_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub fn next_id() -> usize {
+    CHAT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+/// A row for the scroll of a paragraph, the ones past `u16::MAX` are out of reach
+fn to_row(row: usize) -> u16 {
+    row.min(u16::MAX as usize) as u16
+}
+
+/// Color the author of the messages with the accents of the theme
+pub fn accent<'a>(mut text: Text<'a>, theme: &Theme, authors: &Authors) -> Text<'a> {
+    for line in text.lines.iter_mut() {
+        let Some(first) = line.spans.first() else {
+            continue;
+        };
+
+        let (color, length) = match authors.find(&first.content) {
+            Some((true, length)) => (theme.user, length),
+            Some((false, length)) => (theme.assistant, length),
+            None => continue,
+        };
+
+        let first = line.spans.remove(0);
+        let (author, rest) = first.content.split_at(length);
+        let rest = Span::styled(rest.to_string(), first.style);
+        let author = Span::styled(author.to_string(), first.style.fg(color).bold());
+        line.spans.insert(0, rest);
+        line.spans.insert(0, author);
+    }
+
+    text
+}
+
+/// Format the messages of a chat, the way they are displayed while chatting
+pub fn format_chat<'a>(
+    plain_chat: &[String],
+    timestamps: &[Timestamp],
+    reasoning: &[Reasoning],
+    formatter: &Formatter,
+    profile: &RenderProfile,
+) -> Text<'a> {
+    let mut formatted_chat = Text::default();
+
+    for (i, message) in plain_chat.iter().enumerate() {
+        if let Some(reasoning) = reasoning::find(reasoning, i) {
+            formatted_chat.extend(reasoning::format(
+                &reasoning.text,
+                formatter.reasoning(),
+                false,
+                formatter.muted(),
+            ));
+        }
+        if message.starts_with(authors::ASSISTANT) {
+            formatted_chat.extend(formatter.format_answer(message, profile));
+        } else {
+            formatted_chat.extend(formatter.format_with(message, profile));
+        }
+        if message.starts_with(authors::ASSISTANT) {
+            if let Some(timestamp) = timestamp::find(timestamps, i) {
+                formatted_chat.lines.push(timestamp.line(formatter.muted()));
+            }
+        }
+        if !message.starts_with(authors::USER) {
+            formatted_chat.extend(Text::raw("\n"));
+        }
+    }
+
+    formatted_chat
+}
+
+/// An answer replaced by a regenerated one, kept to compare them
+#[derive(Debug, Clone)]
+pub struct PreviousAnswer {
+    pub message: String,
+    timestamp: Option<Timestamp>,
+    reasoning: Option<Reasoning>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Chat<'a> {
+    pub id: usize,
+    pub title: Option<String>,
     pub plain_chat: Vec<String>,
     pub formatted_chat: Text<'a>,
     pub answer: Answer<'a>,
+    pub usage: Usage,
+    pub failed_request: bool,
+    pub pinned: bool,
+    pub render: RenderProfile,
+    pub recordings: Vec<Recording>,
+    pub timestamps: Vec<Timestamp>,
+    pub reasoning: Vec<Reasoning>,
+    pub tags: Vec<String>,
+    pub tool_output: Option<String>,
+    pub previous_answer: Option<PreviousAnswer>,
+    /// The answer being received replaces the previous one, kept until it ends
+    pub regenerating: bool,
+    formatted_tool_output: Text<'a>,
+    tool_output_lines: usize,
+    pub variables: HashMap<String, String>,
+    /// The request being answered, the events of the other requests are not for this chat
+    pub request: Option<u64>,
     pub scroll: u16,
+    /// The columns the code block in view is scrolled by when the code lines are not wrapped
+    code_scroll: u16,
+    /// The lines of the code block scrolled sideways
+    code_block: Option<Range<usize>>,
     area_height: u16,
     area_width: u16,
+    /// The rows of the wrapped chat with the answer being received
+    content_height: usize,
     pub automatic_scroll: Rc<AtomicBool>,
+    /// The messages counted so far and their estimated tokens
+    counted_tokens: Cell<(usize, usize)>,
+    viewport: Viewport,
 }
 
 impl Default for Chat<'_> {
     fn default() -> Self {
         Self {
+            id: next_id(),
+            title: None,
             plain_chat: Vec::new(),
             formatted_chat: Text::raw(""),
             answer: Answer::default(),
+            usage: Usage::default(),
+            failed_request: false,
+            pinned: false,
+            render: RenderProfile::default(),
+            recordings: Vec::new(),
+            timestamps: Vec::new(),
+            reasoning: Vec::new(),
+            tags: Vec::new(),
+            tool_output: None,
+            previous_answer: None,
+            regenerating: false,
+            formatted_tool_output: Text::raw(""),
+            tool_output_lines: 0,
+            variables: HashMap::new(),
+            request: None,
             scroll: 0,
+            code_scroll: 0,
+            code_block: None,
             area_height: 0,
             area_width: 0,
+            content_height: 0,
             automatic_scroll: Rc::new(AtomicBool::new(true)),
+            counted_tokens: Cell::new((0, 0)),
+            viewport: Viewport::default(),
         }
     }
 }
 
-impl Chat<'_> {
+impl<'a> Chat<'a> {
     pub fn new() -> Self {
         Self::default()
     }
 
+    pub fn to_thread(&self) -> Thread<'a> {
+        Thread {
+            id: self.id,
+            title: self.title.clone(),
+            plain_chat: self.plain_chat.clone(),
+            pinned: self.pinned,
+            render: self.render.clone(),
+            timestamps: self.timestamps.clone(),
+            reasoning: self.reasoning.clone(),
+            tags: self.tags.clone(),
+            formatted_chat: self.formatted_chat.clone(),
+        }
+    }
+
+    /// Continue a chat of the history
+    pub fn from_thread(thread: Thread<'a>) -> Self {
+        Self {
+            id: thread.id,
+            title: thread.title,
+            plain_chat: thread.plain_chat,
+            formatted_chat: thread.formatted_chat,
+            pinned: thread.pinned,
+            render: thread.render,
+            timestamps: thread.timestamps,
+            reasoning: thread.reasoning,
+            tags: thread.tags,
+            ..Self::default()
+        }
+    }
+
+    /// Remember when the last message was added
+    pub fn stamp(&mut self) {
+        if let Some(index) = self.plain_chat.len().checked_sub(1) {
+            self.timestamps.push(Timestamp::now(index));
+        }
+    }
+
+    /// A new chat ending with the answer received so far
+    pub fn checkpoint(&self) -> Self {
+        let mut plain_chat = self.plain_chat.clone();
+        plain_chat.push(self.answer.message.clone());
+
+        let mut timestamps = self.timestamps.clone();
+        timestamps.push(Timestamp::now(plain_chat.len() - 1));
+
+        let mut reasoning = self.reasoning.clone();
+        if !self.answer.reasoning.is_empty() {
+            reasoning.push(Reasoning {
+                index: plain_chat.len() - 1,
+                text: self.answer.reasoning.clone(),
+            });
+        }
+
+        let mut formatted_chat = self.formatted_chat.clone();
+        formatted_chat.extend(self.answer.formatted_answer.clone());
+        formatted_chat.extend(Text::raw("\n"));
+
+        Self {
+            title: self
+                .title
+                .as_ref()
+                .map(|title| format!("{} (branch)", title)),
+            plain_chat,
+            formatted_chat,
+            timestamps,
+            reasoning,
+            tags: self.tags.clone(),
+            render: self.render.clone(),
+            variables: self.variables.clone(),
+            ..Self::default()
+        }
+    }
+
     pub fn handle_answer(&mut self, event: LLMAnswer, formatter: &Formatter) {
         match event {
             LLMAnswer::StartAnswer => {
                 self.formatted_chat.lines.pop();
+                self.answer.recording = Recording::new();
             }
 
             LLMAnswer::Answer(answer) => {
-                self.answer.plain_answer.push_str(answer.as_str());
+                self.answer.recording.record(&answer);
+                self.answer.push(&answer);
+                self.format_answer(formatter, true);
+            }
+
+            LLMAnswer::Reasoning(reasoning) => {
+                self.answer.received();
+                self.answer.reasoning.push_str(&reasoning);
+                self.format_answer(formatter, true);
+            }
+
+            LLMAnswer::Role(_) => {}
 
-                self.answer.formatted_answer =
-                    formatter.format(format!("🤖: {}", &self.answer.plain_answer).as_str());
+            LLMAnswer::ToolCall(delta) => {
+                match self
+                    .answer
+                    .tool_calls
+                    .iter_mut()
+                    .find(|call| call.index == delta.index)
+                {
+                    Some(call) => {
+                        call.id = call.id.take().or(delta.id);
+                        call.name = call.name.take().or(delta.name);
+                        call.arguments.push_str(&delta.arguments);
+                    }
+                    None => self.answer.tool_calls.push(delta),
+                }
+            }
+
+            LLMAnswer::FinishReason(reason) => {
+                self.answer.finish_reason = Some(reason);
+            }
+
+            LLMAnswer::Usage(usage) => {
+                if usage.completion_tokens > 0 {
+                    self.answer.tokens = Some(usage.completion_tokens);
+                }
+                if usage.prompt_tokens > 0 {
+                    self.answer.prompt_tokens = Some(usage.prompt_tokens);
+                }
+                self.usage.prompt_tokens += usage.prompt_tokens;
+                self.usage.completion_tokens += usage.completion_tokens;
             }
 
             LLMAnswer::EndAnswer => {
+                self.request = None;
+                self.keep_reasoning(formatter);
                 self.formatted_chat
                     .extend(self.answer.formatted_answer.clone());
 
-                self.formatted_chat.extend(Text::raw("\n"));
+                if let Some(reason) = &self.answer.finish_reason {
+                    if *reason != FinishReason::Stop {
+                        self.formatted_chat.lines.push(Line::styled(
+                            fill("⚠ Answer stopped: {}", &[&reason.description()]),
+                            Style::default().fg(formatter.muted()),
+                        ));
+                    }
+                }
+
+                self.plain_chat.push(self.answer.message.clone());
 
-                self.plain_chat
-                    .push(format!("🤖: {}", self.answer.plain_answer));
+                let timestamp = Timestamp {
+                    duration: Some(self.answer.recording.duration().as_secs_f64()),
+                    tokens: Some(
+                        self.answer
+                            .tokens
+                            .unwrap_or(self.answer.estimated_tokens() as u64),
+                    ),
+                    prompt_tokens: self.answer.prompt_tokens,
+                    first_token: self.answer.first_token.map(|t| t.as_secs_f64()),
+                    inter_chunk: self.answer.recording.inter_chunk().map(|t| t.as_secs_f64()),
+                    ..Timestamp::now(self.plain_chat.len() - 1)
+                };
+                self.formatted_chat
+                    .lines
+                    .push(timestamp.line(formatter.muted()));
+                self.formatted_chat.extend(Text::raw("\n"));
+                self.timestamps.push(timestamp);
 
+                self.save_recording();
                 self.answer = Answer::default();
             }
         }
+
+        self.sync_scroll();
+    }
+
+    /// The reasoning above the answer received so far
+    fn format_answer(&mut self, formatter: &Formatter, streaming: bool) {
+        let mut formatted_answer = reasoning::format(
+            &self.answer.reasoning,
+            formatter.reasoning(),
+            streaming,
+            formatter.muted(),
+        );
+        formatted_answer.extend(self.answer.renderer.format(
+            &self.answer.message,
+            formatter,
+            &self.render,
+        ));
+        self.answer.formatted_answer = formatted_answer;
+    }
+
+    /// Keep the reasoning of the answer that ends, with the position the answer takes
+    fn keep_reasoning(&mut self, formatter: &Formatter) {
+        if self.answer.reasoning.is_empty() {
+            return;
+        }
+        // Thought, not thinking anymore
+        self.format_answer(formatter, false);
+        self.reasoning.push(Reasoning {
+            index: self.plain_chat.len(),
+            text: std::mem::take(&mut self.answer.reasoning),
+        });
+    }
+
+    /// The estimated tokens of the chat and of the answer being streamed.
+    /// The messages are append only, those counted before are not counted again.
+    pub fn estimated_tokens(&self) -> usize {
+        let (mut counted, mut tokens) = self.counted_tokens.get();
+        if counted > self.plain_chat.len() {
+            (counted, tokens) = (0, 0);
+        }
+
+        tokens += self.plain_chat[counted..]
+            .iter()
+            .map(|message| context::estimate_tokens(message))
+            .sum::<usize>();
+        self.counted_tokens.set((self.plain_chat.len(), tokens));
+
+        tokens + self.answer.estimated_tokens()
+    }
+
+    /// Format the whole chat again, after a theme change for instance
+    pub fn reformat(&mut self, formatter: &Formatter) {
+        self.formatted_chat = format_chat(
+            &self.plain_chat,
+            &self.timestamps,
+            &self.reasoning,
+            formatter,
+            &self.render,
+        );
+        self.viewport.invalidate();
+        self.reset_code_scroll();
     }
 
+    /// Remove the timestamp and the reasoning of a message
+    fn detach(&mut self, index: usize) -> (Option<Timestamp>, Option<Reasoning>) {
+        let timestamp = self
+            .timestamps
+            .iter()
+            .position(|t| t.index == index)
+            .map(|i| self.timestamps.remove(i));
+        let reasoning = self
+            .reasoning
+            .iter()
+            .position(|r| r.index == index)
+            .map(|i| self.reasoning.remove(i));
+        (timestamp, reasoning)
+    }
+
+    /// The position of the last message if it is an answer
+    fn last_answer(&self) -> Option<usize> {
+        self.plain_chat
+            .last()
+            .filter(|message| message.starts_with(authors::ASSISTANT))
+            .map(|_| self.plain_chat.len() - 1)
+    }
+
+    /// Take the last answer out of the chat to ask for another one, it is kept to compare them
+    pub fn take_last_answer(&mut self, formatter: &Formatter) -> bool {
+        let Some(index) = self.last_answer() else {
+            return false;
+        };
+
+        let (timestamp, reasoning) = self.detach(index);
+        self.previous_answer = self.plain_chat.pop().map(|message| PreviousAnswer {
+            message,
+            timestamp,
+            reasoning,
+        });
+        self.regenerating = true;
+        self.counted_tokens.set((0, 0));
+        self.reformat(formatter);
+        true
+    }
+
+    /// Put the previous answer back after its regeneration failed or was stopped, the part
+    /// of the regenerated answer received is dropped.
+    /// Returns the answer now in the chat, if the chat was regenerating one.
+    pub fn restore_previous_answer(&mut self, formatter: &Formatter) -> Option<String> {
+        if !std::mem::take(&mut self.regenerating) {
+            return None;
+        }
+        let previous = self.previous_answer.take()?;
+
+        if let Some(index) = self.last_answer() {
+            self.detach(index);
+            self.plain_chat.pop();
+        }
+        self.timestamps.extend(previous.timestamp);
+        self.reasoning.extend(previous.reasoning);
+        self.plain_chat.push(previous.message);
+        self.failed_request = false;
+
+        self.counted_tokens.set((0, 0));
+        self.reformat(formatter);
+        self.plain_chat
+            .last()
+            .and_then(|message| message.strip_prefix(authors::ASSISTANT))
+            .map(str::to_string)
+    }
+
+    /// Put the previous answer back in place of the regenerated one, which is kept instead.
+    /// Returns the answer now in the chat.
+    pub fn swap_answers(&mut self, formatter: &Formatter) -> Option<String> {
+        let index = self.last_answer()?;
+        let previous = self.previous_answer.take()?;
+
+        let (timestamp, reasoning) = self.detach(index);
+        self.timestamps.extend(previous.timestamp);
+        self.reasoning.extend(previous.reasoning);
+        let message = std::mem::replace(&mut self.plain_chat[index], previous.message);
+        self.previous_answer = Some(PreviousAnswer {
+            message,
+            timestamp,
+            reasoning,
+        });
+
+        self.counted_tokens.set((0, 0));
+        self.reformat(formatter);
+        self.plain_chat[index]
+            .strip_prefix(authors::ASSISTANT)
+            .map(str::to_string)
+    }
+
+    pub fn handle_summary(&mut self, summary: &str, formatter: &Formatter) {
+        let summary = format!(
+            "📝: Summary of the conversation, used as context from now on\n{}",
+            summary
+        );
+
+        self.formatted_chat
+            .extend(formatter.format_with(&summary, &self.render));
+        self.formatted_chat.extend(Text::raw("\n"));
+        self.plain_chat.push(summary);
+        self.stamp();
+        self.sync_scroll();
+    }
+
+    /// Show the output of a command or of the code of a language while it runs
+    pub fn start_tool_output(&mut self, cmd: &str, language: &str, formatter: &Formatter) {
+        // The fences start their line, to be told apart from the output
+        let fence = if cmd.contains('\n') {
+            format!(":\n```{}\n{}\n```", language, cmd.trim_end())
+        } else {
+            format!(" `{}`", cmd)
+        };
+        self.tool_output = Some(format!("🔧: Output of{}\n```\n", fence));
+        self.tool_output_lines = 0;
+        self.format_tool_output(formatter);
+    }
+
+    fn format_tool_output(&mut self, formatter: &Formatter) {
+        if let Some(output) = &self.tool_output {
+            self.formatted_tool_output =
+                formatter.format_with(&format!("{}```", output), &self.render);
+        }
+    }
+
+    /// Add a line of output, or end it.
+    /// Returns the whole output once the command exits.
+    pub fn handle_tool_output(
+        &mut self,
+        event: ShellOutput,
+        formatter: &Formatter,
+    ) -> Option<String> {
+        let output = self.tool_output.as_mut()?;
+
+        match event {
+            ShellOutput::Line(line) => {
+                self.tool_output_lines += 1;
+                if self.tool_output_lines <= MAX_OUTPUT_LINES {
+                    output.push_str(&line);
+                    output.push('\n');
+                    // Not highlighted in its fence, the line goes before the closing one
+                    let end = self.formatted_tool_output.lines.len().saturating_sub(1);
+                    self.formatted_tool_output
+                        .lines
+                        .insert(end, Line::raw(line));
+                    self.sync_scroll();
+                }
+                None
+            }
+
+            ShellOutput::Exit(status) => {
+                let mut output = self.tool_output.take()?;
+                output.push_str("```\n");
+                if self.tool_output_lines > MAX_OUTPUT_LINES {
+                    output.push_str(&format!(
+                        "{} more lines not shown\n",
+                        self.tool_output_lines - MAX_OUTPUT_LINES
+                    ));
+                }
+                output.push_str(&format!("The command {}", status));
+
+                self.formatted_chat
+                    .extend(formatter.format_with(&output, &self.render));
+                self.formatted_chat.extend(Text::raw("\n"));
+                self.formatted_tool_output = Text::raw("");
+                self.plain_chat.push(output.clone());
+                self.stamp();
+                self.sync_scroll();
+
+                output.strip_prefix("🔧: ").map(|output| output.to_string())
+            }
+        }
+    }
+
+    fn save_recording(&mut self) {
+        if !self.answer.recording.is_empty() {
+            self.recordings
+                .push(std::mem::take(&mut self.answer.recording));
+        }
+    }
+
+    pub fn handle_error(&mut self, error: &LLMError, formatter: &Formatter) {
+        self.request = None;
+        if !self.answer.plain_answer().is_empty() {
+            if !self.answer.reasoning.is_empty() {
+                self.reasoning.push(Reasoning {
+                    index: self.plain_chat.len(),
+                    text: std::mem::take(&mut self.answer.reasoning),
+                });
+            }
+            self.formatted_chat
+                .extend(self.answer.formatted_answer.clone());
+            self.plain_chat.push(self.answer.message.clone());
+            self.stamp();
+            self.save_recording();
+        }
+
+        self.answer = Answer::default();
+
+        self.formatted_chat.lines.push(Line::styled(
+            fill("⚠ request failed: {}", &[&error.to_string()]),
+            Style::default().fg(formatter.error()),
+        ));
+        self.formatted_chat.lines.push(Line::styled(
+            t("Press ctrl + r to retry"),
+            Style::default().fg(formatter.muted()),
+        ));
+        self.formatted_chat.extend(Text::raw("\n"));
+
+        self.failed_request = true;
+        self.sync_scroll();
+    }
+
+    /// The lines of the answer and of the command output, not in the chat yet
+    pub fn tail(&self) -> impl Iterator<Item = &Line<'a>> + Clone {
+        self.answer
+            .formatted_answer
+            .lines
+            .iter()
+            .chain(self.formatted_tool_output.lines.iter())
+    }
+
+    /// The rows of the wrapped chat, as of the last render
     pub fn height(&self) -> usize {
-        let mut chat = self.formatted_chat.clone();
+        self.viewport.height()
+            + tail_heights(self.tail(), self.area_width, !self.render.nowrap)
+                .iter()
+                .map(|(height, _)| height)
+                .sum::<usize>()
+    }
+
+    /// The row where a line of the formatted chat is shown
+    pub fn row_of(&self, line: usize) -> u16 {
+        to_row(self.viewport.start(line))
+    }
+
+    /// The text of the last question, without its attachments
+    pub fn last_question(&self) -> Option<&str> {
+        self.plain_chat
+            .iter()
+            .rev()
+            .find_map(|message| message.strip_prefix(authors::USER))
+            .map(attachment::strip_markers)
+    }
+
+    /// Wrap the lines added to the chat and follow them, as they are received rather than
+    /// at the next render
+    fn sync_scroll(&mut self) {
+        // Not rendered yet, the width to wrap the lines is unknown
+        if self.area_width == 0 {
+            return;
+        }
+
+        self.viewport.update(
+            &self.formatted_chat.lines,
+            self.area_width,
+            !self.render.nowrap,
+        );
+        self.content_height = self.height();
+
+        let max_scroll = to_row(self.content_height.saturating_sub(self.area_height.into()));
+        if self
+            .automatic_scroll
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            self.scroll = max_scroll;
+        } else {
+            self.scroll = self.scroll.min(max_scroll);
+        }
+    }
+
+    /// The lines of the first code block in view
+    fn code_block_in_view(&self) -> Option<Range<usize>> {
+        let lines = self.formatted_chat.lines.len();
+        let first = self.viewport.line_at(self.scroll as usize);
+        let bottom = self
+            .viewport
+            .line_at(self.scroll as usize + self.area_height as usize)
+            .min(lines);
 
-        chat.extend(self.answer.formatted_answer.clone());
+        let line = (first..bottom).find(|&i| self.viewport.is_code(i))?;
+        let start = (0..line)
+            .rev()
+            .find(|&i| !self.viewport.is_code(i))
+            .map_or(0, |i| i + 1);
+        let end = (line..lines)
+            .find(|&i| !self.viewport.is_code(i))
+            .unwrap_or(lines);
+        Some(start..end)
+    }
+
+    /// Scroll the first code block in view sideways, up to the end of its widest line.
+    /// Another block in view starts from its first column.
+    pub fn scroll_code(&mut self, columns: i16) {
+        let Some(block) = self.code_block_in_view() else {
+            self.reset_code_scroll();
+            return;
+        };
+        if self.code_block.as_ref() != Some(&block) {
+            self.code_scroll = 0;
+        }
+
+        let widest = self.formatted_chat.lines[block.clone()]
+            .iter()
+            .map(Line::width)
+            .max()
+            .unwrap_or_default();
+        let max_scroll = to_row(widest.saturating_sub(self.area_width.into()));
+        self.code_scroll = self
+            .code_scroll
+            .saturating_add_signed(columns)
+            .min(max_scroll);
+        self.code_block = Some(block);
+    }
 
-        let nb_lines = chat.lines.len() + 3;
-        chat.lines.iter().fold(nb_lines, |acc, line| {
-            acc + line.width() / self.area_width as usize
-        })
+    pub fn reset_code_scroll(&mut self) {
+        self.code_scroll = 0;
+        self.code_block = None;
+    }
+
+    /// Scroll down by a row, not past the end of the chat
+    pub fn scroll_down(&mut self) {
+        let max_scroll = to_row(self.content_height.saturating_sub(self.area_height.into()));
+        self.scroll = self.scroll.saturating_add(1).min(max_scroll);
     }
 
     pub fn move_to_bottom(&mut self) {
-        self.scroll = (self.formatted_chat.height() + self.answer.formatted_answer.height())
-            .saturating_sub((self.area_height - 2).into()) as u16;
+        self.scroll = to_row(self.height().saturating_sub(self.area_height.into()));
     }
 
     pub fn move_to_top(&mut self) {
         self.scroll = 0;
     }
 
-    pub fn render(&mut self, frame: &mut Frame, area: Rect) {
-        let mut text = self.formatted_chat.clone();
-        text.extend(self.answer.formatted_answer.clone());
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        indicator: Option<String>,
+        theme: &Theme,
+        authors: &Authors,
+    ) {
+        let block = if self
+            .automatic_scroll
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            Block::default()
+        } else {
+            Block::default()
+                .title(Title::from(format!(" {} ", t("follow: off"))).alignment(Alignment::Right))
+                .title_style(Style::default().fg(theme.muted))
+        };
+
+        let block = match indicator {
+            Some(indicator) => block.title(
+                Title::from(indicator)
+                    .alignment(Alignment::Left)
+                    .position(Position::Top),
+            ),
+            None => block,
+        };
+
+        let inner_area = block.inner(area);
+        self.area_height = inner_area.height;
+        self.area_width = inner_area.width;
+        // After a resize, or a change of the chat not coming from an event
+        self.sync_scroll();
 
-        self.area_height = area.height;
-        self.area_width = area.width;
+        let wrap_code = !self.render.nowrap;
+        let tail = tail_heights(self.tail(), self.area_width, wrap_code);
+        let scroll = self.scroll as usize;
 
-        let scroll: u16 = {
-            if self
-                .automatic_scroll
-                .load(std::sync::atomic::Ordering::Relaxed)
-            {
-                let scroll = self.height().saturating_sub(self.area_height.into()) as u16;
-                self.scroll = scroll;
-                scroll
+        // Only the lines in view are given to the paragraph, the long chats are not wrapped again
+        let first = self.viewport.line_at(scroll);
+        let start = self.viewport.start(first);
+        let bottom = scroll + self.area_height as usize;
+        let mut row = start;
+        let mut visible = Vec::new();
+        let code = (first..self.formatted_chat.lines.len())
+            .map(|i| self.viewport.is_code(i))
+            .chain(tail.iter().map(|(_, code)| *code));
+        let heights = self
+            .viewport
+            .heights_from(first)
+            .chain(tail.iter().map(|(height, _)| *height));
+        for (((i, line), line_height), code) in self
+            .formatted_chat
+            .lines
+            .iter()
+            .chain(self.tail())
+            .enumerate()
+            .skip(first)
+            .zip(heights)
+            .zip(code)
+        {
+            if row >= bottom {
+                break;
+            }
+            if code && !wrap_code {
+                let offset = match &self.code_block {
+                    Some(block) if block.contains(&i) => self.code_scroll as usize,
+                    _ => 0,
+                };
+                visible.push(clip(line, offset, self.area_width as usize));
             } else {
-                self.scroll
+                visible.push(line.clone());
             }
-        };
+            row += line_height;
+        }
 
-        let chat = Paragraph::new(text)
-            .scroll((scroll, 0))
+        let chat = Paragraph::new(accent(Text::from(visible), theme, authors))
+            .scroll((to_row(scroll - start), 0))
             .wrap(Wrap { trim: false })
-            .block(Block::default());
+            .block(block);
 
         frame.render_widget(chat, area);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(formatter: &Formatter) -> Chat<'static> {
+        let mut chat = Chat::default();
+        for message in ["👤: Hello", "🤖: Hi there"] {
+            chat.plain_chat.push(message.to_string());
+        }
+        chat.reformat(formatter);
+        chat
+    }
+
+    #[test]
+    fn scroll_the_code_block_in_view() {
+        let formatter = Formatter::new();
+        let mut chat = Chat::default();
+        chat.render.nowrap = true;
+        chat.plain_chat.push(String::from(
+            "🤖: Two blocks\n```\nshort\nthe widest line of the first block\n```\n\n```\nthe second block is even wider than the first one\n```",
+        ));
+        chat.reformat(&formatter);
+        chat.area_width = 20;
+        chat.area_height = 5;
+        chat.automatic_scroll
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        chat.sync_scroll();
+
+        let block = chat.code_block_in_view().unwrap();
+        assert!(chat.formatted_chat.lines[block.clone()]
+            .iter()
+            .any(|line| line.width() > 30 && line.width() < 40));
+
+        // Not past the end of its widest line
+        for _ in 0..10 {
+            chat.scroll_code(4);
+        }
+        let widest = chat.formatted_chat.lines[block.clone()]
+            .iter()
+            .map(Line::width)
+            .max()
+            .unwrap();
+        assert_eq!(chat.code_scroll as usize, widest - 20);
+        assert_eq!(chat.code_block, Some(block.clone()));
+
+        chat.scroll_code(-4);
+        assert_eq!(chat.code_scroll as usize, widest - 24);
+
+        // The second block in view starts from its first column
+        chat.scroll = block.end as u16 + 1;
+        chat.scroll_code(4);
+        assert_eq!(chat.code_scroll, 4);
+        assert!(chat
+            .code_block
+            .as_ref()
+            .is_some_and(|b| b.start > block.end));
+    }
+
+    #[test]
+    fn previous_answer_kept_when_regeneration_fails() {
+        let formatter = Formatter::new();
+        let mut chat = chat(&formatter);
+
+        assert!(chat.take_last_answer(&formatter));
+        assert_eq!(chat.plain_chat, vec!["👤: Hello"]);
+
+        chat.handle_answer(LLMAnswer::StartAnswer, &formatter);
+        chat.handle_answer(LLMAnswer::Answer("Hel".to_string()), &formatter);
+        chat.handle_error(
+            &LLMError {
+                status: None,
+                message: "timeout".to_string(),
+            },
+            &formatter,
+        );
+
+        assert_eq!(
+            chat.restore_previous_answer(&formatter).as_deref(),
+            Some("Hi there")
+        );
+        assert_eq!(chat.plain_chat, vec!["👤: Hello", "🤖: Hi there"]);
+        assert!(!chat.failed_request);
+        assert!(chat.previous_answer.is_none());
+    }
+
+    #[test]
+    fn regenerated_answer_kept_when_it_ends() {
+        let formatter = Formatter::new();
+        let mut chat = chat(&formatter);
+
+        chat.take_last_answer(&formatter);
+        chat.handle_answer(LLMAnswer::StartAnswer, &formatter);
+        chat.handle_answer(LLMAnswer::Answer("Hello!".to_string()), &formatter);
+        chat.handle_answer(LLMAnswer::EndAnswer, &formatter);
+        chat.regenerating = false;
+
+        // An error of the next question does not bring the replaced answer back
+        assert_eq!(chat.restore_previous_answer(&formatter), None);
+        assert_eq!(chat.plain_chat, vec!["👤: Hello", "🤖: Hello!"]);
+        assert_eq!(chat.swap_answers(&formatter).as_deref(), Some("Hi there"));
+    }
+}
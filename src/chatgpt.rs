@@ -1,52 +1,80 @@
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tokio::time::{sleep, Duration};
+use std::sync::{Arc, Mutex};
 
 use crate::event::Event;
 use async_trait::async_trait;
-use regex::Regex;
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::config::ChatGPTConfig;
-use crate::llm::{LLMAnswer, LLMRole, LLM};
+use crate::config::{fetch_api_key_from_cmd, ChatGPTConfig, LLMParams, NetworkConfig};
+use crate::llm::{
+    apply_params, openai_messages, parse_openai_chunk, system_message, LLMAnswer, LLMRole, LLM,
+};
+use crate::network;
+use crate::sse::{EventStream, SseEvent, SseParser};
 use reqwest::header::HeaderMap;
-use serde_json::{json, Value};
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
 use std;
 use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
 pub struct ChatGPT {
     client: reqwest::Client,
-    openai_api_key: String,
+    openai_api_key: Arc<Mutex<String>>,
+    api_key_cmd: Option<String>,
     model: String,
     url: String,
     messages: Vec<HashMap<String, String>>,
+    network: NetworkConfig,
+    params: LLMParams,
+    extra_headers: HeaderMap,
+    extra_body: Map<String, Value>,
 }
 
 impl ChatGPT {
-    pub fn new(config: ChatGPTConfig) -> Self {
-        let openai_api_key = match std::env::var("OPENAI_API_KEY") {
-            Ok(key) => key,
-            Err(_) => config
-                .openai_api_key
-                .ok_or_else(|| {
-                    eprintln!(
-                        r#"Can not find the openai api key
-You need to define one wether in the configuration file or as an environment variable"#
-                    );
-
-                    std::process::exit(1);
-                })
-                .unwrap(),
-        };
-
-        Self {
-            client: reqwest::Client::new(),
-            openai_api_key,
+    pub fn new(
+        config: ChatGPTConfig,
+        network: NetworkConfig,
+        params: LLMParams,
+    ) -> Result<Self, String> {
+        let openai_api_key = config.api_key().ok_or(
+            r#"Can not find the openai api key
+You need to define one wether in the configuration file, as an environment variable, with `api_key_cmd` or in the keyring"#,
+        )?;
+
+        // Checked with the config
+        let extra_headers = config.headers().unwrap_or_default();
+
+        Ok(Self {
+            client: network::client(&network)?,
+            openai_api_key: Arc::new(Mutex::new(openai_api_key)),
+            api_key_cmd: config.api_key_cmd,
             model: config.model,
             url: config.url,
             messages: Vec::new(),
+            network,
+            params,
+            extra_headers,
+            extra_body: config.extra_body,
+        })
+    }
+
+    async fn send(
+        &self,
+        body: &Value,
+        api_key: &str,
+        sender: &UnboundedSender<Event>,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", "application/json".parse()?);
+        headers.insert("Authorization", format!("Bearer {}", api_key).parse()?);
+        for (name, value) in &self.extra_headers {
+            headers.insert(name, value.clone());
         }
+
+        let request = self.client.post(&self.url).headers(headers).json(body);
+
+        network::send(request, &self.network, sender).await
     }
 }
 
@@ -56,6 +84,14 @@ impl LLM for ChatGPT {
         self.messages = Vec::new();
     }
 
+    fn messages(&self) -> Vec<HashMap<String, String>> {
+        self.messages.clone()
+    }
+
+    fn set_messages(&mut self, messages: Vec<HashMap<String, String>>) {
+        self.messages = messages;
+    }
+
     fn append_chat_msg(&mut self, msg: String, role: LLMRole) {
         let mut conv: HashMap<String, String> = HashMap::new();
         conv.insert("role".to_string(), role.to_string());
@@ -63,82 +99,68 @@ impl LLM for ChatGPT {
         self.messages.push(conv);
     }
 
-    async fn ask(
+    async fn ask_with(
         &self,
+        chat_messages: Vec<HashMap<String, String>>,
         sender: UnboundedSender<Event>,
         terminate_response_signal: Arc<AtomicBool>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut headers = HeaderMap::new();
-        headers.insert("Content-Type", "application/json".parse()?);
-        headers.insert(
-            "Authorization",
-            format!("Bearer {}", self.openai_api_key).parse()?,
-        );
-
-        let mut messages: Vec<HashMap<String, String>> = vec![
-            (HashMap::from([
-                ("role".to_string(), "system".to_string()),
-                (
-                    "content".to_string(),
-                    "You are a helpful assistant.".to_string(),
-                ),
-            ])),
-        ];
-
-        messages.extend(self.messages.clone());
-
-        let body: Value = json!({
+        let mut messages: Vec<HashMap<String, String>> = vec![system_message(&self.params)];
+
+        messages.extend(chat_messages);
+
+        let mut body: Value = json!({
             "model": self.model,
-            "messages": messages,
+            "messages": openai_messages(&messages),
             "stream": true,
+            // The usage comes in a last chunk, without choices
+            "stream_options": { "include_usage": true },
         });
+        apply_params(&mut body, &self.params);
+        for (key, value) in &self.extra_body {
+            body[key] = value.clone();
+        }
 
-        let response = self
-            .client
-            .post(&self.url)
-            .headers(headers)
-            .json(&body)
-            .send()
-            .await?;
+        let api_key = self.openai_api_key.lock().unwrap().clone();
+        let mut response = self.send(&body, &api_key, &sender).await?;
+
+        // The key may have been rotated, fetch a fresh one and try again
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let api_key = match self.api_key_cmd.as_deref() {
+                Some(cmd) => fetch_api_key_from_cmd(cmd).await,
+                None => None,
+            };
+            if let Some(api_key) = api_key {
+                *self.openai_api_key.lock().unwrap() = api_key.clone();
+                response = self.send(&body, &api_key, &sender).await?;
+            }
+        }
 
-        match response.error_for_status() {
-            Ok(mut res) => {
+        match network::error_for_status(response).await {
+            Ok(res) => {
                 sender.send(Event::LLMEvent(LLMAnswer::StartAnswer))?;
-                while let Some(chunk) = res.chunk().await? {
-                    let chunk = std::str::from_utf8(&chunk)?;
-
-                    let re = Regex::new(r"data:\s(.*)")?;
-
-                    for captures in re.captures_iter(chunk) {
-                        if let Some(data_json) = captures.get(1) {
-                            if terminate_response_signal.load(Ordering::Relaxed) {
-                                sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
-                                return Ok(());
-                            }
-
-                            if data_json.as_str() == "[DONE]" {
-                                sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
-                                return Ok(());
-                            }
-
-                            let answer: Value = serde_json::from_str(data_json.as_str())?;
+                let mut stream = EventStream::new(res, &self.network, SseParser::default());
+                while let Some(event) = stream.next().await? {
+                    if terminate_response_signal.load(Ordering::Relaxed) {
+                        break;
+                    }
 
-                            let msg = answer["choices"][0]["delta"]["content"]
-                                .as_str()
-                                .unwrap_or("\n");
+                    let SseEvent::Data(data) = event else {
+                        break;
+                    };
 
-                            if msg != "null" {
-                                sender.send(Event::LLMEvent(LLMAnswer::Answer(msg.to_string())))?;
-                            }
+                    let answer: Value = serde_json::from_str(&data)?;
 
-                            sleep(Duration::from_millis(100)).await;
-                        }
+                    for event in parse_openai_chunk(&answer) {
+                        sender.send(Event::LLMEvent(event))?;
                     }
                 }
             }
             Err(e) => return Err(Box::new(e)),
         }
 
+        sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
+
         Ok(())
     }
 }
@@ -1,5 +1,62 @@
-use clap::Command;
+use std::path::PathBuf;
+
+use clap::{arg, value_parser, Command};
 
 pub fn cli() -> Command {
-    Command::new("tenere").about("TUI interface for LLMs built in Rust")
+    Command::new("tenere")
+        .about("TUI interface for LLMs built in Rust")
+        .arg(
+            arg!(-c --config <path> "Use this config file instead of the default one")
+                .required(false)
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(-b --backend <backend> "Override the configured llm backend")
+                .required(false)
+                .value_parser(["chatgpt", "llamacpp", "ollama", "mock"]),
+        )
+        .arg(arg!(-m --model <model> "Override the model of the backend").required(false))
+        .arg(arg!(-p --profile <name> "Use a profile defined in the config").required(false))
+        .arg(
+            arg!(--"no-project" "Ignore the .tenere.toml of the current directory and of its parents")
+                .required(false),
+        )
+        .arg(
+            arg!(--headless "Read the prompts from stdin and write the answers to stdout, without the TUI")
+                .required(false),
+        )
+        .arg(
+            arg!(--json "With --headless, read and write one json object per line")
+                .required(false)
+                .requires("headless"),
+        )
+        .arg(
+            arg!(--plain "Chat line by line without colors, borders or spinners, for screen readers")
+                .required(false)
+                .conflicts_with("headless"),
+        )
+        .subcommand(
+            Command::new("index")
+                .about("Index the files of a directory to add them as context to the questions")
+                .arg(arg!(<dir> "The directory to index").value_parser(value_parser!(PathBuf))),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Import the conversations of a ChatGPT data export or of a messages json into the history")
+                .arg(arg!(<file> "The json file to import").value_parser(value_parser!(PathBuf))),
+        )
+        .subcommand(
+            Command::new("setup")
+                .about("Choose the backend, the model and the api key, and write the config file"),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("List the archived conversations")
+                .arg(arg!(-t --tag <tag> "Only list the conversations with this tag").required(false)),
+        )
+        .subcommand(
+            Command::new("commit")
+                .about("Write a conventional commit message for the staged changes of the git repository")
+                .arg(arg!(--apply "Commit the staged changes with the message instead of printing it").required(false)),
+        )
 }
@@ -0,0 +1,271 @@
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, sync::mpsc::UnboundedSender};
+
+use crate::{config::ClipboardConfig, event::Event};
+
+// A paste command waiting for something, like `wl-paste` without a compositor, is killed
+const PASTE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Same for a copy command, like `wl-copy`
+const COPY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardProvider {
+    /// The clipboard of the desktop, through the system libraries
+    Arboard,
+    /// An escape sequence asking the terminal to copy the text, works over ssh. Copy only.
+    /// Whether the terminal copied it is unknown, so it is tried after the other providers.
+    Osc52,
+    /// The `copy_command` and the `paste_command` of the config
+    Command,
+}
+
+impl ClipboardProvider {
+    fn name(&self) -> &'static str {
+        match self {
+            ClipboardProvider::Arboard => "arboard",
+            ClipboardProvider::Osc52 => "osc52",
+            ClipboardProvider::Command => "command",
+        }
+    }
+}
+
+/// Where the text to paste comes from
+pub enum PasteSource {
+    Text(String),
+    /// The paste command to run, the other providers have no text
+    Command(String),
+}
+
+impl PasteSource {
+    /// The text of the clipboard, waiting for the paste command
+    pub async fn read(self) -> Result<String, String> {
+        match self {
+            PasteSource::Text(text) => Ok(text),
+            PasteSource::Command(cmd) => run_paste_command(&cmd)
+                .await
+                .map_err(|e| failure("paste", vec![format!("command: {}", e)])),
+        }
+    }
+}
+
+/// The clipboard, through the first provider of the config that works
+pub struct Clipboard {
+    providers: Vec<ClipboardProvider>,
+    copy_command: Option<String>,
+    paste_command: Option<String>,
+    arboard: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new(config: &ClipboardConfig) -> Self {
+        let arboard = if config.providers.contains(&ClipboardProvider::Arboard) {
+            arboard::Clipboard::new().ok()
+        } else {
+            None
+        };
+
+        Self {
+            providers: config.providers.clone(),
+            copy_command: config.copy_command.clone(),
+            paste_command: config.paste_command.clone(),
+            arboard,
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) -> Result<(), String> {
+        self.copy(text, None)
+    }
+
+    /// Copy rich text, only arboard keeps the html, the other providers copy the plain text
+    pub fn set_html(&mut self, html: &str, text: &str) -> Result<(), String> {
+        self.copy(text, Some(html))
+    }
+
+    fn copy(&mut self, text: &str, html: Option<&str>) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        // OSC52 never fails, the providers after it would never be tried
+        let mut providers = self.providers.clone();
+        providers.sort_by_key(|provider| *provider == ClipboardProvider::Osc52);
+
+        for provider in providers {
+            let result = match provider {
+                ClipboardProvider::Arboard => match self.arboard.as_mut() {
+                    Some(clipboard) => match html {
+                        Some(html) => clipboard.set_html(html, Some(text)),
+                        None => clipboard.set_text(text),
+                    }
+                    .map_err(|e| e.to_string()),
+                    None => Err(String::from("not available")),
+                },
+                ClipboardProvider::Osc52 => osc52(text),
+                ClipboardProvider::Command => match self.copy_command.as_deref() {
+                    Some(cmd) => run_copy_command(cmd, text),
+                    None => continue,
+                },
+            };
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    tracing::debug!(provider = provider.name(), "can not copy: {}", e);
+                    errors.push(format!("{}: {}", provider.name(), e));
+                }
+            }
+        }
+
+        Err(failure("copy", errors))
+    }
+
+    /// The text of the clipboard when a provider has it at once, read in the background
+    /// otherwise
+    pub fn paste_source(&mut self) -> Result<PasteSource, String> {
+        let mut errors = Vec::new();
+
+        for provider in self.providers.clone() {
+            let result = match provider {
+                ClipboardProvider::Arboard => match self.arboard.as_mut() {
+                    Some(clipboard) => clipboard.get_text().map_err(|e| e.to_string()),
+                    None => Err(String::from("not available")),
+                },
+                // Reading the answer of the terminal would race with the events
+                ClipboardProvider::Osc52 => continue,
+                ClipboardProvider::Command => match self.paste_command.as_ref() {
+                    Some(cmd) => return Ok(PasteSource::Command(cmd.clone())),
+                    None => continue,
+                },
+            };
+
+            match result {
+                Ok(text) => return Ok(PasteSource::Text(text)),
+                Err(e) => {
+                    tracing::debug!(provider = provider.name(), "can not paste: {}", e);
+                    errors.push(format!("{}: {}", provider.name(), e));
+                }
+            }
+        }
+
+        Err(failure("paste", errors))
+    }
+
+    /// The text of the clipboard, when a provider has it at once. The paste command runs
+    /// in the background instead, its text is sent back with `Event::Clipboard`.
+    pub fn get_text(&mut self, sender: &UnboundedSender<Event>) -> Result<Option<String>, String> {
+        match self.paste_source()? {
+            PasteSource::Text(text) => Ok(Some(text)),
+            PasteSource::Command(cmd) => {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let text = run_paste_command(&cmd)
+                        .await
+                        .map_err(|e| failure("paste", vec![format!("command: {}", e)]));
+                    let _ = sender.send(Event::Clipboard(text));
+                });
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn failure(action: &str, errors: Vec<String>) -> String {
+    if errors.is_empty() {
+        format!("No clipboard provider can {}", action)
+    } else {
+        format!("Can not {}: {}", action, errors.join(", "))
+    }
+}
+
+/// The terminal copies the text, wherever tenere runs. tmux needs the sequence wrapped.
+fn osc52(text: &str) -> Result<(), String> {
+    let mut stderr = std::io::stderr();
+    if !stderr.is_terminal() {
+        return Err(String::from("not a terminal"));
+    }
+
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    let sequence = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;\x1b{}\x1b\\", sequence)
+    } else {
+        sequence
+    };
+
+    stderr
+        .write_all(sequence.as_bytes())
+        .and_then(|_| stderr.flush())
+        .map_err(|e| e.to_string())
+}
+
+fn shell(cmd: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    }
+}
+
+/// Start the copy command, it gets the text and is waited for in the background. Its
+/// failures are only logged, like the copies of the terminal they are not known at once.
+fn run_copy_command(cmd: &str, text: &str) -> Result<(), String> {
+    let mut child = tokio::process::Command::from(shell(cmd))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let stdin = child.stdin.take();
+    let cmd = cmd.to_string();
+    let text = text.to_string();
+    tokio::spawn(async move {
+        let copy = async {
+            // Closed once written, the command reads until the end of its input
+            if let Some(mut stdin) = stdin {
+                stdin
+                    .write_all(text.as_bytes())
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            child.wait().await.map_err(|e| e.to_string())
+        };
+
+        match tokio::time::timeout(COPY_TIMEOUT, copy).await {
+            Ok(Ok(status)) if status.success() => {}
+            Ok(Ok(status)) => tracing::warn!("`{}` exited with {}", cmd, status),
+            Ok(Err(e)) => tracing::warn!("`{}` failed: {}", cmd, e),
+            Err(_) => tracing::warn!("`{}` did not answer, it was killed", cmd),
+        }
+    });
+
+    Ok(())
+}
+
+async fn run_paste_command(cmd: &str) -> Result<String, String> {
+    let mut command = tokio::process::Command::from(shell(cmd));
+    let output = command
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(PASTE_TIMEOUT, output)
+        .await
+        .map_err(|_| format!("`{}` did not answer", cmd))?
+        .map_err(|e| e.to_string())?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!("`{}` exited with {}", cmd, output.status))
+    }
+}
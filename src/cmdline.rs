@@ -0,0 +1,259 @@
+use ratatui::{
+    layout::Rect,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::FocusedBlock,
+    completion::{Candidate, Completion},
+    picker,
+    theme::Theme,
+};
+
+/// The commands with their usage
+const COMMANDS: [(&str, &str); 13] = [
+    ("compare", ":compare [BACKEND BACKEND]"),
+    ("diagnostics", ":diagnostics"),
+    ("export", ":export html [PATH]"),
+    ("history", ":history"),
+    ("model", ":model NAME"),
+    ("new", ":new"),
+    ("q", ":q"),
+    ("registers", ":registers"),
+    ("set", ":set NAME=value"),
+    ("tag", ":tag TAGS"),
+    ("tee", ":tee [PATH]"),
+    ("untag", ":untag"),
+    ("w", ":w [PATH]"),
+];
+
+pub const PARAMS: [&str; 4] = [
+    "max_tokens",
+    "reasoning_effort",
+    "system_prompt",
+    "temperature",
+];
+
+/// An action typed in the command line
+#[derive(Debug, PartialEq)]
+pub enum ExCommand {
+    /// Save the chat, to the archive file if no path is given
+    Write(Option<String>),
+    /// Save the chat as an html page, next to the archive file if no path is given
+    ExportHtml(Option<String>),
+    Quit,
+    New,
+    History,
+    /// List the registers, to paste one of them in the prompt
+    Registers,
+    /// Compare the latency of the models
+    Diagnostics,
+    Model(String),
+    /// Change a parameter of the requests, show it if there is no value
+    Set(String, Option<String>),
+    /// Start comparing two backends, or stop when no backend is given
+    Compare(Vec<String>),
+    /// Replace the tags of the chat, show them if there is none given
+    Tag(Option<Vec<String>>),
+    /// Append the messages to a file as they arrive, or stop when no path is given
+    Tee(Option<String>),
+}
+
+impl ExCommand {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let args = args.trim();
+        let path = (!args.is_empty()).then(|| args.to_string());
+
+        match name {
+            "w" | "write" => Ok(ExCommand::Write(path)),
+            "export" => {
+                let (format, path) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let path = path.trim();
+                match format {
+                    "html" => Ok(ExCommand::ExportHtml(
+                        (!path.is_empty()).then(|| path.to_string()),
+                    )),
+                    _ => Err(String::from("Usage: :export html [PATH]")),
+                }
+            }
+            "q" | "quit" => Ok(ExCommand::Quit),
+            "new" => Ok(ExCommand::New),
+            "history" => Ok(ExCommand::History),
+            "reg" | "registers" => Ok(ExCommand::Registers),
+            "diagnostics" => Ok(ExCommand::Diagnostics),
+            "compare" => {
+                let backends: Vec<String> = args.split_whitespace().map(String::from).collect();
+                if backends.len() == 1 || backends.len() > 2 {
+                    return Err(String::from("Usage: :compare BACKEND BACKEND"));
+                }
+                Ok(ExCommand::Compare(backends))
+            }
+            "model" if args.is_empty() => Err(String::from("Usage: :model NAME")),
+            "model" => Ok(ExCommand::Model(args.to_string())),
+            "set" => {
+                let (param, value) = match args.split_once('=') {
+                    Some((param, value)) => (param.trim(), Some(value.trim().to_string())),
+                    None => (args, None),
+                };
+                if !PARAMS.contains(&param) {
+                    return Err(format!(
+                        "Usage: :set NAME=value, with NAME one of {}",
+                        PARAMS.join(", ")
+                    ));
+                }
+                Ok(ExCommand::Set(param.to_string(), value))
+            }
+            "tag" => {
+                // In the order given, without the repeated ones
+                let mut seen = std::collections::HashSet::new();
+                let tags: Vec<String> = args
+                    .split([',', ' '])
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty() && seen.insert(*tag))
+                    .map(String::from)
+                    .collect();
+                Ok(ExCommand::Tag((!tags.is_empty()).then_some(tags)))
+            }
+            "untag" => Ok(ExCommand::Tag(Some(Vec::new()))),
+            "tee" => Ok(ExCommand::Tee(path)),
+            "" => Err(String::from("No command")),
+            _ => Err(format!("Not a command: {}", name)),
+        }
+    }
+}
+
+/// The `:` line at the bottom of the screen, like in vim
+pub struct CommandLine {
+    pub input: String,
+    pub previous_focus: FocusedBlock,
+}
+
+impl CommandLine {
+    pub fn new(previous_focus: FocusedBlock) -> Self {
+        Self {
+            input: String::new(),
+            previous_focus,
+        }
+    }
+
+    /// Complete the command name, the path after `:w` or `:tee` or the parameter after `:set`.
+    /// Returns the candidates when there are several.
+    pub fn complete(&mut self) -> Result<Option<String>, String> {
+        let (name, partial) = match self.input.split_once(' ') {
+            Some((name, partial)) => (name, Some(partial)),
+            None => (self.input.as_str(), None),
+        };
+
+        let (candidates, prefix): (Vec<String>, String) = match (name, partial) {
+            (name, None) => (
+                COMMANDS
+                    .iter()
+                    .map(|(c, _)| c)
+                    .filter(|c| c.starts_with(name))
+                    .map(|c| format!("{} ", c))
+                    .collect(),
+                String::new(),
+            ),
+            ("w", Some(partial)) => (picker::completions(partial), String::from("w ")),
+            ("tee", Some(partial)) => (picker::completions(partial), String::from("tee ")),
+            ("export", Some(partial)) => match partial.strip_prefix("html ") {
+                Some(path) => (picker::completions(path), String::from("export html ")),
+                None if "html".starts_with(partial) => {
+                    (vec![String::from("html ")], String::from("export "))
+                }
+                None => (Vec::new(), String::new()),
+            },
+            ("set", Some(partial)) => (
+                PARAMS
+                    .iter()
+                    .filter(|p| p.starts_with(partial))
+                    .map(|p| format!("{}=", p))
+                    .collect(),
+                String::from("set "),
+            ),
+            _ => (Vec::new(), String::new()),
+        };
+
+        match candidates.len() {
+            0 => Err(format!("No completion for `{}`", self.input)),
+            1 => {
+                self.input = format!("{}{}", prefix, candidates[0]);
+                Ok(None)
+            }
+            _ => {
+                let common = picker::common_prefix(&candidates);
+                self.input = format!("{}{}", prefix, common);
+                Ok(Some(
+                    candidates
+                        .iter()
+                        .map(|c| c.trim_end())
+                        .collect::<Vec<&str>>()
+                        .join("  "),
+                ))
+            }
+        }
+    }
+
+    /// The pop-up completing the command name, the model after `:model` or the parameter after `:set`
+    pub fn completion(&self, models: &[(String, String)]) -> Option<Completion> {
+        let candidates = match self.input.split_once(' ') {
+            None => {
+                let candidates = COMMANDS
+                    .iter()
+                    .map(|(name, usage)| Candidate::new(name, format!("{} ", name), usage))
+                    .collect();
+                return Completion::new(0, &self.input, candidates);
+            }
+            Some(("model", _)) => models
+                .iter()
+                .map(|(model, source)| Candidate::new(model, model.clone(), source))
+                .collect(),
+            Some(("set", _)) => PARAMS
+                .iter()
+                .map(|param| Candidate::new(param, format!("{}=", param), ""))
+                .collect(),
+            Some(_) => return None,
+        };
+
+        // The argument, after the spaces following the command
+        let start = self.input.find(' ').unwrap_or_default();
+        let start = start + self.input[start..].len() - self.input[start..].trim_start().len();
+        Completion::new(start, &self.input[start..], candidates)
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let line = Line::from(vec![
+            Span::styled(":", Style::default().fg(theme.focused_border).bold()),
+            Span::raw(self.input.as_str()),
+        ]);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(line), area);
+        frame.set_cursor(
+            area.x + 1 + unicode_width::UnicodeWidthStr::width(self.input.as_str()) as u16,
+            area.y,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_without_duplicates() {
+        assert_eq!(
+            ExCommand::parse("tag rust, work rust,,work"),
+            Ok(ExCommand::Tag(Some(vec![
+                String::from("rust"),
+                String::from("work")
+            ])))
+        );
+        assert_eq!(ExCommand::parse("tag"), Ok(ExCommand::Tag(None)));
+    }
+}
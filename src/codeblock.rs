@@ -0,0 +1,259 @@
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap,
+    },
+    Frame,
+};
+
+use crate::{authors, i18n::t, share, theme::Theme};
+
+/// A fenced code block of the answers
+#[derive(Debug, Clone)]
+pub struct CodeBlock {
+    pub language: String,
+    pub code: String,
+}
+
+/// The code blocks of the answers, in the order of the chat
+pub fn code_blocks(plain_chat: &[String]) -> Vec<CodeBlock> {
+    plain_chat
+        .iter()
+        .filter_map(|message| message.strip_prefix(authors::ASSISTANT))
+        .flat_map(share::code_blocks)
+        .filter(|(_, code)| !code.trim().is_empty())
+        .map(|(language, code)| CodeBlock { language, code })
+        .collect()
+}
+
+/// Write the code to the file, its absolute path is returned
+pub fn save(path: &Path, code: &str) -> Result<PathBuf, String> {
+    let mut content = code.to_string();
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    std::fs::write(path, content)
+        .map_err(|e| format!("Can not save to `{}`: {}", path.display(), e))?;
+    Ok(std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// Pick a code block of the answers to copy it, save it to a file or run it
+pub struct CodeBlockPicker {
+    pub blocks: Vec<CodeBlock>,
+    /// The name of the file the selected block is saved to, while it is typed
+    pub file_name: Option<String>,
+    /// The file exists, replacing it waits for a confirmation
+    pub overwriting: bool,
+    /// Running the selected block with this interpreter waits for a confirmation
+    pub running: Option<String>,
+    state: ListState,
+}
+
+impl CodeBlockPicker {
+    pub fn new(plain_chat: &[String]) -> Self {
+        let blocks = code_blocks(plain_chat);
+
+        // Select the last block by default
+        let mut state = ListState::default();
+        state.select(blocks.len().checked_sub(1));
+
+        Self {
+            blocks,
+            file_name: None,
+            overwriting: false,
+            running: None,
+            state,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.blocks.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&CodeBlock> {
+        self.state.selected().and_then(|i| self.blocks.get(i))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let items = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let first_line = block
+                    .code
+                    .lines()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or_default()
+                    .trim();
+                let language = if block.language.is_empty() {
+                    "text"
+                } else {
+                    block.language.as_str()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<10} ", language),
+                        Style::default().fg(theme.muted),
+                    ),
+                    Span::raw(first_line.to_string()),
+                    Span::styled(
+                        format!("  +{}", block.code.lines().count().saturating_sub(1)),
+                        Style::default().fg(theme.muted),
+                    ),
+                ]))
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(" {} ", t("Code blocks")))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .title(
+                        Title::from(format!(
+                            " {} ",
+                            t("Enter: copy · w: save to a file · r: run · Esc: close")
+                        ))
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+
+        if let Some(interpreter) = self.running.as_deref() {
+            self.render_run_confirmation(frame, area, interpreter, theme);
+            return;
+        }
+
+        let Some(file_name) = self.file_name.as_ref() else {
+            return;
+        };
+
+        let (message, color) = if self.overwriting {
+            (
+                format!("`{}` {}", file_name, t("exists, replace it? (y/n)")),
+                theme.warning,
+            )
+        } else {
+            (
+                format!("{} {}▏", t("Save to:"), file_name),
+                theme.focused_border,
+            )
+        };
+
+        let input = Paragraph::new(message).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(color)),
+        );
+
+        let input_area = Rect {
+            x: area.x + area.width / 8,
+            y: area.y + (area.height / 2).saturating_sub(2),
+            width: area.width * 3 / 4,
+            height: 3,
+        };
+
+        frame.render_widget(Clear, input_area);
+        frame.render_widget(input, input_area);
+    }
+
+    fn render_run_confirmation(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        interpreter: &str,
+        theme: &Theme,
+    ) {
+        let Some(block) = self.selected() else {
+            return;
+        };
+
+        let confirmation = Paragraph::new(format!(
+            "{} `{}` ? (y/n)\n\n{}",
+            t("Run with"),
+            interpreter,
+            block.code
+        ))
+        .wrap(Wrap { trim: false })
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(theme.warning)),
+        );
+
+        let height = (block.code.lines().count() as u16 + 4).min(area.height);
+        let confirmation_area = Rect {
+            x: area.x + area.width / 8,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width: area.width * 3 / 4,
+            height,
+        };
+
+        frame.render_widget(Clear, confirmation_area);
+        frame.render_widget(confirmation, confirmation_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_blocks_of_the_answers() {
+        let plain_chat = [
+            String::from("👤: ```sh\nls\n```"),
+            String::from(
+                "🤖: Run:\n```sh\nls -la\n```\nand\n```\n\n```\nthen\n```py\nprint(1)\n```",
+            ),
+        ];
+
+        let blocks = code_blocks(&plain_chat);
+        // Not the ones of the questions, nor the empty ones
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, "sh");
+        assert_eq!(blocks[0].code.trim_end(), "ls -la");
+        assert_eq!(blocks[1].language, "py");
+        assert_eq!(blocks[1].code.trim_end(), "print(1)");
+    }
+
+    #[test]
+    fn code_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.py");
+
+        let saved = save(&path, "print(1)").unwrap();
+        assert!(saved.is_absolute());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "print(1)\n");
+
+        save(&path, "print(2)\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "print(2)\n");
+
+        assert!(save(&dir.path().join("missing").join("main.py"), "").is_err());
+    }
+}
@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    app::App,
+    attachment::{self, Attachment, AttachmentKind},
+    completion::{Candidate, Completion},
+    config::Config,
+    environment,
+    event::Event,
+    git,
+    notification::{Notification, NotificationLevel},
+    pipeline::Pipeline,
+};
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn set_variable(variables: &mut HashMap<String, String>, args: &str) -> Result<String, String> {
+    let (name, value) = args
+        .split_once('=')
+        .ok_or_else(|| String::from("Usage: /set NAME=value"))?;
+
+    let name = name.trim();
+    if !is_valid_name(name) {
+        return Err(format!("`{}` is not a valid variable name", name));
+    }
+
+    variables.insert(name.to_string(), value.trim().to_string());
+    Ok(format!("{} is set", name))
+}
+
+fn unset_variable(variables: &mut HashMap<String, String>, name: &str) -> Result<String, String> {
+    match variables.remove(name.trim()) {
+        Some(_) => Ok(format!("{} is unset", name.trim())),
+        None => Err(format!("{} is not defined", name.trim())),
+    }
+}
+
+fn list_variables(variables: &HashMap<String, String>) -> Result<String, String> {
+    if variables.is_empty() {
+        return Ok(String::from("No variables defined"));
+    }
+
+    let mut variables = variables
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<String>>();
+    variables.sort();
+
+    Ok(variables.join("\n"))
+}
+
+/// Change how the current chat is rendered
+fn set_render(app: &mut App, args: &str) -> Result<String, String> {
+    let mut args = args.split_whitespace();
+
+    match (args.next(), args.next()) {
+        (None, _) => {
+            let render = &app.chat.render;
+            return Ok(format!(
+                "Render: {}, theme: {}, code lines: {}",
+                if render.plain { "plain" } else { "markdown" },
+                render.theme.as_deref().unwrap_or("default"),
+                if render.nowrap { "nowrap" } else { "wrap" }
+            ));
+        }
+        (Some("plain"), None) => app.chat.render.plain = true,
+        (Some("markdown"), None) => app.chat.render.plain = false,
+        (Some("wrap"), None) => app.chat.render.nowrap = false,
+        (Some("nowrap"), None) => {
+            app.chat.render.nowrap = true;
+            app.chat.reset_code_scroll();
+        }
+        (Some("theme"), Some("default")) => app.chat.render.theme = None,
+        (Some("theme"), Some(theme)) => {
+            if !app.formatter.has_theme(theme) {
+                return Err(format!("`{}` is not a known theme", theme));
+            }
+            app.chat.render.theme = Some(theme.to_string());
+        }
+        _ => {
+            return Err(String::from(
+                "Usage: /render [plain|markdown|wrap|nowrap|theme NAME]",
+            ))
+        }
+    }
+
+    if !app.spinner.active && app.chat.answer.plain_answer().is_empty() {
+        app.chat.reformat(app.formatter);
+    }
+    app.split.invalidate();
+
+    Ok(String::from("Render profile updated"))
+}
+
+/// Attach a snapshot of the environment to the prompt, once its commands have run
+fn attach_environment(app: &mut App, sender: &UnboundedSender<Event>) -> Result<String, String> {
+    let config = app.config.env.clone();
+    let sender = sender.clone();
+    tokio::spawn(async move {
+        let attachment = Attachment {
+            kind: AttachmentKind::Context,
+            name: String::from("environment"),
+            content: environment::capture(&config).await,
+        };
+        let _ = sender.send(Event::Attachment(Ok(attachment)));
+    });
+
+    Ok(String::from("Capturing the environment"))
+}
+
+/// Attach a file or an image to the prompt
+fn attach(app: &mut App, path: &str) -> Result<String, String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err(String::from("Usage: /attach PATH"));
+    }
+
+    let max_file_size = app.config.attachments.max_file_size;
+    let (attachment, truncated) = attachment::load(path, max_file_size)?;
+
+    if truncated {
+        app.notifications.push(Notification::new(
+            format!(
+                "`{}` is bigger than {} bytes, only its beginning is attached",
+                path, max_file_size
+            ),
+            NotificationLevel::Warning,
+        ));
+    }
+
+    let marker = attachment.marker();
+    app.prompt.attachments.push(attachment);
+
+    Ok(format!("{} attached to the prompt", marker))
+}
+
+/// Attach lines of a file to the prompt
+fn attach_lines(app: &mut App, spec: &str) -> Result<String, String> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Err(String::from("Usage: /file PATH[:START-END]"));
+    }
+
+    let max_file_size = app.config.attachments.max_file_size;
+    let (attachment, truncated) = attachment::load_lines(spec, max_file_size)?;
+
+    if truncated {
+        app.notifications.push(Notification::new(
+            format!(
+                "`{}` is bigger than {} bytes, only its beginning is attached",
+                attachment.name, max_file_size
+            ),
+            NotificationLevel::Warning,
+        ));
+    }
+
+    let marker = attachment.marker();
+    app.prompt.attachments.push(attachment);
+
+    Ok(format!("{} attached to the prompt", marker))
+}
+
+/// Attach the changes of the git repository to the prompt, once git has run
+fn attach_diff(
+    app: &mut App,
+    args: &str,
+    sender: &UnboundedSender<Event>,
+) -> Result<String, String> {
+    let args: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+    let name = if args.is_empty() {
+        String::from("git diff HEAD")
+    } else {
+        format!("git diff {}", args.join(" "))
+    };
+
+    let max_file_size = app.config.attachments.max_file_size;
+    let sender = sender.clone();
+    tokio::spawn(async move {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let attachment = git::diff(&args).await.map(|mut diff| {
+            if attachment::truncate(&mut diff, max_file_size) {
+                let _ = sender.send(Event::Notification(Notification::new(
+                    format!(
+                        "`{}` is bigger than {} bytes, only its beginning is attached",
+                        name, max_file_size
+                    ),
+                    NotificationLevel::Warning,
+                )));
+            }
+            Attachment {
+                kind: AttachmentKind::Context,
+                name,
+                content: format!("```diff\n{}\n```", diff.trim_end()),
+            }
+        });
+        let _ = sender.send(Event::Attachment(attachment));
+    });
+
+    Ok(String::from("Reading the changes of the repository"))
+}
+
+/// Start a pipeline of the config, its first step is sent once the prompt is cleared
+fn start_pipeline(app: &mut App, args: &str) -> Result<String, String> {
+    let args = args.trim();
+    let (name, input) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+
+    if name.is_empty() {
+        let mut names = app
+            .config
+            .pipelines
+            .keys()
+            .cloned()
+            .collect::<Vec<String>>();
+        if names.is_empty() {
+            return Err(String::from(
+                "No pipeline defined in the `[pipelines]` section of the config",
+            ));
+        }
+        names.sort();
+        return Err(format!(
+            "Usage: /pipeline NAME [INPUT], the pipelines are: {}",
+            names.join(", ")
+        ));
+    }
+
+    let steps = app
+        .config
+        .pipelines
+        .get(name)
+        .filter(|steps| !steps.is_empty())
+        .ok_or_else(|| format!("`{}` is not a known pipeline", name))?
+        .clone();
+
+    if let Some(pipeline) = &app.pipeline {
+        return Err(format!(
+            "The pipeline `{}` is already running",
+            pipeline.name
+        ));
+    }
+    if app.compare.is_some() || app.branched.is_some() {
+        return Err(String::from(
+            "A pipeline can not run in the compare mode or with a branched chat",
+        ));
+    }
+
+    let total = steps.len();
+    app.pipeline = Some(Pipeline::new(name, steps, input.trim(), app.chat.id));
+    Ok(format!("Pipeline `{}` started, {} steps", name, total))
+}
+
+/// Put a template of the config in the prompt, in place of the command
+fn use_template(app: &mut App, name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        let mut names = app
+            .config
+            .templates
+            .keys()
+            .cloned()
+            .collect::<Vec<String>>();
+        if names.is_empty() {
+            return Err(String::from(
+                "No template defined in the `[templates]` section of the config",
+            ));
+        }
+        names.sort();
+        return Err(format!(
+            "Usage: /template NAME, the templates are: {}",
+            names.join(", ")
+        ));
+    }
+
+    let template = app
+        .config
+        .templates
+        .get(name)
+        .ok_or_else(|| format!("`{}` is not a known template", name))?;
+    app.prompt.set_text(template);
+
+    Ok(format!("Template `{}` in the prompt", name))
+}
+
+/// A slash command, run with the arguments typed after its name
+struct SlashCommand {
+    name: &'static str,
+    usage: &'static str,
+    run: fn(&mut App, &str, &UnboundedSender<Event>) -> Result<String, String>,
+}
+
+/// The slash commands, in the order of the completions
+const COMMANDS: [SlashCommand; 10] = [
+    SlashCommand {
+        name: "attach",
+        usage: "/attach PATH",
+        run: |app, args, _| attach(app, args),
+    },
+    SlashCommand {
+        name: "diff",
+        usage: "/diff [ARGS]",
+        run: attach_diff,
+    },
+    SlashCommand {
+        name: "env",
+        usage: "/env",
+        run: |app, _, sender| attach_environment(app, sender),
+    },
+    SlashCommand {
+        name: "file",
+        usage: "/file PATH:START-END",
+        run: |app, args, _| attach_lines(app, args),
+    },
+    SlashCommand {
+        name: "pipeline",
+        usage: "/pipeline NAME [INPUT]",
+        run: |app, args, _| start_pipeline(app, args),
+    },
+    SlashCommand {
+        name: "render",
+        usage: "/render plain|markdown|wrap|nowrap|theme NAME",
+        run: |app, args, _| set_render(app, args),
+    },
+    SlashCommand {
+        name: "set",
+        usage: "/set NAME=value",
+        run: |app, args, _| set_variable(&mut app.chat.variables, args),
+    },
+    SlashCommand {
+        name: "template",
+        usage: "/template NAME",
+        run: |app, args, _| use_template(app, args),
+    },
+    SlashCommand {
+        name: "unset",
+        usage: "/unset NAME",
+        run: |app, args, _| unset_variable(&mut app.chat.variables, args),
+    },
+    SlashCommand {
+        name: "vars",
+        usage: "/vars",
+        run: |app, _, _| list_variables(&app.chat.variables),
+    },
+];
+
+/// The pop-up completing the name of the slash command typed in the prompt, or the name of
+/// the template or of the pipeline it takes
+pub fn completion(lines: &[String], config: &Config) -> Option<Completion> {
+    let [line] = lines else {
+        return None;
+    };
+    let input = line.strip_prefix('/')?;
+
+    let Some((name, arg)) = input.split_once(' ') else {
+        let candidates = COMMANDS
+            .iter()
+            .map(|command| {
+                Candidate::new(command.name, format!("{} ", command.name), command.usage)
+            })
+            .collect();
+        return Completion::new(1, input, candidates);
+    };
+    if arg.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let mut candidates: Vec<Candidate> = match name {
+        "template" => config
+            .templates
+            .iter()
+            .map(|(name, template)| {
+                Candidate::new(
+                    name,
+                    name.clone(),
+                    template.lines().next().unwrap_or_default(),
+                )
+            })
+            .collect(),
+        "pipeline" => config
+            .pipelines
+            .iter()
+            .map(|(name, steps)| {
+                Candidate::new(
+                    name,
+                    format!("{} ", name),
+                    &format!("{} steps", steps.len()),
+                )
+            })
+            .collect(),
+        _ => return None,
+    };
+    candidates.sort_by(|a, b| a.label.cmp(&b.label));
+    Completion::new(line.len() - arg.len(), arg, candidates)
+}
+
+/// Run the slash command typed in the prompt, the prompt is cleared before.
+/// Returns false if the input is not a command and should be sent to the LLM.
+pub fn run(app: &mut App, input: &str, sender: &UnboundedSender<Event>) -> bool {
+    let Some(input) = input.strip_prefix('/') else {
+        return false;
+    };
+
+    let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    let Some(command) = COMMANDS.iter().find(|command| command.name == name) else {
+        return false;
+    };
+
+    app.prompt.clear_text();
+    let notif = match (command.run)(app, args, sender) {
+        Ok(msg) => Notification::new(msg, NotificationLevel::Info),
+        Err(msg) => Notification::new(msg, NotificationLevel::Error),
+    };
+    app.notifications.push(notif);
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(completion: Option<Completion>) -> Vec<String> {
+        let Some(mut completion) = completion else {
+            return Vec::new();
+        };
+        let mut labels = Vec::new();
+        while let Some(candidate) = completion.selected() {
+            if labels.contains(&candidate.label) {
+                break;
+            }
+            labels.push(candidate.label.clone());
+            completion.next();
+        }
+        labels
+    }
+
+    #[test]
+    fn completion_of_the_commands_and_templates() {
+        let config: Config = toml::from_str(
+            "[templates]\nreview = \"Review this\"\nrefactor = \"Refactor this\"\nbug = \"Find the bug\"\n",
+        )
+        .unwrap();
+        let lines = |line: &str| vec![line.to_string()];
+
+        assert_eq!(
+            labels(completion(&lines("/u"), &config)),
+            vec!["unset".to_string()]
+        );
+        assert_eq!(
+            labels(completion(&lines("/template re"), &config)),
+            ["refactor", "review"]
+        );
+        assert_eq!(
+            completion(&lines("/template rev"), &config)
+                .unwrap()
+                .accept("/template rev"),
+            "/template review"
+        );
+        assert!(completion(&lines("/set x"), &config).is_none());
+        assert!(completion(&lines("/template review now"), &config).is_none());
+        assert!(completion(&lines("hello"), &config).is_none());
+    }
+}
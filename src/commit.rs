@@ -0,0 +1,95 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use crate::{
+    attachment,
+    config::Config,
+    git,
+    llm::{message, LLMModel, LLMRole},
+    redaction,
+};
+
+/// The size of the staged changes sent to the LLM, past it they are cut
+const MAX_DIFF_SIZE: usize = 60_000;
+
+const INSTRUCTIONS: &str = "Write a git commit message following the Conventional Commits specification for the staged changes below. \
+The first line is `type(scope): summary`, 72 characters at most, in the imperative mood. \
+Add a body after a blank line only if the changes need explaining. \
+Answer with the commit message only, without code fences.";
+
+/// The answer without the fences or the blank lines the models add around it
+fn clean(answer: &str) -> String {
+    let lines: Vec<&str> = answer
+        .trim()
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Ask the LLM for a commit message of the staged changes
+pub async fn message_for_staged(config: Arc<Config>) -> Result<String, String> {
+    let mut diff = git::run(&["diff", "--staged", "--no-color"]).await?;
+    if diff.trim().is_empty() {
+        return Err(String::from(
+            "No staged changes, stage them with `git add` first",
+        ));
+    }
+    if attachment::truncate(&mut diff, MAX_DIFF_SIZE) {
+        eprintln!("The diff is cut to {} bytes", MAX_DIFF_SIZE);
+    }
+
+    let rules = redaction::outgoing_rules(&config.redaction)?;
+    let (diff, redacted) = redaction::redact(&diff, &rules);
+    if !redacted.is_empty() {
+        eprintln!("Redacted before sending: {}", redaction::summary(&redacted));
+    }
+
+    let llm = LLMModel::init(&config.llm, config.clone()).await?;
+    let request = vec![message(
+        LLMRole::USER,
+        &format!("{}\n\n```diff\n{}\n```", INSTRUCTIONS, diff),
+    )];
+    let answer = llm.complete(request).await.map_err(|e| e.to_string())?;
+
+    let message = clean(&answer);
+    if message.is_empty() {
+        return Err(String::from("The answer is empty"));
+    }
+    Ok(message)
+}
+
+/// Commit the staged changes with the message, git writes its summary
+pub fn apply(message: &str) -> Result<(), String> {
+    let mut child = Command::new("git")
+        .args(["commit", "-F", "-"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Can not run git: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(message.as_bytes())
+            .map_err(|e| format!("Can not write to git: {}", e))?;
+    }
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(String::from("git commit failed"));
+    }
+    Ok(())
+}
+
+/// Print a commit message for the staged changes, or commit them with it
+pub async fn run(config: Arc<Config>, apply_message: bool) -> Result<(), String> {
+    let message = message_for_staged(config).await?;
+    if apply_message {
+        apply(&message)
+    } else {
+        println!("{}", message);
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+    time::Instant,
+};
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders},
+    Frame,
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    Mutex,
+};
+
+use crate::{
+    authors::{self, Authors},
+    chat::Chat,
+    config::Config,
+    event::Event,
+    formatter::Formatter,
+    llm::{LLMAnswer, LLMBackend, LLMError, LLMModel, LLMRole, LLM},
+    theme::Theme,
+};
+
+/// A side of the compare mode, a backend with its own conversation
+pub struct Pane<'a> {
+    pub backend: LLMBackend,
+    pub model: String,
+    pub llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    pub chat: Chat<'a>,
+    /// Until the first event of the answer
+    pub waiting: bool,
+    pub streaming: bool,
+}
+
+/// The same questions sent to two backends, their answers side by side
+pub struct Compare<'a> {
+    pub panes: Vec<Pane<'a>>,
+}
+
+impl<'a> Compare<'a> {
+    pub async fn new(backends: [LLMBackend; 2], config: Arc<Config>) -> Result<Self, String> {
+        let configured = config.backends();
+        let mut panes = Vec::new();
+
+        for backend in backends {
+            if !configured.contains(&backend) {
+                return Err(format!("The backend `{}` is not configured", backend));
+            }
+            panes.push(Pane {
+                backend,
+                model: config.backend_model_name(&backend),
+                llm: Arc::new(Mutex::new(LLMModel::init(&backend, config.clone()).await?)),
+                chat: Chat::new(),
+                waiting: false,
+                streaming: false,
+            });
+        }
+
+        Ok(Self { panes })
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.panes.iter().any(|pane| pane.streaming)
+    }
+
+    /// Send the message to every backend, their events are told apart by the index of the pane
+    pub async fn ask(
+        &mut self,
+        payload: HashMap<String, String>,
+        user_input: &str,
+        formatter: &Formatter,
+        sender: UnboundedSender<Event>,
+        terminate_response_signal: Arc<AtomicBool>,
+    ) {
+        for (i, pane) in self.panes.iter_mut().enumerate() {
+            pane.chat
+                .plain_chat
+                .push(format!("{}{}\n", authors::USER, user_input));
+            pane.chat.stamp();
+
+            let formatted_input = formatter.format_with(
+                &format!("{}{}\n", authors::USER, user_input),
+                &pane.chat.render,
+            );
+            if pane.chat.formatted_chat.width() == 0 {
+                pane.chat.formatted_chat = formatted_input;
+            } else {
+                pane.chat.formatted_chat.extend(formatted_input);
+            }
+            // Removed by the start of the answer
+            pane.chat.formatted_chat.lines.push(Line::raw(format!(
+                "{} Waiting for {}…",
+                formatter.authors().assistant,
+                pane.model
+            )));
+
+            {
+                let mut llm = pane.llm.lock().await;
+                let mut messages = llm.messages();
+                messages.push(payload.clone());
+                llm.set_messages(messages);
+            }
+            pane.waiting = true;
+            pane.streaming = true;
+            pane.chat.answer.requested = Some(Instant::now());
+
+            let llm = pane.llm.clone();
+            let sender = sender.clone();
+            let terminate_response_signal = terminate_response_signal.clone();
+            tokio::spawn(async move {
+                let (pane_sender, mut receiver) = unbounded_channel();
+
+                let forward = {
+                    let sender = sender.clone();
+                    tokio::spawn(async move {
+                        while let Some(event) = receiver.recv().await {
+                            let event = match event {
+                                Event::LLMEvent(answer) => Event::CompareAnswer(i, answer),
+                                event @ Event::Notification(_) => event,
+                                _ => continue,
+                            };
+                            let _ = sender.send(event);
+                        }
+                    })
+                };
+
+                let res = {
+                    let llm = llm.lock().await;
+                    llm.ask(pane_sender, terminate_response_signal)
+                        .await
+                        .map_err(LLMError::from)
+                };
+                // The events of the answer come before its error
+                let _ = forward.await;
+
+                if let Err(e) = res {
+                    let _ = sender.send(Event::CompareError(i, e));
+                }
+            });
+        }
+    }
+
+    /// The answer and the model of the pane, once the answer is ended
+    pub async fn handle_answer(
+        &mut self,
+        i: usize,
+        event: LLMAnswer,
+        formatter: &Formatter,
+    ) -> Option<(String, String)> {
+        let pane = self.panes.get_mut(i)?;
+
+        let mut ended = None;
+        match event {
+            LLMAnswer::StartAnswer => pane.waiting = false,
+            LLMAnswer::EndAnswer => {
+                let answer = pane.chat.answer.plain_answer().to_string();
+                let mut llm = pane.llm.lock().await;
+                llm.append_chat_msg(answer.clone(), LLMRole::ASSISTANT);
+                pane.streaming = false;
+                ended = Some((answer, pane.model.clone()));
+            }
+            _ => {}
+        }
+
+        pane.chat.handle_answer(event, formatter);
+        if ended.is_some() {
+            if let Some(timestamp) = pane.chat.timestamps.last_mut() {
+                timestamp.model = Some(pane.model.clone());
+            }
+        }
+        ended
+    }
+
+    pub fn handle_error(&mut self, i: usize, error: &LLMError, formatter: &Formatter) {
+        let Some(pane) = self.panes.get_mut(i) else {
+            return;
+        };
+
+        if pane.waiting {
+            pane.waiting = false;
+            pane.chat.handle_answer(LLMAnswer::StartAnswer, formatter);
+        }
+        pane.chat.handle_error(error, formatter);
+        pane.streaming = false;
+    }
+
+    /// The chats of the panes, to keep them in the history
+    pub fn into_chats(self) -> Vec<Chat<'a>> {
+        self.panes
+            .into_iter()
+            .filter(|pane| !pane.chat.plain_chat.is_empty())
+            .map(|mut pane| {
+                pane.chat.title = Some(format!("{} · {} (compare)", pane.backend, pane.model));
+                pane.chat
+            })
+            .collect()
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme, authors: &Authors) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(50),
+                Constraint::Length(1),
+                Constraint::Min(1),
+            ])
+            .split(area);
+
+        frame.render_widget(
+            Block::default()
+                .borders(Borders::LEFT)
+                .border_style(Style::default().fg(theme.border)),
+            chunks[1],
+        );
+
+        for (pane, area) in self.panes.iter_mut().zip([chunks[0], chunks[2]]) {
+            let indicator = format!(" ⚖ {} · {} ", pane.backend, pane.model);
+            pane.chat
+                .render(frame, area, Some(indicator), theme, authors);
+        }
+    }
+}
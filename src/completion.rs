@@ -0,0 +1,191 @@
+use ratatui::{
+    layout::Rect,
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::theme::Theme;
+
+/// The candidates shown at once, the list scrolls past them
+const MAX_VISIBLE: usize = 8;
+
+/// A way to complete the word being typed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// The word, as listed
+    pub label: String,
+    /// Replaces the word typed when the candidate is accepted
+    pub replacement: String,
+    /// The usage of a command, the name of a backend for a model
+    pub detail: String,
+}
+
+impl Candidate {
+    pub fn new(label: &str, replacement: String, detail: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            replacement,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// Pop-up listing the completions of the slash command typed in the prompt or of the command line,
+/// updated as the text is typed
+pub struct Completion {
+    /// The word completed starts at this byte of the text
+    pub start: usize,
+    candidates: Vec<Candidate>,
+    state: ListState,
+}
+
+impl Completion {
+    /// The candidates starting with the word typed, none if there is no choice left to make
+    pub fn new(start: usize, word: &str, candidates: Vec<Candidate>) -> Option<Self> {
+        let candidates: Vec<Candidate> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.label.starts_with(word))
+            .collect();
+
+        if candidates.is_empty() || candidates.iter().any(|c| c.label == word) {
+            return None;
+        }
+
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Some(Self {
+            start,
+            candidates,
+            state,
+        })
+    }
+
+    pub fn next(&mut self) {
+        let i = self.state.selected().map_or(0, |i| i + 1) % self.candidates.len();
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        let i = match self.state.selected() {
+            Some(0) | None => self.candidates.len() - 1,
+            Some(i) => i - 1,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&Candidate> {
+        self.state.selected().and_then(|i| self.candidates.get(i))
+    }
+
+    /// The text with the word replaced by the selected candidate
+    pub fn accept(&self, text: &str) -> String {
+        match self.selected() {
+            Some(candidate) => format!("{}{}", &text[..self.start], candidate.replacement),
+            None => text.to_string(),
+        }
+    }
+
+    /// Drawn over the line typed in `anchor`, above it or below it
+    pub fn render(&mut self, frame: &mut Frame, anchor: Rect, above: bool, theme: &Theme) {
+        let frame_size = frame.size();
+
+        let label_width = self
+            .candidates
+            .iter()
+            .map(|c| c.label.width())
+            .max()
+            .unwrap_or_default();
+        let width = self
+            .candidates
+            .iter()
+            .map(|c| label_width + 2 + c.detail.width())
+            .max()
+            .unwrap_or_default() as u16
+            + 2;
+        let height = self.candidates.len().min(MAX_VISIBLE) as u16 + 2;
+
+        let x = anchor.x.min(frame_size.width.saturating_sub(width));
+        let y = if above {
+            anchor.y.saturating_sub(height)
+        } else {
+            (anchor.y + anchor.height).min(frame_size.height.saturating_sub(height))
+        };
+        let area = Rect {
+            x,
+            y,
+            width: width.min(frame_size.width),
+            height: height.min(frame_size.height),
+        };
+
+        let items = self
+            .candidates
+            .iter()
+            .map(|candidate| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:width$}  ", candidate.label, width = label_width)),
+                    Span::styled(candidate.detail.as_str(), Style::default().fg(theme.muted)),
+                ]))
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection).bold());
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<Candidate> {
+        ["attach", "diff", "env", "file"]
+            .into_iter()
+            .map(|name| Candidate::new(name, format!("{} ", name), ""))
+            .collect()
+    }
+
+    #[test]
+    fn candidates_of_the_word() {
+        let completion = Completion::new(1, "", candidates()).unwrap();
+        assert_eq!(completion.candidates.len(), 4);
+
+        let completion = Completion::new(1, "f", candidates()).unwrap();
+        assert_eq!(completion.selected().unwrap().label, "file");
+
+        // Nothing left to choose
+        assert!(Completion::new(1, "x", candidates()).is_none());
+        assert!(Completion::new(1, "env", candidates()).is_none());
+    }
+
+    #[test]
+    fn move_in_the_candidates() {
+        let mut completion = Completion::new(1, "", candidates()).unwrap();
+        completion.previous();
+        assert_eq!(completion.selected().unwrap().label, "file");
+        completion.next();
+        completion.next();
+        assert_eq!(completion.selected().unwrap().label, "diff");
+    }
+
+    #[test]
+    fn accept_the_candidate() {
+        let mut completion = Completion::new(1, "", candidates()).unwrap();
+        completion.next();
+        assert_eq!(completion.accept("/"), "/diff ");
+
+        let completion = Completion::new(1, "at", candidates()).unwrap();
+        assert_eq!(completion.accept("/at"), "/attach ");
+    }
+}
@@ -1,32 +1,275 @@
+use crate::clipboard::ClipboardProvider;
+use crate::context::ContextStrategy;
+use crate::i18n::Language;
 use crate::llm::LLMBackend;
+use crate::reasoning::ReasoningDisplay;
+use crate::split::ChatLayout;
+use crate::theme::Theme;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use toml;
 
 use dirs;
 use serde::Deserialize;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Config {
+    /// The name of the saved chats, with the `{date}`, `{time}` and `{title}` placeholders
     #[serde(default = "default_archive_file_name")]
     pub archive_file_name: String,
 
+    /// Where the chats are saved, the current directory if not set
+    pub archive_dir: Option<String>,
+
+    /// Append the messages to this file as they arrive
+    pub tee: Option<String>,
+
+    /// Where the history and the index are kept, instead of the data directory of the platform
+    pub data_dir: Option<String>,
+
+    /// Where the state, the drafts and the log are kept, instead of the state directory of
+    /// the platform
+    pub state_dir: Option<String>,
+
     #[serde(default)]
     pub key_bindings: KeyBindings,
 
     #[serde(default = "default_llm_backend")]
     pub llm: LLMBackend,
 
+    pub idle_lock_timeout: Option<u64>,
+
+    /// In milliseconds, the first key of a chord like `gg` is forgotten after it, 0 to wait forever
+    #[serde(default = "default_key_timeout")]
+    pub key_timeout: u64,
+
+    #[serde(default = "default_generate_titles")]
+    pub generate_titles: bool,
+
+    /// Ask before quitting while an answer is streamed or when the chats are not saved
+    #[serde(default = "default_confirm_quit")]
+    pub confirm_quit: bool,
+
+    /// Save the prompt being typed and restore it on the next start
+    #[serde(default = "default_save_draft")]
+    pub save_draft: bool,
+
+    #[serde(default = "default_status_bar")]
+    pub status_bar: bool,
+
+    #[serde(default)]
+    pub render_math: bool,
+
+    /// Number the lines of the code blocks of the answers
+    #[serde(default)]
+    pub show_code_line_numbers: bool,
+
+    /// How the chain of thought of the reasoning models is shown
+    #[serde(default)]
+    pub reasoning_display: ReasoningDisplay,
+
+    /// The language of the interface: en, fr, de or zh
+    #[serde(default)]
+    pub language: Language,
+
+    /// One of off, error, warn, info, debug or trace
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+
+    /// Accept commands from other programs on a unix socket
+    #[serde(default)]
+    pub control_socket: bool,
+
+    /// In milliseconds, the spinner and the expiry of the notifications are checked at this rate
+    #[serde(default = "default_tick_rate")]
+    pub tick_rate: u64,
+
+    /// The screen is drawn at most this many times a second while an answer is streamed
+    #[serde(default = "default_max_fps")]
+    pub max_fps: u64,
+
+    #[serde(default)]
+    pub layout: ChatLayout,
+
+    #[serde(default)]
+    pub prompt: PromptConfig,
+
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
+    #[serde(default)]
+    pub authors: AuthorsConfig,
+
     #[serde(default)]
     pub chatgpt: ChatGPTConfig,
 
     pub llamacpp: Option<LLamacppConfig>,
 
     pub ollama: Option<OllamaConfig>,
+
+    pub mock: Option<MockConfig>,
+
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    #[serde(default)]
+    pub context: ContextConfig,
+
+    /// Prices of the models, to estimate the cost of a chat
+    #[serde(default)]
+    pub prices: HashMap<String, Price>,
+
+    #[serde(default)]
+    pub history: HistoryConfig,
+
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+
+    #[serde(default)]
+    pub auto_select: AutoSelectConfig,
+
+    #[serde(default)]
+    pub share: ShareConfig,
+
+    #[serde(default)]
+    pub params: LLMParams,
+
+    #[serde(default)]
+    pub env: EnvConfig,
+
+    #[serde(default)]
+    pub attachments: AttachmentsConfig,
+
+    #[serde(default)]
+    pub shell: ShellConfig,
+
+    #[serde(default)]
+    pub runner: RunnerConfig,
+
+    /// Expanded with `{{name}}` in the prompts and the system prompt
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+
+    /// Sequences of prompts sent one after the other with `/pipeline NAME`
+    #[serde(default)]
+    pub pipelines: HashMap<String, Vec<String>>,
+
+    /// Prompts put in the prompt with `/template NAME`, to be completed before sending them
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+
+    /// Commands run on the messages
+    #[serde(default)]
+    pub hooks: Vec<HookConfig>,
+
+    /// Change the prompts before they are sent
+    #[serde(default)]
+    pub pre_send: Vec<PreSendConfig>,
+
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    pub rag: Option<RagConfig>,
+
+    #[serde(default)]
+    pub voice: VoiceConfig,
+
+    #[serde(default)]
+    pub alert: AlertConfig,
+
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+
+    #[serde(default)]
+    pub spellcheck: SpellcheckConfig,
+
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// The name of the profile applied to this config
+    #[serde(skip)]
+    pub profile: Option<String>,
+
+    /// The file this config was loaded from, None for the default location
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+
+    /// The `.tenere.toml` of the project applied to this config
+    #[serde(skip)]
+    pub project: Option<PathBuf>,
+}
+
+/// Load the config and apply the overrides of the command line
+pub fn from_args(matches: &clap::ArgMatches) -> Config {
+    let exit = |e: String| -> ! {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    };
+
+    let mut config = Config::load(matches.get_one::<PathBuf>("config").cloned())
+        .unwrap_or_else(|e| exit(e.to_string()));
+
+    if !matches.get_flag("no-project") {
+        if let Some(path) = ProjectConfig::find() {
+            config.apply_project(&path).unwrap_or_else(|e| exit(e));
+        }
+    }
+
+    if let Some(profile) = matches.get_one::<String>("profile") {
+        config.apply_profile(profile).unwrap_or_else(|e| exit(e));
+    }
+
+    if let Some(backend) = matches.get_one::<String>("backend") {
+        config.llm = LLMBackend::from_str(backend).unwrap();
+    }
+
+    if let Some(model) = matches.get_one::<String>("model") {
+        config.set_model(model).unwrap_or_else(|e| exit(e));
+    }
+
+    config.check().unwrap_or_else(|e| exit(e));
+    config
 }
 
 pub fn default_archive_file_name() -> String {
     String::from("tenere.archive")
 }
 
+pub fn default_generate_titles() -> bool {
+    true
+}
+
+pub fn default_confirm_quit() -> bool {
+    true
+}
+
+pub fn default_save_draft() -> bool {
+    true
+}
+
+pub fn default_status_bar() -> bool {
+    true
+}
+
+pub fn default_log_level() -> String {
+    String::from("info")
+}
+
+pub fn default_key_timeout() -> u64 {
+    1000
+}
+
+pub fn default_tick_rate() -> u64 {
+    250
+}
+
+pub fn default_max_fps() -> u64 {
+    30
+}
+
 pub fn default_llm_backend() -> LLMBackend {
     LLMBackend::ChatGPT
 }
@@ -36,19 +279,41 @@ pub fn default_llm_backend() -> LLMBackend {
 pub struct ChatGPTConfig {
     pub openai_api_key: Option<String>,
 
+    pub api_key_cmd: Option<String>,
+
+    pub keyring: Option<String>,
+
     #[serde(default = "ChatGPTConfig::default_model")]
     pub model: String,
 
     #[serde(default = "ChatGPTConfig::default_url")]
     pub url: String,
+
+    pub max_tokens: Option<u32>,
+
+    pub stop: Option<Vec<String>>,
+
+    /// Added to the headers of the requests, for the gateways that need them
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>,
+
+    /// Merged into the body of the requests, replacing the fields of the same name
+    #[serde(default)]
+    pub extra_body: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Default for ChatGPTConfig {
     fn default() -> Self {
         Self {
             openai_api_key: None,
+            api_key_cmd: None,
+            keyring: None,
             model: Self::default_model(),
             url: Self::default_url(),
+            max_tokens: None,
+            stop: None,
+            extra_headers: HashMap::new(),
+            extra_body: serde_json::Map::new(),
         }
     }
 }
@@ -61,6 +326,85 @@ impl ChatGPTConfig {
     pub fn default_url() -> String {
         String::from("https://api.openai.com/v1/chat/completions")
     }
+
+    /// The api key from the command, the keyring, the environment or the config, in this order
+    pub fn api_key(&self) -> Option<String> {
+        self.api_key_cmd
+            .as_deref()
+            .and_then(api_key_from_cmd)
+            .or_else(|| self.keyring.as_deref().and_then(api_key_from_keyring))
+            .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+            .or(self.openai_api_key.clone())
+    }
+
+    /// `api_key` on a thread of its own, the command and the keyring may take a while
+    pub async fn fetch_api_key(&self) -> Option<String> {
+        let config = self.clone();
+        tokio::task::spawn_blocking(move || config.api_key())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Whether a key is set up, without running the command or reading the keyring
+    pub fn has_credentials(&self) -> bool {
+        self.api_key_cmd.is_some()
+            || self.keyring.is_some()
+            || std::env::var("OPENAI_API_KEY").is_ok()
+            || self.openai_api_key.is_some()
+    }
+
+    /// The `extra_headers`, checked once when the config is loaded
+    pub fn headers(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.extra_headers {
+            let name = HeaderName::from_str(name)
+                .map_err(|_| format!("`{}` is not a valid header name", name))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| format!("The value of the header `{}` is not valid", name))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+/// Run `cmd` through the shell and use its trimmed stdout as an api key
+pub fn api_key_from_cmd(cmd: &str) -> Option<String> {
+    let output = if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd").args(["/C", cmd]).output()
+    } else {
+        std::process::Command::new("sh").args(["-c", cmd]).output()
+    }
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let key = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+/// `api_key_from_cmd` on a thread of its own, to not block the async tasks while the
+/// command runs
+pub async fn fetch_api_key_from_cmd(cmd: &str) -> Option<String> {
+    let cmd = cmd.to_string();
+    tokio::task::spawn_blocking(move || api_key_from_cmd(&cmd))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Read an api key stored in the system keyring under the `tenere` service
+pub fn api_key_from_keyring(user: &str) -> Option<String> {
+    keyring::Entry::new("tenere", user)
+        .and_then(|entry| entry.get_password())
+        .ok()
+        .filter(|key| !key.trim().is_empty())
 }
 
 // LLamacpp
@@ -69,6 +413,22 @@ impl ChatGPTConfig {
 pub struct LLamacppConfig {
     pub url: String,
     pub api_key: Option<String>,
+    pub api_key_cmd: Option<String>,
+    pub keyring: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub stop: Option<Vec<String>>,
+}
+
+impl LLamacppConfig {
+    /// The api key from the command, the keyring, the environment or the config, in this order
+    pub fn api_key(&self) -> Option<String> {
+        self.api_key_cmd
+            .as_deref()
+            .and_then(api_key_from_cmd)
+            .or_else(|| self.keyring.as_deref().and_then(api_key_from_keyring))
+            .or_else(|| std::env::var("LLAMACPP_API_KEY").ok())
+            .or(self.api_key.clone())
+    }
 }
 
 // Ollama
@@ -77,80 +437,1259 @@ pub struct LLamacppConfig {
 pub struct OllamaConfig {
     pub url: String,
     pub model: String,
+    pub max_tokens: Option<u32>,
+    pub stop: Option<Vec<String>>,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct KeyBindings {
-    #[serde(default = "KeyBindings::default_show_help")]
-    pub show_help: char,
+// Mock
 
-    #[serde(default = "KeyBindings::default_show_history")]
-    pub show_history: char,
+#[derive(Deserialize, Debug, Clone)]
+pub struct MockConfig {
+    /// The file of the answers, a demo answer is streamed without it
+    pub script: Option<String>,
 
-    #[serde(default = "KeyBindings::default_new_chat")]
-    pub new_chat: char,
+    /// The delay between two tokens, in milliseconds
+    #[serde(default = "MockConfig::default_delay")]
+    pub delay: u64,
 
-    #[serde(default = "KeyBindings::default_save_chat")]
-    pub save_chat: char,
+    #[serde(default = "MockConfig::default_model")]
+    pub model: String,
+}
 
-    #[serde(default = "KeyBindings::default_stop_stream")]
-    pub stop_stream: char,
+impl MockConfig {
+    pub fn default_delay() -> u64 {
+        30
+    }
+
+    pub fn default_model() -> String {
+        String::from("mock")
+    }
 }
 
-impl Default for KeyBindings {
+// Network
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NetworkConfig {
+    #[serde(default = "NetworkConfig::default_timeout")]
+    pub timeout: u64,
+
+    #[serde(default = "NetworkConfig::default_max_retries")]
+    pub max_retries: u32,
+
+    pub proxy: Option<String>,
+
+    pub ca_bundle: Option<String>,
+}
+
+impl Default for NetworkConfig {
     fn default() -> Self {
         Self {
-            show_help: '?',
-            show_history: 'h',
-            new_chat: 'n',
-            save_chat: 's',
-            stop_stream: 't',
+            timeout: Self::default_timeout(),
+            max_retries: Self::default_max_retries(),
+            proxy: None,
+            ca_bundle: None,
         }
     }
 }
 
-impl KeyBindings {
-    fn default_show_help() -> char {
-        '?'
+impl NetworkConfig {
+    pub fn default_timeout() -> u64 {
+        60
     }
 
-    fn default_show_history() -> char {
-        'h'
+    pub fn default_max_retries() -> u32 {
+        3
     }
+}
 
-    fn default_new_chat() -> char {
-        'n'
+// History
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct HistoryConfig {
+    /// Save the history and restore it on the next start
+    #[serde(default)]
+    pub persist: bool,
+
+    /// Encrypt the saved history with a key derived from a passphrase asked on startup
+    #[serde(default)]
+    pub encrypt: bool,
+}
+
+// Backend auto selection
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AutoSelectConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "AutoSelectConfig::default_interval")]
+    pub interval: u64,
+}
+
+impl Default for AutoSelectConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Self::default_interval(),
+        }
     }
+}
 
-    fn default_save_chat() -> char {
-        's'
+impl AutoSelectConfig {
+    pub fn default_interval() -> u64 {
+        300
     }
+}
 
-    fn default_stop_stream() -> char {
-        't'
+// Parameters of the requests
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct LLMParams {
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop: Option<Vec<String>>,
+    /// How much the reasoning models think: low, medium or high
+    pub reasoning_effort: Option<String>,
+    /// Stop the stream once the answer is longer, checked by tenere
+    pub max_answer_chars: Option<usize>,
+}
+
+impl LLMParams {
+    pub fn default_system_prompt() -> String {
+        String::from("You are a helpful assistant.")
+    }
+
+    /// Override the parameters that are defined in `other`
+    pub fn merge(&mut self, other: &LLMParams) {
+        if other.system_prompt.is_some() {
+            self.system_prompt = other.system_prompt.clone();
+        }
+        if other.temperature.is_some() {
+            self.temperature = other.temperature;
+        }
+        if other.max_tokens.is_some() {
+            self.max_tokens = other.max_tokens;
+        }
+        if other.stop.is_some() {
+            self.stop = other.stop.clone();
+        }
+        if other.reasoning_effort.is_some() {
+            self.reasoning_effort = other.reasoning_effort.clone();
+        }
+        if other.max_answer_chars.is_some() {
+            self.max_answer_chars = other.max_answer_chars;
+        }
     }
 }
 
-impl Config {
-    pub fn load() -> Self {
-        let conf_path = dirs::config_dir()
-            .unwrap()
-            .join("tenere")
-            .join("config.toml");
+// Environment captured by `/env`
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct EnvConfig {
+    /// Commands printing the version of a tool, like `rustc --version`
+    #[serde(default)]
+    pub commands: Vec<String>,
+
+    /// Environment variables to include
+    #[serde(default)]
+    pub variables: Vec<String>,
+}
 
-        let config = std::fs::read_to_string(conf_path).unwrap_or_default();
-        let app_config: Config = toml::from_str(&config).unwrap();
+// Attachments
 
-        if app_config.llm == LLMBackend::LLamacpp && app_config.llamacpp.is_none() {
-            eprintln!("Config for LLamacpp is not provided");
-            std::process::exit(1)
+#[derive(Deserialize, Debug, Clone)]
+pub struct AttachmentsConfig {
+    /// Files bigger than this, in bytes, are truncated
+    #[serde(default = "AttachmentsConfig::default_max_file_size")]
+    pub max_file_size: usize,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size: Self::default_max_file_size(),
         }
+    }
+}
+
+impl AttachmentsConfig {
+    pub fn default_max_file_size() -> usize {
+        100 * 1024
+    }
+}
+
+// Commands proposed by the assistant
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ShellConfig {
+    /// Allow running the shell code blocks of the answers
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+// Code blocks run by an interpreter
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RunnerConfig {
+    /// Allow running the code blocks of the answers
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// In seconds, the program is killed after it, 0 to let it run
+    #[serde(default = "RunnerConfig::default_timeout")]
+    pub timeout: u64,
+
+    /// The command running the code of each language, given the path of a file with the code
+    #[serde(default = "RunnerConfig::default_interpreters")]
+    pub interpreters: HashMap<String, String>,
 
-        if app_config.llm == LLMBackend::Ollama && app_config.ollama.is_none() {
-            eprintln!("Config for Ollama is not provided");
-            std::process::exit(1)
+    /// More environment variables passed to the programs
+    #[serde(default)]
+    pub env: Vec<String>,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: Self::default_timeout(),
+            interpreters: Self::default_interpreters(),
+            env: Vec::new(),
         }
+    }
+}
+
+impl RunnerConfig {
+    pub fn default_timeout() -> u64 {
+        30
+    }
+
+    pub fn default_interpreters() -> HashMap<String, String> {
+        HashMap::from([
+            (String::from("python"), String::from("python3")),
+            (String::from("bash"), String::from("bash")),
+            (String::from("rust"), String::from("rust-script")),
+        ])
+    }
+}
+
+// Commands run on the messages
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookRole {
+    #[default]
+    Assistant,
+    User,
+    Any,
+}
+
+/// A regex of the config, compiled once when the config is read
+fn deserialize_pattern<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let Some(pattern) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    Regex::new(&pattern).map(Some).map_err(|e| {
+        serde::de::Error::custom(format!("the pattern `{}` is not valid: {}", pattern, e))
+    })
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct HookConfig {
+    /// Run by the shell, the message is written to its standard input
+    pub command: String,
+
+    /// The messages of this role run the command, the answers by default
+    #[serde(default)]
+    pub role: HookRole,
+
+    /// A regex the message has to match to run the command
+    #[serde(default, deserialize_with = "deserialize_pattern")]
+    pub pattern: Option<Regex>,
+
+    /// In seconds, the command is killed after it, 0 to let it run
+    #[serde(default = "HookConfig::default_timeout")]
+    pub timeout: u64,
+}
+
+impl HookConfig {
+    pub fn default_timeout() -> u64 {
+        60
+    }
+}
+
+// Change the prompts before they are sent
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transformer {
+    /// Remove the blanks around the prompt and at the end of its lines
+    Trim,
+    /// Attach the text of the clipboard
+    Clipboard,
+    /// Translate the prompt with the LLM
+    Translate,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PreSendConfig {
+    /// Run by the shell with the prompt on its standard input, its output replaces the prompt.
+    /// The prompt is not sent if it exits with an error.
+    pub command: Option<String>,
+
+    /// A transformer of tenere, instead of a command
+    pub builtin: Option<Transformer>,
+
+    /// The language the prompt is translated into
+    pub language: Option<String>,
+
+    /// A regex the prompt has to match to be changed
+    #[serde(default, deserialize_with = "deserialize_pattern")]
+    pub pattern: Option<Regex>,
+}
+
+// Cache of the answers
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// How long an answer is kept, in seconds. 0 keeps it forever
+    #[serde(default = "CacheConfig::default_ttl")]
+    pub ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: Self::default_ttl(),
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn default_ttl() -> u64 {
+        86400
+    }
+}
+
+// Retrieval over local files
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RagConfig {
+    /// The backend computing the embeddings, `chatgpt` or `ollama`
+    #[serde(default = "default_llm_backend")]
+    pub backend: LLMBackend,
+
+    /// The embedding model, depends on the backend by default
+    pub model: Option<String>,
+
+    /// Number of chunks added to the question
+    #[serde(default = "RagConfig::default_top_k")]
+    pub top_k: usize,
+
+    /// Number of lines of the chunks the files are split into
+    #[serde(default = "RagConfig::default_chunk_lines")]
+    pub chunk_lines: usize,
+}
+
+impl RagConfig {
+    pub fn default_top_k() -> usize {
+        4
+    }
+
+    pub fn default_chunk_lines() -> usize {
+        40
+    }
+
+    pub fn model(&self) -> String {
+        self.model.clone().unwrap_or_else(|| match self.backend {
+            LLMBackend::Ollama => String::from("nomic-embed-text"),
+            _ => String::from("text-embedding-3-small"),
+        })
+    }
+}
+
+// Voice input
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct VoiceConfig {
+    /// Records the microphone to `{file}` until it is interrupted
+    #[serde(default = "VoiceConfig::default_record_cmd")]
+    pub record_cmd: String,
+
+    /// Prints the transcript of `{file}`, the OpenAI API is used otherwise
+    pub transcribe_cmd: Option<String>,
+
+    #[serde(default = "VoiceConfig::default_model")]
+    pub model: String,
+
+    pub language: Option<String>,
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            record_cmd: Self::default_record_cmd(),
+            transcribe_cmd: None,
+            model: Self::default_model(),
+            language: None,
+        }
+    }
+}
+
+impl VoiceConfig {
+    pub fn default_record_cmd() -> String {
+        if cfg!(target_os = "linux") {
+            String::from("arecord -q -f S16_LE -r 16000 -c 1 {file}")
+        } else {
+            String::from("rec -q -r 16000 -c 1 {file}")
+        }
+    }
+
+    pub fn default_model() -> String {
+        String::from("whisper-1")
+    }
+}
+
+// Alert when an answer ends
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlertConfig {
+    /// Needs tenere built with `--features desktop-notification`
+    #[serde(default)]
+    pub desktop: bool,
+
+    #[serde(default)]
+    pub bell: bool,
+
+    /// In seconds, the quick answers are not worth an alert
+    #[serde(default = "AlertConfig::default_min_duration")]
+    pub min_duration: f64,
+
+    /// Only alert when the terminal is not focused, if it reports the focus changes
+    #[serde(default = "AlertConfig::default_unfocused_only")]
+    pub unfocused_only: bool,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            desktop: false,
+            bell: false,
+            min_duration: Self::default_min_duration(),
+            unfocused_only: Self::default_unfocused_only(),
+        }
+    }
+}
+
+impl AlertConfig {
+    pub fn default_min_duration() -> f64 {
+        10.0
+    }
+
+    pub fn default_unfocused_only() -> bool {
+        true
+    }
+}
+
+// Spellcheck
+
+/// The spelling of the prompt, checked against word lists
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpellcheckConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Word lists, one word per line, or hunspell `.dic` files. The ones that exist are read
+    #[serde(default = "SpellcheckConfig::default_dictionaries")]
+    pub dictionaries: Vec<String>,
+
+    /// Words to accept, like names or jargon
+    #[serde(default)]
+    pub words: Vec<String>,
+}
+
+impl Default for SpellcheckConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dictionaries: Self::default_dictionaries(),
+            words: Vec::new(),
+        }
+    }
+}
+
+impl SpellcheckConfig {
+    pub fn default_dictionaries() -> Vec<String> {
+        vec![
+            String::from("/usr/share/hunspell/en_US.dic"),
+            String::from("/usr/share/dict/words"),
+        ]
+    }
+}
+
+// Clipboard
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ClipboardConfig {
+    /// Tried in this order until one of them works
+    #[serde(default = "ClipboardConfig::default_providers")]
+    pub providers: Vec<ClipboardProvider>,
+
+    /// Receives the text on its standard input, like `wl-copy`
+    pub copy_command: Option<String>,
+
+    /// Writes the text on its standard output, like `wl-paste --no-newline`
+    pub paste_command: Option<String>,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            providers: Self::default_providers(),
+            copy_command: None,
+            paste_command: None,
+        }
+    }
+}
+
+impl ClipboardConfig {
+    pub fn default_providers() -> Vec<ClipboardProvider> {
+        vec![
+            ClipboardProvider::Arboard,
+            ClipboardProvider::Osc52,
+            ClipboardProvider::Command,
+        ]
+    }
+}
+
+// Profiles
+
+/// A named set of settings applied on top of the config
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Profile {
+    pub llm: Option<LLMBackend>,
+
+    pub model: Option<String>,
+
+    #[serde(flatten)]
+    pub params: LLMParams,
+}
+
+// Project
+
+/// The settings of a project, read from the closest `.tenere.toml` to the current directory.
+/// The fields of a profile are listed, `flatten` would not let the unknown ones be refused.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// A profile applied before the settings of the project
+    pub profile: Option<String>,
+
+    /// Added to the profiles of the config, replacing the ones of the same name
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// Added to the templates of the config, replacing the ones of the same name
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+
+    pub llm: Option<LLMBackend>,
+    pub model: Option<String>,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop: Option<Vec<String>>,
+    pub reasoning_effort: Option<String>,
+    pub max_answer_chars: Option<usize>,
+}
+
+impl ProjectConfig {
+    pub const FILE_NAME: &'static str = ".tenere.toml";
+
+    /// The `.tenere.toml` of the current directory or of the closest of its parents
+    pub fn find() -> Option<PathBuf> {
+        let dir = std::env::current_dir().ok()?;
+        dir.ancestors()
+            .map(|dir| dir.join(Self::FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Can not read `{}`: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("Invalid `{}`: {}", path.display(), e))
+    }
+
+    /// The settings applied on top of the config, like the ones of a profile
+    pub fn settings(&self) -> Profile {
+        Profile {
+            llm: self.llm,
+            model: self.model.clone(),
+            params: LLMParams {
+                system_prompt: self.system_prompt.clone(),
+                temperature: self.temperature,
+                max_tokens: self.max_tokens,
+                stop: self.stop.clone(),
+                reasoning_effort: self.reasoning_effort.clone(),
+                max_answer_chars: self.max_answer_chars,
+            },
+        }
+    }
+}
+
+// Redaction
+
+/// Rules applied to the messages before they are sent, to keep the secrets out of the requests
+#[derive(Deserialize, Debug, Clone)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Apply the built-in rules: api keys, tokens, passwords, emails and ip addresses
+    #[serde(default = "RedactionConfig::default_builtin_rules")]
+    pub builtin_rules: bool,
+
+    #[serde(default)]
+    pub rules: Vec<RedactionRuleConfig>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            builtin_rules: Self::default_builtin_rules(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl RedactionConfig {
+    pub fn default_builtin_rules() -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RedactionRuleConfig {
+    pub name: String,
+    pub pattern: String,
+    /// Replaces the matches, `[redacted NAME]` by default
+    pub placeholder: Option<String>,
+}
+
+// Share
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ShareConfig {
+    pub slack: Option<SlackConfig>,
+    pub matrix: Option<MatrixConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SlackConfig {
+    pub webhook_url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub room_id: String,
+    pub access_token: Option<String>,
+    pub access_token_cmd: Option<String>,
+}
+
+// Theme
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ThemeConfig {
+    /// The theme used until another one is picked with the key binding
+    pub name: Option<String>,
+
+    #[serde(default)]
+    pub colors: ThemeColors,
+}
+
+/// Colors replacing the ones of the theme, as names like `cyan`, indexes or `#rrggbb`
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ThemeColors {
+    pub focused_border: Option<String>,
+    pub visual_border: Option<String>,
+    pub selection: Option<String>,
+    pub info: Option<String>,
+    pub warning: Option<String>,
+    pub error: Option<String>,
+    pub border: Option<String>,
+    pub muted: Option<String>,
+    pub user: Option<String>,
+    pub assistant: Option<String>,
+}
+
+impl ThemeColors {
+    fn check(&self) -> Result<(), String> {
+        Theme::default().with_overrides(self).map(|_| ())
+    }
+}
+
+// Authors
+
+/// How the authors of the messages are shown, their colors are the ones of the theme
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuthorsConfig {
+    #[serde(default = "AuthorsConfig::default_user_prefix")]
+    pub user_prefix: String,
+
+    #[serde(default = "AuthorsConfig::default_assistant_prefix")]
+    pub assistant_prefix: String,
+
+    /// Shown after the prefix
+    pub user_name: Option<String>,
+    pub assistant_name: Option<String>,
+}
+
+impl Default for AuthorsConfig {
+    fn default() -> Self {
+        Self {
+            user_prefix: Self::default_user_prefix(),
+            assistant_prefix: Self::default_assistant_prefix(),
+            user_name: None,
+            assistant_name: None,
+        }
+    }
+}
+
+impl AuthorsConfig {
+    pub fn default_user_prefix() -> String {
+        String::from("👤")
+    }
+
+    pub fn default_assistant_prefix() -> String {
+        String::from("🤖")
+    }
+}
+
+// Prompt
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptPosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PromptConfig {
+    #[serde(default)]
+    pub position: PromptPosition,
+
+    /// The height the prompt grows up to, in percent of the screen
+    #[serde(default = "PromptConfig::default_max_height")]
+    pub max_height: u16,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            position: PromptPosition::default(),
+            max_height: Self::default_max_height(),
+        }
+    }
+}
+
+impl PromptConfig {
+    pub fn default_max_height() -> u16 {
+        40
+    }
+}
+
+// Context
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ContextConfig {
+    #[serde(default)]
+    pub strategy: ContextStrategy,
+
+    #[serde(default = "ContextConfig::default_threshold")]
+    pub threshold: f32,
+
+    #[serde(default = "ContextConfig::default_size")]
+    pub default_size: usize,
+
+    #[serde(default)]
+    pub sizes: HashMap<String, usize>,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ContextStrategy::default(),
+            threshold: Self::default_threshold(),
+            default_size: Self::default_size(),
+            sizes: HashMap::new(),
+        }
+    }
+}
+
+impl ContextConfig {
+    pub fn default_threshold() -> f32 {
+        0.8
+    }
+
+    pub fn default_size() -> usize {
+        4096
+    }
+
+    /// Context size of the model in tokens
+    pub fn size(&self, model: &str) -> usize {
+        if let Some(size) = self.sizes.get(model) {
+            return *size;
+        }
+
+        match model {
+            m if m.starts_with("gpt-4o") || m.starts_with("gpt-4-turbo") => 128000,
+            m if m.starts_with("gpt-4-32k") => 32768,
+            m if m.starts_with("gpt-4") => 8192,
+            m if m.starts_with("gpt-3.5-turbo") => 16385,
+            _ => self.default_size,
+        }
+    }
+}
+
+// Prices
+
+/// Dollars per million tokens
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Price {
+    #[serde(default)]
+    pub prompt: f64,
+
+    #[serde(default)]
+    pub completion: f64,
+}
+
+impl Price {
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.prompt + completion_tokens as f64 * self.completion)
+            / 1_000_000.0
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct KeyBindings {
+    #[serde(default = "KeyBindings::default_show_help")]
+    pub show_help: char,
+
+    #[serde(default = "KeyBindings::default_show_history")]
+    pub show_history: char,
+
+    #[serde(default = "KeyBindings::default_new_chat")]
+    pub new_chat: char,
+
+    #[serde(default = "KeyBindings::default_save_chat")]
+    pub save_chat: char,
+
+    #[serde(default = "KeyBindings::default_stop_stream")]
+    pub stop_stream: char,
+
+    #[serde(default = "KeyBindings::default_copy_chat")]
+    pub copy_chat: char,
+
+    #[serde(default = "KeyBindings::default_summarize_chat")]
+    pub summarize_chat: char,
+
+    #[serde(default = "KeyBindings::default_cycle_theme")]
+    pub cycle_theme: char,
+
+    #[serde(default = "KeyBindings::default_switch_backend")]
+    pub switch_backend: char,
+
+    #[serde(default = "KeyBindings::default_branch_chat")]
+    pub branch_chat: char,
+
+    #[serde(default = "KeyBindings::default_share")]
+    pub share: char,
+
+    #[serde(default = "KeyBindings::default_switch_profile")]
+    pub switch_profile: char,
+
+    #[serde(default = "KeyBindings::default_replay")]
+    pub replay: char,
+
+    #[serde(default = "KeyBindings::default_voice")]
+    pub voice: char,
+
+    #[serde(default = "KeyBindings::default_toggle_layout")]
+    pub toggle_layout: char,
+
+    #[serde(default = "KeyBindings::default_external_editor")]
+    pub external_editor: char,
+
+    #[serde(default = "KeyBindings::default_chat_info")]
+    pub chat_info: char,
+
+    #[serde(default = "KeyBindings::default_spellcheck")]
+    pub spellcheck: char,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            show_help: '?',
+            show_history: 'h',
+            new_chat: 'n',
+            save_chat: 's',
+            stop_stream: 't',
+            copy_chat: 'y',
+            summarize_chat: 'f',
+            cycle_theme: 'l',
+            switch_backend: 'b',
+            branch_chat: 'k',
+            share: 'u',
+            switch_profile: 'o',
+            replay: 'e',
+            voice: 'v',
+            toggle_layout: 'w',
+            external_editor: 'x',
+            chat_info: 'g',
+            spellcheck: 'p',
+        }
+    }
+}
+
+impl KeyBindings {
+    fn default_show_help() -> char {
+        '?'
+    }
+
+    fn default_show_history() -> char {
+        'h'
+    }
+
+    fn default_new_chat() -> char {
+        'n'
+    }
+
+    fn default_save_chat() -> char {
+        's'
+    }
+
+    fn default_stop_stream() -> char {
+        't'
+    }
+
+    fn default_copy_chat() -> char {
+        'y'
+    }
+
+    fn default_summarize_chat() -> char {
+        'f'
+    }
+
+    fn default_cycle_theme() -> char {
+        'l'
+    }
+
+    fn default_switch_backend() -> char {
+        'b'
+    }
+
+    fn default_branch_chat() -> char {
+        'k'
+    }
+
+    fn default_share() -> char {
+        'u'
+    }
+
+    fn default_switch_profile() -> char {
+        'o'
+    }
+
+    fn default_toggle_layout() -> char {
+        'w'
+    }
+
+    fn default_voice() -> char {
+        'v'
+    }
+
+    fn default_replay() -> char {
+        'e'
+    }
+
+    fn default_external_editor() -> char {
+        'x'
+    }
+
+    fn default_chat_info() -> char {
+        'g'
+    }
+
+    fn default_spellcheck() -> char {
+        'p'
+    }
+}
+
+impl Config {
+    pub fn model_name(&self) -> String {
+        self.backend_model_name(&self.llm)
+    }
+
+    pub fn backend_model_name(&self, backend: &LLMBackend) -> String {
+        match backend {
+            LLMBackend::ChatGPT => self.chatgpt.model.clone(),
+            LLMBackend::LLamacpp => String::from("llama.cpp"),
+            LLMBackend::Ollama => self
+                .ollama
+                .as_ref()
+                .map(|ollama| ollama.model.clone())
+                .unwrap_or_default(),
+            LLMBackend::Mock => self
+                .mock
+                .as_ref()
+                .map(|mock| mock.model.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The models named in the config, with where they come from, for the completion of `:model`
+    pub fn known_models(&self) -> Vec<(String, String)> {
+        let mut models = vec![(self.chatgpt.model.clone(), LLMBackend::ChatGPT.to_string())];
+        if let Some(ollama) = &self.ollama {
+            models.push((ollama.model.clone(), LLMBackend::Ollama.to_string()));
+        }
+        for (name, profile) in &self.profiles {
+            if let Some(model) = &profile.model {
+                models.push((model.clone(), format!("profile {}", name)));
+            }
+        }
+        let mut priced: Vec<&String> = self.prices.keys().collect();
+        priced.sort();
+        for model in priced {
+            models.push((model.clone(), String::from("prices")));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        models.retain(|(model, _)| seen.insert(model.clone()));
+        models
+    }
+
+    /// The parameters of the requests, with the limits set in the section of the backend
+    pub fn backend_params(&self, backend: &LLMBackend) -> LLMParams {
+        let (max_tokens, stop) = match backend {
+            LLMBackend::ChatGPT => (self.chatgpt.max_tokens, self.chatgpt.stop.clone()),
+            LLMBackend::LLamacpp => self
+                .llamacpp
+                .as_ref()
+                .map(|c| (c.max_tokens, c.stop.clone()))
+                .unwrap_or_default(),
+            LLMBackend::Ollama => self
+                .ollama
+                .as_ref()
+                .map(|c| (c.max_tokens, c.stop.clone()))
+                .unwrap_or_default(),
+            LLMBackend::Mock => (None, None),
+        };
+
+        let mut params = self.params.clone();
+        params.merge(&LLMParams {
+            max_tokens,
+            stop,
+            ..LLMParams::default()
+        });
+        params
+    }
+
+    /// The backends that can be used with this config
+    pub fn backends(&self) -> Vec<LLMBackend> {
+        let mut backends = Vec::new();
+        if self.chatgpt.has_credentials() {
+            backends.push(LLMBackend::ChatGPT);
+        }
+        if self.llamacpp.is_some() {
+            backends.push(LLMBackend::LLamacpp);
+        }
+        if self.ollama.is_some() {
+            backends.push(LLMBackend::Ollama);
+        }
+        if self.mock.is_some() {
+            backends.push(LLMBackend::Mock);
+        }
+        backends
+    }
+
+    pub fn backend_url(&self, backend: &LLMBackend) -> Option<String> {
+        match backend {
+            LLMBackend::ChatGPT => Some(self.chatgpt.url.clone()),
+            LLMBackend::LLamacpp => self.llamacpp.as_ref().map(|c| c.url.clone()),
+            LLMBackend::Ollama => self.ollama.as_ref().map(|c| c.url.clone()),
+            LLMBackend::Mock => None,
+        }
+    }
+
+    /// Use another model for the selected backend
+    pub fn set_model(&mut self, model: &str) -> Result<(), String> {
+        match self.llm {
+            LLMBackend::ChatGPT => self.chatgpt.model = model.to_string(),
+            LLMBackend::LLamacpp => {
+                return Err(String::from(
+                    "The model of llama.cpp is chosen when starting the server",
+                ))
+            }
+            LLMBackend::Ollama => {
+                if let Some(ollama) = self.ollama.as_mut() {
+                    ollama.model = model.to_string();
+                }
+            }
+            LLMBackend::Mock => {
+                if let Some(mock) = self.mock.as_mut() {
+                    mock.model = model.to_string();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("The profile `{}` is not defined", name))?;
+
+        self.apply_settings(&profile)?;
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    fn apply_settings(&mut self, settings: &Profile) -> Result<(), String> {
+        if let Some(llm) = settings.llm {
+            self.llm = llm;
+        }
+
+        if let Some(model) = &settings.model {
+            self.set_model(model)?;
+        }
+
+        self.params.merge(&settings.params);
+        Ok(())
+    }
+
+    /// Apply the settings of a `.tenere.toml`, after the profile it names
+    pub fn apply_project(&mut self, path: &Path) -> Result<(), String> {
+        let project = ProjectConfig::load(path)?;
+        self.profiles.extend(project.profiles.clone());
+        self.templates.extend(project.templates.clone());
+
+        if let Some(profile) = &project.profile {
+            self.apply_profile(profile)?;
+        }
+        self.apply_settings(&project.settings())?;
+
+        self.project = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Read the config file again and apply a profile to it, after the project settings
+    pub fn with_profile(&self, name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = Self::load(self.path.clone())?;
+        if let Some(path) = &self.project {
+            config.apply_project(path)?;
+        }
+        config.apply_profile(name)?;
+        config.check()?;
+        Ok(config)
+    }
+
+    /// `config.toml` in the tenere config directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tenere").join("config.toml"))
+    }
+
+    /// Load the config file, `path` defaults to `config.toml` in the tenere config directory
+    pub fn load(path: Option<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = match &path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Can not read the config file `{}`: {}", path.display(), e))?,
+            None => Self::default_path()
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .unwrap_or_default(),
+        };
+
+        let mut config: Config = toml::from_str(&config)?;
+        config.path = path;
+        Ok(config)
+    }
+
+    /// Fail if the selected backend is not configured
+    pub fn check(&self) -> Result<(), String> {
+        if self.llm == LLMBackend::LLamacpp && self.llamacpp.is_none() {
+            return Err(String::from("Config for LLamacpp is not provided"));
+        }
+
+        if self.llm == LLMBackend::Ollama && self.ollama.is_none() {
+            return Err(String::from("Config for Ollama is not provided"));
+        }
+
+        if self.llm == LLMBackend::Mock && self.mock.is_none() {
+            return Err(String::from("Config for Mock is not provided"));
+        }
+
+        if let Some(name) = &self.theme.name {
+            if Theme::from_name(name).is_none() {
+                return Err(format!("`{}` is not a known theme", name));
+            }
+        }
+        self.theme.colors.check()?;
+        self.chatgpt.headers()?;
+        crate::redaction::outgoing_rules(&self.redaction)?;
+        for hook in &self.pre_send {
+            match (&hook.command, hook.builtin) {
+                (Some(_), None) => {}
+                (None, Some(Transformer::Translate)) if hook.language.is_none() => {
+                    return Err(String::from(
+                        "The `translate` pre-send hook needs a `language`",
+                    ))
+                }
+                (None, Some(_)) => {}
+                _ => {
+                    return Err(String::from(
+                        "Each pre-send hook needs either a `command` or a `builtin`",
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_settings() {
+        let project: ProjectConfig = toml::from_str(
+            "profile = \"work\"\nmodel = \"gpt-4o\"\ntemperature = 0.2\n\n\
+            [templates]\nreview = \"Review this code:\"\n",
+        )
+        .unwrap();
+        assert_eq!(project.profile.as_deref(), Some("work"));
+        assert_eq!(project.settings().model.as_deref(), Some("gpt-4o"));
+        assert_eq!(project.settings().params.temperature, Some(0.2));
+        assert_eq!(project.templates["review"], "Review this code:");
 
-        app_config
+        // A typo is not silently ignored
+        let error = toml::from_str::<ProjectConfig>("sytem_prompt = \"Be brief\"\n").unwrap_err();
+        assert!(error.to_string().contains("unknown field `sytem_prompt`"));
     }
 }
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    config::ContextConfig,
+    event::Event,
+    llm::{message, LLMRole, LLM},
+    notification::{Notification, NotificationLevel},
+};
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextStrategy {
+    #[default]
+    Truncate,
+    Summarize,
+}
+
+/// The characters of a text, counted as it grows
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharCount {
+    pub ascii: usize,
+    pub others: usize,
+}
+
+impl CharCount {
+    pub fn of(text: &str) -> Self {
+        let mut count = Self::default();
+        count.add(text);
+        count
+    }
+
+    pub fn add(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_ascii() {
+                self.ascii += 1;
+            } else {
+                self.others += 1;
+            }
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.ascii + self.others
+    }
+
+    /// Rough token estimation: about 4 ascii chars per token, one token per other char
+    pub fn tokens(&self) -> usize {
+        self.ascii.div_ceil(4) + self.others
+    }
+}
+
+pub fn estimate_tokens(text: &str) -> usize {
+    CharCount::of(text).tokens()
+}
+
+pub fn estimate_messages_tokens(messages: &[HashMap<String, String>]) -> usize {
+    messages
+        .iter()
+        .map(|msg| 4 + estimate_tokens(msg.get("content").map(|c| c.as_str()).unwrap_or_default()))
+        .sum()
+}
+
+fn is_system(msg: &HashMap<String, String>) -> bool {
+    msg.get("role").map(|r| r.as_str()) == Some("system")
+}
+
+fn truncate(
+    mut messages: Vec<HashMap<String, String>>,
+    limit: usize,
+) -> Vec<HashMap<String, String>> {
+    while estimate_messages_tokens(&messages) > limit {
+        // Keep the system messages and the last message
+        match messages[..messages.len().saturating_sub(1)]
+            .iter()
+            .position(|msg| !is_system(msg))
+        {
+            Some(i) => {
+                messages.remove(i);
+            }
+            None => break,
+        }
+    }
+    messages
+}
+
+/// Ask the LLM to summarize the messages
+pub async fn summarize(
+    llm: &dyn LLM,
+    messages: &[HashMap<String, String>],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut request = messages.to_vec();
+    request.push(message(
+        LLMRole::USER,
+        "Summarize the conversation above in a few paragraphs. Keep every fact, decision and piece of code that may be needed to continue it.",
+    ));
+
+    llm.complete(request).await
+}
+
+/// The system message replacing the summarized messages
+pub fn summary_message(summary: &str) -> HashMap<String, String> {
+    message(
+        LLMRole::SYSTEM,
+        &format!("Summary of the earlier conversation: {}", summary),
+    )
+}
+
+async fn summarize_oldest(
+    llm: &dyn LLM,
+    messages: Vec<HashMap<String, String>>,
+) -> Result<Vec<HashMap<String, String>>, Box<dyn std::error::Error>> {
+    // Keep the system messages and the last exchange as they are
+    let (mut system, others): (Vec<_>, Vec<_>) = messages.into_iter().partition(is_system);
+    let (old, recent) = others.split_at(others.len().saturating_sub(2));
+
+    if !old.is_empty() {
+        let summary = summarize(llm, old).await?;
+        system.push(summary_message(&summary));
+    }
+    system.extend(recent.to_vec());
+
+    Ok(system)
+}
+
+/// Shrink the conversation kept by the LLM when it gets close to the context size of the model
+pub async fn fit(
+    llm: &mut Box<dyn LLM>,
+    config: &ContextConfig,
+    model: &str,
+    sender: &UnboundedSender<Event>,
+) {
+    let messages = llm.messages();
+    let limit = (config.size(model) as f32 * config.threshold) as usize;
+
+    if estimate_messages_tokens(&messages) <= limit {
+        return;
+    }
+
+    let (messages, msg) = match config.strategy {
+        ContextStrategy::Truncate => (
+            truncate(messages, limit),
+            "Oldest messages were dropped to fit the context window",
+        ),
+        ContextStrategy::Summarize => {
+            match summarize_oldest(llm.as_ref(), messages.clone()).await {
+                Ok(messages) => (
+                    truncate(messages, limit),
+                    "Oldest messages were summarized to fit the context window",
+                ),
+                Err(_) => (
+                    truncate(messages, limit),
+                    "Summarization failed, oldest messages were dropped to fit the context window",
+                ),
+            }
+        }
+    };
+
+    llm.set_messages(messages);
+
+    let notif = Notification::new(msg.to_string(), NotificationLevel::Info);
+    sender.send(Event::Notification(notif)).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Vec<HashMap<String, String>> {
+        vec![
+            message(LLMRole::SYSTEM, "You are a helpful assistant."),
+            message(LLMRole::USER, &"question ".repeat(100)),
+            message(LLMRole::ASSISTANT, &"answer ".repeat(100)),
+            message(LLMRole::USER, "Last question"),
+            message(LLMRole::ASSISTANT, "Last answer"),
+        ]
+    }
+
+    fn contents(messages: &[HashMap<String, String>]) -> Vec<&str> {
+        messages.iter().map(|msg| msg["content"].as_str()).collect()
+    }
+
+    #[test]
+    fn truncate_keeps_the_system_and_last_messages() {
+        let messages = conversation();
+
+        assert_eq!(truncate(messages.clone(), 1000), messages);
+        assert_eq!(
+            contents(&truncate(messages.clone(), 50)),
+            [
+                "You are a helpful assistant.",
+                "Last question",
+                "Last answer"
+            ]
+        );
+        // Not under the limit, but the system and last messages are kept
+        assert_eq!(
+            contents(&truncate(messages, 1)),
+            ["You are a helpful assistant.", "Last answer"]
+        );
+    }
+
+    #[tokio::test]
+    async fn fit_summarizes_the_oldest_messages_only() {
+        let config: ContextConfig =
+            toml::from_str("strategy = \"summarize\"\nthreshold = 1.0\ndefault_size = 200\n")
+                .unwrap();
+        let mut llm: Box<dyn LLM> = Box::new(crate::mock::Mock::new(
+            toml::from_str("delay = 0").unwrap(),
+            Default::default(),
+        ));
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        // Under the limit, kept as it is
+        llm.set_messages(conversation()[3..].to_vec());
+        fit(&mut llm, &config, "model", &sender).await;
+        assert_eq!(llm.messages(), conversation()[3..]);
+        assert!(receiver.try_recv().is_err());
+
+        llm.set_messages(conversation());
+        fit(&mut llm, &config, "model", &sender).await;
+        let messages = llm.messages();
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0], conversation()[0]);
+        assert_eq!(messages[1]["role"], "system");
+        assert!(messages[1]["content"].starts_with("Summary of the earlier conversation: "));
+        assert_eq!(messages[2..], conversation()[3..]);
+        assert!(matches!(receiver.try_recv(), Ok(Event::Notification(_))));
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::event::Event;
+
+/// A command received on the control socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlCommand {
+    /// Ask a question, like if it was written in the prompt
+    Send(String),
+    New,
+    /// Save the chat, to the archive file if no path is given
+    Export(Option<String>),
+    Quit,
+}
+
+impl ControlCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+
+        match name {
+            "send" if args.is_empty() => Err(String::from("Usage: send TEXT")),
+            "send" => Ok(ControlCommand::Send(args.replace("\\n", "\n"))),
+            "new" => Ok(ControlCommand::New),
+            "export" => Ok(ControlCommand::Export(
+                (!args.is_empty()).then(|| args.to_string()),
+            )),
+            "quit" => Ok(ControlCommand::Quit),
+            "" => Err(String::from("No command")),
+            _ => Err(format!("Not a command: {}", name)),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/tenere.sock`, without a runtime directory in a `tenere-UID` directory
+/// of the temporary one
+pub fn path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(|| {
+            #[cfg(unix)]
+            let user = unsafe { libc::getuid() };
+            #[cfg(not(unix))]
+            let user = 0;
+            std::env::temp_dir().join(format!("tenere-{}", user))
+        })
+        .join("tenere.sock")
+}
+
+/// Create the directory only accessible by the user, or check that it still is.
+/// The temporary directory is shared by all the users, one of them could have created it.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+            return Err(format!("Can not create {}: {}", dir.display(), e))
+        }
+        _ => {}
+    }
+
+    let metadata = std::fs::symlink_metadata(dir).map_err(|e| e.to_string())?;
+    if !metadata.is_dir()
+        || metadata.uid() != unsafe { libc::getuid() }
+        || metadata.mode() & 0o077 != 0
+    {
+        return Err(format!(
+            "{} is not a directory only accessible by you",
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Listen on the control socket, each line received is forwarded as a command.
+/// The client gets `ok` back once the command is queued, the result is shown in tenere.
+#[cfg(unix)]
+pub fn spawn(sender: UnboundedSender<Event>) -> Result<(), String> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixListener;
+
+    let path = path();
+    // Bound in a private directory, the socket can not be reached by the other users.
+    // The runtime directory is already private.
+    if dirs::runtime_dir().is_none() {
+        if let Some(dir) = path.parent() {
+            private_dir(dir)?;
+        }
+    }
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(format!(
+                "The control socket {} is used by another tenere",
+                path.display()
+            ));
+        }
+        // Left by a tenere that did not exit cleanly
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    }
+
+    let listener = UnixListener::bind(&path)
+        .map_err(|e| format!("Can not create the control socket: {}", e))?;
+    tracing::info!(path = %path.display(), "control socket listening");
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let reply = match ControlCommand::parse(&line) {
+                        Ok(command) => {
+                            tracing::debug!(?command, "control command");
+                            if sender.send(Event::Control(command)).is_err() {
+                                break;
+                            }
+                            String::from("ok\n")
+                        }
+                        Err(e) => format!("error: {}\n", e),
+                    };
+                    if writer.write_all(reply.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn spawn(_sender: UnboundedSender<Event>) -> Result<(), String> {
+    Err(String::from("The control socket is only available on unix"))
+}
+
+/// Remove the socket file on exit
+pub fn remove() {
+    let _ = std::fs::remove_file(path());
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn only_a_private_directory() {
+        let parent = tempfile::tempdir().unwrap();
+        let dir = parent.path().join("tenere");
+
+        private_dir(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        private_dir(&dir).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(private_dir(&dir).is_err());
+
+        let link = parent.path().join("link");
+        std::os::unix::fs::symlink(parent.path(), &link).unwrap();
+        assert!(private_dir(&link).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use ratatui::{
+    layout::{Alignment, Constraint, Rect},
+    style::{Style, Stylize},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, Padding, Row, Table,
+    },
+    Frame,
+};
+
+use crate::{app::FocusedBlock, i18n::t, theme::Theme, timestamp::Timestamp};
+
+/// The sum of the values measured, and how many there are
+#[derive(Default)]
+struct Mean {
+    sum: f64,
+    count: usize,
+}
+
+impl Mean {
+    fn add(&mut self, value: Option<f64>) {
+        if let Some(value) = value {
+            self.sum += value;
+            self.count += 1;
+        }
+    }
+
+    fn value(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+#[derive(Default)]
+struct Latency {
+    answers: usize,
+    first_token: Mean,
+    inter_chunk: Mean,
+    speed: Mean,
+}
+
+/// Pop-up comparing the latency of the models, over the answers of the chat and of the history
+pub struct Diagnostics {
+    rows: Vec<[String; 5]>,
+    pub previous_focus: FocusedBlock,
+}
+
+impl Diagnostics {
+    pub fn new<'a>(
+        timestamps: impl Iterator<Item = &'a Timestamp>,
+        previous_focus: FocusedBlock,
+    ) -> Self {
+        let mut models: BTreeMap<String, Latency> = BTreeMap::new();
+        for timestamp in timestamps.filter(|t| t.duration.is_some()) {
+            let model = timestamp.model.clone().unwrap_or_else(|| String::from("-"));
+            let latency = models.entry(model).or_default();
+            latency.answers += 1;
+            latency.first_token.add(timestamp.first_token);
+            latency.inter_chunk.add(timestamp.inter_chunk);
+            latency.speed.add(timestamp.tokens_per_second());
+        }
+
+        let rows = models
+            .into_iter()
+            .map(|(model, latency)| {
+                [
+                    model,
+                    latency.answers.to_string(),
+                    latency
+                        .first_token
+                        .value()
+                        .map_or(String::from("-"), |s| format!("{:.2}s", s)),
+                    latency
+                        .inter_chunk
+                        .value()
+                        .map_or(String::from("-"), |s| format!("{:.0}ms", s * 1000.0)),
+                    latency
+                        .speed
+                        .value()
+                        .map_or(String::from("-"), |speed| format!("{:.0}", speed)),
+                ]
+            })
+            .collect();
+
+        Self {
+            rows,
+            previous_focus,
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        // As high as the rows, the header and the padding, in the middle of the area
+        let height = area.height.min(self.rows.len().max(1) as u16 + 6);
+        let area = Rect {
+            y: area.y + (area.height - height) / 2,
+            height,
+            ..area
+        };
+
+        let header = Row::new(
+            [
+                "Model",
+                "Answers",
+                "First token",
+                "Between chunks",
+                "Tokens/s",
+            ]
+            .map(|label| t(label).to_string()),
+        )
+        .style(Style::default().bold())
+        .bottom_margin(1);
+
+        let rows: Vec<Row> = if self.rows.is_empty() {
+            vec![Row::new(vec![t("No answer yet").to_string()])]
+        } else {
+            self.rows.iter().map(|row| Row::new(row.clone())).collect()
+        };
+
+        let widths = [
+            Constraint::Min(12),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Length(15),
+            Constraint::Length(10),
+        ];
+        let table = Table::new(rows, widths).header(header).block(
+            Block::default()
+                .padding(Padding::new(2, 2, 1, 1))
+                .title(format!(" {} ", t("Latency of the models")))
+                .title_style(Style::default().bold())
+                .title_alignment(Alignment::Center)
+                .title(
+                    Title::from(format!(" {} ", t("Esc: close")))
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                )
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(theme.focused_border)),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(table, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mean_of_the_values_measured() {
+        let mut mean = Mean::default();
+        assert_eq!(mean.value(), None);
+
+        mean.add(Some(1.0));
+        mean.add(None);
+        mean.add(Some(2.0));
+        assert_eq!(mean.value(), Some(1.5));
+    }
+
+    #[test]
+    fn latency_by_model() {
+        let timestamp = |model: &str, first_token, inter_chunk| Timestamp {
+            duration: Some(2.0),
+            tokens: Some(100),
+            model: Some(model.to_string()),
+            first_token,
+            inter_chunk,
+            ..Timestamp::now(1)
+        };
+        let timestamps = [
+            timestamp("gpt", Some(0.5), Some(0.02)),
+            timestamp("gpt", Some(1.5), None),
+            timestamp("llama", None, Some(0.1)),
+            // A question without its answer
+            Timestamp::now(0),
+        ];
+
+        let diagnostics = Diagnostics::new(timestamps.iter(), FocusedBlock::Prompt);
+
+        assert_eq!(
+            diagnostics.rows,
+            vec![
+                ["gpt", "2", "1.00s", "20ms", "50"].map(String::from),
+                ["llama", "1", "-", "100ms", "50"].map(String::from),
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::{Line, Text},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, Paragraph,
+    },
+    Frame,
+};
+
+use crate::{app::FocusedBlock, i18n::t, theme::Theme};
+
+/// The unchanged lines shown around the changes
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The changes turning the old lines into the new ones, from their longest common subsequence
+fn changes<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Change<'a>> {
+    // lengths[i][j]: the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push(Change::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            changes.push(Change::Removed(old[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j]));
+            j += 1;
+        }
+    }
+    changes
+}
+
+/// A unified diff: the changed lines prefixed with `-` or `+`, with a few lines around them
+/// and a `@@ -start,count +start,count @@` header for each hunk
+pub fn unified(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let changes = changes(&old, &new);
+
+    // Keep the lines close enough to a change
+    let changed: Vec<usize> = changes
+        .iter()
+        .enumerate()
+        .filter(|(_, change)| !matches!(change, Change::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+    let shown = |i: usize| changed.iter().any(|c| c.abs_diff(i) <= CONTEXT_LINES);
+
+    let mut lines = Vec::new();
+    let (mut old_line, mut new_line) = (1, 1);
+    let mut i = 0;
+    while i < changes.len() {
+        if !shown(i) {
+            match changes[i] {
+                Change::Same(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                Change::Removed(_) => old_line += 1,
+                Change::Added(_) => new_line += 1,
+            }
+            i += 1;
+            continue;
+        }
+
+        let end = (i..changes.len())
+            .find(|&j| !shown(j))
+            .unwrap_or(changes.len());
+        let hunk = &changes[i..end];
+        let old_count = hunk
+            .iter()
+            .filter(|c| !matches!(c, Change::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|c| !matches!(c, Change::Removed(_)))
+            .count();
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            old_line, old_count, new_line, new_count
+        ));
+        lines.extend(hunk.iter().map(|change| match change {
+            Change::Same(line) => format!(" {}", line),
+            Change::Removed(line) => format!("-{}", line),
+            Change::Added(line) => format!("+{}", line),
+        }));
+
+        old_line += old_count;
+        new_line += new_count;
+        i = end;
+    }
+    lines
+}
+
+/// Pop-up comparing the answer replaced by a regeneration with the one in the chat
+pub struct AnswerDiff {
+    lines: Vec<String>,
+    scroll: u16,
+    area_height: u16,
+    pub previous_focus: FocusedBlock,
+}
+
+impl AnswerDiff {
+    pub fn new(previous: &str, current: &str, previous_focus: FocusedBlock) -> Self {
+        Self {
+            lines: unified(previous, current),
+            scroll: 0,
+            area_height: 0,
+            previous_focus,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let visible = self.area_height.saturating_sub(2) as usize;
+        if (self.scroll as usize) + visible < self.lines.len() {
+            self.scroll += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        self.area_height = area.height;
+
+        let text = if self.lines.is_empty() {
+            Text::styled(
+                t("The answers are the same"),
+                Style::default().fg(theme.muted),
+            )
+        } else {
+            Text::from(
+                self.lines
+                    .iter()
+                    .map(|line| {
+                        let style = match line.chars().next() {
+                            Some('+') => Style::default().fg(theme.info),
+                            Some('-') => Style::default().fg(theme.error),
+                            Some('@') => Style::default().fg(theme.muted),
+                            _ => Style::default(),
+                        };
+                        Line::styled(line.clone(), style)
+                    })
+                    .collect::<Vec<Line>>(),
+            )
+        };
+
+        let paragraph = Paragraph::new(text).scroll((self.scroll, 0)).block(
+            Block::default()
+                .title(format!(" {} ", t("Replaced answer → answer in the chat")))
+                .title_style(Style::default().bold())
+                .title_alignment(Alignment::Center)
+                .title(
+                    Title::from(format!(
+                        " {} ",
+                        t("j/k: scroll · o: keep the replaced one · Esc: close")
+                    ))
+                    .position(Position::Bottom)
+                    .alignment(Alignment::Center),
+                )
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(theme.focused_border)),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj";
+        assert_eq!(
+            unified(old, new),
+            vec![
+                "@@ -2,8 +2,9 @@",
+                " b",
+                " c",
+                " d",
+                "-e",
+                "+E",
+                " f",
+                " g",
+                " h",
+                " i",
+                "+j",
+            ]
+        );
+        assert!(unified(old, old).is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::paths;
+use crate::vault::{self, Vault};
+
+/// How often the prompt being typed is written to the draft file
+const SAVE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// The text of the prompt, saved while it is typed and restored on the next start,
+/// in case the app is quit or crashes before it is sent.
+/// Each running instance has its own file, named after its pid.
+pub struct Draft {
+    /// The text in the file
+    saved: String,
+    saved_at: Instant,
+    /// Encrypts the file when the history is encrypted
+    vault: Option<&'static Vault>,
+}
+
+fn dir() -> Option<PathBuf> {
+    paths::state_dir()
+}
+
+fn extension(encrypted: bool) -> &'static str {
+    if encrypted {
+        "enc"
+    } else {
+        "txt"
+    }
+}
+
+/// The pid of the instance that wrote the draft file
+fn owner(path: &Path, extension: &str) -> Option<u32> {
+    if path.extension()? != extension {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("draft-")?
+        .parse()
+        .ok()
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // Signal 0 only checks that the process exists
+    let exists = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+impl Draft {
+    fn path(&self) -> Option<PathBuf> {
+        dir().map(|dir| {
+            dir.join(format!(
+                "draft-{}.{}",
+                std::process::id(),
+                extension(self.vault.is_some())
+            ))
+        })
+    }
+
+    /// The draft left by the last instance that quit, taken over by this one.
+    /// None if the history is encrypted and not unlocked, the drafts are not saved then.
+    pub fn load(encrypt: bool) -> Option<(Self, Option<String>)> {
+        let vault = match encrypt {
+            true => Some(vault::get()?),
+            false => None,
+        };
+        let mut draft = Self {
+            saved: String::new(),
+            saved_at: Instant::now(),
+            vault,
+        };
+
+        let text = draft.take_over().filter(|text| !text.trim().is_empty());
+        draft.saved = text.clone().unwrap_or_default();
+        Some((draft, text))
+    }
+
+    /// Rename the latest draft of the instances no longer running to the one of this instance,
+    /// the other instances starting at the same time can not take it too
+    fn take_over(&self) -> Option<String> {
+        let extension = extension(self.vault.is_some());
+        let (path, _) = std::fs::read_dir(dir()?)
+            .ok()?
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                let pid = owner(&path, extension)?;
+                if pid == std::process::id() || is_running(pid) {
+                    return None;
+                }
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((path, modified))
+            })
+            .max_by_key(|(_, modified)| *modified)?;
+
+        let own = self.path()?;
+        std::fs::rename(path, &own).ok()?;
+        let content = std::fs::read(&own).ok()?;
+        let content = match self.vault {
+            Some(vault) => vault.decrypt(&content).ok()?,
+            None => content,
+        };
+        String::from_utf8(content).ok()
+    }
+
+    /// The interval has passed since the last save
+    pub fn is_due(&self) -> bool {
+        self.saved_at.elapsed() >= SAVE_INTERVAL
+    }
+
+    /// Write the text if it changed, an empty prompt removes the file
+    pub fn save(&mut self, text: &str) -> Result<(), String> {
+        self.saved_at = Instant::now();
+        if text == self.saved {
+            return Ok(());
+        }
+        // Not retried until the text changes again
+        self.saved = text.to_string();
+
+        let path = self.path().ok_or("Can not find the state directory")?;
+        if text.trim().is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .map_err(|e| format!("Can not remove the draft: {}", e))?;
+            }
+            return Ok(());
+        }
+
+        let content = match self.vault {
+            Some(vault) => vault.encrypt(text.as_bytes())?,
+            None => text.as_bytes().to_vec(),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Can not save the draft: {}", e))?;
+        }
+        std::fs::write(&path, content).map_err(|e| format!("Can not save the draft: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_of_the_draft() {
+        assert_eq!(owner(Path::new("/state/draft-42.txt"), "txt"), Some(42));
+        assert_eq!(owner(Path::new("/state/draft-42.enc"), "txt"), None);
+        assert_eq!(owner(Path::new("/state/draft.txt"), "txt"), None);
+        assert_eq!(owner(Path::new("/state/history.txt"), "txt"), None);
+        assert!(is_running(std::process::id()));
+    }
+}
@@ -0,0 +1,50 @@
+use std::{io::Write, process::Command};
+
+/// The editor from `$VISUAL` or `$EDITOR`, like git does
+fn editor() -> String {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "windows") {
+                String::from("notepad")
+            } else {
+                String::from("vi")
+            }
+        })
+}
+
+/// Edit the text in the external editor, through a temporary file.
+/// The terminal must be given to the editor while it runs.
+pub fn edit(text: &str) -> Result<String, String> {
+    // With a random name and only readable by the user, removed when dropped
+    let mut file = tempfile::Builder::new()
+        .prefix("tenere-prompt-")
+        .suffix(".md")
+        .tempfile()
+        .map_err(|e| format!("Can not write the prompt: {}", e))?;
+    file.write_all(text.as_bytes())
+        .and_then(|_| file.flush())
+        .map_err(|e| format!("Can not write the prompt: {}", e))?;
+    let path = file.path().to_path_buf();
+
+    // Through the shell, the editor may come with arguments like `code --wait`
+    let cmd = format!("{} \"{}\"", editor(), path.display());
+    let status = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", &cmd]).status()
+    } else {
+        Command::new("sh").args(["-c", &cmd]).status()
+    };
+
+    let result = match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path)
+            .map(|text| text.trim_end_matches(['\n', '\r']).to_string())
+            .map_err(|e| format!("Can not read the prompt: {}", e)),
+        Ok(status) => Err(format!("The editor exited with {}", status)),
+        Err(e) => Err(format!("Can not start the editor: {}", e)),
+    };
+
+    drop(file);
+    result
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use tokio::process::Command;
+
+use crate::config::EnvConfig;
+
+/// A command taking longer is killed and reported as not available
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn run(cmd: &str) -> Option<String> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    };
+    command.kill_on_drop(true);
+
+    let output = tokio::time::timeout(TIMEOUT, command.output())
+        .await
+        .ok()?
+        .ok()?;
+
+    // Some tools like `java -version` print their version to stderr
+    let text = if output.stdout.is_empty() {
+        output.stderr
+    } else {
+        output.stdout
+    };
+
+    String::from_utf8_lossy(&text)
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| line.trim().to_string())
+}
+
+async fn os() -> String {
+    let release = if cfg!(target_os = "linux") {
+        std::fs::read_to_string("/etc/os-release")
+            .ok()
+            .and_then(|release| {
+                release.lines().find_map(|line| {
+                    line.strip_prefix("PRETTY_NAME=")
+                        .map(|name| name.trim_matches('"').to_string())
+                })
+            })
+    } else if cfg!(target_os = "macos") {
+        run("sw_vers -productVersion")
+            .await
+            .map(|version| format!("macOS {}", version))
+    } else {
+        None
+    };
+
+    match release {
+        Some(release) => format!("{} ({})", release, std::env::consts::ARCH),
+        None => format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+    }
+}
+
+fn shell() -> Option<String> {
+    std::env::var("SHELL")
+        .or_else(|_| std::env::var("COMSPEC"))
+        .ok()
+}
+
+/// Snapshot of the environment, to give context to debugging questions
+pub async fn capture(config: &EnvConfig) -> String {
+    let mut lines = vec![format!("- OS: {}", os().await)];
+
+    if let Some(shell) = shell() {
+        lines.push(format!("- Shell: {}", shell));
+    }
+
+    for variable in config.variables.iter() {
+        if let Ok(value) = std::env::var(variable) {
+            lines.push(format!("- {}: {}", variable, value));
+        }
+    }
+
+    // Run side by side, a slow command does not add up with the others
+    let outputs = futures::future::join_all(config.commands.iter().map(|cmd| run(cmd))).await;
+    for (cmd, output) in config.commands.iter().zip(outputs) {
+        let output = output.unwrap_or_else(|| String::from("not available"));
+        lines.push(format!("- `{}`: {}", cmd, output));
+    }
+
+    lines.join("\n")
+}
@@ -1,20 +1,60 @@
 use std::time::Duration;
 
 use crate::app::AppResult;
-use crate::llm::LLMAnswer;
+use crate::attachment::Attachment;
+use crate::control::ControlCommand;
+use crate::hooks::PreSent;
+use crate::latency::BackendStatus;
+use crate::llm::{LLMAnswer, LLMError};
 use crate::notification::Notification;
+use crate::shell::ShellOutput;
 use crossterm::event::{Event as CrosstermEvent, KeyEvent, MouseEvent};
 use futures::{FutureExt, StreamExt};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
+/// The request an answer belongs to, and the id of the chat that sent it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestId {
+    pub request: u64,
+    pub chat: usize,
+}
+
 #[derive(Clone, Debug)]
 pub enum Event {
     Tick,
     Key(KeyEvent),
     Mouse(MouseEvent),
+    Paste(String),
+    /// The terminal gained or lost the focus
+    Focus(bool),
     Resize(u16, u16),
     LLMEvent(LLMAnswer),
+    LLMError(LLMError),
+    /// The events of the answer to a question, routed to the chat that asked it.
+    /// The ones of an aborted request are dropped.
+    Answer(RequestId, LLMAnswer),
+    AnswerError(RequestId, LLMError),
+    /// The events of the answers in the compare mode, with the index of their pane
+    CompareAnswer(usize, LLMAnswer),
+    CompareError(usize, LLMError),
+    Summary(String),
+    Title(usize, String),
+    Latency(Vec<BackendStatus>),
+    ShellOutput(ShellOutput),
+    Transcript(Result<String, String>),
+    /// An attachment prepared in the background, added to the prompt once it is ready
+    Attachment(Result<Attachment, String>),
+    /// The text of the paste command, to insert in the prompt
+    Clipboard(Result<String, String>),
     Notification(Notification),
+    /// A command received on the control socket
+    Control(ControlCommand),
+    /// The prompt changed by the pre-send hooks, or the failure of one of them
+    PreSent(PreSent),
+    /// The next step of the pipeline is to be sent
+    PipelineStep,
+    /// Stop the process like `ctrl + z` does, until it is continued
+    Suspend,
 }
 
 #[allow(dead_code)]
@@ -23,61 +63,95 @@ pub struct EventHandler {
     pub sender: UnboundedSender<Event>,
     receiver: UnboundedReceiver<Event>,
     handler: tokio::task::JoinHandle<()>,
+    tick_rate: Duration,
+}
+
+/// Forward the terminal events and the ticks to the sender
+fn spawn_reader(
+    sender: UnboundedSender<Event>,
+    tick_rate: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut reader = crossterm::event::EventStream::new();
+        let mut tick = tokio::time::interval(tick_rate);
+        loop {
+            let tick_delay = tick.tick();
+            let crossterm_event = reader.next().fuse();
+            tokio::select! {
+              _ = tick_delay => {
+                sender.send(Event::Tick).unwrap();
+              }
+              Some(Ok(evt)) = crossterm_event => {
+                match evt {
+                  CrosstermEvent::Key(key) => {
+                    if key.kind == crossterm::event::KeyEventKind::Press {
+                      sender.send(Event::Key(key)).unwrap();
+                    }
+                  },
+                  CrosstermEvent::Mouse(mouse) => {
+                    sender.send(Event::Mouse(mouse)).unwrap();
+                  },
+                  CrosstermEvent::Resize(x, y) => {
+                    sender.send(Event::Resize(x, y)).unwrap();
+                  },
+                  CrosstermEvent::FocusLost => {
+                    sender.send(Event::Focus(false)).unwrap();
+                  },
+                  CrosstermEvent::FocusGained => {
+                    sender.send(Event::Focus(true)).unwrap();
+                  },
+                  CrosstermEvent::Paste(text) => {
+                    sender.send(Event::Paste(text)).unwrap();
+                  },
+                }
+              }
+            };
+        }
+    })
 }
 
 impl EventHandler {
     pub fn new(tick_rate: u64) -> Self {
         let tick_rate = Duration::from_millis(tick_rate);
         let (sender, receiver) = unbounded_channel();
-        let _sender = sender.clone();
-        let handler = tokio::spawn(async move {
-            let mut reader = crossterm::event::EventStream::new();
-            let mut tick = tokio::time::interval(tick_rate);
-            loop {
-                let tick_delay = tick.tick();
-                let crossterm_event = reader.next().fuse();
-                tokio::select! {
-                  _ = tick_delay => {
-                    _sender.send(Event::Tick).unwrap();
-                  }
-                  Some(Ok(evt)) = crossterm_event => {
-                    match evt {
-                      CrosstermEvent::Key(key) => {
-                        if key.kind == crossterm::event::KeyEventKind::Press {
-                          _sender.send(Event::Key(key)).unwrap();
-                        }
-                      },
-                      CrosstermEvent::Mouse(mouse) => {
-                        _sender.send(Event::Mouse(mouse)).unwrap();
-                      },
-                      CrosstermEvent::Resize(x, y) => {
-                        _sender.send(Event::Resize(x, y)).unwrap();
-                      },
-                      CrosstermEvent::FocusLost => {
-                      },
-                      CrosstermEvent::FocusGained => {
-                      },
-                      CrosstermEvent::Paste(_) => {
-                      },
+        let handler = spawn_reader(sender.clone(), tick_rate);
+
+        // `kill -TSTP` from another terminal, in raw mode `ctrl + z` is a key event
+        #[cfg(unix)]
+        {
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                use tokio::signal::unix::{signal, SignalKind};
+                if let Ok(mut signals) = signal(SignalKind::from_raw(libc::SIGTSTP)) {
+                    while signals.recv().await.is_some() {
+                        let _ = sender.send(Event::Suspend);
                     }
-                  }
-                };
-            }
-        });
+                }
+            });
+        }
+
         Self {
             sender,
             receiver,
             handler,
+            tick_rate,
         }
     }
 
+    /// Stop reading the terminal, so that another program can use it
+    pub async fn pause(&mut self) {
+        self.handler.abort();
+        let _ = (&mut self.handler).await;
+    }
+
+    pub fn resume(&mut self) {
+        self.handler = spawn_reader(self.sender.clone(), self.tick_rate);
+    }
+
     pub async fn next(&mut self) -> AppResult<Event> {
         self.receiver
             .recv()
             .await
-            .ok_or(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "This is an IO error",
-            )))
+            .ok_or(Box::new(std::io::Error::other("This is an IO error")))
     }
 }
@@ -0,0 +1,226 @@
+use std::fmt;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Text,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    clipboard::Clipboard,
+    event::Event,
+    notification::{Notification, NotificationLevel},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportAction {
+    Save,
+    /// Save a standalone html page, with the highlighting of the chat
+    SaveHtml,
+    Copy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportSource {
+    Chat,
+    History(usize),
+}
+
+/// A chat leaving the app, to a file or to the clipboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    pub action: ExportAction,
+    pub source: ExportSource,
+    /// The file to save to, instead of the archive file
+    pub path: Option<String>,
+}
+
+impl fmt::Display for Export {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.action {
+            ExportAction::Save | ExportAction::SaveHtml => write!(f, "saving the chat"),
+            ExportAction::Copy => write!(f, "copying the chat"),
+        }
+    }
+}
+
+fn escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Standard xterm palette for the first 16 colors
+static ANSI_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+fn indexed_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_COLORS[index as usize],
+        16..=231 => {
+            let index = index - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            (level(index / 36), level((index / 6) % 6), level(index % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn color_to_css(color: Color) -> Option<String> {
+    let (r, g, b) = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i) => indexed_to_rgb(i),
+        Color::Black => ANSI_COLORS[0],
+        Color::Red => ANSI_COLORS[1],
+        Color::Green => ANSI_COLORS[2],
+        Color::Yellow => ANSI_COLORS[3],
+        Color::Blue => ANSI_COLORS[4],
+        Color::Magenta => ANSI_COLORS[5],
+        Color::Cyan => ANSI_COLORS[6],
+        Color::Gray => ANSI_COLORS[7],
+        Color::DarkGray => ANSI_COLORS[8],
+        Color::LightRed => ANSI_COLORS[9],
+        Color::LightGreen => ANSI_COLORS[10],
+        Color::LightYellow => ANSI_COLORS[11],
+        Color::LightBlue => ANSI_COLORS[12],
+        Color::LightMagenta => ANSI_COLORS[13],
+        Color::LightCyan => ANSI_COLORS[14],
+        Color::White => ANSI_COLORS[15],
+    };
+    Some(format!("#{:02x}{:02x}{:02x}", r, g, b))
+}
+
+fn style_to_css(style: Style) -> String {
+    let mut css = Vec::new();
+
+    if let Some(color) = style.fg.and_then(color_to_css) {
+        css.push(format!("color: {}", color));
+    }
+    if let Some(color) = style.bg.and_then(color_to_css) {
+        css.push(format!("background-color: {}", color));
+    }
+    if style.add_modifier.contains(Modifier::BOLD) {
+        css.push("font-weight: bold".to_string());
+    }
+    if style.add_modifier.contains(Modifier::ITALIC) {
+        css.push("font-style: italic".to_string());
+    }
+    if style.add_modifier.contains(Modifier::UNDERLINED) {
+        css.push("text-decoration: underline".to_string());
+    }
+
+    css.join("; ")
+}
+
+/// Convert a formatted text to an html fragment, keeping its colors
+pub fn to_html(text: &Text) -> String {
+    let mut html = String::from(r#"<pre style="font-family: monospace; white-space: pre-wrap;">"#);
+
+    for line in text.lines.iter() {
+        for span in line.spans.iter() {
+            let css = style_to_css(span.style);
+            if css.is_empty() {
+                html.push_str(&escape(&span.content));
+            } else {
+                html.push_str(&format!(
+                    r#"<span style="{}">{}</span>"#,
+                    css,
+                    escape(&span.content)
+                ));
+            }
+        }
+        html.push('\n');
+    }
+
+    html.push_str("</pre>");
+    html
+}
+
+/// A standalone html page of a chat, readable without tenere.
+/// The background follows the theme the chat was highlighted with.
+pub fn to_html_document(title: Option<&str>, text: &Text, light: bool) -> String {
+    let (background, foreground) = if light {
+        ("#fafafa", "#272822")
+    } else {
+        ("#272822", "#f8f8f2")
+    };
+
+    let heading = title
+        .map(|title| format!("<h1>{}</h1>\n", escape(title)))
+        .unwrap_or_default();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="generator" content="tenere">
+<title>{}</title>
+<style>
+  body {{
+    max-width: 960px;
+    margin: 2em auto;
+    padding: 0 1em;
+    background-color: {};
+    color: {};
+    font-family: sans-serif;
+  }}
+  pre {{
+    font-size: 14px;
+    line-height: 1.4;
+  }}
+</style>
+</head>
+<body>
+{}{}
+</body>
+</html>
+"#,
+        escape(title.unwrap_or("tenere chat")),
+        background,
+        foreground,
+        heading,
+        to_html(text)
+    )
+}
+
+/// Copy a chat to the clipboard as html with the plain text as fallback
+pub fn copy_to_clipboard(
+    clipboard: &mut Clipboard,
+    plain_text: &str,
+    formatted_chat: &Text,
+    sender: UnboundedSender<Event>,
+) {
+    let notif = match clipboard.set_html(&to_html(formatted_chat), plain_text) {
+        Ok(_) => Notification::new(
+            "Chat copied to the clipboard".to_string(),
+            NotificationLevel::Info,
+        ),
+        Err(e) => Notification::new(e, NotificationLevel::Error),
+    };
+
+    sender.send(Event::Notification(notif)).unwrap();
+}
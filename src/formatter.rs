@@ -1,33 +1,377 @@
+use std::cell::{Cell, Ref, RefCell};
+
 use ansi_to_tui::IntoText;
 
 use bat::{assets::HighlightingAssets, config::Config, controller::Controller, Input};
-use ratatui::text::Text;
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::authors::Authors;
+use crate::math;
+use crate::reasoning::ReasoningDisplay;
+use crate::streaming::is_fence;
+use crate::theme::Theme;
 
-pub struct Formatter<'a> {
-    controller: Controller<'a>,
+/// A part of a message highlighted on its own
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Prose(&'a str),
+    /// The line opening or closing a code block
+    Fence(&'a str),
+    /// The language is the first word after the opening fence, it may be empty
+    Code {
+        language: &'a str,
+        code: &'a str,
+    },
 }
 
-impl<'a> Formatter<'a> {
-    pub fn new(config: &'a Config, assets: &'a HighlightingAssets) -> Self {
-        let controller = Controller::new(config, assets);
-        Self { controller }
+/// The code blocks of the message and the prose between them, the lines of the
+/// segments put together are the lines of the message
+fn segments(input: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut language = None;
+    let mut start = 0;
+    let mut offset = 0;
+
+    for line in input.split_inclusive('\n') {
+        if is_fence(line) {
+            let body = &input[start..offset];
+            match language.take() {
+                Some(language) => segments.push(Segment::Code {
+                    language,
+                    code: body,
+                }),
+                None => {
+                    if !body.is_empty() {
+                        segments.push(Segment::Prose(body));
+                    }
+                    let info = line.trim().trim_start_matches(['`', '~']);
+                    language = Some(info.split_whitespace().next().unwrap_or_default());
+                }
+            }
+            segments.push(Segment::Fence(line));
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+
+    // A block left open by a partial answer ends with the message
+    let rest = &input[start..];
+    match language {
+        Some(language) => segments.push(Segment::Code {
+            language,
+            code: rest,
+        }),
+        None if !rest.is_empty() => segments.push(Segment::Prose(rest)),
+        None => {}
     }
+    segments
+}
+
+/// Ends the number of a code line in its gutter
+const GUTTER_SEPARATOR: &str = " │ ";
+
+/// The lines of a code block with their number in a gutter
+fn number(lines: Vec<Line<'static>>) -> Vec<Line<'static>> {
+    let width = lines.len().to_string().len();
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut line)| {
+            line.spans.insert(
+                0,
+                Span::styled(
+                    format!("{:>width$}{}", i + 1, GUTTER_SEPARATOR),
+                    Style::default().dim(),
+                ),
+            );
+            line
+        })
+        .collect()
+}
+
+/// The columns of the gutter of a numbered code line, 0 for the other lines
+pub fn gutter_width(line: &Line) -> usize {
+    line.spans
+        .first()
+        .filter(|span| {
+            span.content
+                .strip_suffix(GUTTER_SEPARATOR)
+                .map(str::trim_start)
+                .is_some_and(|number| {
+                    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+                })
+        })
+        .map_or(0, Span::width)
+}
+
+/// The prose is not highlighted, only the headings stand out
+fn prose(text: &str) -> Vec<Line<'static>> {
+    text.lines()
+        .map(|line| {
+            if line.trim_start().starts_with('#') {
+                Line::styled(line.to_string(), Style::default().bold())
+            } else {
+                Line::raw(line.to_string())
+            }
+        })
+        .collect()
+}
+
+/// How the messages of a chat are rendered, saved with the chat
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RenderProfile {
+    /// Show the messages as they are, without markdown highlighting
+    #[serde(default)]
+    pub plain: bool,
+
+    /// Bat theme used instead of the one of the app theme
+    #[serde(default)]
+    pub theme: Option<String>,
+
+    /// The code lines are not wrapped, they are scrolled sideways
+    #[serde(default)]
+    pub nowrap: bool,
+}
+
+pub struct Formatter {
+    assets: HighlightingAssets,
+    theme: RefCell<String>,
+    /// The color of the secondary lines, like the times of the answers
+    muted: Cell<Color>,
+    /// The color of the failed requests
+    error: Cell<Color>,
+    math: Cell<bool>,
+    code_line_numbers: Cell<bool>,
+    reasoning: Cell<ReasoningDisplay>,
+    authors: RefCell<Authors>,
+}
 
-    pub fn init() -> (Config<'static>, HighlightingAssets) {
-        let config = bat::config::Config {
+impl Default for Formatter {
+    fn default() -> Self {
+        Self {
+            assets: HighlightingAssets::from_binary(),
+            theme: RefCell::new(HighlightingAssets::default_theme().to_string()),
+            muted: Cell::new(Theme::default().muted),
+            error: Cell::new(Theme::default().error),
+            math: Cell::new(false),
+            code_line_numbers: Cell::new(false),
+            reasoning: Cell::new(ReasoningDisplay::default()),
+            authors: RefCell::new(Authors::default()),
+        }
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlight the code with the bat theme of the app theme, and use its colors
+    pub fn set_theme(&self, theme: &Theme) {
+        *self.theme.borrow_mut() = theme.bat_theme.to_string();
+        self.muted.set(theme.muted);
+        self.error.set(theme.error);
+    }
+
+    pub fn muted(&self) -> Color {
+        self.muted.get()
+    }
+
+    pub fn error(&self) -> Color {
+        self.error.get()
+    }
+
+    /// Show the LaTeX math of the messages with Unicode characters
+    pub fn set_math(&self, enabled: bool) {
+        self.math.set(enabled);
+    }
+
+    /// Number the lines of the code blocks of the answers
+    pub fn set_code_line_numbers(&self, enabled: bool) {
+        self.code_line_numbers.set(enabled);
+    }
+
+    /// Fold, unfold or hide the reasoning of the answers
+    pub fn set_reasoning(&self, display: ReasoningDisplay) {
+        self.reasoning.set(display);
+    }
+
+    pub fn reasoning(&self) -> ReasoningDisplay {
+        self.reasoning.get()
+    }
+
+    /// The labels shown before the messages
+    pub fn set_authors(&self, authors: Authors) {
+        *self.authors.borrow_mut() = authors;
+    }
+
+    pub fn authors(&self) -> Ref<'_, Authors> {
+        self.authors.borrow()
+    }
+
+    /// The file extension of a language named after a fence, `txt` if it is unknown
+    pub fn extension(&self, language: &str) -> String {
+        self.assets
+            .get_syntax_set()
+            .ok()
+            .filter(|_| !language.is_empty())
+            .and_then(|syntaxes| syntaxes.find_syntax_by_token(language))
+            .and_then(|syntax| syntax.file_extensions.first().cloned())
+            .unwrap_or_else(|| String::from("txt"))
+    }
+
+    pub fn has_theme(&self, theme: &str) -> bool {
+        self.assets.themes().any(|t| t == theme)
+    }
+
+    pub fn format(&self, input: &str) -> Text<'static> {
+        self.format_with(input, &RenderProfile::default())
+    }
+
+    pub fn format_with(&self, input: &str, profile: &RenderProfile) -> Text<'static> {
+        self.render(input, profile, false)
+    }
+
+    /// Format an answer, its code blocks get line numbers if they are enabled
+    pub fn format_answer(&self, input: &str, profile: &RenderProfile) -> Text<'static> {
+        self.render(input, profile, self.code_line_numbers.get())
+    }
+
+    fn render(&self, input: &str, profile: &RenderProfile, line_numbers: bool) -> Text<'static> {
+        let authors = self.authors.borrow();
+        let input = authors.display(input);
+        if profile.plain {
+            return Text::raw(input.to_string());
+        }
+
+        let theme = profile
+            .theme
+            .clone()
+            .unwrap_or_else(|| self.theme.borrow().clone());
+
+        let input = if self.math.get() {
+            math::prettify(&input)
+        } else {
+            input.to_string()
+        };
+
+        let mut lines = Vec::new();
+        for segment in segments(&input) {
+            match segment {
+                Segment::Prose(text) => lines.extend(prose(text)),
+                Segment::Fence(line) => lines.push(Line::styled(
+                    line.trim_end().to_string(),
+                    Style::default().dim(),
+                )),
+                Segment::Code { language, code } => {
+                    let code = self.highlight(code, language, &theme);
+                    if line_numbers {
+                        lines.extend(number(code.lines));
+                    } else {
+                        lines.extend(code.lines);
+                    }
+                }
+            }
+        }
+        Text::from(lines)
+    }
+
+    /// Highlight a code block with the syntax of its language, as plain text if it is unknown
+    fn highlight(&self, code: &str, language: &str, theme: &str) -> Text<'static> {
+        let known = !language.is_empty()
+            && self
+                .assets
+                .get_syntax_set()
+                .is_ok_and(|syntaxes| syntaxes.find_syntax_by_token(language).is_some());
+        if !known {
+            return Text::raw(code.to_string());
+        }
+
+        let config = Config {
             colored_output: true,
+            theme: theme.to_string(),
+            language: Some(language),
             ..Default::default()
         };
-        let assets = bat::assets::HighlightingAssets::from_binary();
-        (config, assets)
-    }
+        let controller = Controller::new(&config, &self.assets);
 
-    pub fn format(&self, input: &str) -> Text<'static> {
         let mut buffer = String::new();
-        let input = Input::from_bytes(input.as_bytes()).name("text.md");
-        self.controller
-            .run(vec![input.into()], Some(&mut buffer))
-            .unwrap();
-        buffer.into_text().unwrap_or(Text::from(buffer))
+        let input = Input::from_bytes(code.as_bytes());
+        match controller.run(vec![input.into()], Some(&mut buffer)) {
+            Ok(true) => buffer.into_text().unwrap_or(Text::from(buffer)),
+            _ => Text::raw(code.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_blocks_and_prose() {
+        let message =
+            "Run:\n```rust title\nfn main() {}\n```\n\n~~~\nls\n~~~\nDone\n```py\nprint(1)";
+
+        assert_eq!(
+            segments(message),
+            vec![
+                Segment::Prose("Run:\n"),
+                Segment::Fence("```rust title\n"),
+                Segment::Code {
+                    language: "rust",
+                    code: "fn main() {}\n"
+                },
+                Segment::Fence("```\n"),
+                Segment::Prose("\n"),
+                Segment::Fence("~~~\n"),
+                Segment::Code {
+                    language: "",
+                    code: "ls\n"
+                },
+                Segment::Fence("~~~\n"),
+                Segment::Prose("Done\n"),
+                Segment::Fence("```py\n"),
+                Segment::Code {
+                    language: "py",
+                    code: "print(1)"
+                },
+            ]
+        );
+        assert_eq!(
+            Formatter::new().format(message).lines.len(),
+            message.lines().count()
+        );
+    }
+
+    #[test]
+    fn extension_of_the_language() {
+        let formatter = Formatter::new();
+        assert_eq!(formatter.extension("rust"), "rs");
+        assert_eq!(formatter.extension("python"), "py");
+        assert_eq!(formatter.extension("unknown-language"), "txt");
+        assert_eq!(formatter.extension(""), "txt");
+    }
+
+    #[test]
+    fn numbered_code_lines() {
+        let code: Vec<Line<'static>> = (1..=10).map(|i| Line::raw(format!("line {}", i))).collect();
+        let numbered = number(code);
+
+        let text = |line: &Line| -> String {
+            line.spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        };
+        assert_eq!(text(&numbered[0]), " 1 │ line 1");
+        assert_eq!(text(&numbered[9]), "10 │ line 10");
+        assert_eq!(gutter_width(&numbered[0]), 5);
+        assert_eq!(gutter_width(&Line::raw("line 1")), 0);
+        assert_eq!(gutter_width(&Line::raw("a │ b")), 0);
     }
 }
@@ -0,0 +1,28 @@
+use tokio::process::Command;
+
+/// Run git in the current directory, its error message is returned if it fails
+pub async fn run(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Can not run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The changes of the repository, compared to `HEAD` if no revision is given,
+/// with the staged and the unstaged changes
+pub async fn diff(args: &[&str]) -> Result<String, String> {
+    let args = if args.is_empty() { &["HEAD"] } else { args };
+
+    let diff = run(&[&["diff", "--no-color"], args].concat()).await?;
+    if diff.trim().is_empty() {
+        return Err(String::from("No changes"));
+    }
+    Ok(diff)
+}
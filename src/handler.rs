@@ -1,42 +1,2384 @@
-use crate::llm::{LLMAnswer, LLMRole};
+use crate::alert;
+use crate::archive;
+use crate::attachment::{self, Attachment};
+use crate::authors;
+use crate::cmdline::{CommandLine, ExCommand};
+use crate::codeblock::{self, CodeBlockPicker};
+use crate::command;
+use crate::compare::Compare;
+use crate::config::{Config, HookRole, Transformer};
+use crate::context;
+use crate::control::ControlCommand;
+use crate::diagnostics::Diagnostics;
+use crate::diff::AnswerDiff;
+use crate::export::{self, Export, ExportAction, ExportSource};
+use crate::hooks::{self, PreSent};
+use crate::info::ChatInfo;
+use crate::llm::{
+    message, message_with_images, FinishReason, LLMAnswer, LLMBackend, LLMError, LLMModel, LLMRole,
+};
+use crate::logging::LogViewer;
+use crate::picker::{self, FilePicker};
+use crate::privacy::PrivacyReport;
+use crate::profile::ProfileSwitcher;
+use crate::queue;
+use crate::rag;
+use crate::redaction;
+use crate::registers::{RegisterViewer, Registers};
+use crate::runner;
+use crate::scrubber::Scrubber;
+use crate::share::{self, Share};
+use crate::shell::{self, CommandRunner};
+use crate::spellcheck::Corrections;
+use crate::split::ChatLayout;
+use crate::tee::Tee;
+use crate::undo::{self, Operation};
+use crate::variables;
+#[cfg(feature = "voice")]
+use crate::voice;
 use crate::{chat::Chat, prompt::Mode};
 
 use crate::{
     app::{App, AppResult, FocusedBlock},
-    event::Event,
+    event::{Event, RequestId},
 };
 
 use crate::llm::LLM;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use ratatui::text::Line;
+use crate::notification::{Notification, NotificationLevel};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// Send the conversation to the LLM and stream the answer back as events
+pub fn ask(app: &mut App, llm: Arc<Mutex<Box<dyn LLM + 'static>>>, sender: UnboundedSender<Event>) {
+    app.chat.failed_request = false;
+    app.spinner.active = true;
+
+    let line = app.waiting_line();
+    app.chat.formatted_chat.lines.push(line);
+
+    let terminate_response_signal = app.terminate_response_signal.clone();
+    let config = app.config.clone();
+    let model_name = config.backend_model_name(&app.backend);
+    let index = app.index.clone();
+
+    app.request_id += 1;
+    app.chat.request = Some(app.request_id);
+    app.chat.answer.requested = Some(Instant::now());
+    let request_id = RequestId {
+        request: app.request_id,
+        chat: app.chat.id,
+    };
+
+    app.answer_task = Some(tokio::spawn(async move {
+        let (request_sender, mut receiver) = unbounded_channel();
+
+        // Tell the events of this request apart from the ones of an aborted request or of another chat
+        let forward = {
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                while let Some(event) = receiver.recv().await {
+                    let event = match event {
+                        Event::LLMEvent(answer) => Event::Answer(request_id, answer),
+                        event => event,
+                    };
+                    let _ = sender.send(event);
+                }
+            })
+        };
+
+        let res = {
+            let mut llm = llm.lock().await;
+            if let Some(index) = index {
+                rag::augment(&mut llm, &index, &config, &request_sender).await;
+            }
+            context::fit(&mut llm, &config.context, &model_name, &request_sender).await;
+
+            llm.ask(request_sender, terminate_response_signal)
+                .await
+                .map_err(LLMError::from)
+        };
+        // The events of the answer come before its error
+        let _ = forward.await;
+
+        if let Err(e) = res {
+            let _ = sender.send(Event::AnswerError(request_id, e));
+        }
+    }));
+}
+
+/// Stop the answer being streamed, before the chat is left. The part already received is
+/// kept in the chat, and the events of the request still on their way are dropped.
+pub async fn abort_answer(app: &mut App<'_>) {
+    let Some(task) = app.answer_task.take() else {
+        return;
+    };
+    // The events of an answer that ended are handled as usual
+    if task.is_finished() {
+        return;
+    }
+
+    app.terminate_response_signal
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    task.abort();
+    let _ = task.await;
+    app.terminate_response_signal
+        .store(false, std::sync::atomic::Ordering::Relaxed);
+    tracing::info!("answer aborted");
+
+    let formatter = app.formatter;
+    if app.spinner.active {
+        app.spinner.active = false;
+        app.streaming_chat()
+            .handle_answer(LLMAnswer::StartAnswer, formatter);
+    }
+    let chat = app.streaming_chat();
+    chat.request = None;
+    if !chat.answer.plain_answer().is_empty() {
+        chat.answer.finish_reason = Some(FinishReason::Other(String::from("aborted")));
+        chat.handle_answer(LLMAnswer::EndAnswer, formatter);
+    }
+    chat.restore_previous_answer(formatter);
+}
+
+/// Replace the conversation sent to the LLM with a summary of it
+pub fn summarize(
+    app: &mut App,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) {
+    app.spinner.active = true;
+
+    let line = app.waiting_line();
+    app.chat.formatted_chat.lines.push(line);
+
+    tokio::spawn(async move {
+        let mut llm = llm.lock().await;
+        let messages = llm.messages();
+
+        match context::summarize(llm.as_ref(), &messages).await {
+            Ok(summary) => {
+                llm.set_messages(vec![context::summary_message(&summary)]);
+                sender.send(Event::Summary(summary)).unwrap();
+            }
+            Err(e) => {
+                sender.send(Event::LLMError(LLMError::from(e))).unwrap();
+            }
+        }
+    });
+}
+
+/// Ask the LLM for a short title of the chat based on its first exchange
+pub fn generate_title(
+    app: &App,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) {
+    let chat_id = app.chat.id;
+    let request = vec![message(
+        LLMRole::USER,
+        &format!(
+            "Write a short title, 6 words at most, for a conversation starting with the exchange below. Answer with the title only.\n\n{}",
+            app.chat.plain_chat.join("\n")
+        ),
+    )];
+
+    tokio::spawn(async move {
+        let llm = llm.lock().await;
+        if let Ok(title) = llm.complete(request).await {
+            let title = title
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or_default()
+                .trim()
+                .trim_matches(|c| c == '"' || c == '#' || c == '*')
+                .trim()
+                .to_string();
+
+            if !title.is_empty() {
+                sender.send(Event::Title(chat_id, title)).unwrap();
+            }
+        }
+    });
+}
+
+/// Switch to the preferred backend if it is not the one in use.
+/// Only call it before a conversation starts since the messages are lost.
+pub async fn apply_preferred_backend(app: &mut App<'_>, llm: Arc<Mutex<Box<dyn LLM + 'static>>>) {
+    let backend = app.preferred_backend();
+    if backend == app.backend {
+        return;
+    }
+
+    let mut llm = llm.lock().await;
+    match LLMModel::init(&backend, app.config.clone()).await {
+        Ok(new) => {
+            *llm = new;
+            app.backend = backend;
+        }
+        Err(e) => app
+            .notifications
+            .push(Notification::new(e, NotificationLevel::Error)),
+    }
+}
+
+/// Once the answer of a branched chat ends, move it to the history and
+/// continue with the branch, sending the message written in the meantime
+pub async fn merge_branch(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) {
+    if let Some(chat) = app.branched.take() {
+        app.history.push(chat.to_thread());
+        app.save_history();
+    }
+
+    let mut messages = app.chat.to_thread().messages();
+    let queued_prompt = app.queued_prompt.take();
+    if let Some(queued_message) = &queued_prompt {
+        messages.pop();
+        messages.push(queued_message.clone());
+    }
+
+    {
+        let mut llm = llm.lock().await;
+        llm.set_messages(messages);
+    }
+
+    if queued_prompt.is_some() {
+        ask(app, llm, sender);
+    }
+}
+
+/// Once the answer of a chat left for another thread ends, move it to the history
+/// and send the conversation of the active chat to the LLM again
+pub async fn save_detached(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    chat_id: usize,
+) {
+    let Some(i) = app.detached.iter().position(|chat| chat.id == chat_id) else {
+        return;
+    };
+    let chat = app.detached.remove(i);
+    app.history.push(chat.to_thread());
+    app.save_history();
+
+    let mut llm = llm.lock().await;
+    llm.set_messages(app.chat.to_thread().messages());
+}
+
+/// The llm is busy with the answer of a chat left for another thread
+fn wait_detached(app: &mut App) -> bool {
+    if app.detached.is_empty() {
+        return false;
+    }
+    app.notifications.push(Notification::new(
+        "Wait for the answer of the previous chat to end".to_string(),
+        NotificationLevel::Warning,
+    ));
+    true
+}
+
+/// The chat to export, depending on the focused block
+fn export_source(app: &App) -> Option<ExportSource> {
+    match app.focused_block {
+        FocusedBlock::History | FocusedBlock::Preview => {
+            app.history.selected().map(ExportSource::History)
+        }
+        FocusedBlock::Chat | FocusedBlock::Prompt => Some(ExportSource::Chat),
+        _ => None,
+    }
+}
+
+/// Look for secrets in the chat before it leaves the app, and ask for a
+/// confirmation if there are any
+fn request_export(app: &mut App, export: Export, sender: UnboundedSender<Event>) {
+    let text = match export.source {
+        ExportSource::Chat => &app.chat.formatted_chat,
+        ExportSource::History(i) => &app.history.threads[i].formatted_chat,
+    };
+
+    let findings = redaction::scan(text, redaction::default_rules());
+    if findings.is_empty() {
+        run_export(app, export, sender);
+    } else {
+        app.privacy_report = Some(PrivacyReport::new(
+            export,
+            findings,
+            app.focused_block.clone(),
+        ));
+        app.focused_block = FocusedBlock::Privacy;
+    }
+}
+
+/// The path given to the export, or the archive file of the chat
+fn export_path(app: &App, export: &Export) -> PathBuf {
+    if let Some(path) = export.path.as_deref() {
+        return attachment::expand_home(path);
+    }
+
+    let extension = matches!(export.action, ExportAction::SaveHtml).then_some("html");
+    match export.source {
+        ExportSource::Chat => archive::path(&app.config, &app.chat.to_thread(), extension),
+        ExportSource::History(i) => archive::path(&app.config, &app.history.threads[i], extension),
+    }
+}
+
+fn run_export(app: &mut App, export: Export, sender: UnboundedSender<Event>) {
+    match (export.action, export.source) {
+        (ExportAction::Save, source) => {
+            let path = export_path(app, &export);
+            let content = match source {
+                ExportSource::Chat => app.chat.to_thread().archive(&app.formatter.authors()),
+                ExportSource::History(i) => {
+                    app.history.threads[i].archive(&app.formatter.authors())
+                }
+            };
+
+            let notif = match archive::write(&path, &content) {
+                Ok(_) => Notification::new(
+                    format!("Chat saved to `{}`", path.display()),
+                    NotificationLevel::Info,
+                ),
+                Err(e) => Notification::new(
+                    format!("Can not save the chat to `{}`: {}", path.display(), e),
+                    NotificationLevel::Error,
+                ),
+            };
+            sender.send(Event::Notification(notif)).unwrap();
+        }
+        (ExportAction::SaveHtml, source) => {
+            let path = export_path(app, &export);
+            let (title, text) = match source {
+                ExportSource::Chat => (app.chat.title.as_deref(), &app.chat.formatted_chat),
+                ExportSource::History(i) => (
+                    app.history.threads[i].title.as_deref(),
+                    &app.history.threads[i].formatted_chat,
+                ),
+            };
+            let html = export::to_html_document(title, text, app.theme.name.contains("light"));
+
+            let notif = match archive::write(&path, &html) {
+                Ok(_) => Notification::new(
+                    format!("Chat exported to `{}`", path.display()),
+                    NotificationLevel::Info,
+                ),
+                Err(e) => Notification::new(
+                    format!("Can not export the chat to `{}`: {}", path.display(), e),
+                    NotificationLevel::Error,
+                ),
+            };
+            sender.send(Event::Notification(notif)).unwrap();
+        }
+        (ExportAction::Copy, ExportSource::History(i)) => {
+            export::copy_to_clipboard(
+                &mut app.clipboard,
+                &app.formatter
+                    .authors()
+                    .display_chat(&app.history.threads[i].plain_chat),
+                &app.history.threads[i].formatted_chat,
+                sender,
+            );
+        }
+        (ExportAction::Copy, ExportSource::Chat) => {
+            export::copy_to_clipboard(
+                &mut app.clipboard,
+                &app.formatter.authors().display_chat(&app.chat.plain_chat),
+                &app.chat.formatted_chat,
+                sender,
+            );
+        }
+    }
+}
+
+fn handle_privacy_report(app: &mut App, key_event: KeyEvent, sender: UnboundedSender<Event>) {
+    let Some(report) = app.privacy_report.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => report.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => report.scroll_up(),
+
+        // Jump to the occurrence
+        KeyCode::Enter => {
+            if let Some(finding) = report.selected() {
+                match report.export.source {
+                    ExportSource::Chat => {
+                        app.chat.scroll = app.chat.row_of(finding.line);
+                        app.chat
+                            .automatic_scroll
+                            .store(false, std::sync::atomic::Ordering::Relaxed);
+                    }
+                    ExportSource::History(_) => {
+                        app.history.preview_scroll = finding.line;
+                    }
+                }
+            }
+        }
+
+        KeyCode::Char('y') => {
+            if let Some(report) = app.privacy_report.take() {
+                app.focused_block = report.previous_focus;
+                run_export(app, report.export, sender);
+            }
+        }
+
+        KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+            if let Some(report) = app.privacy_report.take() {
+                app.focused_block = report.previous_focus;
+                app.notifications.push(Notification::new(
+                    "Export canceled".to_string(),
+                    NotificationLevel::Info,
+                ));
+            }
+        }
+
+        _ => {}
+    }
+}
+
+fn handle_quit_confirmation(app: &mut App, key_event: KeyEvent) {
+    let Some(confirmation) = app.quit_confirmation.take() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('y') | KeyCode::Char('q') | KeyCode::Enter => app.running = false,
+        KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => app.running = false,
+        KeyCode::Char('n') | KeyCode::Esc => app.focused_block = confirmation.previous_focus,
+        _ => app.quit_confirmation = Some(confirmation),
+    }
+}
+
+fn handle_share(app: &mut App, key_event: KeyEvent, sender: UnboundedSender<Event>) {
+    let Some(popup) = app.share.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    if popup.confirming {
+        if key_event.code == KeyCode::Char('y') {
+            if let Some(snippet) = popup.selected() {
+                share::send(
+                    popup.targets[popup.target],
+                    app.config.share.clone(),
+                    app.config.network.clone(),
+                    snippet.content.clone(),
+                    sender,
+                );
+            }
+            app.share = None;
+            app.focused_block = FocusedBlock::Chat;
+        } else {
+            popup.confirming = false;
+        }
+        return;
+    }
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => popup.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => popup.scroll_up(),
+
+        KeyCode::Tab => popup.next_target(),
+
+        KeyCode::Enter => popup.confirming = popup.selected().is_some(),
+
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.share = None;
+            app.focused_block = FocusedBlock::Chat;
+        }
+
+        _ => {}
+    }
+}
+
+fn handle_command_runner(app: &mut App, key_event: KeyEvent, sender: UnboundedSender<Event>) {
+    let Some(runner) = app.command_runner.as_mut() else {
+        app.focused_block = FocusedBlock::Chat;
+        return;
+    };
+
+    if runner.confirming {
+        if key_event.code == KeyCode::Char('y') {
+            if let Some(cmd) = runner.selected().cloned() {
+                app.chat.start_tool_output(&cmd, "sh", app.formatter);
+                app.stop_command = Some(shell::run(cmd, app.variables(), sender));
+            }
+            app.command_runner = None;
+            app.focused_block = FocusedBlock::Chat;
+        } else {
+            runner.confirming = false;
+        }
+        return;
+    }
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => runner.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => runner.scroll_up(),
+
+        KeyCode::Enter => runner.confirming = runner.selected().is_some(),
+
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.command_runner = None;
+            app.focused_block = FocusedBlock::Chat;
+        }
+
+        _ => {}
+    }
+}
+
+fn handle_code_blocks(app: &mut App, key_event: KeyEvent, sender: UnboundedSender<Event>) {
+    let Some(picker) = app.code_blocks.as_mut() else {
+        app.focused_block = FocusedBlock::Chat;
+        return;
+    };
+
+    if let Some(interpreter) = picker.running.take() {
+        if key_event.code == KeyCode::Char('y') {
+            if let Some(block) = picker.selected().cloned() {
+                let extension = app.formatter.extension(&block.language);
+                app.chat
+                    .start_tool_output(&block.code, &block.language, app.formatter);
+                app.stop_command = Some(runner::run(
+                    block.code,
+                    interpreter,
+                    extension,
+                    &app.config.runner,
+                    app.variables(),
+                    sender,
+                ));
+            }
+            app.code_blocks = None;
+            app.focused_block = FocusedBlock::Chat;
+        }
+        return;
+    }
+
+    if picker.overwriting {
+        if key_event.code == KeyCode::Char('y') {
+            save_code_block(app);
+        } else {
+            picker.overwriting = false;
+        }
+        return;
+    }
+
+    if let Some(file_name) = picker.file_name.as_mut() {
+        match key_event.code {
+            KeyCode::Char(c) => file_name.push(c),
+            KeyCode::Backspace => {
+                file_name.pop();
+            }
+            KeyCode::Esc => picker.file_name = None,
+            KeyCode::Enter if !file_name.trim().is_empty() => {
+                if attachment::expand_home(file_name.trim()).exists() {
+                    picker.overwriting = true;
+                } else {
+                    save_code_block(app);
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => picker.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => picker.scroll_up(),
+
+        KeyCode::Enter => {
+            let Some(block) = picker.selected() else {
+                return;
+            };
+            let notif = match app.clipboard.set_text(&block.code) {
+                Ok(()) => Notification::new(
+                    "Code block copied to the clipboard".to_string(),
+                    NotificationLevel::Info,
+                ),
+                Err(e) => Notification::new(e, NotificationLevel::Error),
+            };
+            app.notifications.push(notif);
+            app.code_blocks = None;
+            app.focused_block = FocusedBlock::Chat;
+        }
+
+        KeyCode::Char('r') => {
+            let Some(block) = picker.selected() else {
+                return;
+            };
+            if !app.config.runner.enabled {
+                app.notifications.push(Notification::new(
+                    "Set `enabled = true` in the `[runner]` section to run code blocks".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else if app.spinner.active
+                || !app.chat.answer.plain_answer().is_empty()
+                || app.chat.tool_output.is_some()
+            {
+                app.notifications.push(Notification::new(
+                    "Wait for the answer or the running command to end".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else {
+                match runner::interpreter(&app.config.runner, &block.language) {
+                    Some(interpreter) => picker.running = Some(interpreter.to_string()),
+                    None => app.notifications.push(Notification::new(
+                        format!(
+                            "No interpreter for `{}` in the `[runner]` section",
+                            block.language
+                        ),
+                        NotificationLevel::Warning,
+                    )),
+                }
+            }
+        }
+
+        // The name of the file follows the language of the block
+        KeyCode::Char('w') => {
+            if let Some(block) = picker.selected() {
+                let extension = app.formatter.extension(&block.language);
+                picker.file_name = Some(format!("snippet.{}", extension));
+            }
+        }
+
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.code_blocks = None;
+            app.focused_block = FocusedBlock::Chat;
+        }
+
+        _ => {}
+    }
+}
+
+/// Write the selected code block to the file typed in the picker
+fn save_code_block(app: &mut App) {
+    let Some(picker) = app.code_blocks.take() else {
+        return;
+    };
+    app.focused_block = FocusedBlock::Chat;
+
+    let (Some(block), Some(file_name)) = (picker.selected(), picker.file_name.as_deref()) else {
+        return;
+    };
+    let notif = match codeblock::save(&attachment::expand_home(file_name.trim()), &block.code) {
+        Ok(path) => Notification::new(
+            format!("Saved to {}", path.display()),
+            NotificationLevel::Info,
+        ),
+        Err(e) => Notification::new(e, NotificationLevel::Error),
+    };
+    app.notifications.push(notif);
+}
+
+/// Reload the config with another profile, the current chat goes on with it
+async fn switch_profile(app: &mut App<'_>, llm: Arc<Mutex<Box<dyn LLM + 'static>>>, name: &str) {
+    let config = match app.config.with_profile(name) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            app.notifications
+                .push(Notification::new(e.to_string(), NotificationLevel::Error));
+            return;
+        }
+    };
+
+    {
+        let new = match LLMModel::init(&config.llm, config.clone()).await {
+            Ok(new) => new,
+            Err(e) => {
+                app.notifications
+                    .push(Notification::new(e, NotificationLevel::Error));
+                return;
+            }
+        };
+        let mut llm = llm.lock().await;
+        *llm = new;
+        llm.set_messages(app.chat.to_thread().messages());
+    }
+
+    app.backend = config.llm;
+    app.backend_override = None;
+    app.config = config;
+
+    app.notifications.push(Notification::new(
+        format!("Profile: {}", name),
+        NotificationLevel::Info,
+    ));
+}
+
+/// The answers sent to the model are the ones in the chat
+async fn set_last_answer(llm: Arc<Mutex<Box<dyn LLM + 'static>>>, answer: Option<String>) {
+    let mut llm = llm.lock().await;
+    let mut messages = llm.messages();
+    let assistant = LLMRole::ASSISTANT.to_string();
+    if messages.last().and_then(|message| message.get("role")) != Some(&assistant) {
+        return;
+    }
+    match answer {
+        Some(answer) => {
+            if let Some(message) = messages.last_mut() {
+                message.insert("content".to_string(), answer);
+            }
+        }
+        None => {
+            messages.pop();
+        }
+    }
+    llm.set_messages(messages);
+}
+
+/// Put back the answer of the chat being regenerated, after its regeneration failed or was
+/// stopped
+pub async fn keep_previous_answer(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    chat_id: usize,
+) {
+    let formatter = app.formatter;
+    let Some(answer) = app
+        .chat_by_id(chat_id)
+        .and_then(|chat| chat.restore_previous_answer(formatter))
+    else {
+        return;
+    };
+
+    // The llm holds the conversation of the active chat only
+    if app.chat.id == chat_id {
+        set_last_answer(llm.clone(), None).await;
+        llm.lock().await.append_chat_msg(answer, LLMRole::ASSISTANT);
+    }
+
+    app.notifications.push(Notification::new(
+        "The answer was not regenerated, the previous one is kept".to_string(),
+        NotificationLevel::Info,
+    ));
+}
+
+/// Apply an event of the answer to the chat that asked for it. Its end is added to the
+/// conversation of the llm, runs the hooks and the alerts, and goes on with the pipeline.
+pub async fn handle_answer(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    id: RequestId,
+    answer: LLMAnswer,
+) {
+    // Answers of an aborted request, or of a chat that is gone
+    if !app.is_answering(id) {
+        return;
+    }
+
+    let formatter = app.formatter;
+    match answer {
+        LLMAnswer::Answer(answer) => {
+            if let Some(tee) = app.tee.as_mut() {
+                tee.chunk(&formatter.authors().assistant, &answer);
+            }
+            if let Some(chat) = app.chat_by_id(id.chat) {
+                chat.handle_answer(LLMAnswer::Answer(answer), formatter);
+            }
+            app.check_answer_length(id);
+        }
+        LLMAnswer::EndAnswer => end_answer(app, llm, sender, id).await,
+        LLMAnswer::StartAnswer => {
+            app.spinner.active = false;
+            if let Some(chat) = app.chat_by_id(id.chat) {
+                chat.handle_answer(LLMAnswer::StartAnswer, formatter);
+            }
+        }
+        event => {
+            if let Some(chat) = app.chat_by_id(id.chat) {
+                chat.handle_answer(event, formatter);
+            }
+        }
+    }
+}
+
+async fn end_answer(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    id: RequestId,
+) {
+    let formatter = app.formatter;
+
+    // The llm holds the conversation of the active chat, not the one of a detached chat
+    let detached = app.detached.iter().any(|chat| chat.id == id.chat);
+    if !detached {
+        if let Some(chat) = app.chat_by_id(id.chat) {
+            let answer = chat.answer.plain_answer().to_string();
+            let mut llm = llm.lock().await;
+            llm.append_chat_msg(answer, LLMRole::ASSISTANT);
+        }
+    }
+
+    let stopped = app
+        .terminate_response_signal
+        .swap(false, std::sync::atomic::Ordering::Relaxed);
+
+    if let Some(tee) = app.tee.as_mut() {
+        tee.end(None);
+    }
+
+    let model = app.config.backend_model_name(&app.backend);
+    let Some(chat) = app.chat_by_id(id.chat) else {
+        return;
+    };
+    chat.handle_answer(LLMAnswer::EndAnswer, formatter);
+    if let Some(timestamp) = chat.timestamps.last_mut() {
+        timestamp.model = Some(model.clone());
+    }
+    if chat.regenerating && stopped {
+        keep_previous_answer(app, llm.clone(), id.chat).await;
+        return;
+    }
+    chat.regenerating = false;
+    let regenerated = chat.previous_answer.is_some();
+    let chat_variables = chat.variables.clone();
+    let answer = chat
+        .plain_chat
+        .last()
+        .map(|message| message.trim_start_matches(authors::ASSISTANT).to_string());
+    let duration = chat.timestamps.last().and_then(|t| t.duration);
+    if let Some(timestamp) = chat.timestamps.last() {
+        tracing::debug!(
+            duration = timestamp.duration,
+            tokens = timestamp.tokens,
+            first_token = timestamp.first_token,
+            inter_chunk = timestamp.inter_chunk,
+            "answer ended"
+        );
+    }
+
+    if let Some(answer) = answer {
+        if let Some(duration) = duration {
+            if let Err(e) = alert::answer_ended(
+                &app.config.alert,
+                app.terminal_focused,
+                duration,
+                &model,
+                &answer,
+            ) {
+                app.notifications
+                    .push(Notification::new(e, NotificationLevel::Warning));
+            }
+        }
+        let mut variables = app.config.variables.clone();
+        variables.extend(chat_variables);
+        hooks::run(
+            &app.config.hooks,
+            HookRole::Assistant,
+            answer.trim_end(),
+            &model,
+            &variables,
+            &sender,
+        );
+    }
+    if regenerated {
+        app.notifications.push(Notification::new(
+            "Answer regenerated, `D` shows what changed".to_string(),
+            NotificationLevel::Info,
+        ));
+    }
+
+    if detached {
+        save_detached(app, llm.clone(), id.chat).await;
+    } else if app.branched.is_some() {
+        merge_branch(app, llm.clone(), sender.clone()).await;
+    } else if app.config.generate_titles
+        && app.chat.title.is_none()
+        && app.chat.plain_chat.len() == 2
+    {
+        generate_title(app, llm.clone(), sender.clone());
+    }
+
+    if app.pipeline.as_ref().is_some_and(|p| p.chat == id.chat) {
+        if detached {
+            stop_pipeline(app);
+        } else if let Some(pipeline) = app.pipeline.as_mut() {
+            let answer = app.chat.plain_chat.last().map_or("", |message| {
+                message.trim_start_matches(authors::ASSISTANT).trim_end()
+            });
+            pipeline.answered(answer);
+            send_pipeline_step(app, llm, sender).await;
+        }
+    }
+}
+
+/// Show the error of the request in the chat that sent it, the answer being regenerated is
+/// kept
+pub async fn handle_answer_error(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    id: RequestId,
+    error: LLMError,
+) {
+    if !app.is_answering(id) {
+        return;
+    }
+
+    tracing::error!(status = error.status, "{}", error.message);
+    if let Some(tee) = app.tee.as_mut() {
+        tee.end(Some(&error.message));
+    }
+    let formatter = app.formatter;
+    let waiting = std::mem::take(&mut app.spinner.active);
+    if let Some(chat) = app.chat_by_id(id.chat) {
+        if waiting {
+            chat.handle_answer(LLMAnswer::StartAnswer, formatter);
+        }
+        chat.handle_error(&error, formatter);
+    }
+    keep_previous_answer(app, llm.clone(), id.chat).await;
+    app.terminate_response_signal
+        .store(false, std::sync::atomic::Ordering::Relaxed);
+
+    if app.detached.iter().any(|chat| chat.id == id.chat) {
+        save_detached(app, llm.clone(), id.chat).await;
+    } else if app.branched.is_some() {
+        merge_branch(app, llm.clone(), sender).await;
+    }
+    if app.pipeline.as_ref().is_some_and(|p| p.chat == id.chat) {
+        stop_pipeline(app);
+    }
+
+    app.notifications.push(Notification::new(
+        error.to_string(),
+        NotificationLevel::Error,
+    ));
+}
+
+async fn handle_answer_diff(
+    app: &mut App<'_>,
+    key_event: KeyEvent,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+) {
+    let Some(diff) = app.answer_diff.as_mut() else {
+        app.focused_block = FocusedBlock::Chat;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => diff.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => diff.scroll_up(),
+
+        // Keep the replaced answer
+        KeyCode::Char('o') => {
+            if let Some(diff) = app.answer_diff.take() {
+                app.focused_block = diff.previous_focus;
+            }
+            if let Some(answer) = app.chat.swap_answers(app.formatter) {
+                set_last_answer(llm, Some(answer)).await;
+                app.notifications.push(Notification::new(
+                    "The replaced answer is back, `D` compares them again".to_string(),
+                    NotificationLevel::Info,
+                ));
+            }
+        }
+
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => {
+            if let Some(diff) = app.answer_diff.take() {
+                app.focused_block = diff.previous_focus;
+            }
+        }
+
+        _ => {}
+    }
+}
+
+async fn handle_profile_switcher(
+    app: &mut App<'_>,
+    key_event: KeyEvent,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+) {
+    let Some(switcher) = app.profile_switcher.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => switcher.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => switcher.scroll_up(),
+
+        KeyCode::Enter => {
+            let name = switcher.selected().cloned();
+            app.profile_switcher = None;
+            app.focused_block = FocusedBlock::Prompt;
+            if let Some(name) = name {
+                switch_profile(app, llm, &name).await;
+            }
+        }
+
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.profile_switcher = None;
+            app.focused_block = FocusedBlock::Prompt;
+        }
+
+        _ => {}
+    }
+}
+
+/// The command and the path typed after `/attach` or `/file`, if the prompt is one of them
+fn attach_path(app: &App) -> Option<(&'static str, String)> {
+    match app.prompt.editor.lines() {
+        [line] => ["/attach ", "/file "].into_iter().find_map(|command| {
+            line.strip_prefix(command)
+                .map(|path| (command, path.to_string()))
+        }),
+        _ => None,
+    }
+}
+
+/// Complete the path typed after `/attach` or `/file`, or list the candidates
+fn complete_attach_path(app: &mut App, command: &str, partial: &str) {
+    let paths = picker::completions(partial);
+
+    match paths.len() {
+        0 => app.notifications.push(Notification::new(
+            format!("No file matches `{}`", partial),
+            NotificationLevel::Warning,
+        )),
+        1 => app.prompt.set_text(&format!("{}{}", command, paths[0])),
+        _ => {
+            app.prompt
+                .set_text(&format!("{}{}", command, picker::common_prefix(&paths)));
+            app.file_picker = Some(FilePicker::new(paths));
+            app.focused_block = FocusedBlock::FilePicker;
+        }
+    }
+}
+
+fn handle_file_picker(app: &mut App, key_event: KeyEvent) {
+    let Some(file_picker) = app.file_picker.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => file_picker.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => file_picker.scroll_up(),
+
+        KeyCode::Enter | KeyCode::Tab => {
+            let path = file_picker.selected().cloned();
+            app.file_picker = None;
+            app.focused_block = FocusedBlock::Prompt;
+            // The prompt still holds the command being completed
+            let command = attach_path(app).map_or("/attach ", |(command, _)| command);
+            if let Some(path) = path {
+                app.prompt.set_text(&format!("{}{}", command, path));
+            }
+        }
+
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.file_picker = None;
+            app.focused_block = FocusedBlock::Prompt;
+        }
+
+        _ => {}
+    }
+}
+
+fn handle_corrections(app: &mut App, key_event: KeyEvent) {
+    let Some(corrections) = app.corrections.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => corrections.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => corrections.scroll_up(),
+
+        KeyCode::Enter => {
+            if let Some(corrections) = app.corrections.take() {
+                if let Some(suggestion) = corrections.selected() {
+                    app.prompt.replace(
+                        (corrections.row, corrections.start, corrections.end),
+                        suggestion,
+                    );
+                }
+                app.focused_block = corrections.previous_focus;
+            }
+        }
+
+        // Accept the word until tenere quits
+        KeyCode::Char('a') => {
+            if let Some(corrections) = app.corrections.take() {
+                if let Some(dictionary) = app.dictionary.as_mut() {
+                    dictionary.add(&corrections.word);
+                    app.prompt.recheck_spelling();
+                }
+                app.focused_block = corrections.previous_focus;
+            }
+        }
+
+        KeyCode::Esc | KeyCode::Char('q') => {
+            if let Some(corrections) = app.corrections.take() {
+                app.focused_block = corrections.previous_focus;
+            }
+        }
+
+        _ => {}
+    }
+}
+
+fn handle_register_viewer(app: &mut App, key_event: KeyEvent) {
+    let Some(viewer) = app.register_viewer.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => viewer.scroll_down(),
+
+        KeyCode::Char('k') | KeyCode::Up => viewer.scroll_up(),
+
+        KeyCode::Enter => {
+            let text = viewer.selected().map(str::to_string);
+            app.register_viewer = None;
+            app.focused_block = FocusedBlock::Prompt;
+            if let Some(text) = text {
+                app.prompt.editor.insert_str(text);
+            }
+            app.prompt.update(&app.focused_block);
+        }
+
+        KeyCode::Esc | KeyCode::Char('q') => {
+            if let Some(viewer) = app.register_viewer.take() {
+                app.focused_block = viewer.previous_focus;
+            }
+        }
+
+        _ => {}
+    }
+}
+
+fn handle_scrubber(app: &mut App, key_event: KeyEvent) {
+    let Some(scrubber) = app.scrubber.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+    let recordings = &app.chat.recordings;
+
+    match key_event.code {
+        KeyCode::Char('l') | KeyCode::Right => scrubber.step(recordings, 1),
+        KeyCode::Char('h') | KeyCode::Left => scrubber.step(recordings, -1),
+        KeyCode::Char('L') => scrubber.step(recordings, 10),
+        KeyCode::Char('H') => scrubber.step(recordings, -10),
+        KeyCode::Char('0') => scrubber.go_to(0),
+        KeyCode::Char('$') => scrubber.go_to(recordings[scrubber.recording].len()),
+        KeyCode::Char('n') => scrubber.switch(recordings, 1),
+        KeyCode::Char('p') => scrubber.switch(recordings, -1),
+        KeyCode::Char(' ') => scrubber.toggle_play(recordings),
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.scrubber = None;
+            app.focused_block = FocusedBlock::Chat;
+        }
+        _ => {}
+    }
+}
+
+fn handle_log_viewer(app: &mut App, key_event: KeyEvent) {
+    let Some(viewer) = app.log_viewer.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => viewer.scroll_down(1),
+        KeyCode::Char('k') | KeyCode::Up => viewer.scroll_up(1),
+        KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => {
+            viewer.scroll_down(20)
+        }
+        KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => viewer.scroll_up(20),
+        KeyCode::Char('G') => viewer.scroll_down(usize::MAX),
+        KeyCode::Char('g') => viewer.scroll_up(usize::MAX),
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => {
+            app.log_viewer = None;
+            app.focused_block = FocusedBlock::Chat;
+        }
+        _ => {}
+    }
+}
+
+/// Archive the current chat and start an empty one
+async fn new_chat(app: &mut App<'_>, llm: Arc<Mutex<Box<dyn LLM + 'static>>>) {
+    if wait_detached(app) {
+        return;
+    }
+    abort_answer(app).await;
+    stop_pipeline(app);
+    if let Some(chat) = app.branched.take() {
+        app.history.push(chat.to_thread());
+        app.save_history();
+    }
+    app.queued_prompt = None;
+
+    let text = app.prompt.editor.lines().join("\n");
+    let attachments = app.prompt.attachments.clone();
+    app.prompt.clear();
+
+    app.archive_chat();
+
+    let chat = std::mem::take(&mut app.chat);
+    if !chat.plain_chat.is_empty() || !text.is_empty() || !attachments.is_empty() {
+        app.undo.push(Operation::Chat {
+            chat: Box::new(chat),
+            text,
+            attachments,
+        });
+    }
+
+    {
+        let mut llm = llm.lock().await;
+        llm.clear();
+    }
+    apply_preferred_backend(app, llm.clone()).await;
+
+    app.chat.scroll = 0;
+}
+
+/// Use the changed config for the next requests, the conversation goes on
+async fn reload_llm(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    config: Config,
+) -> Result<(), String> {
+    if app.spinner.active || app.branched.is_some() {
+        return Err(String::from(
+            "Wait for the answer before changing the settings",
+        ));
+    }
+
+    let config = Arc::new(config);
+    {
+        let new = LLMModel::init(&app.backend, config.clone()).await?;
+        let mut llm = llm.lock().await;
+        let messages = llm.messages();
+        *llm = new;
+        llm.set_messages(messages);
+    }
+    app.config = config;
+
+    Ok(())
+}
+
+/// Change the model of the backend in use
+async fn set_model(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    model: String,
+) -> Result<String, String> {
+    let mut config = (*app.config).clone();
+    match app.backend {
+        LLMBackend::ChatGPT => config.chatgpt.model = model.clone(),
+        LLMBackend::Ollama => {
+            if let Some(ollama) = config.ollama.as_mut() {
+                ollama.model = model.clone();
+            }
+        }
+        LLMBackend::Mock => {
+            if let Some(mock) = config.mock.as_mut() {
+                mock.model = model.clone();
+            }
+        }
+        LLMBackend::LLamacpp => {
+            return Err(String::from(
+                "llama.cpp answers with the model the server was started with",
+            ))
+        }
+    }
+
+    reload_llm(app, llm, config).await?;
+    Ok(format!("Model: {}", model))
+}
+
+/// Change a parameter of the requests, or show its value
+async fn set_param(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    name: &str,
+    value: Option<String>,
+) -> Result<String, String> {
+    let params = app.config.backend_params(&app.backend);
+    let Some(value) = value else {
+        let current = match name {
+            "temperature" => params.temperature.map(|t| t.to_string()),
+            "max_tokens" => params.max_tokens.map(|t| t.to_string()),
+            "reasoning_effort" => params.reasoning_effort,
+            _ => params.system_prompt,
+        };
+        return Ok(format!(
+            "{}={}",
+            name,
+            current.unwrap_or_else(|| String::from("(default)"))
+        ));
+    };
+
+    let mut config = (*app.config).clone();
+    // An empty value goes back to the default of the backend
+    let value = Some(value).filter(|v| !v.is_empty());
+    match name {
+        "temperature" => {
+            config.params.temperature = value
+                .map(|v| match v.parse::<f32>() {
+                    Ok(t) if (0.0..=2.0).contains(&t) => Ok(t),
+                    _ => Err(format!("`{}` is not a temperature between 0 and 2", v)),
+                })
+                .transpose()?;
+        }
+        "max_tokens" => {
+            config.params.max_tokens = value
+                .map(|v| match v.parse::<u32>() {
+                    Ok(n) if n > 0 => Ok(n),
+                    _ => Err(format!("`{}` is not a positive number", v)),
+                })
+                .transpose()?;
+
+            // The limit of the backend section would take precedence
+            match app.backend {
+                LLMBackend::ChatGPT => config.chatgpt.max_tokens = None,
+                LLMBackend::LLamacpp => {
+                    if let Some(llamacpp) = config.llamacpp.as_mut() {
+                        llamacpp.max_tokens = None;
+                    }
+                }
+                LLMBackend::Ollama => {
+                    if let Some(ollama) = config.ollama.as_mut() {
+                        ollama.max_tokens = None;
+                    }
+                }
+                LLMBackend::Mock => {}
+            }
+        }
+        "reasoning_effort" => {
+            config.params.reasoning_effort = value
+                .map(|v| match v.as_str() {
+                    "minimal" | "low" | "medium" | "high" => Ok(v),
+                    _ => Err(format!("`{}` is not minimal, low, medium or high", v)),
+                })
+                .transpose()?;
+        }
+        _ => config.params.system_prompt = value,
+    }
+
+    reload_llm(app, llm, config).await?;
+    Ok(format!("{} updated", name))
+}
+
+/// Run the command typed after `:`
+async fn run_ex_command(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    command: ExCommand,
+) -> Result<Option<String>, String> {
+    match command {
+        ExCommand::Write(path) => {
+            if app.chat.plain_chat.is_empty() {
+                return Err(String::from("The chat is empty"));
+            }
+            request_export(
+                app,
+                Export {
+                    action: ExportAction::Save,
+                    source: ExportSource::Chat,
+                    path,
+                },
+                sender,
+            );
+            Ok(None)
+        }
+        ExCommand::ExportHtml(path) => {
+            if app.chat.plain_chat.is_empty() {
+                return Err(String::from("The chat is empty"));
+            }
+            request_export(
+                app,
+                Export {
+                    action: ExportAction::SaveHtml,
+                    source: ExportSource::Chat,
+                    path,
+                },
+                sender,
+            );
+            Ok(None)
+        }
+        ExCommand::Quit => {
+            app.quit();
+            Ok(None)
+        }
+        ExCommand::New => {
+            new_chat(app, llm).await;
+            Ok(None)
+        }
+        ExCommand::History => {
+            app.focused_block = FocusedBlock::History;
+            app.prompt.update(&app.focused_block);
+            Ok(None)
+        }
+        ExCommand::Registers if app.registers.is_empty() => Err(String::from(
+            "The registers are empty, yank into one with \"ay",
+        )),
+        ExCommand::Diagnostics => {
+            let compared = app.compare.iter().flat_map(|compare| compare.panes.iter());
+            let timestamps = app
+                .chat
+                .timestamps
+                .iter()
+                .chain(app.history.threads.iter().flat_map(|t| t.timestamps.iter()))
+                .chain(compared.flat_map(|pane| pane.chat.timestamps.iter()));
+            app.diagnostics = Some(Diagnostics::new(timestamps, app.focused_block.clone()));
+            app.focused_block = FocusedBlock::Diagnostics;
+            Ok(None)
+        }
+        ExCommand::Registers => {
+            app.register_viewer = Some(RegisterViewer::new(
+                &app.registers,
+                app.focused_block.clone(),
+            ));
+            app.focused_block = FocusedBlock::Registers;
+            Ok(None)
+        }
+        ExCommand::Model(model) => set_model(app, llm, model).await.map(Some),
+        ExCommand::Set(name, value) => set_param(app, llm, &name, value).await.map(Some),
+        ExCommand::Compare(backends) => toggle_compare(app, backends).await.map(Some),
+        ExCommand::Tag(tags) => tag(app, tags).map(Some),
+        ExCommand::Tee(Some(path)) => {
+            let tee = Tee::open(&path)?;
+            let message = format!("Writing the messages to `{}`", tee.path.display());
+            app.tee = Some(tee);
+            Ok(Some(message))
+        }
+        ExCommand::Tee(None) => match app.tee.take() {
+            Some(tee) => Ok(Some(format!(
+                "Stopped writing the messages to `{}`",
+                tee.path.display()
+            ))),
+            None => Err(String::from("Usage: :tee PATH")),
+        },
+    }
+}
+
+/// Tag the thread selected in the history, or the current chat
+fn tag(app: &mut App<'_>, tags: Option<Vec<String>>) -> Result<String, String> {
+    let in_history = matches!(
+        app.focused_block,
+        FocusedBlock::History | FocusedBlock::Preview
+    );
+    let current = match app.history.selected() {
+        Some(i) if in_history => &mut app.history.threads[i].tags,
+        _ => &mut app.chat.tags,
+    };
+
+    let Some(tags) = tags else {
+        return Ok(if current.is_empty() {
+            String::from("No tags")
+        } else {
+            format!("Tags: {}", current.join(", "))
+        });
+    };
+
+    *current = tags;
+    let message = if current.is_empty() {
+        String::from("Tags removed")
+    } else {
+        format!("Tagged {}", current.join(", "))
+    };
+    if in_history {
+        app.save_history();
+    }
+    Ok(message)
+}
+
+/// Start the compare mode with two backends, or end it and keep its chats in the history
+async fn toggle_compare(app: &mut App<'_>, backends: Vec<String>) -> Result<String, String> {
+    if let Some(compare) = app.compare.as_ref() {
+        if !backends.is_empty() {
+            return Err(String::from("Already comparing, `:compare` ends it"));
+        }
+        if compare.is_streaming() {
+            return Err(String::from("Wait for the answers to end"));
+        }
+
+        if let Some(compare) = app.compare.take() {
+            for chat in compare.into_chats() {
+                app.history.push(chat.to_thread());
+            }
+            app.save_history();
+        }
+        return Ok(String::from(
+            "Compare mode ended, its chats are in the history",
+        ));
+    }
+
+    let backends: Vec<LLMBackend> = if backends.is_empty() {
+        app.config.backends().into_iter().take(2).collect()
+    } else {
+        backends
+            .iter()
+            .map(|name| {
+                LLMBackend::from_str(name).map_err(|_| format!("Unknown backend `{}`", name))
+            })
+            .collect::<Result<_, _>>()?
+    };
+    let [first, second] = backends[..] else {
+        return Err(String::from(
+            "Configure a second backend or use `:compare BACKEND BACKEND`",
+        ));
+    };
+
+    let compare = Compare::new([first, second], app.config.clone()).await?;
+    app.compare = Some(compare);
+    app.focused_block = FocusedBlock::Prompt;
+    app.prompt.update(&app.focused_block);
+
+    Ok(format!(
+        "Comparing {} and {}, `:compare` ends it",
+        first, second
+    ))
+}
+
+async fn handle_command_line(
+    app: &mut App<'_>,
+    key_event: KeyEvent,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) {
+    let Some(command_line) = app.command_line.as_mut() else {
+        app.focused_block = FocusedBlock::Prompt;
+        return;
+    };
+
+    // The pop-up of the completions takes the keys moving in it
+    if let Some(completion) = app.completion.as_mut() {
+        match key_event.code {
+            KeyCode::Tab | KeyCode::Down => return completion.next(),
+            KeyCode::BackTab | KeyCode::Up => return completion.previous(),
+            KeyCode::Enter => {
+                command_line.input = completion.accept(&command_line.input);
+                app.completion = command_line.completion(&app.config.known_models());
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    match key_event.code {
+        KeyCode::Char(c) => command_line.input.push(c),
+
+        // Like vim, erasing the `:` closes the command line
+        KeyCode::Backspace if command_line.input.is_empty() => {
+            app.focused_block = command_line.previous_focus.clone();
+            app.command_line = None;
+        }
+
+        KeyCode::Backspace => {
+            command_line.input.pop();
+        }
+
+        KeyCode::Tab => match command_line.complete() {
+            Ok(None) => {}
+            Ok(Some(candidates)) => app
+                .notifications
+                .push(Notification::new(candidates, NotificationLevel::Info)),
+            Err(e) => app
+                .notifications
+                .push(Notification::new(e, NotificationLevel::Warning)),
+        },
+
+        KeyCode::Enter => {
+            let input = command_line.input.clone();
+            app.focused_block = command_line.previous_focus.clone();
+            app.command_line = None;
+            app.completion = None;
+
+            let result = match ExCommand::parse(&input) {
+                Ok(command) => run_ex_command(app, llm, sender, command).await,
+                Err(e) => Err(e),
+            };
+            let notif = match result {
+                Ok(Some(msg)) => Notification::new(msg, NotificationLevel::Info),
+                Ok(None) => return,
+                Err(e) => Notification::new(e, NotificationLevel::Error),
+            };
+            app.notifications.push(notif);
+        }
+
+        KeyCode::Esc => {
+            app.focused_block = command_line.previous_focus.clone();
+            app.command_line = None;
+        }
+
+        _ => {}
+    }
+
+    app.completion = app
+        .command_line
+        .as_ref()
+        .and_then(|command_line| command_line.completion(&app.config.known_models()));
+}
+
+/// Send the prompt to the LLM, or to both backends in the compare mode
+pub async fn send_prompt(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) {
+    send_input(app, llm, sender, true).await;
+}
+
+/// Send the prompt, its slash command is run instead if `commands` is set
+async fn send_input(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    commands: bool,
+) {
+    let user_input = app.prompt.editor.lines().join("\n");
+    let user_input = user_input.trim();
+    if user_input.is_empty() && app.prompt.attachments.is_empty() {
+        return;
+    }
+
+    if app.compare.as_ref().is_some_and(|c| c.is_streaming()) {
+        app.notifications.push(Notification::new(
+            "Wait for the answers to end".to_string(),
+            NotificationLevel::Warning,
+        ));
+        return;
+    }
+
+    if wait_detached(app) {
+        return;
+    }
+
+    if app.queued_prompt.is_some() {
+        app.notifications.push(Notification::new(
+            "A message is already waiting for the branched answer to end".to_string(),
+            NotificationLevel::Warning,
+        ));
+        return;
+    }
+
+    if app.pre_sending {
+        app.notifications.push(Notification::new(
+            "Wait for the pre-send hooks to end".to_string(),
+            NotificationLevel::Warning,
+        ));
+        return;
+    }
+
+    if commands && command::run(app, user_input, &sender) {
+        app.save_draft();
+        // `/pipeline` goes on with its first step
+        if app
+            .pipeline
+            .as_ref()
+            .is_some_and(|pipeline| pipeline.progress().0 == 0)
+        {
+            let _ = sender.send(Event::PipelineStep);
+        }
+        return;
+    }
+
+    // The variables of the chat before the ones of the config
+    let mut variables = app.variables();
+    if let Some(pipeline) = app
+        .pipeline
+        .as_ref()
+        .filter(|pipeline| pipeline.chat == app.chat.id)
+    {
+        variables.extend(pipeline.variables());
+    }
+    let expanded = variables::expand(user_input, &variables);
+
+    let prompt = user_input.to_string();
+    let attachments = std::mem::take(&mut app.prompt.attachments);
+    app.prompt.clear();
+    app.save_draft();
+
+    let config = app.config.clone();
+    if config.pre_send.is_empty() {
+        send_message(app, llm, sender, &expanded, attachments).await;
+        return;
+    }
+
+    // Read at once from the clipboard of the desktop, its paste command runs with the hooks
+    let clipboard = config
+        .pre_send
+        .iter()
+        .any(|hook| hook.builtin == Some(Transformer::Clipboard))
+        .then(|| app.clipboard.paste_source());
+    let model = config.backend_model_name(&app.backend);
+    let chat = app.chat.id;
+
+    // The commands of the hooks and the translation take time, the screen is still drawn
+    app.pre_sending = true;
+    tokio::spawn(async move {
+        let mut attachments = attachments;
+        let result = hooks::pre_send(
+            &config.pre_send,
+            &expanded,
+            &model,
+            &variables,
+            clipboard,
+            &mut attachments,
+            &llm,
+        )
+        .await;
+        let _ = sender.send(Event::PreSent(PreSent {
+            chat,
+            prompt,
+            attachments,
+            result,
+        }));
+    });
+}
+
+/// Send the prompt changed by the pre-send hooks, or put it back in the prompt if one of
+/// them failed
+pub async fn handle_pre_sent(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    pre_sent: PreSent,
+) {
+    app.pre_sending = false;
+
+    let result = pre_sent.result.and_then(|text| {
+        if pre_sent.chat == app.chat.id {
+            Ok(text)
+        } else {
+            Err(String::from("The chat changed before the prompt was sent"))
+        }
+    });
+    match result {
+        Ok(text) => send_message(app, llm, sender, &text, pre_sent.attachments).await,
+        // The prompt is kept to be changed, unless another one is being typed
+        Err(e) => {
+            app.notifications
+                .push(Notification::new(e, NotificationLevel::Error));
+            if app.prompt.editor.is_empty() && app.prompt.attachments.is_empty() {
+                app.prompt.set_text(&pre_sent.prompt);
+                app.prompt.attachments = pre_sent.attachments;
+                app.save_draft();
+            }
+        }
+    }
+}
+
+/// Send the text and the attachments of the prompt to the chat, or to the compare mode
+async fn send_message(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    user_input: &str,
+    attachments: Vec<Attachment>,
+) {
+    if user_input.is_empty() && attachments.is_empty() {
+        return;
+    }
+
+    // The secrets are replaced in the chat as well, it shows what was sent
+    let (payload_text, redacted) = redaction::redact(
+        &attachment::payload(&attachments, user_input),
+        &app.redaction_rules,
+    );
+    let (user_input, _) = redaction::redact(user_input, &app.redaction_rules);
+    if !redacted.is_empty() {
+        app.notifications.push(Notification::new(
+            format!("Redacted before sending: {}", redaction::summary(&redacted)),
+            NotificationLevel::Warning,
+        ));
+    }
+
+    let payload = message_with_images(
+        LLMRole::USER,
+        &payload_text,
+        &attachment::images(&attachments),
+    );
+    let user_input = attachments.iter().fold(user_input.to_string(), |acc, a| {
+        format!("{}\n{}", acc, a.marker())
+    });
+    let user_input = user_input.trim();
+
+    hooks::run(
+        &app.config.hooks,
+        HookRole::User,
+        user_input,
+        &app.config.backend_model_name(&app.backend),
+        &app.variables(),
+        &sender,
+    );
+
+    let formatter = app.formatter;
+    if let Some(compare) = app.compare.as_mut() {
+        compare
+            .ask(
+                payload,
+                user_input,
+                formatter,
+                sender.clone(),
+                app.terminate_response_signal.clone(),
+            )
+            .await;
+        return;
+    }
+
+    app.chat
+        .plain_chat
+        .push(format!("{}{}\n", authors::USER, user_input));
+    app.chat.stamp();
+    if let Some(tee) = app.tee.as_mut() {
+        tee.question(
+            &app.formatter
+                .authors()
+                .display(&format!("{}{}", authors::USER, user_input)),
+        );
+    }
+    app.chat.previous_answer = None;
+
+    let formatted_input = app.formatter.format_with(
+        &format!("{}{}\n", authors::USER, user_input),
+        &app.chat.render,
+    );
+    if app.chat.formatted_chat.width() == 0 {
+        app.chat.formatted_chat = formatted_input;
+    } else {
+        app.chat.formatted_chat.extend(formatted_input);
+    }
+
+    // The llm is busy with the answer of the branched chat
+    if app.branched.is_some() {
+        app.queued_prompt = Some(payload);
+        app.notifications.push(Notification::new(
+            "The message will be sent once the branched answer ends".to_string(),
+            NotificationLevel::Info,
+        ));
+        return;
+    }
+
+    {
+        let mut llm = llm.lock().await;
+        let mut messages = llm.messages();
+        messages.push(payload);
+        llm.set_messages(messages);
+    }
+
+    ask(app, llm, sender);
+}
+
+/// Send the text as if it was typed in the prompt, as a message only unless `commands` is set.
+/// The prompt being written is kept.
+async fn send_text(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    text: &str,
+    commands: bool,
+) {
+    let draft = app.prompt.editor.lines().join("\n");
+    let attachments = std::mem::take(&mut app.prompt.attachments);
+    app.prompt.set_text(text);
+
+    send_input(app, llm, sender, commands).await;
+
+    app.prompt.set_text(&draft);
+    app.prompt.attachments = attachments;
+}
+
+/// Send the next step of the pipeline, or end it after the last one
+pub async fn send_pipeline_step(
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) {
+    let Some(pipeline) = app.pipeline.as_mut() else {
+        return;
+    };
+    if pipeline.chat != app.chat.id {
+        stop_pipeline(app);
+        return;
+    }
+
+    let Some(step) = pipeline.next_step() else {
+        app.notifications.push(Notification::new(
+            format!("Pipeline `{}` done", pipeline.name),
+            NotificationLevel::Info,
+        ));
+        app.pipeline = None;
+        return;
+    };
+    let (current, total) = pipeline.progress();
+    app.notifications.push(Notification::new(
+        format!("Pipeline `{}`: step {}/{}", pipeline.name, current, total),
+        NotificationLevel::Info,
+    ));
+
+    // A step starting with `/` is a message too, not a command
+    send_text(app, llm, sender, &step, false).await;
+}
+
+/// Stop the pipeline, the steps already answered stay in the chat
+pub fn stop_pipeline(app: &mut App) {
+    if let Some(pipeline) = app.pipeline.take() {
+        let (current, total) = pipeline.progress();
+        app.notifications.push(Notification::new(
+            format!(
+                "Pipeline `{}` stopped at step {}/{}",
+                pipeline.name, current, total
+            ),
+            NotificationLevel::Warning,
+        ));
+    }
+}
+
+/// Run a command received on the control socket. The prompt being written is kept.
+pub async fn handle_control(
+    app: &mut App<'_>,
+    command: ControlCommand,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) {
+    let command = match command {
+        ControlCommand::Send(text) => {
+            send_text(app, llm, sender, &text, true).await;
+            return;
+        }
+        ControlCommand::New => ExCommand::New,
+        ControlCommand::Export(path) => ExCommand::Write(path),
+        // Asked by another program, no one is there to confirm
+        ControlCommand::Quit => {
+            app.running = false;
+            return;
+        }
+    };
+
+    let notif = match run_ex_command(app, llm, sender, command).await {
+        Ok(Some(msg)) => Notification::new(msg, NotificationLevel::Info),
+        Ok(None) => return,
+        Err(e) => Notification::new(e, NotificationLevel::Error),
+    };
+    app.notifications.push(notif);
+}
+
+/// Make a thread of the history the active chat, the current chat goes to the history.
+/// A chat still answering keeps receiving its answer in the background.
+async fn load_thread(app: &mut App<'_>, llm: Arc<Mutex<Box<dyn LLM + 'static>>>, i: usize) {
+    let thread = app.history.threads[i].clone();
+    if thread.id != app.chat.id {
+        app.archive_chat();
+
+        // The llm is busy until the answer ends, the conversation is sent to it then
+        if app.chat.request.is_some() {
+            let chat = std::mem::take(&mut app.chat);
+            app.detached.push(chat);
+        } else {
+            let mut llm = llm.lock().await;
+            llm.set_messages(thread.messages());
+        }
+
+        app.chat = Chat::from_thread(thread);
+    }
+    app.prompt.clear();
+    app.focused_block = FocusedBlock::Prompt;
+    app.prompt.update(&app.focused_block);
+}
+
+/// Insert a bracketed paste in one go, instead of a key event per character
+pub fn handle_paste(app: &mut App, text: String) {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
+    match app.focused_block {
+        FocusedBlock::Prompt => {
+            app.prompt.editor.insert_str(text);
+            if app.prompt.mode != Mode::Insert {
+                app.prompt.mode = Mode::Insert;
+                app.prompt.update(&app.focused_block);
+            }
+        }
+        FocusedBlock::CommandLine => {
+            if let Some(command_line) = app.command_line.as_mut() {
+                command_line
+                    .input
+                    .push_str(text.lines().next().unwrap_or_default());
+            }
+        }
+        _ => {}
+    }
+}
+
+pub async fn handle_key_events(
+    key_event: KeyEvent,
+    app: &mut App<'_>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+) -> AppResult<()> {
+    app.expire_previous_key();
+    app.previous_key_time = Instant::now();
+
+    if app.focused_block == FocusedBlock::CommandLine {
+        handle_command_line(app, key_event, llm, sender).await;
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Scrubber {
+        handle_scrubber(app, key_event);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Log {
+        handle_log_viewer(app, key_event);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Info {
+        if let KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter = key_event.code {
+            if let Some(info) = app.chat_info.take() {
+                app.focused_block = info.previous_focus;
+            }
+        }
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Diagnostics {
+        if let KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter = key_event.code {
+            if let Some(diagnostics) = app.diagnostics.take() {
+                app.focused_block = diagnostics.previous_focus;
+            }
+        }
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Diff {
+        handle_answer_diff(app, key_event, llm).await;
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Corrections {
+        handle_corrections(app, key_event);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Registers {
+        handle_register_viewer(app, key_event);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::FilePicker {
+        handle_file_picker(app, key_event);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Profiles {
+        handle_profile_switcher(app, key_event, llm).await;
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::CodeBlocks {
+        handle_code_blocks(app, key_event, sender);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::CommandRunner {
+        handle_command_runner(app, key_event, sender);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Share {
+        handle_share(app, key_event, sender);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Quit {
+        handle_quit_confirmation(app, key_event);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    if app.focused_block == FocusedBlock::Privacy {
+        handle_privacy_report(app, key_event, sender);
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
+
+    // Confirm the deletion of a thread from the history
+    if let Some(i) = app.history.pending_deletion.take() {
+        if key_event.code == KeyCode::Char('y') {
+            if let Some(thread) = app.history.threads.get(i).cloned() {
+                app.undo.push(Operation::DeletedThread(i, thread));
+            }
+            app.history.delete(i);
+            app.save_history();
+        }
+        app.previous_key = key_event.code;
+        return Ok(());
+    }
 
-use crate::notification::{Notification, NotificationLevel};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+    // The pop-up of the completions takes the keys moving in it
+    if app.focused_block == FocusedBlock::Prompt {
+        if let Some(completion) = app.completion.as_mut() {
+            match key_event.code {
+                KeyCode::Tab | KeyCode::Down => {
+                    completion.next();
+                    return Ok(());
+                }
+                KeyCode::BackTab | KeyCode::Up => {
+                    completion.previous();
+                    return Ok(());
+                }
+                KeyCode::Enter => {
+                    let text = completion.accept(&app.prompt.editor.lines().join("\n"));
+                    app.prompt.set_text(&text);
+                    app.completion = None;
+                    return Ok(());
+                }
+                KeyCode::Esc => {
+                    app.completion = None;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
 
-use tokio::sync::mpsc::UnboundedSender;
+    // `"a` chooses the register of the next yank or paste
+    let registers = match app.focused_block {
+        FocusedBlock::Chat => true,
+        FocusedBlock::Prompt => app.prompt.mode != Mode::Insert,
+        _ => false,
+    };
+    if registers {
+        if let KeyCode::Char(c) = key_event.code {
+            if app.previous_key == KeyCode::Char('"') && Registers::is_name(c) {
+                app.registers.selected = Some(c);
+                // `"g` is not the start of `gg`
+                app.previous_key = KeyCode::Null;
+                return Ok(());
+            }
+        }
+        if !matches!(key_event.code, KeyCode::Char('y' | 'p' | '"')) {
+            app.registers.selected = None;
+        }
+    }
 
-pub async fn handle_key_events(
-    key_event: KeyEvent,
-    app: &mut App<'_>,
-    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
-    sender: UnboundedSender<Event>,
-) -> AppResult<()> {
     match key_event.code {
         // Quit the app
         KeyCode::Char('q') if app.prompt.mode != Mode::Insert => {
-            app.running = false;
+            app.quit();
         }
 
         KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
-            app.running = false;
+            app.quit();
+        }
+
+        // Suspend to the shell, resumed with `fg`
+        #[cfg(unix)]
+        KeyCode::Char('z') if key_event.modifiers == KeyModifiers::CONTROL => {
+            let _ = sender.send(Event::Suspend);
+        }
+
+        // Show the end of the log file
+        KeyCode::Char('L')
+            if app.prompt.mode != Mode::Insert
+                && matches!(app.focused_block, FocusedBlock::Prompt | FocusedBlock::Chat) =>
+        {
+            app.log_viewer = Some(LogViewer::load());
+            app.focused_block = FocusedBlock::Log;
+        }
+
+        // Open the command line
+        KeyCode::Char(':')
+            if app.prompt.mode != Mode::Insert
+                && matches!(
+                    app.focused_block,
+                    FocusedBlock::Prompt
+                        | FocusedBlock::Chat
+                        | FocusedBlock::History
+                        | FocusedBlock::Preview
+                ) =>
+        {
+            let command_line = CommandLine::new(app.focused_block.clone());
+            app.completion = command_line.completion(&app.config.known_models());
+            app.command_line = Some(command_line);
+            app.focused_block = FocusedBlock::CommandLine;
+            app.previous_key = key_event.code;
+            return Ok(());
+        }
+
+        // Redo what was undone first, then retry the failed request
+        KeyCode::Char('r')
+            if matches!(
+                app.focused_block,
+                FocusedBlock::Chat | FocusedBlock::History
+            ) && key_event.modifiers == KeyModifiers::CONTROL
+                && app.undo.can_redo() =>
+        {
+            undo::redo(app, llm.clone()).await;
+        }
+
+        KeyCode::Char('r')
+            if key_event.modifiers == KeyModifiers::CONTROL && app.chat.failed_request =>
+        {
+            ask(app, llm.clone(), sender.clone());
+        }
+
+        // Undo or redo the last destructive operation
+        KeyCode::Char('u')
+            if matches!(
+                app.focused_block,
+                FocusedBlock::Chat | FocusedBlock::History
+            ) && key_event.modifiers == KeyModifiers::NONE =>
+        {
+            undo::undo(app, llm.clone()).await;
+        }
+
+        KeyCode::Char('r')
+            if matches!(
+                app.focused_block,
+                FocusedBlock::Chat | FocusedBlock::History
+            ) && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            undo::redo(app, llm.clone()).await;
         }
 
         // Terminate the stream response
         KeyCode::Char('t') if key_event.modifiers == KeyModifiers::CONTROL => {
             app.terminate_response_signal
                 .store(true, std::sync::atomic::Ordering::Relaxed);
+            queue::cancel();
+            stop_pipeline(app);
+            if let Some(stop) = app.stop_command.take() {
+                let _ = stop.send(());
+            }
+        }
+
+        // Go through the questions in the split layout
+        KeyCode::Char('j') | KeyCode::Down
+            if app.focused_block == FocusedBlock::Chat && app.layout == ChatLayout::Split =>
+        {
+            app.split.scroll_down(&app.chat.plain_chat);
+        }
+
+        KeyCode::Char('k') | KeyCode::Up
+            if app.focused_block == FocusedBlock::Chat && app.layout == ChatLayout::Split =>
+        {
+            app.split.scroll_up(&app.chat.plain_chat);
+        }
+
+        // Scroll the selected question and its answer
+        KeyCode::Char('J')
+            if app.focused_block == FocusedBlock::Chat && app.layout == ChatLayout::Split =>
+        {
+            app.split.scroll = app.split.scroll.saturating_add(1);
+        }
+
+        KeyCode::Char('K')
+            if app.focused_block == FocusedBlock::Chat && app.layout == ChatLayout::Split =>
+        {
+            app.split.scroll = app.split.scroll.saturating_sub(1);
+        }
+
+        KeyCode::Char('G')
+            if app.focused_block == FocusedBlock::Chat && app.layout == ChatLayout::Split =>
+        {
+            app.split.move_to_bottom();
+        }
+
+        KeyCode::Char('g')
+            if app.previous_key == KeyCode::Char('g')
+                && app.focused_block == FocusedBlock::Chat
+                && app.layout == ChatLayout::Split =>
+        {
+            app.split.move_to_top();
+        }
+
+        // Grow or shrink the prompt
+        KeyCode::Up if key_event.modifiers == KeyModifiers::CONTROL => app.prompt.resize(5),
+
+        KeyCode::Down if key_event.modifiers == KeyModifiers::CONTROL => app.prompt.resize(-5),
+
+        // Switch between the stacked and the split layouts
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.toggle_layout
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            app.toggle_layout();
+
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
+        }
+
+        // Scroll the code lines sideways when they are not wrapped
+        KeyCode::Char('l') | KeyCode::Right
+            if app.focused_block == FocusedBlock::Chat && app.chat.render.nowrap =>
+        {
+            app.chat.scroll_code(4);
+        }
+        KeyCode::Char('h') | KeyCode::Left
+            if app.focused_block == FocusedBlock::Chat && app.chat.render.nowrap =>
+        {
+            app.chat.scroll_code(-4);
+        }
+
+        // Wrap the code lines or scroll them sideways
+        KeyCode::Char('w') if app.focused_block == FocusedBlock::Chat => {
+            app.chat.render.nowrap = !app.chat.render.nowrap;
+            app.chat.reset_code_scroll();
+            let message = if app.chat.render.nowrap {
+                "Code lines not wrapped, h and l scroll them"
+            } else {
+                "Code lines wrapped"
+            };
+            app.notifications.push(Notification::new(
+                message.to_string(),
+                NotificationLevel::Info,
+            ));
+        }
+
+        // Browse the pages of the help
+        KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab
+            if app.focused_block == FocusedBlock::Help =>
+        {
+            app.help.next_page();
+        }
+        KeyCode::Char('h') | KeyCode::Left | KeyCode::BackTab
+            if app.focused_block == FocusedBlock::Help =>
+        {
+            app.help.previous_page();
         }
 
         // scroll down
@@ -45,19 +2387,17 @@ pub async fn handle_key_events(
                 app.history.scroll_down();
             }
 
-            FocusedBlock::Chat => {
-                app.chat
-                    .automatic_scroll
-                    .store(false, std::sync::atomic::Ordering::Relaxed);
-                app.chat.scroll = app.chat.scroll.saturating_add(1);
-            }
+            FocusedBlock::Chat => app.chat.scroll_down(),
 
             FocusedBlock::Preview => {
-                app.history.preview.scroll = app.history.preview.scroll.saturating_add(1);
+                app.history.preview_scroll = app.history.preview_scroll.saturating_add(1);
             }
             FocusedBlock::Help => {
                 app.help.scroll_down();
             }
+            FocusedBlock::Payload => {
+                app.prompt.payload_scroll = app.prompt.payload_scroll.saturating_add(1);
+            }
             _ => (),
         },
 
@@ -66,7 +2406,7 @@ pub async fn handle_key_events(
             FocusedBlock::History => app.history.scroll_up(),
 
             FocusedBlock::Preview => {
-                app.history.preview.scroll = app.history.preview.scroll.saturating_sub(1);
+                app.history.preview_scroll = app.history.preview_scroll.saturating_sub(1);
             }
 
             FocusedBlock::Chat => {
@@ -80,54 +2420,333 @@ pub async fn handle_key_events(
                 app.help.scroll_up();
             }
 
+            FocusedBlock::Payload => {
+                app.prompt.payload_scroll = app.prompt.payload_scroll.saturating_sub(1);
+            }
+
             _ => (),
         },
 
         // `G`:  Mo to the bottom
         KeyCode::Char('G') => match app.focused_block {
-            FocusedBlock::Chat => app.chat.move_to_bottom(),
+            FocusedBlock::Chat => {
+                app.chat.move_to_bottom();
+                app.chat
+                    .automatic_scroll
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+            }
             FocusedBlock::History => app.history.move_to_bottom(),
             _ => (),
         },
 
+        // Toggle following the stream in the chat
+        KeyCode::Char('f')
+            if app.focused_block == FocusedBlock::Chat
+                && key_event.modifiers == KeyModifiers::NONE =>
+        {
+            app.chat
+                .automatic_scroll
+                .fetch_xor(true, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        // Fold or unfold the reasoning of the answers
+        KeyCode::Char('z')
+            if app.focused_block == FocusedBlock::Chat
+                && key_event.modifiers == KeyModifiers::NONE =>
+        {
+            app.toggle_reasoning();
+        }
+
         // `gg`: Move to the top
-        KeyCode::Char('g') => {
-            if app.previous_key == KeyCode::Char('g') {
-                match app.focused_block {
-                    FocusedBlock::Chat => {
-                        app.chat.move_to_top();
-                    }
-                    FocusedBlock::History => {
-                        app.history.move_to_top();
-                    }
-                    _ => (),
-                }
+        KeyCode::Char('g') if app.previous_key == KeyCode::Char('g') => match app.focused_block {
+            FocusedBlock::Chat => {
+                app.chat.move_to_top();
             }
-        }
+            FocusedBlock::History => {
+                app.history.move_to_top();
+            }
+            _ => (),
+        },
 
         // New chat
         KeyCode::Char(c)
             if c == app.config.key_bindings.new_chat
                 && key_event.modifiers == KeyModifiers::CONTROL =>
         {
-            app.prompt.clear();
+            new_chat(app, llm.clone()).await;
+        }
+
+        // Checkpoint the partial answer and write a follow up in a new chat
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.branch_chat
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            app.branch();
+        }
+
+        // Send a message or a code block to Slack or Matrix
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.share
+                && key_event.modifiers == KeyModifiers::CONTROL
+                && !app.chat.plain_chat.is_empty() =>
+        {
+            let popup = Share::new(
+                &app.chat.plain_chat,
+                &app.config.share,
+                &app.formatter.authors(),
+            );
+            if popup.targets.is_empty() {
+                app.notifications.push(Notification::new(
+                    "Configure Slack or Matrix in the `[share]` section to share snippets"
+                        .to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else {
+                app.share = Some(popup);
+                app.focused_block = FocusedBlock::Share;
+            }
+        }
+
+        // Record the voice input, then insert its transcript into the prompt
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.voice
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            #[cfg(feature = "voice")]
+            voice::toggle(app, sender.clone());
+
+            #[cfg(not(feature = "voice"))]
+            app.notifications.push(Notification::new(
+                "Voice input is not available, build tenere with `--features voice`".to_string(),
+                NotificationLevel::Warning,
+            ));
+
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
+        }
+
+        // Write the prompt in $EDITOR, the main loop hands it the terminal
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.external_editor
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            app.external_editor = true;
+
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
+        }
+
+        // Ask for another answer to the last question
+        KeyCode::Char('R') if app.focused_block == FocusedBlock::Chat => {
+            if app.spinner.active
+                || app.compare.is_some()
+                || app.branched.is_some()
+                || app.chat.tool_output.is_some()
+            {
+                app.notifications.push(Notification::new(
+                    "Wait for the answer to end before regenerating it".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else if app.chat.take_last_answer(app.formatter) {
+                set_last_answer(llm.clone(), None).await;
+                ask(app, llm.clone(), sender.clone());
+            } else {
+                app.notifications.push(Notification::new(
+                    "No answer to regenerate".to_string(),
+                    NotificationLevel::Info,
+                ));
+            }
+        }
 
-            app.history
-                .preview
-                .text
-                .push(app.chat.formatted_chat.clone());
+        // Compare the regenerated answer with the one it replaced
+        KeyCode::Char('D') if app.focused_block == FocusedBlock::Chat => {
+            match (&app.chat.previous_answer, app.chat.plain_chat.last()) {
+                (Some(previous), Some(current))
+                    if !app.spinner.active && current.starts_with(authors::ASSISTANT) =>
+                {
+                    app.answer_diff = Some(AnswerDiff::new(
+                        previous.message.trim_start_matches(authors::ASSISTANT),
+                        current.trim_start_matches(authors::ASSISTANT),
+                        app.focused_block.clone(),
+                    ));
+                    app.focused_block = FocusedBlock::Diff;
+                }
+                _ => app.notifications.push(Notification::new(
+                    "No regenerated answer to compare".to_string(),
+                    NotificationLevel::Info,
+                )),
+            }
+        }
 
-            app.history.text.push(app.chat.plain_chat.clone());
+        // Yank the last answer, into the register chosen with `"` or to the clipboard
+        KeyCode::Char('y')
+            if app.focused_block == FocusedBlock::Chat
+                && key_event.modifiers == KeyModifiers::NONE =>
+        {
+            let answer = app
+                .chat
+                .plain_chat
+                .iter()
+                .rev()
+                .find_map(|message| message.strip_prefix(authors::ASSISTANT))
+                .map(str::trim)
+                .map(str::to_string);
+            let notif = match (answer, app.registers.selected.take()) {
+                (None, _) => Notification::new(
+                    "No answer in this chat".to_string(),
+                    NotificationLevel::Info,
+                ),
+                (Some(answer), Some(name)) => {
+                    app.registers.yank(name, &answer);
+                    Notification::new(
+                        format!("Answer yanked into register {}", name.to_ascii_lowercase()),
+                        NotificationLevel::Info,
+                    )
+                }
+                (Some(answer), None) => match app.clipboard.set_text(&answer) {
+                    Ok(_) => Notification::new(
+                        "Answer copied to the clipboard".to_string(),
+                        NotificationLevel::Info,
+                    ),
+                    Err(e) => Notification::new(e, NotificationLevel::Error),
+                },
+            };
+            app.notifications.push(notif);
+        }
 
-            app.chat = Chat::default();
+        // Copy the last question to the prompt, to tweak it and send it again
+        KeyCode::Char('Y') if app.focused_block == FocusedBlock::Chat => {
+            match app.chat.last_question().map(str::to_string) {
+                Some(question) => {
+                    let text = app.prompt.editor.lines().join("\n");
+                    if !text.trim().is_empty() {
+                        app.undo.push(Operation::Prompt {
+                            text,
+                            attachments: app.prompt.attachments.clone(),
+                        });
+                    }
+                    app.prompt.set_text(&question);
+                    app.focused_block = FocusedBlock::Prompt;
+                    app.prompt.update(&app.focused_block);
+                }
+                None => app.notifications.push(Notification::new(
+                    "No question in this chat".to_string(),
+                    NotificationLevel::Info,
+                )),
+            }
+        }
 
-            let llm = llm.clone();
+        // Run a command proposed by the assistant
+        KeyCode::Char('x')
+            if app.focused_block == FocusedBlock::Chat
+                && key_event.modifiers == KeyModifiers::NONE =>
+        {
+            if !app.config.shell.enabled {
+                app.notifications.push(Notification::new(
+                    "Set `enabled = true` in the `[shell]` section to run commands".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else if app.spinner.active
+                || !app.chat.answer.plain_answer().is_empty()
+                || app.chat.tool_output.is_some()
             {
-                let mut llm = llm.lock().await;
-                llm.clear();
+                app.notifications.push(Notification::new(
+                    "Wait for the answer or the running command to end".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else {
+                let runner = CommandRunner::new(&app.chat.plain_chat);
+                if runner.commands.is_empty() {
+                    app.notifications.push(Notification::new(
+                        "No shell command in the answers".to_string(),
+                        NotificationLevel::Info,
+                    ));
+                } else {
+                    app.command_runner = Some(runner);
+                    app.focused_block = FocusedBlock::CommandRunner;
+                }
+            }
+        }
+
+        // Pick a code block of the answers to copy it or save it
+        KeyCode::Char('b') if app.focused_block == FocusedBlock::Chat => {
+            let picker = CodeBlockPicker::new(&app.chat.plain_chat);
+            if picker.blocks.is_empty() {
+                app.notifications.push(Notification::new(
+                    "No code block in the answers".to_string(),
+                    NotificationLevel::Info,
+                ));
+            } else {
+                app.code_blocks = Some(picker);
+                app.focused_block = FocusedBlock::CodeBlocks;
+            }
+        }
+
+        // Sum up the chat
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.chat_info
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            app.chat_info = Some(ChatInfo::new(
+                &app.chat,
+                &app.config.prices,
+                app.focused_block.clone(),
+            ));
+            app.focused_block = FocusedBlock::Info;
+        }
+
+        // Replay how the answers were streamed
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.replay
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            if app.chat.recordings.is_empty() {
+                app.notifications.push(Notification::new(
+                    "No answer to replay in this chat".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else {
+                app.scrubber = Some(Scrubber::new(&app.chat.recordings));
+                app.focused_block = FocusedBlock::Scrubber;
+            }
+        }
+
+        // Switch to another profile
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.switch_profile
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            if app.config.profiles.is_empty() {
+                app.notifications.push(Notification::new(
+                    "No profiles are defined in the config".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else if app.spinner.active || app.branched.is_some() {
+                app.notifications.push(Notification::new(
+                    "Wait for the answer before switching the profile".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else {
+                app.profile_switcher = Some(ProfileSwitcher::new(&app.config));
+                app.focused_block = FocusedBlock::Profiles;
+            }
+        }
+
+        // Switch the backend manually
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.switch_backend
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            app.cycle_backend_override();
+            if app.chat.plain_chat.is_empty() && !app.spinner.active {
+                apply_preferred_backend(app, llm.clone()).await;
             }
 
-            app.chat.scroll = 0;
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
         }
 
         // Save chat
@@ -135,32 +2754,119 @@ pub async fn handle_key_events(
             if c == app.config.key_bindings.save_chat
                 && key_event.modifiers == KeyModifiers::CONTROL =>
         {
-            match app.focused_block {
-                FocusedBlock::History | FocusedBlock::Preview => {
-                    app.history
-                        .save(app.config.archive_file_name.as_str(), sender.clone());
-                }
-                FocusedBlock::Chat | FocusedBlock::Prompt => {
-                    match std::fs::write(
-                        app.config.archive_file_name.clone(),
-                        app.chat.plain_chat.join(""),
-                    ) {
-                        Ok(_) => {
-                            let notif = Notification::new(
-                                format!("Chat saved to `{}` file", app.config.archive_file_name),
-                                NotificationLevel::Info,
-                            );
-
-                            sender.send(Event::Notification(notif)).unwrap();
-                        }
-                        Err(e) => {
-                            let notif = Notification::new(e.to_string(), NotificationLevel::Error);
-
-                            sender.send(Event::Notification(notif)).unwrap();
-                        }
-                    }
+            if let Some(source) = export_source(app) {
+                request_export(
+                    app,
+                    Export {
+                        action: ExportAction::Save,
+                        source,
+                        path: None,
+                    },
+                    sender.clone(),
+                );
+            }
+        }
+
+        // Summarize the chat
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.summarize_chat
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            if !app.chat.plain_chat.is_empty() && !app.spinner.active {
+                summarize(app, llm.clone(), sender.clone());
+            }
+
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
+        }
+
+        // Switch to the next theme
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.cycle_theme
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            app.cycle_theme();
+
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
+        }
+
+        // Underline the misspelled words of the prompt
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.spellcheck
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            app.toggle_spellcheck();
+
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
+        }
+
+        // Offer the corrections of the word under the cursor, like in vim
+        KeyCode::Char('=')
+            if app.previous_key == KeyCode::Char('z')
+                && app.focused_block == FocusedBlock::Prompt
+                && app.prompt.mode == Mode::Normal =>
+        {
+            let word = app.prompt.word_at_cursor().map(|(word, range)| {
+                let suggestions = app
+                    .spellcheck_dictionary()
+                    .map(|dictionary| dictionary.suggestions(&word));
+                (word, range, suggestions)
+            });
+            match (app.spellcheck, word) {
+                (false, _) => app.notifications.push(Notification::new(
+                    format!(
+                        "Turn the spellcheck on with ctrl + {} first",
+                        app.config.key_bindings.spellcheck
+                    ),
+                    NotificationLevel::Info,
+                )),
+                (true, None) => app.notifications.push(Notification::new(
+                    "No word under the cursor".to_string(),
+                    NotificationLevel::Info,
+                )),
+                (true, Some((word, range, suggestions))) => {
+                    app.corrections = Some(Corrections::new(
+                        word,
+                        range,
+                        suggestions.unwrap_or_default(),
+                        app.focused_block.clone(),
+                    ));
+                    app.focused_block = FocusedBlock::Corrections;
                 }
-                _ => (),
+            }
+        }
+
+        // Copy the chat to the clipboard
+        KeyCode::Char(c)
+            if c == app.config.key_bindings.copy_chat
+                && key_event.modifiers == KeyModifiers::CONTROL =>
+        {
+            if let Some(source) = export_source(app) {
+                request_export(
+                    app,
+                    Export {
+                        action: ExportAction::Copy,
+                        source,
+                        path: None,
+                    },
+                    sender.clone(),
+                );
+            }
+
+            // Not typed in the prompt as well
+            app.previous_key = KeyCode::Null;
+            return Ok(());
+        }
+
+        // Complete the path of the file to attach
+        KeyCode::Tab if app.focused_block == FocusedBlock::Prompt && attach_path(app).is_some() => {
+            if let Some((command, partial)) = attach_path(app) {
+                complete_attach_path(app, command, &partial);
             }
         }
 
@@ -184,12 +2890,12 @@ pub async fn handle_key_events(
             }
             FocusedBlock::History => {
                 app.focused_block = FocusedBlock::Preview;
-                app.history.preview.scroll = 0;
+                app.history.preview_scroll = 0;
                 app.prompt.update(&app.focused_block);
             }
             FocusedBlock::Preview => {
                 app.focused_block = FocusedBlock::History;
-                app.history.preview.scroll = 0;
+                app.history.preview_scroll = 0;
             }
             _ => (),
         },
@@ -198,6 +2904,7 @@ pub async fn handle_key_events(
         KeyCode::Char(c)
             if c == app.config.key_bindings.show_help && app.prompt.mode != Mode::Insert =>
         {
+            app.help.open(&app.focused_block);
             app.focused_block = FocusedBlock::Help;
             app.prompt.update(&app.focused_block);
             app.chat
@@ -218,11 +2925,58 @@ pub async fn handle_key_events(
                 .store(true, std::sync::atomic::Ordering::Relaxed);
         }
 
+        // Preview the message with its attachments, if any
+        KeyCode::Char('P')
+            if app.focused_block == FocusedBlock::Prompt
+                && app.prompt.mode == Mode::Normal
+                && app.prompt.estimated_tokens() > 0 =>
+        {
+            app.prompt.payload_scroll = 0;
+            app.focused_block = FocusedBlock::Payload;
+        }
+
+        // Continue the selected thread
+        KeyCode::Enter if app.focused_block == FocusedBlock::History => {
+            if app.branched.is_some() || !app.detached.is_empty() {
+                app.notifications.push(Notification::new(
+                    "Wait for the answer before loading another chat".to_string(),
+                    NotificationLevel::Warning,
+                ));
+            } else if let Some(i) = app.history.selected() {
+                load_thread(app, llm.clone(), i).await;
+                app.previous_key = key_event.code;
+                return Ok(());
+            }
+        }
+
+        // Delete the selected thread from the history
+        KeyCode::Char('d')
+            if app.focused_block == FocusedBlock::History
+                && key_event.modifiers == KeyModifiers::NONE =>
+        {
+            app.history.pending_deletion = app.history.selected();
+        }
+
+        // Pin or unpin the selected thread
+        KeyCode::Char('P') if app.focused_block == FocusedBlock::History => {
+            app.history.toggle_pin();
+            app.save_history();
+        }
+
+        // Only list the threads of the next tag
+        KeyCode::Char('t')
+            if app.focused_block == FocusedBlock::History
+                && key_event.modifiers == KeyModifiers::NONE =>
+        {
+            app.history.cycle_tag_filter();
+        }
+
         // Discard help & history popups
         KeyCode::Esc => match app.focused_block {
-            FocusedBlock::History | FocusedBlock::Preview | FocusedBlock::Help => {
-                app.focused_block = FocusedBlock::Prompt
-            }
+            FocusedBlock::History
+            | FocusedBlock::Preview
+            | FocusedBlock::Help
+            | FocusedBlock::Payload => app.focused_block = FocusedBlock::Prompt,
             _ => {}
         },
 
@@ -232,67 +2986,41 @@ pub async fn handle_key_events(
     if let FocusedBlock::Prompt = app.focused_block {
         if let Mode::Normal = app.prompt.mode {
             if key_event.code == KeyCode::Enter {
-                let user_input = app.prompt.editor.lines().join("\n");
-                let user_input = user_input.trim();
-                if user_input.is_empty() {
-                    return Ok(());
-                }
-
-                app.prompt.clear();
-
-                app.chat.plain_chat.push(format!("👤 : {}\n", user_input));
-
-                if app.chat.formatted_chat.width() == 0 {
-                    app.chat.formatted_chat = app
-                        .formatter
-                        .format(format!("👤: {}\n", user_input).as_str());
-                } else {
-                    app.chat.formatted_chat.extend(
-                        app.formatter
-                            .format(format!("👤: {}\n", user_input).as_str()),
-                    );
-                }
-
-                let llm = llm.clone();
-                {
-                    let mut llm = llm.lock().await;
-                    llm.append_chat_msg(user_input.into(), LLMRole::USER);
-                }
-
-                app.spinner.active = true;
-
-                app.chat
-                    .formatted_chat
-                    .lines
-                    .push(Line::raw("🤖: ".to_string()));
-
-                let terminate_response_signal = app.terminate_response_signal.clone();
+                send_prompt(app, llm.clone(), sender.clone()).await;
+                app.previous_key = key_event.code;
+                return Ok(());
+            }
+        }
 
-                let sender = sender.clone();
+        // Deleting the whole prompt from the normal mode can be undone
+        let text = app.prompt.editor.lines().join("\n");
+        let normal_mode = app.prompt.mode != Mode::Insert && key_event.code != KeyCode::Char('u');
 
-                let llm = llm.clone();
+        app.prompt.handler(
+            key_event,
+            app.previous_key,
+            &mut app.clipboard,
+            &mut app.registers,
+            &sender,
+        );
 
-                tokio::spawn(async move {
-                    let llm = llm.lock().await;
-                    let res = llm.ask(sender.clone(), terminate_response_signal).await;
+        app.completion = match app.prompt.mode {
+            Mode::Insert => command::completion(app.prompt.editor.lines(), &app.config),
+            _ => None,
+        };
 
-                    if let Err(e) = res {
-                        sender
-                            .send(Event::LLMEvent(LLMAnswer::StartAnswer))
-                            .unwrap();
-                        sender
-                            .send(Event::LLMEvent(LLMAnswer::Answer(e.to_string())))
-                            .unwrap();
-                    }
-                });
-            }
+        if normal_mode && !text.trim().is_empty() && app.prompt.editor.is_empty() {
+            app.undo.push(Operation::Prompt {
+                text,
+                attachments: app.prompt.attachments.clone(),
+            });
         }
-
-        app.prompt
-            .handler(key_event, app.previous_key, app.clipboard.as_mut());
     }
 
-    app.previous_key = key_event.code;
+    // The second key of a chord like `gg` or `dd` ends it instead of starting another one
+    let chord = app.previous_key == key_event.code
+        && matches!(key_event.code, KeyCode::Char('g' | 'd' | 'c' | 'y' | 'z'));
+    app.previous_key = if chord { KeyCode::Null } else { key_event.code };
 
     Ok(())
 }
@@ -0,0 +1,246 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    sync::mpsc::unbounded_channel,
+};
+
+use crate::{
+    app::AppResult,
+    config::{Config, HookRole},
+    context,
+    event::Event,
+    hooks,
+    llm::{FinishReason, LLMAnswer, LLMError, LLMModel, LLMRole},
+    notification::NotificationLevel,
+    redaction, variables,
+};
+
+/// A line of the standard input in the json mode
+#[derive(Deserialize, Debug, Default)]
+struct Request {
+    /// Echoed in the events of the answer
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(default)]
+    prompt: Option<String>,
+    /// Forget the conversation before the prompt, if any
+    #[serde(default)]
+    new: bool,
+}
+
+fn finish_reason(reason: &FinishReason) -> String {
+    match reason {
+        FinishReason::Stop => String::from("stop"),
+        FinishReason::Length => String::from("length"),
+        FinishReason::ContentFilter => String::from("content_filter"),
+        FinishReason::ToolCalls => String::from("tool_calls"),
+        FinishReason::Truncated => String::from("truncated"),
+        FinishReason::Other(reason) => reason.clone(),
+    }
+}
+
+/// Write an event, flushed right away so that the reader gets the answer as it is streamed
+fn emit(json: bool, event: Value) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = if json {
+        writeln!(stdout, "{}", event)
+    } else {
+        match (&event["event"], &event["text"]) {
+            (Value::String(name), Value::String(text)) if name == "chunk" => {
+                write!(stdout, "{}", text)
+            }
+            (Value::String(name), _) if name == "end" => writeln!(stdout),
+            (Value::String(name), _) if name == "error" || name == "notification" => {
+                eprintln!("{}", event["message"].as_str().unwrap_or_default());
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    };
+    let _ = stdout.flush();
+}
+
+fn warn(json: bool, failures: Vec<String>) {
+    for failure in failures {
+        emit(
+            json,
+            json!({ "event": "notification", "level": "warning", "message": failure }),
+        );
+    }
+}
+
+/// Read the prompts from the standard input and write the answers to the standard output,
+/// one json object per line with `json`, as plain text otherwise
+pub async fn run(config: Arc<Config>, json: bool) -> AppResult<()> {
+    let mut llm = match LLMModel::init(&config.llm, config.clone()).await {
+        Ok(llm) => llm,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let model = config.model_name();
+    let redaction_rules = redaction::outgoing_rules(&config.redaction).unwrap_or_default();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut running_hooks = Vec::new();
+
+    while let Some(line) = lines.next_line().await? {
+        warn(json, hooks::failures(&mut running_hooks, false).await);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = if json {
+            match serde_json::from_str::<Request>(&line) {
+                Ok(request) => request,
+                Err(e) => {
+                    emit(
+                        json,
+                        json!({ "event": "error", "message": format!("Invalid request: {}", e) }),
+                    );
+                    continue;
+                }
+            }
+        } else {
+            Request {
+                prompt: Some(line),
+                ..Default::default()
+            }
+        };
+
+        if request.new {
+            llm.clear();
+        }
+        let Some(prompt) = request.prompt else {
+            continue;
+        };
+
+        let prompt = variables::expand(&prompt, &config.variables);
+        let (prompt, redacted) = redaction::redact(&prompt, &redaction_rules);
+        if !redacted.is_empty() {
+            emit(
+                json,
+                json!({
+                    "event": "notification",
+                    "level": "warning",
+                    "message": format!("Redacted before sending: {}", redaction::summary(&redacted)),
+                }),
+            );
+        }
+        running_hooks.push(hooks::spawn(
+            &config.hooks,
+            HookRole::User,
+            &prompt,
+            &model,
+            &config.variables,
+        ));
+        llm.append_chat_msg(prompt, LLMRole::USER);
+
+        let (sender, mut receiver) = unbounded_channel();
+        context::fit(&mut llm, &config.context, &model, &sender).await;
+
+        let signal = Arc::new(AtomicBool::new(false));
+        let id = request.id.clone();
+
+        let printer = async {
+            let mut answer = String::new();
+            while let Some(event) = receiver.recv().await {
+                let event = match event {
+                    Event::LLMEvent(LLMAnswer::StartAnswer) => {
+                        json!({ "event": "start", "id": id, "model": model })
+                    }
+                    Event::LLMEvent(LLMAnswer::Answer(chunk)) => {
+                        answer.push_str(&chunk);
+                        if config
+                            .params
+                            .max_answer_chars
+                            .is_some_and(|max| answer.chars().count() > max)
+                            && !signal.swap(true, Ordering::Relaxed)
+                        {
+                            emit(
+                                json,
+                                json!({ "event": "finish_reason", "id": id, "reason": "truncated" }),
+                            );
+                        }
+                        json!({ "event": "chunk", "id": id, "text": chunk })
+                    }
+                    Event::LLMEvent(LLMAnswer::Reasoning(chunk)) => {
+                        json!({ "event": "reasoning", "id": id, "text": chunk })
+                    }
+                    Event::LLMEvent(LLMAnswer::FinishReason(reason)) => {
+                        json!({ "event": "finish_reason", "id": id, "reason": finish_reason(&reason) })
+                    }
+                    Event::LLMEvent(LLMAnswer::Usage(usage)) => json!({
+                        "event": "usage",
+                        "id": id,
+                        "prompt_tokens": usage.prompt_tokens,
+                        "completion_tokens": usage.completion_tokens,
+                    }),
+                    Event::LLMEvent(LLMAnswer::EndAnswer) => {
+                        json!({ "event": "end", "id": id, "text": answer })
+                    }
+                    Event::Notification(notification) => json!({
+                        "event": "notification",
+                        "level": match notification.level {
+                            NotificationLevel::Error => "error",
+                            NotificationLevel::Warning => "warning",
+                            NotificationLevel::Info => "info",
+                        },
+                        "message": notification.message,
+                    }),
+                    _ => continue,
+                };
+                emit(json, event);
+            }
+            answer
+        };
+
+        let ask = async {
+            llm.ask(sender, signal.clone())
+                .await
+                .map_err(LLMError::from)
+        };
+        let (res, answer) = tokio::join!(ask, printer);
+
+        match res {
+            Ok(_) => {
+                running_hooks.push(hooks::spawn(
+                    &config.hooks,
+                    HookRole::Assistant,
+                    answer.trim(),
+                    &model,
+                    &config.variables,
+                ));
+                llm.append_chat_msg(answer, LLMRole::ASSISTANT);
+            }
+            Err(error) => {
+                emit(
+                    json,
+                    json!({
+                        "event": "error",
+                        "id": request.id,
+                        "status": error.status,
+                        "message": error.message,
+                    }),
+                );
+                // The question can be asked again
+                let mut messages = llm.messages();
+                messages.pop();
+                llm.set_messages(messages);
+            }
+        }
+    }
+
+    // The hooks still running are not cut by the end of the input
+    warn(json, hooks::failures(&mut running_hooks, true).await);
+    Ok(())
+}
@@ -1,102 +1,352 @@
 use ratatui::{
-    layout::{Alignment, Constraint, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Style, Stylize},
-    widgets::{Block, Borders, Clear, Padding, Row, Table, TableState},
+    text::Line,
+    widgets::{
+        block::{Position, Title},
+        Block, Borders, Clear, Padding, Row, Table, TableState, Tabs,
+    },
     Frame,
 };
 
-pub struct Help {
-    block_height: usize,
-    state: TableState,
-    keys: &'static [(&'static str, &'static str)],
+use crate::{app::FocusedBlock, config::KeyBindings, i18n::t};
+
+/// The pages of the help, in the order they are browsed
+const PAGES: [Page; 6] = [
+    Page::Global,
+    Page::Chat,
+    Page::Prompt,
+    Page::History,
+    Page::Config,
+    Page::Backends,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Page {
+    Global,
+    Chat,
+    Prompt,
+    History,
+    Config,
+    Backends,
 }
 
-impl Default for Help {
-    fn default() -> Self {
-        let mut state = TableState::new().with_offset(0);
-        state.select(Some(0));
+impl Page {
+    fn title(self) -> &'static str {
+        match self {
+            Page::Global => "Global",
+            Page::Chat => "Chat",
+            Page::Prompt => "Prompt",
+            Page::History => "History",
+            Page::Config => "Config",
+            Page::Backends => "Backends",
+        }
+    }
+
+    /// The lines of the page, the keys are the ones of the config
+    fn rows(self, keys: &KeyBindings) -> Vec<(String, &'static str)> {
+        let ctrl = |c: char| format!("ctrl + {}", c);
+        let text = |rows: &[(&str, &'static str)]| {
+            rows.iter()
+                .map(|(key, description)| (key.to_string(), *description))
+                .collect()
+        };
 
-        Self {
-            block_height: 0,
-            state,
-            keys: &[
-                ("Esc", "Switch to Normal mode / Dismiss pop-up"),
-                ("Tab", "Switch the focus"),
+        match self {
+            Page::Global => vec![
+                ("Esc".to_string(), "Switch to Normal mode / Dismiss pop-up"),
+                ("Tab".to_string(), "Switch the focus"),
                 (
-                    "ctrl + n",
+                    ctrl(keys.new_chat),
                     "Start new chat and save the previous one to the history",
                 ),
                 (
-                    "ctrl + s",
-                    "Save the chat to  file in the current directory",
+                    ctrl(keys.save_chat),
+                    "Save the chat to a file in the current directory",
+                ),
+                (
+                    ctrl(keys.copy_chat),
+                    "Copy the chat to the clipboard as rich text",
+                ),
+                (
+                    ctrl(keys.summarize_chat),
+                    "Summarize the chat to shrink the context",
+                ),
+                (ctrl(keys.voice), "Start or stop recording the voice input"),
+                (
+                    ctrl(keys.toggle_layout),
+                    "Switch between the stacked and the split layouts",
+                ),
+                ("ctrl + Up/Down".to_string(), "Grow or shrink the prompt"),
+                (ctrl(keys.cycle_theme), "Switch to the next theme"),
+                (ctrl(keys.switch_backend), "Switch the backend manually"),
+                (ctrl(keys.switch_profile), "Switch to another profile"),
+                (ctrl(keys.replay), "Replay how the answers were streamed"),
+                (ctrl(keys.external_editor), "Write the prompt in $EDITOR"),
+                (
+                    ctrl(keys.chat_info),
+                    "Show the words, tokens and cost of the chat",
+                ),
+                (
+                    ctrl(keys.spellcheck),
+                    "Underline the misspelled words of the prompt",
+                ),
+                (
+                    "ctrl + z".to_string(),
+                    "Suspend to the shell, resume with fg",
+                ),
+                (
+                    ctrl(keys.share),
+                    "Send a message or a code block to Slack or Matrix",
+                ),
+                (ctrl(keys.show_history), "Show history"),
+                (ctrl(keys.stop_stream), "Stop the stream response"),
+                (
+                    ctrl(keys.branch_chat),
+                    "Branch from the answer being generated",
+                ),
+                (
+                    "ctrl + r".to_string(),
+                    "Retry the failed request, or redo what was undone",
                 ),
-                ("ctrl + h", "Show history"),
-                ("ctrl + t", "Stop the stream response"),
+                (":".to_string(), "Open the command line"),
+                ("L".to_string(), "Show the log"),
+                (keys.show_help.to_string(), "Show help"),
+                ("q or ctrl + c".to_string(), "Quit"),
+            ],
+            Page::Chat => text(&[
                 ("j or Down", "Scroll down"),
                 ("k or Up", "Scroll up"),
                 ("G", "Go to the end"),
                 ("gg", "Go to the top"),
-                ("?", "show help"),
-            ],
+                ("J / K", "Scroll the answer in the split layout"),
+                ("f", "Toggle following the stream"),
+                ("z", "Fold or unfold the reasoning of the answers"),
+                ("u", "Undo a new chat"),
+                ("R", "Regenerate the last answer"),
+                ("D", "Compare the regenerated answer with the replaced one"),
+                ("y", "Yank the last answer, into a register with \"a y"),
+                ("Y", "Copy the last question to the prompt"),
+                ("x", "Run a shell command proposed in the chat"),
+                ("b", "Pick a code block to copy, save or run it"),
+                ("w", "Wrap the code lines or scroll them sideways"),
+                ("h / l", "Scroll the code lines that are not wrapped"),
+            ]),
+            Page::Prompt => text(&[
+                (
+                    "Enter",
+                    "Send the prompt in Normal mode, new line in Insert mode",
+                ),
+                (
+                    "i / a / I / A",
+                    "Insert before, after, at the start or the end",
+                ),
+                ("o / O", "Add a line below or above"),
+                ("h j k l", "Move the cursor"),
+                ("w / b", "Move to the next or previous word"),
+                ("0 / $", "Move to the start or the end of the line"),
+                ("dd / dw / db", "Delete the line or a word"),
+                ("cc / cw / cb", "Change the line or a word"),
+                ("v", "Switch to Visual mode, y yanks the selection"),
+                ("p", "Paste"),
+                ("\"a y / \"a p", "Yank into or paste from the register a"),
+                ("z=", "Correct the misspelled word under the cursor"),
+                ("u", "Undo, or bring back a deleted prompt"),
+                ("P", "Preview the prompt with its attachments"),
+                ("Tab", "Complete the path after /attach or /file"),
+                ("/", "Start a slash command"),
+            ]),
+            Page::History => text(&[
+                ("j or Down", "Select the next thread"),
+                ("k or Up", "Select the previous thread"),
+                ("Enter", "Continue the selected thread"),
+                ("d", "Delete the selected thread"),
+                ("P", "Pin the selected thread"),
+                ("t", "Filter by tag, the next tag on each press"),
+                ("u", "Undo a deletion"),
+                ("Tab", "Switch between the list and the preview"),
+            ]),
+            Page::Config => text(&[
+                ("llm", "The backend: chatgpt, llamacpp, ollama or mock"),
+                (
+                    "archive_file_name",
+                    "Saved chats, with {date}, {time} and {title}",
+                ),
+                ("archive_dir", "The directory of the saved chats"),
+                ("generate_titles", "Name the threads of the history"),
+                ("status_bar", "Show the status bar"),
+                ("render_math", "Show the LaTeX math with Unicode characters"),
+                (
+                    "show_code_line_numbers",
+                    "Number the lines of the code blocks",
+                ),
+                ("reasoning_display", "collapsed, expanded or hidden"),
+                ("language", "The language of the interface"),
+                ("layout", "stacked or split"),
+                ("log_level", "error, warn, info, debug or trace"),
+                (
+                    "idle_lock_timeout",
+                    "Lock the screen after this many minutes",
+                ),
+                (
+                    "key_timeout",
+                    "Milliseconds to wait for the second key of a chord",
+                ),
+                ("control_socket", "Accept commands from other programs"),
+                ("[params]", "system_prompt, temperature, max_tokens, stop"),
+                (
+                    "[profiles.<name>]",
+                    "llm, model and the params of a profile",
+                ),
+                ("[prompt]", "position and max_height"),
+                ("[theme]", "name and [theme.colors]"),
+                ("[network]", "timeout, max_retries, proxy, ca_bundle"),
+                ("[history]", "persist and encrypt"),
+                (
+                    "[context]",
+                    "strategy, threshold and the sizes of the models",
+                ),
+                ("[prices]", "Dollars per million tokens of the models"),
+                ("[redaction]", "Rules hiding the secrets before sending"),
+                ("[auto_select]", "Switch to a backend that answers"),
+                ("[voice]", "record_cmd, transcribe_cmd, model, language"),
+                ("[alert]", "desktop, bell, min_duration, unfocused_only"),
+                ("[clipboard]", "providers, copy_command, paste_command"),
+                ("[shell]", "enabled, to run the commands of the answers"),
+                ("[rag]", "Search the local files for the context"),
+                ("[share]", "[share.slack] and [share.matrix]"),
+                ("[key_bindings]", "The ctrl keys, shown on the Global page"),
+            ]),
+            Page::Backends => text(&[
+                ("chatgpt", "llm = \"chatgpt\""),
+                ("", "The key from OPENAI_API_KEY, or in [chatgpt]:"),
+                ("", "openai_api_key, api_key_cmd or keyring"),
+                ("", "model = \"gpt-3.5-turbo\" by default"),
+                ("", "url for the OpenAI compatible servers"),
+                ("", "extra_headers and extra_body for the gateways"),
+                ("", ""),
+                ("llamacpp", "llm = \"llamacpp\""),
+                (
+                    "",
+                    "[llamacpp] url = \"http://localhost:8080/v1/chat/completions\"",
+                ),
+                (
+                    "",
+                    "The key, if any, from LLAMACPP_API_KEY, or in [llamacpp]:",
+                ),
+                ("", "api_key, api_key_cmd or keyring"),
+                ("", ""),
+                ("ollama", "llm = \"ollama\""),
+                ("", "[ollama] url = \"http://localhost:11434/api/chat\""),
+                ("", "model = \"llama3\", from `ollama list`"),
+                ("", ""),
+                ("tenere setup", "Write a config for a backend step by step"),
+            ]),
         }
     }
 }
 
+#[derive(Default)]
+pub struct Help {
+    block_height: usize,
+    page: usize,
+    /// The first line shown
+    scroll: usize,
+    length: usize,
+}
+
 impl Help {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn scroll_down(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i >= self.keys.len().saturating_sub(self.block_height - 6) {
-                    i
-                } else {
-                    i + 1
-                }
-            }
-            None => 1,
+    /// Show the keys of the block that was focused
+    pub fn open(&mut self, focused_block: &FocusedBlock) {
+        let page = match focused_block {
+            FocusedBlock::Chat => Page::Chat,
+            FocusedBlock::Prompt => Page::Prompt,
+            FocusedBlock::History | FocusedBlock::Preview => Page::History,
+            _ => Page::Global,
         };
-        *self.state.offset_mut() = i;
-        self.state.select(Some(i));
+        self.page = PAGES.iter().position(|p| *p == page).unwrap_or_default();
+        self.scroll = 0;
+    }
+
+    pub fn next_page(&mut self) {
+        self.page = (self.page + 1) % PAGES.len();
+        self.scroll = 0;
+    }
+
+    pub fn previous_page(&mut self) {
+        self.page = (self.page + PAGES.len() - 1) % PAGES.len();
+        self.scroll = 0;
+    }
+
+    pub fn scroll_down(&mut self) {
+        let visible = self.block_height.saturating_sub(6);
+        if self.scroll + visible < self.length {
+            self.scroll += 1;
+        }
     }
+
     pub fn scroll_up(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i > 1 {
-                    i - 1
-                } else {
-                    0
-                }
-            }
-            None => 1,
-        };
-        *self.state.offset_mut() = i;
-        self.state.select(Some(i));
+        self.scroll = self.scroll.saturating_sub(1);
     }
 
-    pub fn render(&mut self, frame: &mut Frame, block: Rect) {
+    pub fn render(&mut self, frame: &mut Frame, block: Rect, keys: &KeyBindings) {
         self.block_height = block.height as usize;
-        let widths = [Constraint::Length(15), Constraint::Min(60)];
-        let rows: Vec<Row> = self
-            .keys
+
+        let page = PAGES[self.page];
+        let rows = page.rows(keys);
+        self.length = rows.len();
+
+        let key_width = rows
             .iter()
-            .map(|key| Row::new(vec![key.0, key.1]))
+            .map(|(key, _)| key.chars().count())
+            .max()
+            .unwrap_or_default()
+            .max(13) as u16
+            + 2;
+        let rows: Vec<Row> = rows
+            .into_iter()
+            .map(|(key, description)| Row::new(vec![key, t(description).to_string()]))
             .collect();
 
-        let table = Table::new(rows, widths).block(
-            Block::default()
-                .padding(Padding::uniform(2))
-                .title(" Help ")
-                .title_style(Style::default().bold())
-                .title_alignment(Alignment::Center)
-                .borders(Borders::ALL)
-                .style(Style::default())
-                .border_style(Style::default()),
-        );
+        let outer = Block::default()
+            .padding(Padding::new(2, 2, 1, 1))
+            .title(format!(" {} ", t("Help")))
+            .title_style(Style::default().bold())
+            .title_alignment(Alignment::Center)
+            .title(
+                Title::from(Line::from(format!(" {} ", t("h/l or Tab: page"))))
+                    .position(Position::Bottom)
+                    .alignment(Alignment::Right),
+            )
+            .borders(Borders::ALL)
+            .style(Style::default())
+            .border_style(Style::default());
+        let inner = outer.inner(block);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(1)])
+            .split(inner);
+
+        let tabs = Tabs::new(PAGES.iter().map(|page| t(page.title())).collect())
+            .select(self.page)
+            .highlight_style(Style::default().bold().reversed());
 
         frame.render_widget(Clear, block);
-        frame.render_stateful_widget(table, block, &mut self.state);
+        frame.render_widget(outer, block);
+        frame.render_widget(tabs, chunks[0]);
+        let widths = [
+            Constraint::Length(key_width),
+            Constraint::Length(chunks[1].width.saturating_sub(key_width + 1)),
+        ];
+        // The table keeps the selected row visible, the first one shown is selected
+        let mut state = TableState::new()
+            .with_offset(self.scroll)
+            .with_selected(Some(self.scroll));
+        frame.render_stateful_widget(Table::new(rows, widths), chunks[1], &mut state);
     }
 }
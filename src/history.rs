@@ -1,62 +1,347 @@
-use tokio::sync::mpsc::UnboundedSender;
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
 
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style, Stylize},
-    text::Text,
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    style::{Style, Stylize},
+    text::{Span, Text},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
 use crate::{
     app::FocusedBlock,
-    event::Event,
-    notification::{Notification, NotificationLevel},
+    authors::{self, Authors},
+    chat::{self, format_chat},
+    context,
+    formatter::{Formatter, RenderProfile},
+    i18n::{fill, t},
+    llm::{message, LLMRole},
+    paths,
+    reasoning::Reasoning,
+    theme::Theme,
+    timestamp::{self, Timestamp},
+    vault,
 };
 
-#[derive(Debug, Default, Clone)]
-pub struct Preview<'a> {
-    pub text: Vec<Text<'a>>,
-    pub scroll: usize,
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Thread<'a> {
+    #[serde(skip)]
+    pub id: usize,
+    pub title: Option<String>,
+    pub plain_chat: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub render: RenderProfile,
+    #[serde(default)]
+    pub timestamps: Vec<Timestamp>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasoning: Vec<Reasoning>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip)]
+    pub formatted_chat: Text<'a>,
+}
+
+impl Thread<'_> {
+    /// The title if it is generated, the first message otherwise
+    pub fn name(&self) -> String {
+        match &self.title {
+            Some(title) => title.clone(),
+            None => self.plain_chat.first().cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Rebuild the messages sent to the llm, starting from the last summary if any
+    pub fn messages(&self) -> Vec<HashMap<String, String>> {
+        let mut messages = Vec::new();
+
+        for entry in self.plain_chat.iter() {
+            if let Some(content) = entry.strip_prefix(authors::USER) {
+                messages.push(message(LLMRole::USER, content.trim_end()));
+            } else if let Some(content) = entry.strip_prefix("🔧: ") {
+                messages.push(message(LLMRole::USER, content));
+            } else if let Some(content) = entry.strip_prefix(authors::ASSISTANT) {
+                messages.push(message(LLMRole::ASSISTANT, content));
+            } else if let Some(content) = entry.strip_prefix("📝: ") {
+                let summary = content.split_once('\n').map(|(_, s)| s).unwrap_or(content);
+                messages = vec![context::summary_message(summary)];
+            }
+        }
+
+        messages
+    }
+
+    /// The content written to the archive file, with the labels of the authors
+    pub fn archive(&self, authors: &Authors) -> String {
+        let messages: String = self
+            .plain_chat
+            .iter()
+            .enumerate()
+            .map(|(i, message)| {
+                let message = authors.display(message);
+                match timestamp::find(&self.timestamps, i) {
+                    Some(timestamp) => {
+                        format!("{}\n🕑 {}\n", message.trim_end(), timestamp.describe(true))
+                    }
+                    None => message.to_string(),
+                }
+            })
+            .collect();
+
+        match &self.title {
+            Some(title) => format!("# {}\n\n{}", title, messages),
+            None => messages,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct History<'a> {
     block_height: usize,
     state: ListState,
-    pub text: Vec<Vec<String>>,
-    pub preview: Preview<'a>,
+    pub threads: Vec<Thread<'a>>,
+    pub preview_scroll: usize,
+    pub pending_deletion: Option<usize>,
+    /// Only the threads with this tag are listed
+    pub tag_filter: Option<String>,
 }
 
-impl History<'_> {
+impl<'a> History<'a> {
     pub fn new() -> Self {
         Self {
             block_height: 0,
             state: ListState::default(),
-            text: Vec::new(),
-            preview: Preview::default(),
+            threads: Vec::new(),
+            preview_scroll: 0,
+            pending_deletion: None,
+            tag_filter: None,
+        }
+    }
+
+    fn path() -> Option<PathBuf> {
+        paths::data_dir().map(|dir| dir.join("history.json"))
+    }
+
+    /// The history once encrypted, with `encrypt` in the config
+    pub fn encrypted_path() -> Option<PathBuf> {
+        paths::data_dir().map(|dir| dir.join("history.enc"))
+    }
+
+    /// The threads saved by the previous sessions, not formatted
+    pub fn read() -> Vec<Thread<'static>> {
+        let plain = || Self::path().and_then(|path| std::fs::read_to_string(path).ok());
+
+        let history = match vault::get() {
+            Some(vault) => Self::encrypted_path()
+                .and_then(|path| std::fs::read(path).ok())
+                .and_then(|file| vault.decrypt(&file).ok())
+                .and_then(|history| String::from_utf8(history).ok())
+                // Not encrypted yet
+                .or_else(plain),
+            None => plain(),
+        };
+
+        history
+            .and_then(|history| serde_json::from_str(&history).ok())
+            .unwrap_or_default()
+    }
+
+    /// A line per archived thread, with its date and its tags
+    pub fn list(tag: Option<&str>) -> Vec<String> {
+        Self::read()
+            .iter()
+            .filter(|thread| tag.is_none_or(|tag| thread.has_tag(tag)))
+            .map(|thread| {
+                let date = thread
+                    .timestamps
+                    .first()
+                    .map(|timestamp| timestamp.time.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                let name = thread.name();
+                let mut line = format!("{:16}  {}", date, name.lines().next().unwrap_or_default());
+                if !thread.tags.is_empty() {
+                    line.push_str(&format!("  [{}]", thread.tags.join(", ")));
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Load the threads saved by the previous sessions
+    pub fn load(formatter: &Formatter) -> Self {
+        let mut threads = Self::read();
+
+        for thread in threads.iter_mut() {
+            thread.id = chat::next_id();
+            thread.plain_chat = std::mem::take(&mut thread.plain_chat)
+                .into_iter()
+                .map(authors::migrate)
+                .collect();
+            thread.formatted_chat = format_chat(
+                &thread.plain_chat,
+                &thread.timestamps,
+                &thread.reasoning,
+                formatter,
+                &thread.render,
+            );
+        }
+
+        let history = Self {
+            threads,
+            ..Self::new()
+        };
+
+        // Encrypt the history saved in clear before the encryption was enabled
+        if vault::get().is_some() && Self::path().is_some_and(|path| path.exists()) {
+            if let Err(e) = history.persist() {
+                tracing::warn!("can not encrypt the history: {}", e);
+            }
+        }
+
+        history
+    }
+
+    pub fn persist(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("Can not find the data directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let history = serde_json::to_string(&self.threads)?;
+
+        match (vault::get(), Self::encrypted_path()) {
+            (Some(vault), Some(encrypted_path)) => {
+                std::fs::write(encrypted_path, vault.encrypt(history.as_bytes())?)?;
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            _ => std::fs::write(path, history)?,
         }
+        Ok(())
+    }
+
+    /// Add a thread after the pinned ones, or update it if it was loaded from the history
+    pub fn push(&mut self, thread: Thread<'a>) {
+        match self.threads.iter_mut().find(|t| t.id == thread.id) {
+            Some(t) => *t = thread,
+            None => self.threads.push(thread),
+        }
+        self.sort();
+    }
+
+    fn sort(&mut self) {
+        self.threads.sort_by_key(|thread| !thread.pinned);
+    }
+
+    /// The indexes of the threads listed, with the tag of the filter
+    fn visible(&self) -> Vec<usize> {
+        self.threads
+            .iter()
+            .enumerate()
+            .filter(|(_, thread)| {
+                self.tag_filter
+                    .as_deref()
+                    .is_none_or(|tag| thread.has_tag(tag))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Select the thread in the list, the first one if it is not listed
+    fn select_thread(&mut self, i: usize) {
+        let visible = self.visible();
+        if visible.is_empty() {
+            self.state.select(None);
+        } else {
+            self.state
+                .select(Some(visible.iter().position(|v| *v == i).unwrap_or(0)));
+        }
+    }
+
+    /// All the tags of the threads, sorted
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .threads
+            .iter()
+            .flat_map(|thread| thread.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// List the threads of the next tag, all of them after the last tag
+    pub fn cycle_tag_filter(&mut self) {
+        let tags = self.tags();
+        self.tag_filter = match &self.tag_filter {
+            None => tags.first().cloned(),
+            Some(current) => tags.iter().find(|tag| *tag > current).cloned(),
+        };
+        self.state.select(None);
+        self.preview_scroll = 0;
+    }
+
+    pub fn toggle_pin(&mut self) {
+        if let Some(i) = self.selected() {
+            self.threads[i].pinned = !self.threads[i].pinned;
+            let id = self.threads[i].id;
+            self.sort();
+            if let Some(i) = self.threads.iter().position(|thread| thread.id == id) {
+                self.select_thread(i);
+            }
+        }
+    }
+
+    /// Put a thread back at its position, after an undo
+    pub fn insert(&mut self, i: usize, thread: Thread<'a>) {
+        let i = i.min(self.threads.len());
+        self.threads.insert(i, thread);
+        self.select_thread(i);
+        self.preview_scroll = 0;
+    }
+
+    pub fn delete(&mut self, i: usize) {
+        let position = self.state.selected().unwrap_or(0);
+        if i < self.threads.len() {
+            self.threads.remove(i);
+        }
+        let len = self.visible().len();
+        if len == 0 {
+            self.state.select(None);
+        } else {
+            self.state.select(Some(position.min(len - 1)));
+        }
+        self.preview_scroll = 0;
     }
 
     pub fn move_to_bottom(&mut self) {
-        if !self.text.is_empty() {
-            self.state.select(Some(self.text.len() - 1));
+        let len = self.visible().len();
+        if len > 0 {
+            self.state.select(Some(len - 1));
         }
     }
 
     pub fn move_to_top(&mut self) {
-        if !self.text.is_empty() {
+        if !self.visible().is_empty() {
             self.state.select(Some(0));
         }
     }
 
     pub fn scroll_down(&mut self) {
-        if self.text.is_empty() {
+        let len = self.visible().len();
+        if len == 0 {
             return;
         }
         let i = match self.state.selected() {
             Some(i) => {
-                if i < self.text.len() - 1 {
+                if i < len - 1 {
                     i + 1
                 } else {
                     i
@@ -67,49 +352,35 @@ impl History<'_> {
         self.state.select(Some(i));
     }
     pub fn scroll_up(&mut self) {
-        if self.text.is_empty() {
+        if self.visible().is_empty() {
             return;
         }
         let i = match self.state.selected() {
-            Some(i) => {
-                if i > 1 {
-                    i - 1
-                } else {
-                    0
-                }
-            }
+            Some(i) => i.saturating_sub(1),
             None => 0,
         };
         self.state.select(Some(i));
     }
 
-    pub fn save(&mut self, archive_file_name: &str, sender: UnboundedSender<Event>) {
-        if !self.text.is_empty() {
-            match std::fs::write(
-                archive_file_name,
-                self.text[self.state.selected().unwrap_or(0)].join(""),
-            ) {
-                Ok(_) => {
-                    let notif = Notification::new(
-                        format!("Chat saved to `{}` file", archive_file_name),
-                        NotificationLevel::Info,
-                    );
-
-                    sender.send(Event::Notification(notif)).unwrap();
-                }
-                Err(e) => {
-                    let notif = Notification::new(e.to_string(), NotificationLevel::Error);
-
-                    sender.send(Event::Notification(notif)).unwrap();
-                }
-            }
-        }
+    /// The index of the selected thread in `threads`
+    pub fn selected(&self) -> Option<usize> {
+        let visible = self.visible();
+        let position = self.state.selected().unwrap_or(0);
+        visible.get(position).or(visible.last()).copied()
     }
 
-    pub fn render(&mut self, frame: &mut Frame, area: Rect, focused_block: FocusedBlock) {
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        focused_block: FocusedBlock,
+        theme: &Theme,
+        authors: &Authors,
+    ) {
         self.block_height = area.height as usize;
 
-        if !self.text.is_empty() && self.state.selected().is_none() {
+        let visible = self.visible();
+        if !visible.is_empty() && self.state.selected().is_none() {
             *self.state.offset_mut() = 0;
             self.state.select(Some(0));
         }
@@ -122,20 +393,38 @@ impl History<'_> {
             (chunks[0], chunks[1])
         };
 
-        let items = self
-            .text
+        let items = visible
             .iter()
-            .map(|chat| match chat.first() {
-                Some(v) => ListItem::new(v.to_owned()),
-                None => ListItem::new(""),
+            .map(|i| {
+                let thread = &self.threads[*i];
+                let name = thread.name();
+                let name = authors.display(&name);
+                let name = if thread.pinned {
+                    format!("📌 {}", name)
+                } else {
+                    name.to_string()
+                };
+                let mut text = Text::from(name);
+                if let (false, Some(line)) = (thread.tags.is_empty(), text.lines.first_mut()) {
+                    line.spans.push(Span::styled(
+                        format!(" [{}]", thread.tags.join(", ")),
+                        Style::default().fg(theme.border),
+                    ));
+                }
+                ListItem::new(text)
             })
             .collect::<Vec<ListItem>>();
 
+        let title = match &self.tag_filter {
+            Some(tag) => format!(" {} ", fill("History [{}]", &[tag])),
+            None => format!(" {} ", t("History")),
+        };
+
         let list = List::new(items)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(" History ")
+                    .title(title)
                     .title_style(match focused_block {
                         FocusedBlock::History => Style::default().bold(),
                         _ => Style::default(),
@@ -143,21 +432,21 @@ impl History<'_> {
                     .title_alignment(Alignment::Center)
                     .style(Style::default())
                     .border_style(match focused_block {
-                        FocusedBlock::History => Style::default().fg(Color::Green),
-                        _ => Style::default(),
+                        FocusedBlock::History => Style::default().fg(theme.focused_border),
+                        _ => Style::default().fg(theme.border),
                     }),
             )
-            .highlight_style(Style::default().bg(Color::DarkGray));
+            .highlight_style(Style::default().bg(theme.selection));
 
-        let preview = Paragraph::new(match self.state.selected() {
-            Some(i) => self.preview.text[i].clone(),
+        let preview = Paragraph::new(match self.selected() {
+            Some(i) => self.threads[i].formatted_chat.clone(),
             None => Text::raw(""),
         })
         .wrap(Wrap { trim: false })
-        .scroll((self.preview.scroll as u16, 0))
+        .scroll((self.preview_scroll as u16, 0))
         .block(
             Block::default()
-                .title(" Preview ")
+                .title(format!(" {} ", t("Preview")))
                 .title_style(match focused_block {
                     FocusedBlock::Preview => Style::default().bold(),
                     _ => Style::default(),
@@ -166,13 +455,38 @@ impl History<'_> {
                 .borders(Borders::ALL)
                 .style(Style::default())
                 .border_style(match focused_block {
-                    FocusedBlock::Preview => Style::default().fg(Color::Green),
-                    _ => Style::default(),
+                    FocusedBlock::Preview => Style::default().fg(theme.focused_border),
+                    _ => Style::default().fg(theme.border),
                 }),
         );
 
         frame.render_widget(Clear, area);
         frame.render_widget(preview, preview_block);
         frame.render_stateful_widget(list, history_block, &mut self.state);
+
+        if let Some(i) = self.pending_deletion {
+            let confirmation = Paragraph::new(format!(
+                "Delete `{}` ? (y/n)",
+                self.threads[i].name().lines().next().unwrap_or_default()
+            ))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.warning)),
+            );
+
+            let confirmation_area = Rect {
+                x: area.x + area.width / 4,
+                y: area.y + (area.height / 2).saturating_sub(2),
+                width: area.width / 2,
+                height: 4,
+            };
+
+            frame.render_widget(Clear, confirmation_area);
+            frame.render_widget(confirmation, confirmation_area);
+        }
     }
 }
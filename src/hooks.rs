@@ -0,0 +1,460 @@
+use std::{collections::HashMap, process::Stdio, sync::Arc, time::Duration};
+
+use regex::Regex;
+use tokio::{
+    io::AsyncWriteExt,
+    process::Command,
+    sync::{mpsc::UnboundedSender, Mutex},
+    task::JoinHandle,
+};
+
+use crate::{
+    attachment::{Attachment, AttachmentKind},
+    clipboard::PasteSource,
+    config::{HookConfig, HookRole, PreSendConfig, Transformer},
+    event::Event,
+    llm::{message, LLMRole, LLM},
+    notification::{Notification, NotificationLevel},
+};
+
+/// The prompt waits for the pre-send commands, they are killed after it
+const PRE_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn matches(pattern: Option<&Regex>, message: &str) -> bool {
+    pattern.is_none_or(|pattern| pattern.is_match(message))
+}
+
+/// The hooks of the role whose pattern matches the message
+fn matching<'a>(hooks: &'a [HookConfig], role: HookRole, message: &str) -> Vec<&'a HookConfig> {
+    hooks
+        .iter()
+        .filter(|hook| {
+            (hook.role == HookRole::Any || hook.role == role)
+                && matches(hook.pattern.as_ref(), message)
+        })
+        .collect()
+}
+
+/// Write the message to the standard input of the command, and wait for its output. The
+/// variables are in its environment. It is killed after the timeout.
+async fn execute(
+    cmd: &str,
+    role: HookRole,
+    message: &str,
+    model: &str,
+    variables: &HashMap<String, String>,
+    timeout: Option<Duration>,
+) -> Result<String, String> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    };
+
+    let role = match role {
+        HookRole::User => "user",
+        _ => "assistant",
+    };
+    let mut child = command
+        .envs(variables)
+        .env("TENERE_ROLE", role)
+        .env("TENERE_MODEL", model)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(timeout.is_some())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Written while the output is read, a command answering before the end of its input
+    // would wait for it to be read
+    let stdin = child.stdin.take();
+    let input = format!("{}\n", message);
+    let write = async move {
+        if let Some(mut stdin) = stdin {
+            // A command not reading its input is not a failure
+            let _ = stdin.write_all(input.as_bytes()).await;
+        }
+    };
+    let exchange = async { tokio::join!(write, child.wait_with_output()).1 };
+
+    let output = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| format!("killed after {}s", timeout.as_secs()))?,
+        None => exchange.await,
+    }
+    .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().rfind(|line| !line.trim().is_empty()) {
+        Some(line) => Err(format!("{}: {}", output.status, line.trim())),
+        None => Err(output.status.to_string()),
+    }
+}
+
+/// Run the hooks matching the message in the background, each one up to its timeout.
+/// The task gives their failures.
+pub fn spawn(
+    hooks: &[HookConfig],
+    role: HookRole,
+    message: &str,
+    model: &str,
+    variables: &HashMap<String, String>,
+) -> JoinHandle<Vec<String>> {
+    let hooks: Vec<HookConfig> = matching(hooks, role, message)
+        .into_iter()
+        .cloned()
+        .collect();
+    let message = message.to_string();
+    let model = model.to_string();
+    let variables = variables.clone();
+
+    tokio::spawn(async move {
+        let runs = hooks.iter().map(|hook| async {
+            let timeout = (hook.timeout > 0).then(|| Duration::from_secs(hook.timeout));
+            let e = execute(&hook.command, role, &message, &model, &variables, timeout)
+                .await
+                .err()?;
+            tracing::warn!(command = hook.command, "hook failed: {}", e);
+            Some(format!("The hook `{}` failed: {}", hook.command, e))
+        });
+        futures::future::join_all(runs)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    })
+}
+
+/// Run the hooks matching the message in the background, their failures are sent as warnings
+pub fn run(
+    hooks: &[HookConfig],
+    role: HookRole,
+    message: &str,
+    model: &str,
+    variables: &HashMap<String, String>,
+    sender: &UnboundedSender<Event>,
+) {
+    let task = spawn(hooks, role, message, model, variables);
+    let sender = sender.clone();
+
+    tokio::spawn(async move {
+        for failure in task.await.unwrap_or_default() {
+            let _ = sender.send(Event::Notification(Notification::new(
+                failure,
+                NotificationLevel::Warning,
+            )));
+        }
+    });
+}
+
+/// The failures of the finished hooks, the others are kept running unless `wait` is set
+pub async fn failures(tasks: &mut Vec<JoinHandle<Vec<String>>>, wait: bool) -> Vec<String> {
+    let mut failures = Vec::new();
+    for task in std::mem::take(tasks) {
+        if wait || task.is_finished() {
+            failures.extend(task.await.unwrap_or_default());
+        } else {
+            tasks.push(task);
+        }
+    }
+    failures
+}
+
+fn trim(text: &str) -> String {
+    text.lines()
+        .map(str::trim_end)
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Attach the text of the clipboard, in place of the one attached before
+async fn attach_clipboard(
+    clipboard: Option<Result<PasteSource, String>>,
+    attachments: &mut Vec<Attachment>,
+) -> Result<(), String> {
+    let Some(source) = clipboard else {
+        return Ok(());
+    };
+    let text = match source {
+        Ok(source) => source.read().await,
+        Err(e) => Err(e),
+    }
+    .map_err(|e| format!("Can not read the clipboard: {}", e))?;
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+
+    attachments.retain(|a| !(a.kind == AttachmentKind::Context && a.name == "clipboard"));
+    attachments.push(Attachment {
+        kind: AttachmentKind::Context,
+        name: String::from("clipboard"),
+        content: text,
+    });
+    Ok(())
+}
+
+async fn translate(
+    llm: &Arc<Mutex<Box<dyn LLM + 'static>>>,
+    text: &str,
+    language: &str,
+) -> Result<String, String> {
+    let request = vec![message(
+        LLMRole::USER,
+        &format!(
+            "Translate the text below into {}. Answer with the translation only.\n\n{}",
+            language, text
+        ),
+    )];
+
+    let llm = llm.lock().await;
+    let translation = llm
+        .complete(request)
+        .await
+        .map_err(|e| format!("Can not translate the prompt: {}", e))?;
+    Ok(translation.trim().to_string())
+}
+
+/// The prompt changed by the pre-send hooks, sent back to the chat it was typed in
+#[derive(Debug, Clone)]
+pub struct PreSent {
+    pub chat: usize,
+    /// The text typed, put back in the prompt if a hook fails
+    pub prompt: String,
+    pub attachments: Vec<Attachment>,
+    pub result: Result<String, String>,
+}
+
+/// Change the prompt with the pre-send hooks matching it, one after the other.
+/// The prompt is not sent if one of them fails. The clipboard is the one to attach with the
+/// `clipboard` transformer.
+pub async fn pre_send(
+    hooks: &[PreSendConfig],
+    text: &str,
+    model: &str,
+    variables: &HashMap<String, String>,
+    mut clipboard: Option<Result<PasteSource, String>>,
+    attachments: &mut Vec<Attachment>,
+    llm: &Arc<Mutex<Box<dyn LLM + 'static>>>,
+) -> Result<String, String> {
+    let mut text = text.to_string();
+
+    for hook in hooks {
+        if !matches(hook.pattern.as_ref(), &text) {
+            continue;
+        }
+
+        match (&hook.command, hook.builtin) {
+            (Some(command), _) => {
+                let output = execute(
+                    command,
+                    HookRole::User,
+                    &text,
+                    model,
+                    variables,
+                    Some(PRE_SEND_TIMEOUT),
+                )
+                .await
+                .map_err(|e| {
+                    tracing::warn!(command, "pre-send hook failed: {}", e);
+                    format!("Not sent, a pre-send hook failed: {}", e)
+                })?;
+                text = output.trim_end().to_string();
+            }
+            (None, Some(Transformer::Trim)) => text = trim(&text),
+            (None, Some(Transformer::Clipboard)) => {
+                attach_clipboard(clipboard.take(), attachments).await?
+            }
+            (None, Some(Transformer::Translate)) => {
+                if let Some(language) = &hook.language {
+                    text = translate(llm, &text, language).await?;
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks() -> Vec<HookConfig> {
+        toml::from_str::<crate::config::Config>(
+            r#"
+            [[hooks]]
+            command = "answers"
+
+            [[hooks]]
+            command = "prompts"
+            role = "user"
+
+            [[hooks]]
+            command = "todos"
+            role = "any"
+            pattern = "(?i)todo"
+            "#,
+        )
+        .unwrap()
+        .hooks
+    }
+
+    #[test]
+    fn hooks_of_the_role_and_pattern() {
+        let hooks = hooks();
+        let commands = |role, message| -> Vec<String> {
+            matching(&hooks, role, message)
+                .into_iter()
+                .map(|hook| hook.command.clone())
+                .collect()
+        };
+
+        assert_eq!(commands(HookRole::Assistant, "Done"), vec!["answers"]);
+        assert_eq!(commands(HookRole::User, "Done"), vec!["prompts"]);
+        assert_eq!(
+            commands(HookRole::User, "A TODO list"),
+            vec!["prompts", "todos"]
+        );
+        assert_eq!(
+            commands(HookRole::Assistant, "todo: tests"),
+            vec!["answers", "todos"]
+        );
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let config = toml::from_str::<crate::config::Config>(
+            "[[hooks]]\ncommand = \"true\"\npattern = \"(\"\n",
+        );
+
+        assert!(config
+            .unwrap_err()
+            .to_string()
+            .contains("the pattern `(` is not valid"));
+    }
+
+    #[test]
+    fn trim_the_prompt() {
+        assert_eq!(trim("\n  Hello  \n\tworld\t\n\n"), "Hello\n\tworld");
+        assert_eq!(trim(" \n "), "");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn prompt_changed_by_the_pre_send_hooks() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            [[pre_send]]
+            builtin = "trim"
+
+            [[pre_send]]
+            command = "tr a-z A-Z"
+            pattern = "^shout"
+
+            [[pre_send]]
+            command = "exit 3"
+            pattern = "fail"
+            "#,
+        )
+        .unwrap();
+        let llm: Arc<Mutex<Box<dyn LLM + 'static>>> = Arc::new(Mutex::new(Box::new(
+            crate::mock::Mock::new(toml::from_str("").unwrap(), Default::default()),
+        )));
+        let send = |text: &'static str| {
+            let config = config.clone();
+            let llm = llm.clone();
+            async move {
+                pre_send(
+                    &config.pre_send,
+                    text,
+                    "model",
+                    &HashMap::new(),
+                    None,
+                    &mut Vec::new(),
+                    &llm,
+                )
+                .await
+            }
+        };
+
+        assert_eq!(send("  shout this \n").await.unwrap(), "SHOUT THIS");
+        assert_eq!(send(" whisper this ").await.unwrap(), "whisper this");
+        assert_eq!(
+            send("this will fail").await.unwrap_err(),
+            "Not sent, a pre-send hook failed: exit status: 3"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn long_message_read_while_it_is_written() {
+        // More than the buffer of a pipe, `cat` waits for its output to be read
+        let message = "tenere ".repeat(100_000);
+
+        let output = execute(
+            "cat",
+            HookRole::User,
+            &message,
+            "model",
+            &HashMap::new(),
+            Some(Duration::from_secs(5)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(output, format!("{}\n", message));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn variables_in_the_environment() {
+        let variables = HashMap::from([(String::from("project"), String::from("tenere"))]);
+
+        let output = execute(
+            "printf '%s %s' \"$project\" \"$TENERE_ROLE\"",
+            HookRole::User,
+            "Hello",
+            "model",
+            &variables,
+            Some(Duration::from_secs(5)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(output, "tenere user");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hook_killed_after_its_timeout() {
+        let hooks: Vec<HookConfig> = toml::from_str::<crate::config::Config>(
+            "[[hooks]]\ncommand = \"sleep 5\"\ntimeout = 1\n",
+        )
+        .unwrap()
+        .hooks;
+
+        let failures = spawn(
+            &hooks,
+            HookRole::Assistant,
+            "Done",
+            "model",
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(failures, vec!["The hook `sleep 5` failed: killed after 1s"]);
+    }
+}
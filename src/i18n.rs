@@ -0,0 +1,1747 @@
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+/// The language of the interface. The messages without a translation stay in English.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Fr,
+    De,
+    Zh,
+}
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Set the language once the config is loaded
+pub fn init(language: Language) {
+    let _ = LANGUAGE.set(language);
+}
+
+fn translations() -> &'static [(&'static str, &'static str)] {
+    match LANGUAGE.get().copied().unwrap_or_default() {
+        Language::En => &[],
+        Language::Fr => FR,
+        Language::De => DE,
+        Language::Zh => ZH,
+    }
+}
+
+/// The translation of a fixed text
+pub fn t(text: &'static str) -> &'static str {
+    translations()
+        .iter()
+        .find(|(english, _)| *english == text)
+        .map(|(_, translation)| *translation)
+        .unwrap_or(text)
+}
+
+/// The translation of a template, with its `{}` replaced by the values in turn
+pub fn fill(template: &'static str, values: &[&str]) -> String {
+    let mut parts = t(template).split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for (value, part) in values.iter().zip(parts) {
+        filled.push_str(value);
+        filled.push_str(part);
+    }
+    filled
+}
+
+/// The translation of a message built with `format!`: the `{}` of the English templates
+/// match any text, which is put at the same place in the translation.
+/// Every template is tried, it is meant for the messages built once like the notifications.
+pub fn translate(message: &str) -> String {
+    translate_with(message, translations())
+}
+
+fn translate_with(message: &str, translations: &[(&str, &str)]) -> String {
+    translations
+        .iter()
+        .find_map(|(english, translation)| {
+            let values = matches(english, message)?;
+            let mut parts = translation.split("{}");
+            let mut translated = parts.next().unwrap_or_default().to_string();
+            for (value, part) in values.iter().zip(parts) {
+                translated.push_str(value);
+                translated.push_str(part);
+            }
+            Some(translated)
+        })
+        .unwrap_or_else(|| message.to_string())
+}
+
+/// The texts in place of the `{}` of the template, if the message follows it
+fn matches<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let mut parts = template.split("{}");
+    let mut rest = message.strip_prefix(parts.next()?)?;
+    let parts: Vec<&str> = parts.collect();
+
+    let mut values = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let end = if i == parts.len() - 1 {
+            if part.is_empty() {
+                rest.len()
+            } else {
+                rest.strip_suffix(part).map(str::len)?
+            }
+        } else if part.is_empty() {
+            return None;
+        } else {
+            rest.find(part)?
+        };
+        values.push(&rest[..end]);
+        rest = &rest[end + part.len()..];
+    }
+
+    rest.is_empty().then_some(values)
+}
+
+const FR: &[(&str, &str)] = &[
+    // Titles
+    ("Help", "Aide"),
+    ("History", "Historique"),
+    ("History [{}]", "Historique [{}]"),
+    ("Preview", "Aperçu"),
+    ("Questions", "Questions"),
+    ("Answering…", "Réponse en cours…"),
+    ("Attach", "Joindre"),
+    ("Payload", "Message envoyé"),
+    ("Profiles", "Profils"),
+    ("Chat info", "Infos de la discussion"),
+    ("Latency of the models", "Latence des modèles"),
+    ("Model", "Modèle"),
+    ("First token", "Premier token"),
+    ("Between chunks", "Entre les fragments"),
+    ("Tokens/s", "Tokens/s"),
+    ("No answer yet", "Aucune réponse pour l'instant"),
+    (
+        "Replaced answer → answer in the chat",
+        "Réponse remplacée → réponse de la discussion",
+    ),
+    (
+        "j/k: scroll · o: keep the replaced one · Esc: close",
+        "j/k : défiler · o : garder la réponse remplacée · Esc : fermer",
+    ),
+    ("The answers are the same", "Les réponses sont identiques"),
+    ("Esc: close", "Esc : fermer"),
+    ("Answers", "Réponses"),
+    ("Words", "Mots"),
+    ("Reading time", "Temps de lecture"),
+    ("Estimated tokens", "Tokens estimés"),
+    ("Tokens used", "Tokens utilisés"),
+    ("Models", "Modèles"),
+    ("Cost", "Coût"),
+    ("Duration", "Durée"),
+    ("Time answering", "Temps de réponse"),
+    (
+        "No [prices] for these models",
+        "Pas de [prices] pour ces modèles",
+    ),
+    (
+        "{}, {} answers without a price",
+        "{}, {} réponses sans prix",
+    ),
+    ("Log", "Journal"),
+    ("Info", "Info"),
+    ("Warning", "Attention"),
+    ("Error", "Erreur"),
+    ("follow: off", "suivi : désactivé"),
+    ("h/l or Tab: page", "h/l ou Tab : page"),
+    (
+        "Enter: run · Esc: cancel",
+        "Entrée : exécuter · Échap : annuler",
+    ),
+    (
+        "j/k: scroll · g/G: top/bottom · Esc: quit",
+        "j/k : défiler · g/G : début/fin · Échap : quitter",
+    ),
+    (
+        "Enter: send · Tab: switch target · Esc: cancel",
+        "Entrée : envoyer · Tab : changer de cible · Échap : annuler",
+    ),
+    (
+        "Enter: jump · y: continue · Esc: cancel",
+        "Entrée : aller à · y : continuer · Échap : annuler",
+    ),
+    ("{} · P to preview", "{} · P pour l'aperçu"),
+    // Pages of the help
+    ("Global", "Général"),
+    ("Chat", "Discussion"),
+    ("Prompt", "Saisie"),
+    ("Config", "Configuration"),
+    ("Backends", "Backends"),
+    (
+        "Switch to Normal mode / Dismiss pop-up",
+        "Passer en mode Normal / Fermer la fenêtre",
+    ),
+    ("Switch the focus", "Changer de bloc"),
+    (
+        "Start new chat and save the previous one to the history",
+        "Nouvelle discussion, la précédente va dans l'historique",
+    ),
+    (
+        "Save the chat to a file in the current directory",
+        "Enregistrer la discussion dans le dossier courant",
+    ),
+    (
+        "Copy the chat to the clipboard as rich text",
+        "Copier la discussion en texte enrichi",
+    ),
+    (
+        "Summarize the chat to shrink the context",
+        "Résumer la discussion pour réduire le contexte",
+    ),
+    (
+        "Start or stop recording the voice input",
+        "Démarrer ou arrêter l'enregistrement vocal",
+    ),
+    (
+        "Switch between the stacked and the split layouts",
+        "Basculer entre la disposition empilée et scindée",
+    ),
+    ("Grow or shrink the prompt", "Agrandir ou réduire la saisie"),
+    ("Switch to the next theme", "Passer au thème suivant"),
+    ("Switch the backend manually", "Changer de backend"),
+    ("Switch to another profile", "Changer de profil"),
+    (
+        "Replay how the answers were streamed",
+        "Rejouer l'arrivée des réponses",
+    ),
+    (
+        "Write the prompt in $EDITOR",
+        "Écrire la question dans $EDITOR",
+    ),
+    (
+        "Show the words, tokens and cost of the chat",
+        "Afficher les mots, les tokens et le coût de la discussion",
+    ),
+    (
+        "Underline the misspelled words of the prompt",
+        "Souligner les fautes d'orthographe de la zone de saisie",
+    ),
+    (
+        "Correct the misspelled word under the cursor",
+        "Corriger le mot mal orthographié sous le curseur",
+    ),
+    (
+        "Spellcheck on, z= corrects the word under the cursor",
+        "Correcteur activé, z= corrige le mot sous le curseur",
+    ),
+    ("Spellcheck off", "Correcteur désactivé"),
+    ("No word under the cursor", "Aucun mot sous le curseur"),
+    (
+        "Turn the spellcheck on with ctrl + {} first",
+        "Activez d'abord le correcteur avec ctrl + {}",
+    ),
+    ("No suggestion", "Aucune suggestion"),
+    (
+        "Enter: replace · a: add to the dictionary · Esc: close",
+        "Entrée : remplacer · a : ajouter au dictionnaire · Échap : fermer",
+    ),
+    (
+        "No dictionary found for the spellcheck in {}",
+        "Aucun dictionnaire trouvé pour le correcteur dans {}",
+    ),
+    (
+        "Suspend to the shell, resume with fg",
+        "Suspendre dans le shell, reprendre avec fg",
+    ),
+    (
+        "Send a message or a code block to Slack or Matrix",
+        "Envoyer un message ou un bloc de code sur Slack ou Matrix",
+    ),
+    ("Show history", "Afficher l'historique"),
+    ("Stop the stream response", "Arrêter la réponse"),
+    (
+        "Branch from the answer being generated",
+        "Bifurquer depuis la réponse en cours",
+    ),
+    (
+        "Retry the failed request, or redo what was undone",
+        "Relancer la requête échouée, ou rétablir l'annulation",
+    ),
+    ("Open the command line", "Ouvrir la ligne de commande"),
+    ("Show the log", "Afficher le journal"),
+    ("Show help", "Afficher l'aide"),
+    ("Quit", "Quitter"),
+    ("Scroll down", "Défiler vers le bas"),
+    ("Scroll up", "Défiler vers le haut"),
+    ("Go to the end", "Aller à la fin"),
+    ("Go to the top", "Aller au début"),
+    (
+        "Scroll the answer in the split layout",
+        "Défiler la réponse en disposition scindée",
+    ),
+    ("Toggle following the stream", "Suivre ou non la réponse"),
+    (
+        "Fold or unfold the reasoning of the answers",
+        "Plier ou déplier le raisonnement des réponses",
+    ),
+    ("Undo a new chat", "Annuler une nouvelle discussion"),
+    (
+        "Run a shell command proposed in the chat",
+        "Exécuter une commande proposée dans la discussion",
+    ),
+    (
+        "Copy the last question to the prompt",
+        "Copier la dernière question dans la zone de saisie",
+    ),
+    (
+        "Code lines not wrapped, h and l scroll them",
+        "Les lignes de code ne sont pas coupées, h et l les font défiler",
+    ),
+    ("Code lines wrapped", "Lignes de code coupées à la largeur"),
+    (
+        "Wrap the code lines or scroll them sideways",
+        "Couper les lignes de code ou les faire défiler",
+    ),
+    (
+        "Scroll the code lines that are not wrapped",
+        "Faire défiler les lignes de code non coupées",
+    ),
+    (
+        "Yank the last answer, into a register with \"a y",
+        "Copier la dernière réponse, dans un registre avec \"a y",
+    ),
+    (
+        "Yank into or paste from the register a",
+        "Copier dans le registre a ou coller son contenu",
+    ),
+    ("Registers", "Registres"),
+    (
+        "Enter: paste in the prompt · Esc: close",
+        "Entrée : coller dans la zone de saisie · Échap : fermer",
+    ),
+    (
+        "No answer in this chat",
+        "Aucune réponse dans cette discussion",
+    ),
+    (
+        "Answer yanked into register {}",
+        "Réponse copiée dans le registre {}",
+    ),
+    (
+        "Answer copied to the clipboard",
+        "Réponse copiée dans le presse-papiers",
+    ),
+    (
+        "Regenerate the last answer",
+        "Régénérer la dernière réponse",
+    ),
+    (
+        "Compare the regenerated answer with the replaced one",
+        "Comparer la réponse régénérée avec celle qu'elle remplace",
+    ),
+    (
+        "Send the prompt in Normal mode, new line in Insert mode",
+        "Envoyer en mode Normal, nouvelle ligne en mode Insertion",
+    ),
+    (
+        "Insert before, after, at the start or the end",
+        "Insérer avant, après, au début ou à la fin",
+    ),
+    (
+        "Add a line below or above",
+        "Ajouter une ligne dessous ou dessus",
+    ),
+    ("Move the cursor", "Déplacer le curseur"),
+    (
+        "Move to the next or previous word",
+        "Aller au mot suivant ou précédent",
+    ),
+    (
+        "Move to the start or the end of the line",
+        "Aller au début ou à la fin de la ligne",
+    ),
+    ("Delete the line or a word", "Supprimer la ligne ou un mot"),
+    ("Change the line or a word", "Remplacer la ligne ou un mot"),
+    (
+        "Switch to Visual mode, y yanks the selection",
+        "Passer en mode Visuel, y copie la sélection",
+    ),
+    ("Paste", "Coller"),
+    (
+        "Undo, or bring back a deleted prompt",
+        "Annuler, ou récupérer une saisie supprimée",
+    ),
+    (
+        "Preview the prompt with its attachments",
+        "Aperçu de la question et de ses pièces jointes",
+    ),
+    (
+        "Complete the path after /attach or /file",
+        "Compléter le chemin après /attach ou /file",
+    ),
+    ("Start a slash command", "Commencer une commande /"),
+    (
+        "Select the next thread",
+        "Sélectionner la discussion suivante",
+    ),
+    (
+        "Select the previous thread",
+        "Sélectionner la discussion précédente",
+    ),
+    ("Continue the selected thread", "Reprendre la discussion"),
+    ("Delete the selected thread", "Supprimer la discussion"),
+    ("Pin the selected thread", "Épingler la discussion"),
+    (
+        "Filter by tag, the next tag on each press",
+        "Filtrer par étiquette, la suivante à chaque appui",
+    ),
+    ("Undo a deletion", "Annuler une suppression"),
+    (
+        "Switch between the list and the preview",
+        "Basculer entre la liste et l'aperçu",
+    ),
+    (
+        "The backend: chatgpt, llamacpp, ollama or mock",
+        "Le backend : chatgpt, llamacpp, ollama ou mock",
+    ),
+    (
+        "The directory of the saved chats",
+        "Le dossier des discussions enregistrées",
+    ),
+    (
+        "Name the threads of the history",
+        "Nommer les discussions de l'historique",
+    ),
+    ("Show the status bar", "Afficher la barre d'état"),
+    (
+        "Show the LaTeX math with Unicode characters",
+        "Afficher les maths LaTeX en caractères Unicode",
+    ),
+    (
+        "Number the lines of the code blocks",
+        "Numéroter les lignes des blocs de code",
+    ),
+    ("The language of the interface", "La langue de l'interface"),
+    (
+        "Lock the screen after this many minutes",
+        "Verrouiller l'écran après ce nombre de minutes",
+    ),
+    (
+        "Milliseconds to wait for the second key of a chord",
+        "Millisecondes d'attente de la seconde touche d'un enchaînement",
+    ),
+    (
+        "Accept commands from other programs",
+        "Accepter les commandes d'autres programmes",
+    ),
+    (
+        "Rules hiding the secrets before sending",
+        "Règles masquant les secrets avant l'envoi",
+    ),
+    (
+        "Dollars per million tokens of the models",
+        "Dollars par million de tokens des modèles",
+    ),
+    (
+        "Switch to a backend that answers",
+        "Passer à un backend qui répond",
+    ),
+    (
+        "Search the local files for the context",
+        "Chercher le contexte dans les fichiers locaux",
+    ),
+    (
+        "Write a config for a backend step by step",
+        "Écrire la configuration d'un backend pas à pas",
+    ),
+    // Notifications
+    ("Chat copied to the clipboard", "Discussion copiée"),
+    ("Chat saved to `{}`", "Discussion enregistrée dans `{}`"),
+    ("Chat exported to `{}`", "Discussion exportée dans `{}`"),
+    (
+        "Writing the messages to `{}`",
+        "Écriture des messages dans `{}`",
+    ),
+    (
+        "Stopped writing the messages to `{}`",
+        "Fin de l'écriture des messages dans `{}`",
+    ),
+    (
+        "Can not save the chat to `{}`: {}",
+        "Impossible d'enregistrer la discussion dans `{}` : {}",
+    ),
+    (
+        "Can not export the chat to `{}`: {}",
+        "Impossible d'exporter la discussion dans `{}` : {}",
+    ),
+    (
+        "Can not save the history: {}",
+        "Impossible d'enregistrer l'historique : {}",
+    ),
+    ("Export canceled", "Export annulé"),
+    ("Nothing to undo", "Rien à annuler"),
+    ("Nothing to redo", "Rien à rétablir"),
+    ("Undo: {}", "Annulé : {}"),
+    ("Redo: {}", "Rétabli : {}"),
+    ("Theme: {}", "Thème : {}"),
+    ("Profile: {}", "Profil : {}"),
+    ("Project settings: {}", "Réglages du projet : {}"),
+    ("Draft restored", "Brouillon restauré"),
+    (
+        "An answer is being streamed, quit and lose it? (y/n)",
+        "Une réponse est en cours, quitter et la perdre ? (y/n)",
+    ),
+    (
+        "The history is not saved, quit and lose the chats? (y/n)",
+        "L'historique n'est pas enregistré, quitter et perdre les discussions ? (y/n)",
+    ),
+    (
+        "Can not save the draft: {}",
+        "Impossible d'enregistrer le brouillon : {}",
+    ),
+    (
+        "Can not remove the draft: {}",
+        "Impossible de supprimer le brouillon : {}",
+    ),
+    ("Sent to {}", "Envoyé sur {}"),
+    ("Can not send to {}: {}", "Impossible d'envoyer sur {} : {}"),
+    (
+        "Wait for the answer to end",
+        "Attendez la fin de la réponse",
+    ),
+    (
+        "Wait for the answers to end",
+        "Attendez la fin des réponses",
+    ),
+    (
+        "Wait for the pre-send hooks to end",
+        "Attendez la fin des hooks avant envoi",
+    ),
+    (
+        "The chat changed before the prompt was sent",
+        "La discussion a changé avant l'envoi du message",
+    ),
+    (
+        "Wait for the answer of the previous chat to end",
+        "Attendez la fin de la réponse de la discussion précédente",
+    ),
+    (
+        "Wait for the answer before loading another chat",
+        "Attendez la réponse avant d'ouvrir une autre discussion",
+    ),
+    (
+        "Wait for the answer before switching the profile",
+        "Attendez la réponse avant de changer de profil",
+    ),
+    (
+        "Wait for the answer or the running command to end",
+        "Attendez la fin de la réponse ou de la commande",
+    ),
+    (
+        "There is no answer being generated to branch from",
+        "Aucune réponse en cours depuis laquelle bifurquer",
+    ),
+    (
+        "The message will be sent once the branched answer ends",
+        "Le message sera envoyé à la fin de la réponse bifurquée",
+    ),
+    (
+        "A message is already waiting for the branched answer to end",
+        "Un message attend déjà la fin de la réponse bifurquée",
+    ),
+    (
+        "No answer to replay in this chat",
+        "Aucune réponse à rejouer dans cette discussion",
+    ),
+    (
+        "No shell command in the answers",
+        "Aucune commande dans les réponses",
+    ),
+    ("Code blocks", "Blocs de code"),
+    (
+        "Enter: copy · w: save to a file · r: run · Esc: close",
+        "Entrée : copier · w : enregistrer dans un fichier · r : exécuter · Échap : fermer",
+    ),
+    ("Run with", "Exécuter avec"),
+    ("exists, replace it? (y/n)", "existe, le remplacer ? (y/n)"),
+    ("Save to:", "Enregistrer sous :"),
+    ("Code block copied to the clipboard", "Bloc de code copié"),
+    (
+        "No code block in the answers",
+        "Aucun bloc de code dans les réponses",
+    ),
+    ("Saved to {}", "Enregistré dans {}"),
+    (
+        "Can not save to `{}`: {}",
+        "Impossible d'enregistrer dans `{}` : {}",
+    ),
+    (
+        "Pick a code block to copy, save or run it",
+        "Choisir un bloc de code à copier, enregistrer ou exécuter",
+    ),
+    (
+        "Pipeline `{}` started, {} steps",
+        "Pipeline `{}` démarré, {} étapes",
+    ),
+    ("Pipeline `{}`: step {}/{}", "Pipeline `{}` : étape {}/{}"),
+    ("Pipeline `{}` done", "Pipeline `{}` terminé"),
+    ("The hook `{}` failed: {}", "Le hook `{}` a échoué : {}"),
+    ("Answered from the cache", "Réponse tirée du cache"),
+    (
+        "Not sent, a pre-send hook failed: {}",
+        "Non envoyé, un hook avant envoi a échoué : {}",
+    ),
+    (
+        "Can not translate the prompt: {}",
+        "Impossible de traduire le message : {}",
+    ),
+    (
+        "Can not read the clipboard: {}",
+        "Impossible de lire le presse-papiers : {}",
+    ),
+    (
+        "Pipeline `{}` stopped at step {}/{}",
+        "Pipeline `{}` arrêté à l'étape {}/{}",
+    ),
+    (
+        "No question in this chat",
+        "Aucune question dans cette discussion",
+    ),
+    ("No answer to regenerate", "Aucune réponse à régénérer"),
+    (
+        "The answer was not regenerated, the previous one is kept",
+        "La réponse n'a pas été régénérée, la précédente est gardée",
+    ),
+    (
+        "Wait for the answer to end before regenerating it",
+        "Attendez la fin de la réponse pour la régénérer",
+    ),
+    (
+        "No regenerated answer to compare",
+        "Aucune réponse régénérée à comparer",
+    ),
+    (
+        "Answer regenerated, `D` shows what changed",
+        "Réponse régénérée, `D` montre ce qui a changé",
+    ),
+    (
+        "The replaced answer is back, `D` compares them again",
+        "La réponse remplacée est revenue, `D` les compare à nouveau",
+    ),
+    (
+        "No profiles are defined in the config",
+        "Aucun profil n'est défini dans la configuration",
+    ),
+    ("No file matches `{}`", "Aucun fichier ne correspond à `{}`"),
+    (
+        "The reasoning is hidden by `reasoning_display`",
+        "Le raisonnement est masqué par `reasoning_display`",
+    ),
+    ("Redacted before sending: {}", "Masqué avant l'envoi : {}"),
+    (
+        "Can not retrieve the context: {}",
+        "Impossible de récupérer le contexte : {}",
+    ),
+    (
+        "Can not transcribe the recording: {}",
+        "Impossible de transcrire l'enregistrement : {}",
+    ),
+    (
+        "{}, retrying in {}s ({}/{})",
+        "{}, nouvel essai dans {} s ({}/{})",
+    ),
+    (
+        "Rate limited by {}, the request is queued for {}s. `ctrl + t` cancels it",
+        "Limite de débit de {}, la requête attend {} s. `ctrl + t` l'annule",
+    ),
+    ("⚠ request failed: {}", "⚠ échec de la requête : {}"),
+    (
+        "Press ctrl + r to retry",
+        "Appuyez sur ctrl + r pour réessayer",
+    ),
+    ("⚠ Answer stopped: {}", "⚠ Réponse interrompue : {}"),
+    ("Run a command", "Lancer une commande"),
+    (
+        "Run in a subshell ? (y/n)",
+        "Lancer dans un sous-shell ? (y/n)",
+    ),
+    ("Share to {}", "Partager vers {}"),
+    ("Send `{}` to {} ? (y/n)", "Envoyer `{}` vers {} ? (y/n)"),
+    (
+        "⚠ {} potential secrets found",
+        "⚠ {} secrets potentiels trouvés",
+    ),
+];
+
+const DE: &[(&str, &str)] = &[
+    // Titles
+    ("Help", "Hilfe"),
+    ("History", "Verlauf"),
+    ("History [{}]", "Verlauf [{}]"),
+    ("Preview", "Vorschau"),
+    ("Questions", "Fragen"),
+    ("Answering…", "Antwort läuft…"),
+    ("Attach", "Anhängen"),
+    ("Payload", "Gesendete Nachricht"),
+    ("Profiles", "Profile"),
+    ("Chat info", "Chat-Infos"),
+    ("Latency of the models", "Latenz der Modelle"),
+    ("Model", "Modell"),
+    ("First token", "Erstes Token"),
+    ("Between chunks", "Zwischen Fragmenten"),
+    ("Tokens/s", "Tokens/s"),
+    ("No answer yet", "Noch keine Antwort"),
+    (
+        "Replaced answer → answer in the chat",
+        "Ersetzte Antwort → Antwort im Chat",
+    ),
+    (
+        "j/k: scroll · o: keep the replaced one · Esc: close",
+        "j/k: scrollen · o: die ersetzte behalten · Esc: schließen",
+    ),
+    ("The answers are the same", "Die Antworten sind gleich"),
+    ("Esc: close", "Esc: schließen"),
+    ("Answers", "Antworten"),
+    ("Words", "Wörter"),
+    ("Reading time", "Lesezeit"),
+    ("Estimated tokens", "Geschätzte Tokens"),
+    ("Tokens used", "Verbrauchte Tokens"),
+    ("Models", "Modelle"),
+    ("Cost", "Kosten"),
+    ("Duration", "Dauer"),
+    ("Time answering", "Antwortzeit"),
+    (
+        "No [prices] for these models",
+        "Keine [prices] für diese Modelle",
+    ),
+    (
+        "{}, {} answers without a price",
+        "{}, {} Antworten ohne Preis",
+    ),
+    ("Log", "Protokoll"),
+    ("Info", "Info"),
+    ("Warning", "Warnung"),
+    ("Error", "Fehler"),
+    ("follow: off", "Mitlaufen: aus"),
+    ("h/l or Tab: page", "h/l oder Tab: Seite"),
+    (
+        "Enter: run · Esc: cancel",
+        "Enter: ausführen · Esc: abbrechen",
+    ),
+    (
+        "j/k: scroll · g/G: top/bottom · Esc: quit",
+        "j/k: scrollen · g/G: Anfang/Ende · Esc: schließen",
+    ),
+    (
+        "Enter: send · Tab: switch target · Esc: cancel",
+        "Enter: senden · Tab: Ziel wechseln · Esc: abbrechen",
+    ),
+    (
+        "Enter: jump · y: continue · Esc: cancel",
+        "Enter: anzeigen · y: fortfahren · Esc: abbrechen",
+    ),
+    ("{} · P to preview", "{} · P für die Vorschau"),
+    // Pages of the help
+    ("Global", "Allgemein"),
+    ("Chat", "Chat"),
+    ("Prompt", "Eingabe"),
+    ("Config", "Konfiguration"),
+    ("Backends", "Backends"),
+    (
+        "Switch to Normal mode / Dismiss pop-up",
+        "In den Normal-Modus wechseln / Fenster schließen",
+    ),
+    ("Switch the focus", "Fokus wechseln"),
+    (
+        "Start new chat and save the previous one to the history",
+        "Neuer Chat, der vorherige kommt in den Verlauf",
+    ),
+    (
+        "Save the chat to a file in the current directory",
+        "Chat im aktuellen Verzeichnis speichern",
+    ),
+    (
+        "Copy the chat to the clipboard as rich text",
+        "Chat als formatierten Text kopieren",
+    ),
+    (
+        "Summarize the chat to shrink the context",
+        "Chat zusammenfassen, um den Kontext zu verkleinern",
+    ),
+    (
+        "Start or stop recording the voice input",
+        "Spracheingabe starten oder beenden",
+    ),
+    (
+        "Switch between the stacked and the split layouts",
+        "Zwischen gestapelter und geteilter Ansicht wechseln",
+    ),
+    (
+        "Grow or shrink the prompt",
+        "Eingabe vergrößern oder verkleinern",
+    ),
+    ("Switch to the next theme", "Zum nächsten Theme wechseln"),
+    ("Switch the backend manually", "Backend wechseln"),
+    ("Switch to another profile", "Profil wechseln"),
+    (
+        "Replay how the answers were streamed",
+        "Das Eintreffen der Antworten abspielen",
+    ),
+    (
+        "Write the prompt in $EDITOR",
+        "Die Frage in $EDITOR schreiben",
+    ),
+    (
+        "Show the words, tokens and cost of the chat",
+        "Wörter, Tokens und Kosten des Chats anzeigen",
+    ),
+    (
+        "Underline the misspelled words of the prompt",
+        "Falsch geschriebene Wörter der Eingabe unterstreichen",
+    ),
+    (
+        "Correct the misspelled word under the cursor",
+        "Das falsch geschriebene Wort unter dem Cursor korrigieren",
+    ),
+    (
+        "Spellcheck on, z= corrects the word under the cursor",
+        "Rechtschreibprüfung an, z= korrigiert das Wort unter dem Cursor",
+    ),
+    ("Spellcheck off", "Rechtschreibprüfung aus"),
+    ("No word under the cursor", "Kein Wort unter dem Cursor"),
+    (
+        "Turn the spellcheck on with ctrl + {} first",
+        "Schalte zuerst die Rechtschreibprüfung mit ctrl + {} ein",
+    ),
+    ("No suggestion", "Kein Vorschlag"),
+    (
+        "Enter: replace · a: add to the dictionary · Esc: close",
+        "Enter: ersetzen · a: zum Wörterbuch hinzufügen · Esc: schließen",
+    ),
+    (
+        "No dictionary found for the spellcheck in {}",
+        "Kein Wörterbuch für die Rechtschreibprüfung in {} gefunden",
+    ),
+    (
+        "Suspend to the shell, resume with fg",
+        "In die Shell wechseln, mit fg zurück",
+    ),
+    (
+        "Send a message or a code block to Slack or Matrix",
+        "Nachricht oder Codeblock an Slack oder Matrix senden",
+    ),
+    ("Show history", "Verlauf anzeigen"),
+    ("Stop the stream response", "Antwort stoppen"),
+    (
+        "Branch from the answer being generated",
+        "Von der laufenden Antwort abzweigen",
+    ),
+    (
+        "Retry the failed request, or redo what was undone",
+        "Fehlgeschlagene Anfrage wiederholen oder Rückgängiges wiederherstellen",
+    ),
+    ("Open the command line", "Befehlszeile öffnen"),
+    ("Show the log", "Protokoll anzeigen"),
+    ("Show help", "Hilfe anzeigen"),
+    ("Quit", "Beenden"),
+    ("Scroll down", "Nach unten scrollen"),
+    ("Scroll up", "Nach oben scrollen"),
+    ("Go to the end", "Zum Ende"),
+    ("Go to the top", "Zum Anfang"),
+    (
+        "Scroll the answer in the split layout",
+        "Die Antwort in der geteilten Ansicht scrollen",
+    ),
+    (
+        "Toggle following the stream",
+        "Mitlaufen ein- oder ausschalten",
+    ),
+    (
+        "Fold or unfold the reasoning of the answers",
+        "Das Nachdenken der Antworten ein- oder ausklappen",
+    ),
+    ("Undo a new chat", "Neuen Chat rückgängig machen"),
+    (
+        "Run a shell command proposed in the chat",
+        "Einen vorgeschlagenen Shell-Befehl ausführen",
+    ),
+    (
+        "Copy the last question to the prompt",
+        "Die letzte Frage in die Eingabe kopieren",
+    ),
+    (
+        "Code lines not wrapped, h and l scroll them",
+        "Codezeilen werden nicht umbrochen, h und l scrollen sie",
+    ),
+    ("Code lines wrapped", "Codezeilen werden umbrochen"),
+    (
+        "Wrap the code lines or scroll them sideways",
+        "Codezeilen umbrechen oder seitlich scrollen",
+    ),
+    (
+        "Scroll the code lines that are not wrapped",
+        "Nicht umbrochene Codezeilen scrollen",
+    ),
+    (
+        "Yank the last answer, into a register with \"a y",
+        "Die letzte Antwort kopieren, mit \"a y in ein Register",
+    ),
+    (
+        "Yank into or paste from the register a",
+        "In das Register a kopieren oder daraus einfügen",
+    ),
+    ("Registers", "Register"),
+    (
+        "Enter: paste in the prompt · Esc: close",
+        "Enter: in die Eingabe einfügen · Esc: schließen",
+    ),
+    ("No answer in this chat", "Keine Antwort in diesem Chat"),
+    (
+        "Answer yanked into register {}",
+        "Antwort in Register {} kopiert",
+    ),
+    (
+        "Answer copied to the clipboard",
+        "Antwort in die Zwischenablage kopiert",
+    ),
+    (
+        "Regenerate the last answer",
+        "Die letzte Antwort neu erzeugen",
+    ),
+    (
+        "Compare the regenerated answer with the replaced one",
+        "Die neu erzeugte Antwort mit der ersetzten vergleichen",
+    ),
+    (
+        "Send the prompt in Normal mode, new line in Insert mode",
+        "Senden im Normal-Modus, neue Zeile im Einfüge-Modus",
+    ),
+    (
+        "Insert before, after, at the start or the end",
+        "Davor, danach, am Anfang oder am Ende einfügen",
+    ),
+    (
+        "Add a line below or above",
+        "Zeile darunter oder darüber einfügen",
+    ),
+    ("Move the cursor", "Cursor bewegen"),
+    (
+        "Move to the next or previous word",
+        "Zum nächsten oder vorherigen Wort",
+    ),
+    (
+        "Move to the start or the end of the line",
+        "Zum Anfang oder Ende der Zeile",
+    ),
+    ("Delete the line or a word", "Zeile oder Wort löschen"),
+    ("Change the line or a word", "Zeile oder Wort ersetzen"),
+    (
+        "Switch to Visual mode, y yanks the selection",
+        "In den Visual-Modus wechseln, y kopiert die Auswahl",
+    ),
+    ("Paste", "Einfügen"),
+    (
+        "Undo, or bring back a deleted prompt",
+        "Rückgängig, oder gelöschte Eingabe zurückholen",
+    ),
+    (
+        "Preview the prompt with its attachments",
+        "Vorschau der Frage mit ihren Anhängen",
+    ),
+    (
+        "Complete the path after /attach or /file",
+        "Den Pfad nach /attach oder /file vervollständigen",
+    ),
+    ("Start a slash command", "Einen /-Befehl beginnen"),
+    ("Select the next thread", "Nächsten Chat auswählen"),
+    ("Select the previous thread", "Vorherigen Chat auswählen"),
+    (
+        "Continue the selected thread",
+        "Ausgewählten Chat fortsetzen",
+    ),
+    ("Delete the selected thread", "Ausgewählten Chat löschen"),
+    ("Pin the selected thread", "Ausgewählten Chat anheften"),
+    (
+        "Filter by tag, the next tag on each press",
+        "Nach Tag filtern, bei jedem Druck der nächste",
+    ),
+    ("Undo a deletion", "Löschen rückgängig machen"),
+    (
+        "Switch between the list and the preview",
+        "Zwischen Liste und Vorschau wechseln",
+    ),
+    (
+        "The backend: chatgpt, llamacpp, ollama or mock",
+        "Das Backend: chatgpt, llamacpp, ollama oder mock",
+    ),
+    (
+        "The directory of the saved chats",
+        "Das Verzeichnis der gespeicherten Chats",
+    ),
+    (
+        "Name the threads of the history",
+        "Die Chats im Verlauf benennen",
+    ),
+    ("Show the status bar", "Statusleiste anzeigen"),
+    (
+        "Show the LaTeX math with Unicode characters",
+        "LaTeX-Formeln mit Unicode-Zeichen anzeigen",
+    ),
+    (
+        "Number the lines of the code blocks",
+        "Die Zeilen der Codeblöcke nummerieren",
+    ),
+    (
+        "The language of the interface",
+        "Die Sprache der Oberfläche",
+    ),
+    (
+        "Lock the screen after this many minutes",
+        "Bildschirm nach so vielen Minuten sperren",
+    ),
+    (
+        "Milliseconds to wait for the second key of a chord",
+        "Millisekunden Wartezeit auf die zweite Taste einer Tastenfolge",
+    ),
+    (
+        "Accept commands from other programs",
+        "Befehle anderer Programme annehmen",
+    ),
+    (
+        "Rules hiding the secrets before sending",
+        "Regeln, die Geheimnisse vor dem Senden verbergen",
+    ),
+    (
+        "Dollars per million tokens of the models",
+        "Dollar pro Million Tokens der Modelle",
+    ),
+    (
+        "Switch to a backend that answers",
+        "Zu einem antwortenden Backend wechseln",
+    ),
+    (
+        "Search the local files for the context",
+        "Lokale Dateien nach Kontext durchsuchen",
+    ),
+    (
+        "Write a config for a backend step by step",
+        "Schritt für Schritt eine Konfiguration schreiben",
+    ),
+    // Notifications
+    ("Chat copied to the clipboard", "Chat kopiert"),
+    ("Chat saved to `{}`", "Chat gespeichert in `{}`"),
+    ("Chat exported to `{}`", "Chat exportiert nach `{}`"),
+    (
+        "Writing the messages to `{}`",
+        "Die Nachrichten werden in `{}` geschrieben",
+    ),
+    (
+        "Stopped writing the messages to `{}`",
+        "Die Nachrichten werden nicht mehr in `{}` geschrieben",
+    ),
+    (
+        "Can not save the chat to `{}`: {}",
+        "Chat kann nicht in `{}` gespeichert werden: {}",
+    ),
+    (
+        "Can not export the chat to `{}`: {}",
+        "Chat kann nicht nach `{}` exportiert werden: {}",
+    ),
+    (
+        "Can not save the history: {}",
+        "Verlauf kann nicht gespeichert werden: {}",
+    ),
+    ("Export canceled", "Export abgebrochen"),
+    ("Nothing to undo", "Nichts rückgängig zu machen"),
+    ("Nothing to redo", "Nichts wiederherzustellen"),
+    ("Undo: {}", "Rückgängig: {}"),
+    ("Redo: {}", "Wiederhergestellt: {}"),
+    ("Theme: {}", "Theme: {}"),
+    ("Profile: {}", "Profil: {}"),
+    ("Project settings: {}", "Projekteinstellungen: {}"),
+    ("Draft restored", "Entwurf wiederhergestellt"),
+    (
+        "An answer is being streamed, quit and lose it? (y/n)",
+        "Eine Antwort wird gestreamt, beenden und sie verlieren? (y/n)",
+    ),
+    (
+        "The history is not saved, quit and lose the chats? (y/n)",
+        "Der Verlauf wird nicht gespeichert, beenden und die Chats verlieren? (y/n)",
+    ),
+    (
+        "Can not save the draft: {}",
+        "Entwurf kann nicht gespeichert werden: {}",
+    ),
+    (
+        "Can not remove the draft: {}",
+        "Entwurf kann nicht entfernt werden: {}",
+    ),
+    ("Sent to {}", "An {} gesendet"),
+    ("Can not send to {}: {}", "Senden an {} nicht möglich: {}"),
+    (
+        "Wait for the answer to end",
+        "Warten Sie das Ende der Antwort ab",
+    ),
+    (
+        "Wait for the answers to end",
+        "Warten Sie das Ende der Antworten ab",
+    ),
+    (
+        "Wait for the pre-send hooks to end",
+        "Warten Sie das Ende der Hooks vor dem Senden ab",
+    ),
+    (
+        "The chat changed before the prompt was sent",
+        "Der Chat wurde gewechselt, bevor die Nachricht gesendet wurde",
+    ),
+    (
+        "Wait for the answer of the previous chat to end",
+        "Warten Sie das Ende der Antwort des vorherigen Chats ab",
+    ),
+    (
+        "Wait for the answer before loading another chat",
+        "Warten Sie die Antwort ab, bevor Sie einen anderen Chat öffnen",
+    ),
+    (
+        "Wait for the answer before switching the profile",
+        "Warten Sie die Antwort ab, bevor Sie das Profil wechseln",
+    ),
+    (
+        "Wait for the answer or the running command to end",
+        "Warten Sie das Ende der Antwort oder des Befehls ab",
+    ),
+    (
+        "There is no answer being generated to branch from",
+        "Es läuft keine Antwort, von der abgezweigt werden kann",
+    ),
+    (
+        "The message will be sent once the branched answer ends",
+        "Die Nachricht wird nach der abgezweigten Antwort gesendet",
+    ),
+    (
+        "A message is already waiting for the branched answer to end",
+        "Eine Nachricht wartet bereits auf die abgezweigte Antwort",
+    ),
+    (
+        "No answer to replay in this chat",
+        "Keine Antwort zum Abspielen in diesem Chat",
+    ),
+    (
+        "No shell command in the answers",
+        "Kein Shell-Befehl in den Antworten",
+    ),
+    ("Code blocks", "Codeblöcke"),
+    (
+        "Enter: copy · w: save to a file · r: run · Esc: close",
+        "Enter: kopieren · w: in Datei speichern · r: ausführen · Esc: schließen",
+    ),
+    ("Run with", "Ausführen mit"),
+    ("exists, replace it? (y/n)", "existiert, ersetzen? (y/n)"),
+    ("Save to:", "Speichern unter:"),
+    ("Code block copied to the clipboard", "Codeblock kopiert"),
+    (
+        "No code block in the answers",
+        "Kein Codeblock in den Antworten",
+    ),
+    ("Saved to {}", "Gespeichert unter {}"),
+    (
+        "Can not save to `{}`: {}",
+        "Speichern unter `{}` nicht möglich: {}",
+    ),
+    (
+        "Pick a code block to copy, save or run it",
+        "Einen Codeblock kopieren, speichern oder ausführen",
+    ),
+    (
+        "Pipeline `{}` started, {} steps",
+        "Pipeline `{}` gestartet, {} Schritte",
+    ),
+    ("Pipeline `{}`: step {}/{}", "Pipeline `{}`: Schritt {}/{}"),
+    ("Pipeline `{}` done", "Pipeline `{}` abgeschlossen"),
+    (
+        "The hook `{}` failed: {}",
+        "Der Hook `{}` ist fehlgeschlagen: {}",
+    ),
+    ("Answered from the cache", "Aus dem Cache beantwortet"),
+    (
+        "Not sent, a pre-send hook failed: {}",
+        "Nicht gesendet, ein Hook vor dem Senden ist fehlgeschlagen: {}",
+    ),
+    (
+        "Can not translate the prompt: {}",
+        "Die Nachricht kann nicht übersetzt werden: {}",
+    ),
+    (
+        "Can not read the clipboard: {}",
+        "Die Zwischenablage kann nicht gelesen werden: {}",
+    ),
+    (
+        "Pipeline `{}` stopped at step {}/{}",
+        "Pipeline `{}` bei Schritt {}/{} angehalten",
+    ),
+    ("No question in this chat", "Keine Frage in diesem Chat"),
+    ("No answer to regenerate", "Keine Antwort zum neu Erzeugen"),
+    (
+        "The answer was not regenerated, the previous one is kept",
+        "Die Antwort wurde nicht neu erzeugt, die vorherige bleibt erhalten",
+    ),
+    (
+        "Wait for the answer to end before regenerating it",
+        "Warten Sie das Ende der Antwort ab, um sie neu zu erzeugen",
+    ),
+    (
+        "No regenerated answer to compare",
+        "Keine neu erzeugte Antwort zum Vergleichen",
+    ),
+    (
+        "Answer regenerated, `D` shows what changed",
+        "Antwort neu erzeugt, `D` zeigt die Änderungen",
+    ),
+    (
+        "The replaced answer is back, `D` compares them again",
+        "Die ersetzte Antwort ist zurück, `D` vergleicht sie erneut",
+    ),
+    (
+        "No profiles are defined in the config",
+        "In der Konfiguration sind keine Profile definiert",
+    ),
+    ("No file matches `{}`", "Keine Datei passt zu `{}`"),
+    (
+        "The reasoning is hidden by `reasoning_display`",
+        "Das Nachdenken ist durch `reasoning_display` verborgen",
+    ),
+    (
+        "Redacted before sending: {}",
+        "Vor dem Senden geschwärzt: {}",
+    ),
+    (
+        "Can not retrieve the context: {}",
+        "Kontext kann nicht abgerufen werden: {}",
+    ),
+    (
+        "Can not transcribe the recording: {}",
+        "Aufnahme kann nicht transkribiert werden: {}",
+    ),
+    (
+        "{}, retrying in {}s ({}/{})",
+        "{}, neuer Versuch in {} s ({}/{})",
+    ),
+    (
+        "Rate limited by {}, the request is queued for {}s. `ctrl + t` cancels it",
+        "Ratenlimit von {}, die Anfrage wartet {} s. `ctrl + t` bricht sie ab",
+    ),
+    ("⚠ request failed: {}", "⚠ Anfrage fehlgeschlagen: {}"),
+    (
+        "Press ctrl + r to retry",
+        "Drücken Sie ctrl + r, um es erneut zu versuchen",
+    ),
+    ("⚠ Answer stopped: {}", "⚠ Antwort abgebrochen: {}"),
+    ("Run a command", "Einen Befehl ausführen"),
+    (
+        "Run in a subshell ? (y/n)",
+        "In einer Subshell ausführen? (y/n)",
+    ),
+    ("Share to {}", "Teilen mit {}"),
+    ("Send `{}` to {} ? (y/n)", "`{}` an {} senden? (y/n)"),
+    (
+        "⚠ {} potential secrets found",
+        "⚠ {} mögliche Geheimnisse gefunden",
+    ),
+];
+
+const ZH: &[(&str, &str)] = &[
+    // Titles
+    ("Help", "帮助"),
+    ("History", "历史"),
+    ("History [{}]", "历史 [{}]"),
+    ("Preview", "预览"),
+    ("Questions", "问题"),
+    ("Answering…", "回答中…"),
+    ("Attach", "附加文件"),
+    ("Payload", "发送内容"),
+    ("Profiles", "配置档"),
+    ("Chat info", "对话信息"),
+    ("Latency of the models", "模型延迟"),
+    ("Model", "模型"),
+    ("First token", "首个词元"),
+    ("Between chunks", "片段间隔"),
+    ("Tokens/s", "词元/秒"),
+    ("No answer yet", "暂无回答"),
+    (
+        "Replaced answer → answer in the chat",
+        "被替换的回答 → 对话中的回答",
+    ),
+    (
+        "j/k: scroll · o: keep the replaced one · Esc: close",
+        "j/k：滚动 · o：保留被替换的回答 · Esc：关闭",
+    ),
+    ("The answers are the same", "两个回答相同"),
+    ("Esc: close", "Esc：关闭"),
+    ("Answers", "回答"),
+    ("Words", "词数"),
+    ("Reading time", "阅读时间"),
+    ("Estimated tokens", "估计 token 数"),
+    ("Tokens used", "已用 token 数"),
+    ("Models", "模型"),
+    ("Cost", "费用"),
+    ("Duration", "时长"),
+    ("Time answering", "回答用时"),
+    ("No [prices] for these models", "这些模型没有 [prices]"),
+    ("{}, {} answers without a price", "{}，{} 个回答没有价格"),
+    ("Log", "日志"),
+    ("Info", "提示"),
+    ("Warning", "警告"),
+    ("Error", "错误"),
+    ("follow: off", "跟随：关"),
+    ("h/l or Tab: page", "h/l 或 Tab：翻页"),
+    ("Enter: run · Esc: cancel", "Enter：运行 · Esc：取消"),
+    (
+        "j/k: scroll · g/G: top/bottom · Esc: quit",
+        "j/k：滚动 · g/G：顶部/底部 · Esc：退出",
+    ),
+    (
+        "Enter: send · Tab: switch target · Esc: cancel",
+        "Enter：发送 · Tab：切换目标 · Esc：取消",
+    ),
+    (
+        "Enter: jump · y: continue · Esc: cancel",
+        "Enter：跳转 · y：继续 · Esc：取消",
+    ),
+    ("{} · P to preview", "{} · P 预览"),
+    // Pages of the help
+    ("Global", "全局"),
+    ("Chat", "对话"),
+    ("Prompt", "输入"),
+    ("Config", "配置"),
+    ("Backends", "后端"),
+    (
+        "Switch to Normal mode / Dismiss pop-up",
+        "切换到普通模式 / 关闭弹窗",
+    ),
+    ("Switch the focus", "切换焦点"),
+    (
+        "Start new chat and save the previous one to the history",
+        "新建对话，并将上一个对话存入历史",
+    ),
+    (
+        "Save the chat to a file in the current directory",
+        "将对话保存到当前目录",
+    ),
+    (
+        "Copy the chat to the clipboard as rich text",
+        "以富文本复制对话",
+    ),
+    (
+        "Summarize the chat to shrink the context",
+        "总结对话以缩减上下文",
+    ),
+    (
+        "Start or stop recording the voice input",
+        "开始或停止语音输入",
+    ),
+    (
+        "Switch between the stacked and the split layouts",
+        "在堆叠和分栏布局之间切换",
+    ),
+    ("Grow or shrink the prompt", "放大或缩小输入框"),
+    ("Switch to the next theme", "切换到下一个主题"),
+    ("Switch the backend manually", "手动切换后端"),
+    ("Switch to another profile", "切换配置档"),
+    ("Replay how the answers were streamed", "回放回答的流式输出"),
+    ("Write the prompt in $EDITOR", "在 $EDITOR 中编写问题"),
+    (
+        "Show the words, tokens and cost of the chat",
+        "显示对话的词数、token 数和费用",
+    ),
+    (
+        "Underline the misspelled words of the prompt",
+        "在输入框中为拼写错误的单词加下划线",
+    ),
+    (
+        "Correct the misspelled word under the cursor",
+        "更正光标下拼写错误的单词",
+    ),
+    (
+        "Spellcheck on, z= corrects the word under the cursor",
+        "拼写检查已开启，z= 更正光标下的单词",
+    ),
+    ("Spellcheck off", "拼写检查已关闭"),
+    ("No word under the cursor", "光标下没有单词"),
+    (
+        "Turn the spellcheck on with ctrl + {} first",
+        "请先用 ctrl + {} 开启拼写检查",
+    ),
+    ("No suggestion", "没有建议"),
+    (
+        "Enter: replace · a: add to the dictionary · Esc: close",
+        "Enter：替换 · a：加入词典 · Esc：关闭",
+    ),
+    (
+        "No dictionary found for the spellcheck in {}",
+        "在 {} 中找不到拼写检查的词典",
+    ),
+    (
+        "Suspend to the shell, resume with fg",
+        "挂起到 shell，用 fg 恢复",
+    ),
+    (
+        "Send a message or a code block to Slack or Matrix",
+        "将消息或代码块发送到 Slack 或 Matrix",
+    ),
+    ("Show history", "显示历史"),
+    ("Stop the stream response", "停止回答"),
+    (
+        "Branch from the answer being generated",
+        "从正在生成的回答分支",
+    ),
+    (
+        "Retry the failed request, or redo what was undone",
+        "重试失败的请求，或重做已撤销的操作",
+    ),
+    ("Open the command line", "打开命令行"),
+    ("Show the log", "显示日志"),
+    ("Show help", "显示帮助"),
+    ("Quit", "退出"),
+    ("Scroll down", "向下滚动"),
+    ("Scroll up", "向上滚动"),
+    ("Go to the end", "跳到末尾"),
+    ("Go to the top", "跳到开头"),
+    (
+        "Scroll the answer in the split layout",
+        "在分栏布局中滚动回答",
+    ),
+    ("Toggle following the stream", "开关跟随回答"),
+    (
+        "Fold or unfold the reasoning of the answers",
+        "折叠或展开回答的思考过程",
+    ),
+    ("Undo a new chat", "撤销新建对话"),
+    (
+        "Run a shell command proposed in the chat",
+        "运行对话中建议的 shell 命令",
+    ),
+    (
+        "Copy the last question to the prompt",
+        "将最后一个问题复制到输入框",
+    ),
+    (
+        "Code lines not wrapped, h and l scroll them",
+        "代码行不换行，h 和 l 横向滚动",
+    ),
+    ("Code lines wrapped", "代码行自动换行"),
+    (
+        "Wrap the code lines or scroll them sideways",
+        "代码行换行或横向滚动",
+    ),
+    (
+        "Scroll the code lines that are not wrapped",
+        "横向滚动未换行的代码行",
+    ),
+    (
+        "Yank the last answer, into a register with \"a y",
+        "复制最后一个回答，\"a y 复制到寄存器",
+    ),
+    (
+        "Yank into or paste from the register a",
+        "复制到寄存器 a 或从中粘贴",
+    ),
+    ("Registers", "寄存器"),
+    (
+        "Enter: paste in the prompt · Esc: close",
+        "Enter：粘贴到输入框 · Esc：关闭",
+    ),
+    ("No answer in this chat", "此对话中没有回答"),
+    ("Answer yanked into register {}", "回答已复制到寄存器 {}"),
+    ("Answer copied to the clipboard", "回答已复制到剪贴板"),
+    ("Regenerate the last answer", "重新生成最后一个回答"),
+    (
+        "Compare the regenerated answer with the replaced one",
+        "比较重新生成的回答与被替换的回答",
+    ),
+    (
+        "Send the prompt in Normal mode, new line in Insert mode",
+        "普通模式下发送，插入模式下换行",
+    ),
+    (
+        "Insert before, after, at the start or the end",
+        "在光标前、后、行首或行尾插入",
+    ),
+    ("Add a line below or above", "在下方或上方新增一行"),
+    ("Move the cursor", "移动光标"),
+    (
+        "Move to the next or previous word",
+        "移到下一个或上一个单词",
+    ),
+    ("Move to the start or the end of the line", "移到行首或行尾"),
+    ("Delete the line or a word", "删除整行或单词"),
+    ("Change the line or a word", "修改整行或单词"),
+    (
+        "Switch to Visual mode, y yanks the selection",
+        "切换到可视模式，y 复制选中内容",
+    ),
+    ("Paste", "粘贴"),
+    (
+        "Undo, or bring back a deleted prompt",
+        "撤销，或恢复已删除的输入",
+    ),
+    (
+        "Preview the prompt with its attachments",
+        "预览问题及其附件",
+    ),
+    (
+        "Complete the path after /attach or /file",
+        "补全 /attach 或 /file 后的路径",
+    ),
+    ("Start a slash command", "输入斜杠命令"),
+    ("Select the next thread", "选择下一个对话"),
+    ("Select the previous thread", "选择上一个对话"),
+    ("Continue the selected thread", "继续所选对话"),
+    ("Delete the selected thread", "删除所选对话"),
+    ("Pin the selected thread", "置顶所选对话"),
+    (
+        "Filter by tag, the next tag on each press",
+        "按标签筛选，每按一次切换到下一个标签",
+    ),
+    ("Undo a deletion", "撤销删除"),
+    (
+        "Switch between the list and the preview",
+        "在列表和预览之间切换",
+    ),
+    (
+        "The backend: chatgpt, llamacpp, ollama or mock",
+        "后端：chatgpt、llamacpp、ollama 或 mock",
+    ),
+    ("The directory of the saved chats", "保存对话的目录"),
+    ("Name the threads of the history", "为历史对话生成标题"),
+    ("Show the status bar", "显示状态栏"),
+    (
+        "Show the LaTeX math with Unicode characters",
+        "用 Unicode 字符显示 LaTeX 公式",
+    ),
+    ("Number the lines of the code blocks", "为代码块的行编号"),
+    ("The language of the interface", "界面语言"),
+    (
+        "Lock the screen after this many minutes",
+        "闲置这么多分钟后锁屏",
+    ),
+    (
+        "Milliseconds to wait for the second key of a chord",
+        "等待组合键第二个按键的毫秒数",
+    ),
+    ("Accept commands from other programs", "接受其他程序的命令"),
+    (
+        "Rules hiding the secrets before sending",
+        "发送前隐藏机密的规则",
+    ),
+    (
+        "Dollars per million tokens of the models",
+        "模型每百万 token 的美元价格",
+    ),
+    ("Switch to a backend that answers", "切换到可用的后端"),
+    (
+        "Search the local files for the context",
+        "在本地文件中检索上下文",
+    ),
+    (
+        "Write a config for a backend step by step",
+        "逐步编写后端配置",
+    ),
+    // Notifications
+    ("Chat copied to the clipboard", "对话已复制到剪贴板"),
+    ("Chat saved to `{}`", "对话已保存到 `{}`"),
+    ("Chat exported to `{}`", "对话已导出到 `{}`"),
+    ("Writing the messages to `{}`", "正在将消息写入 `{}`"),
+    (
+        "Stopped writing the messages to `{}`",
+        "已停止将消息写入 `{}`",
+    ),
+    (
+        "Can not save the chat to `{}`: {}",
+        "无法将对话保存到 `{}`：{}",
+    ),
+    (
+        "Can not export the chat to `{}`: {}",
+        "无法将对话导出到 `{}`：{}",
+    ),
+    ("Can not save the history: {}", "无法保存历史：{}"),
+    ("Export canceled", "已取消导出"),
+    ("Nothing to undo", "没有可撤销的操作"),
+    ("Nothing to redo", "没有可重做的操作"),
+    ("Undo: {}", "已撤销：{}"),
+    ("Redo: {}", "已重做：{}"),
+    ("Theme: {}", "主题：{}"),
+    ("Profile: {}", "配置档：{}"),
+    ("Project settings: {}", "项目设置：{}"),
+    ("Draft restored", "已恢复草稿"),
+    (
+        "An answer is being streamed, quit and lose it? (y/n)",
+        "回答正在生成，退出并丢弃它？(y/n)",
+    ),
+    (
+        "The history is not saved, quit and lose the chats? (y/n)",
+        "历史记录未保存，退出并丢弃对话？(y/n)",
+    ),
+    ("Can not save the draft: {}", "无法保存草稿：{}"),
+    ("Can not remove the draft: {}", "无法删除草稿：{}"),
+    ("Sent to {}", "已发送到 {}"),
+    ("Can not send to {}: {}", "无法发送到 {}：{}"),
+    ("Wait for the answer to end", "请等待回答结束"),
+    ("Wait for the answers to end", "请等待所有回答结束"),
+    ("Wait for the pre-send hooks to end", "请等待发送前钩子结束"),
+    (
+        "The chat changed before the prompt was sent",
+        "发送消息前对话已切换",
+    ),
+    (
+        "Wait for the answer of the previous chat to end",
+        "请等待上一个对话的回答结束",
+    ),
+    (
+        "Wait for the answer before loading another chat",
+        "请等待回答结束后再打开其他对话",
+    ),
+    (
+        "Wait for the answer before switching the profile",
+        "请等待回答结束后再切换配置档",
+    ),
+    (
+        "Wait for the answer or the running command to end",
+        "请等待回答或正在运行的命令结束",
+    ),
+    (
+        "There is no answer being generated to branch from",
+        "没有正在生成的回答可供分支",
+    ),
+    (
+        "The message will be sent once the branched answer ends",
+        "消息将在分支的回答结束后发送",
+    ),
+    (
+        "A message is already waiting for the branched answer to end",
+        "已有消息在等待分支的回答结束",
+    ),
+    (
+        "No answer to replay in this chat",
+        "此对话中没有可回放的回答",
+    ),
+    ("No shell command in the answers", "回答中没有 shell 命令"),
+    ("Code blocks", "代码块"),
+    (
+        "Enter: copy · w: save to a file · r: run · Esc: close",
+        "Enter：复制 · w：保存到文件 · r：运行 · Esc：关闭",
+    ),
+    ("Run with", "运行方式"),
+    ("exists, replace it? (y/n)", "已存在，是否替换？(y/n)"),
+    ("Save to:", "保存到："),
+    ("Code block copied to the clipboard", "代码块已复制到剪贴板"),
+    ("No code block in the answers", "回答中没有代码块"),
+    ("Saved to {}", "已保存到 {}"),
+    ("Can not save to `{}`: {}", "无法保存到 `{}`：{}"),
+    (
+        "Pick a code block to copy, save or run it",
+        "选择代码块以复制、保存或运行",
+    ),
+    (
+        "Pipeline `{}` started, {} steps",
+        "流水线 `{}` 已启动，共 {} 步",
+    ),
+    ("Pipeline `{}`: step {}/{}", "流水线 `{}`：第 {}/{} 步"),
+    ("Pipeline `{}` done", "流水线 `{}` 已完成"),
+    ("The hook `{}` failed: {}", "钩子 `{}` 执行失败：{}"),
+    ("Answered from the cache", "已从缓存中回答"),
+    (
+        "Not sent, a pre-send hook failed: {}",
+        "未发送，发送前钩子失败：{}",
+    ),
+    ("Can not translate the prompt: {}", "无法翻译消息：{}"),
+    ("Can not read the clipboard: {}", "无法读取剪贴板：{}"),
+    (
+        "Pipeline `{}` stopped at step {}/{}",
+        "流水线 `{}` 停止于第 {}/{} 步",
+    ),
+    ("No question in this chat", "此对话中没有问题"),
+    ("No answer to regenerate", "没有可重新生成的回答"),
+    (
+        "The answer was not regenerated, the previous one is kept",
+        "回答未重新生成，保留之前的回答",
+    ),
+    (
+        "Wait for the answer to end before regenerating it",
+        "请等待回答结束后再重新生成",
+    ),
+    (
+        "No regenerated answer to compare",
+        "没有可比较的重新生成的回答",
+    ),
+    (
+        "Answer regenerated, `D` shows what changed",
+        "回答已重新生成，`D` 显示变化",
+    ),
+    (
+        "The replaced answer is back, `D` compares them again",
+        "被替换的回答已恢复，`D` 可再次比较",
+    ),
+    (
+        "No profiles are defined in the config",
+        "配置中没有定义配置档",
+    ),
+    ("No file matches `{}`", "没有与 `{}` 匹配的文件"),
+    (
+        "The reasoning is hidden by `reasoning_display`",
+        "思考过程已被 `reasoning_display` 隐藏",
+    ),
+    ("Redacted before sending: {}", "发送前已隐藏：{}"),
+    ("Can not retrieve the context: {}", "无法检索上下文：{}"),
+    ("Can not transcribe the recording: {}", "无法转写录音：{}"),
+    ("{}, retrying in {}s ({}/{})", "{}，{} 秒后重试（{}/{}）"),
+    (
+        "Rate limited by {}, the request is queued for {}s. `ctrl + t` cancels it",
+        "{} 限流，请求将排队 {} 秒。`ctrl + t` 可取消",
+    ),
+    ("⚠ request failed: {}", "⚠ 请求失败：{}"),
+    ("Press ctrl + r to retry", "按 ctrl + r 重试"),
+    ("⚠ Answer stopped: {}", "⚠ 回答已停止：{}"),
+    ("Run a command", "运行命令"),
+    ("Run in a subshell ? (y/n)", "在子 shell 中运行？(y/n)"),
+    ("Share to {}", "分享到 {}"),
+    ("Send `{}` to {} ? (y/n)", "将 `{}` 发送到 {}？(y/n)"),
+    ("⚠ {} potential secrets found", "⚠ 发现 {} 个可能的密钥"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates() {
+        assert_eq!(
+            translate_with("Chat saved to `a.md`", FR),
+            "Discussion enregistrée dans `a.md`"
+        );
+        assert_eq!(
+            translate_with("timeout, retrying in 2s (1/3)", DE),
+            "timeout, neuer Versuch in 2 s (1/3)"
+        );
+        assert_eq!(translate_with("Nothing to undo", ZH), "没有可撤销的操作");
+        assert_eq!(translate_with("Not translated", FR), "Not translated");
+    }
+
+    #[test]
+    fn fill_template() {
+        // Without a language, in English
+        assert_eq!(fill("History [{}]", &["rust"]), "History [rust]");
+        assert_eq!(
+            fill("{}, {} answers without a price", &["$0.01", "2"]),
+            "$0.01, 2 answers without a price"
+        );
+    }
+
+    #[test]
+    fn same_placeholders() {
+        for translations in [FR, DE, ZH] {
+            for (english, translation) in translations {
+                assert_eq!(
+                    english.matches("{}").count(),
+                    translation.matches("{}").count(),
+                    "{}",
+                    english
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+use serde_json::Value;
+
+use crate::{
+    authors, chat,
+    formatter::Formatter,
+    history::{History, Thread},
+    timestamp::Timestamp,
+};
+
+struct Message {
+    user: bool,
+    content: String,
+    time: Option<DateTime<Local>>,
+}
+
+fn time(seconds: &Value) -> Option<DateTime<Local>> {
+    let seconds = seconds.as_f64()?;
+    DateTime::from_timestamp(seconds.trunc() as i64, (seconds.fract() * 1e9) as u32)
+        .map(|time| time.with_timezone(&Local))
+}
+
+/// The text of a message, either a string or a list of parts
+fn text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.as_str().or(part["text"].as_str()))
+            .collect::<Vec<&str>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// A message of the user or of the assistant, the others are left out
+fn message(role: &str, content: String, time: Option<DateTime<Local>>) -> Option<Message> {
+    let user = match role {
+        "user" => true,
+        "assistant" => false,
+        _ => return None,
+    };
+    if content.trim().is_empty() {
+        return None;
+    }
+    Some(Message {
+        user,
+        content,
+        time,
+    })
+}
+
+/// A conversation of the ChatGPT data export, a tree of messages
+/// of which only the branch leading to `current_node` is kept
+fn chatgpt_conversation(conversation: &Value) -> Option<(Option<String>, Vec<Message>)> {
+    let mapping = conversation["mapping"].as_object()?;
+
+    let mut node = conversation["current_node"].as_str().map(String::from);
+    let mut messages = Vec::new();
+    while let Some(id) = node {
+        let entry = mapping.get(&id)?;
+        let msg = &entry["message"];
+        let hidden = msg["metadata"]["is_visually_hidden_from_conversation"]
+            .as_bool()
+            .unwrap_or(false);
+
+        if !hidden && msg["content"]["content_type"].as_str() == Some("text") {
+            if let Some(message) = message(
+                msg["author"]["role"].as_str().unwrap_or_default(),
+                text(&msg["content"]["parts"]),
+                time(&msg["create_time"]),
+            ) {
+                messages.push(message);
+            }
+        }
+
+        node = entry["parent"].as_str().map(String::from);
+    }
+    messages.reverse();
+
+    Some((conversation["title"].as_str().map(String::from), messages))
+}
+
+/// `{"title": …, "messages": [{"role": …, "content": …}]}` or only the messages
+fn generic_conversation(conversation: &Value) -> Option<(Option<String>, Vec<Message>)> {
+    let (title, messages) = match conversation {
+        Value::Array(messages) => (None, messages),
+        Value::Object(_) => (
+            conversation["title"].as_str().map(String::from),
+            conversation["messages"].as_array()?,
+        ),
+        _ => return None,
+    };
+
+    let messages = messages
+        .iter()
+        .filter_map(|msg| {
+            message(
+                msg["role"].as_str()?,
+                text(&msg["content"]),
+                time(&msg["timestamp"]).or(time(&msg["create_time"])),
+            )
+        })
+        .collect();
+
+    Some((title, messages))
+}
+
+fn to_thread(title: Option<String>, messages: Vec<Message>) -> Thread<'static> {
+    let mut plain_chat = Vec::new();
+    let mut timestamps = Vec::new();
+
+    for message in messages {
+        plain_chat.push(if message.user {
+            format!("{}{}\n", authors::USER, message.content.trim_end())
+        } else {
+            format!("{}{}", authors::ASSISTANT, message.content)
+        });
+        if let Some(time) = message.time {
+            timestamps.push(Timestamp {
+                time,
+                ..Timestamp::now(plain_chat.len() - 1)
+            });
+        }
+    }
+
+    Thread {
+        id: chat::next_id(),
+        title,
+        plain_chat,
+        timestamps,
+        ..Default::default()
+    }
+}
+
+/// Parse the conversations of a ChatGPT data export (`conversations.json`)
+/// or of a generic messages json
+pub fn parse(json: &str) -> Result<Vec<Thread<'static>>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Invalid json: {}", e))?;
+
+    let conversations: Vec<&Value> = match &value {
+        // A list of conversations, unless it is a list of messages
+        Value::Array(items) if items.iter().all(|item| item.get("role").is_none()) => {
+            items.iter().collect()
+        }
+        _ => vec![&value],
+    };
+
+    let threads: Vec<Thread> = conversations
+        .into_iter()
+        .filter_map(|conversation| {
+            if conversation.get("mapping").is_some() {
+                chatgpt_conversation(conversation)
+            } else {
+                generic_conversation(conversation)
+            }
+        })
+        .filter(|(_, messages)| !messages.is_empty())
+        .map(|(title, messages)| to_thread(title, messages))
+        .collect();
+
+    if threads.is_empty() {
+        return Err(String::from("No conversation found in the file"));
+    }
+    Ok(threads)
+}
+
+/// Add the conversations of the file to the history, returns how many were added.
+/// Those already imported are skipped.
+pub fn run(path: &Path) -> Result<usize, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Can not read `{}`: {}", path.display(), e))?;
+    let threads = parse(&json)?;
+
+    let mut history = History::load(&Formatter::new());
+    let mut imported = 0;
+    for thread in threads {
+        if history
+            .threads
+            .iter()
+            .any(|t| t.plain_chat == thread.plain_chat)
+        {
+            continue;
+        }
+        history.push(thread);
+        imported += 1;
+    }
+
+    history.persist().map_err(|e| e.to_string())?;
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chatgpt_export() {
+        // The edited question is a branch left out
+        let json = r#"[{
+            "title": "Rust",
+            "current_node": "c",
+            "mapping": {
+                "root": {"parent": null, "message": {
+                    "author": {"role": "system"},
+                    "content": {"content_type": "text", "parts": ["You are helpful"]},
+                    "metadata": {"is_visually_hidden_from_conversation": true}
+                }},
+                "a": {"parent": "root", "message": {
+                    "author": {"role": "user"}, "create_time": 1700000000.5,
+                    "content": {"content_type": "text", "parts": ["What is Rust?"]}
+                }},
+                "edited": {"parent": "root", "message": {
+                    "author": {"role": "user"},
+                    "content": {"content_type": "text", "parts": ["What is rust?"]}
+                }},
+                "b": {"parent": "a", "message": {
+                    "author": {"role": "tool"},
+                    "content": {"content_type": "code", "text": "search"}
+                }},
+                "c": {"parent": "b", "message": {
+                    "author": {"role": "assistant"}, "create_time": 1700000010,
+                    "content": {"content_type": "text", "parts": ["A language"]}
+                }}
+            }
+        }]"#;
+
+        let threads = parse(json).unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].title.as_deref(), Some("Rust"));
+        assert_eq!(
+            threads[0].plain_chat,
+            vec!["👤: What is Rust?\n", "🤖: A language"]
+        );
+        assert_eq!(threads[0].timestamps.len(), 2);
+        assert_eq!(threads[0].timestamps[0].time.timestamp(), 1700000000);
+    }
+
+    #[test]
+    fn generic_messages() {
+        let json = r#"{"title": "Hi", "messages": [
+            {"role": "system", "content": "Be brief"},
+            {"role": "user", "content": [{"type": "text", "text": "Hello"}]},
+            {"role": "assistant", "content": "Hi!"},
+            {"role": "user", "content": "  "}
+        ]}"#;
+        let threads = parse(json).unwrap();
+        assert_eq!(threads[0].title.as_deref(), Some("Hi"));
+        assert_eq!(threads[0].plain_chat, vec!["👤: Hello\n", "🤖: Hi!"]);
+        assert!(threads[0].timestamps.is_empty());
+
+        // Only the messages
+        let json = r#"[{"role": "user", "content": "Hello"}]"#;
+        let threads = parse(json).unwrap();
+        assert_eq!(threads[0].title, None);
+        assert_eq!(threads[0].plain_chat, vec!["👤: Hello\n"]);
+    }
+
+    #[test]
+    fn nothing_to_import() {
+        assert!(parse("{").unwrap_err().starts_with("Invalid json"));
+        assert_eq!(
+            parse(r#"[{"messages": []}, 1]"#).unwrap_err(),
+            "No conversation found in the file"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ratatui::{
+    layout::{Alignment, Constraint, Rect},
+    style::{Style, Stylize},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, Padding, Row, Table,
+    },
+    Frame,
+};
+
+use crate::{
+    app::FocusedBlock,
+    authors,
+    chat::Chat,
+    config::Price,
+    context::estimate_tokens,
+    i18n::{fill, t},
+    theme::Theme,
+    timestamp::Timestamp,
+};
+
+/// The reading speed used for the reading time
+const WORDS_PER_MINUTE: usize = 200;
+
+const LABEL_WIDTH: u16 = 18;
+
+/// Pop-up summing up the current chat, before exporting or sharing it
+pub struct ChatInfo {
+    rows: Vec<(&'static str, String)>,
+    pub previous_focus: FocusedBlock,
+}
+
+/// In dollars, with more digits for the cheap chats
+fn describe_cost(cost: f64) -> String {
+    if cost > 0.0 && cost < 0.01 {
+        format!("${:.6}", cost)
+    } else {
+        format!("${:.4}", cost)
+    }
+}
+
+/// Like `42s`, `3m 20s` or `1h 02m`
+fn describe_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+impl ChatInfo {
+    pub fn new(chat: &Chat, prices: &HashMap<String, Price>, previous_focus: FocusedBlock) -> Self {
+        let mut questions = 0;
+        let mut words = 0;
+        let mut estimated_tokens = 0;
+        // The estimated tokens of the messages before each one, what was sent to answer it
+        let mut tokens_before = Vec::with_capacity(chat.plain_chat.len());
+        for message in &chat.plain_chat {
+            let text = if let Some(question) = message.strip_prefix(authors::USER) {
+                questions += 1;
+                question
+            } else {
+                message.strip_prefix(authors::ASSISTANT).unwrap_or(message)
+            };
+            tokens_before.push(estimated_tokens as u64);
+            words += text.split_whitespace().count();
+            estimated_tokens += estimate_tokens(text);
+        }
+        let answers = chat.plain_chat.len() - questions;
+
+        // The answers, with what the backend reported
+        let answered = chat.timestamps.iter().filter(|t| t.duration.is_some());
+
+        let models: BTreeSet<&str> = answered
+            .clone()
+            .filter_map(|t| t.model.as_deref())
+            .collect();
+
+        // Estimated when the backend did not report them
+        let mut estimated = false;
+        let mut prompt_of = |timestamp: &Timestamp| {
+            timestamp.prompt_tokens.unwrap_or_else(|| {
+                estimated = true;
+                tokens_before
+                    .get(timestamp.index)
+                    .copied()
+                    .unwrap_or_default()
+            })
+        };
+
+        let mut prompt_tokens = 0;
+        let mut completion_tokens = 0;
+        let mut cost = 0.0;
+        let mut unpriced = 0;
+        for timestamp in answered.clone() {
+            let prompt = prompt_of(timestamp);
+            let completion = timestamp.tokens.unwrap_or_default();
+            prompt_tokens += prompt;
+            completion_tokens += completion;
+            match timestamp.model.as_ref().and_then(|model| prices.get(model)) {
+                Some(price) => cost += price.cost(prompt, completion),
+                None => unpriced += 1,
+            }
+        }
+        let approximate = |value: String| {
+            if estimated {
+                format!("~{}", value)
+            } else {
+                value
+            }
+        };
+
+        let streaming: f64 = answered.clone().filter_map(|t| t.duration).sum();
+        let duration = match (chat.timestamps.first(), chat.timestamps.last()) {
+            (Some(first), Some(last)) => {
+                (last.time - first.time).num_milliseconds() as f64 / 1000.0
+                    + last.duration.unwrap_or_default()
+            }
+            _ => 0.0,
+        };
+
+        let rows = vec![
+            ("Questions", questions.to_string()),
+            ("Answers", answers.to_string()),
+            ("Words", words.to_string()),
+            (
+                "Reading time",
+                describe_duration((words * 60) as f64 / WORDS_PER_MINUTE as f64),
+            ),
+            ("Estimated tokens", format!("~{}", estimated_tokens)),
+            (
+                "Tokens used",
+                if prompt_tokens > 0 {
+                    approximate(format!(
+                        "{} ({} + {})",
+                        prompt_tokens + completion_tokens,
+                        prompt_tokens,
+                        completion_tokens
+                    ))
+                } else {
+                    completion_tokens.to_string()
+                },
+            ),
+            (
+                "Models",
+                if models.is_empty() {
+                    "-".to_string()
+                } else {
+                    models.into_iter().collect::<Vec<&str>>().join(", ")
+                },
+            ),
+            (
+                "Cost",
+                match unpriced {
+                    _ if answers == 0 => "-".to_string(),
+                    0 => approximate(describe_cost(cost)),
+                    n if n == answered.count() => t("No [prices] for these models").to_string(),
+                    n => fill(
+                        "{}, {} answers without a price",
+                        &[&approximate(describe_cost(cost)), &n.to_string()],
+                    ),
+                },
+            ),
+            ("Duration", describe_duration(duration)),
+            ("Time answering", describe_duration(streaming)),
+        ];
+
+        Self {
+            rows,
+            previous_focus,
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        // As high as the rows and the padding, in the middle of the area
+        let height = area.height.min(self.rows.len() as u16 + 4);
+        let area = Rect {
+            y: area.y + (area.height - height) / 2,
+            height,
+            ..area
+        };
+
+        let rows: Vec<Row> = self
+            .rows
+            .iter()
+            .map(|(label, value)| Row::new(vec![t(label).to_string(), value.clone()]))
+            .collect();
+
+        let widths = [
+            Constraint::Length(LABEL_WIDTH),
+            Constraint::Length(area.width.saturating_sub(LABEL_WIDTH + 7)),
+        ];
+        let table = Table::new(rows, widths).block(
+            Block::default()
+                .padding(Padding::new(2, 2, 1, 1))
+                .title(format!(" {} ", t("Chat info")))
+                .title_style(Style::default().bold())
+                .title_alignment(Alignment::Center)
+                .title(
+                    Title::from(format!(" {} ", t("Esc: close")))
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                )
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(theme.focused_border)),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(table, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(describe_duration(0.4), "0s");
+        assert_eq!(describe_duration(42.0), "42s");
+        assert_eq!(describe_duration(200.0), "3m 20s");
+        assert_eq!(describe_duration(3720.0), "1h 02m");
+    }
+
+    #[test]
+    fn costs() {
+        assert_eq!(describe_cost(0.0), "$0.0000");
+        assert_eq!(describe_cost(0.000123), "$0.000123");
+        assert_eq!(describe_cost(1.5), "$1.5000");
+    }
+
+    #[test]
+    fn prompt_tokens_estimated() {
+        let mut chat = Chat::default();
+        chat.plain_chat = vec![
+            format!("{}{}\n", authors::USER, "word ".repeat(100)),
+            format!("{}Answer", authors::ASSISTANT),
+        ];
+        // Without the usage of the backend
+        chat.timestamps = vec![Timestamp {
+            duration: Some(1.0),
+            tokens: Some(1000),
+            model: Some(String::from("model")),
+            ..Timestamp::now(1)
+        }];
+        let prices = HashMap::from([(
+            String::from("model"),
+            Price {
+                prompt: 1_000_000.0,
+                completion: 0.0,
+            },
+        )]);
+
+        let info = ChatInfo::new(&chat, &prices, FocusedBlock::Chat);
+        let row = |label| {
+            info.rows
+                .iter()
+                .find(|(l, _)| *l == label)
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        };
+        let prompt = estimate_tokens(&format!("{}\n", "word ".repeat(100)));
+        assert_eq!(
+            row("Tokens used"),
+            format!("~{} ({} + 1000)", prompt + 1000, prompt)
+        );
+        assert_eq!(row("Cost"), format!("~{}", describe_cost(prompt as f64)));
+    }
+}
@@ -0,0 +1,105 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::mpsc::UnboundedSender, time::Instant};
+
+use crate::{config::Config, event::Event, llm::LLMBackend, network};
+
+#[derive(Debug, Clone)]
+pub struct BackendStatus {
+    pub backend: LLMBackend,
+    pub latency: Option<Duration>,
+}
+
+impl BackendStatus {
+    pub fn healthy(&self) -> bool {
+        self.latency.is_some()
+    }
+}
+
+/// The url of the backend answering a `GET`, with the key it needs: the list of the
+/// models next to the chat endpoint
+fn probe_request(config: &Config, backend: &LLMBackend) -> Option<(String, Option<String>)> {
+    let url = config.backend_url(backend)?;
+    match backend {
+        LLMBackend::ChatGPT => Some((
+            url.replace("/chat/completions", "/models"),
+            config.chatgpt.api_key(),
+        )),
+        LLMBackend::LLamacpp => Some((
+            url.replace("/chat/completions", "/models"),
+            config.llamacpp.as_ref().and_then(|c| c.api_key()),
+        )),
+        LLMBackend::Ollama => Some((url.replace("/api/chat", "/api/tags"), None)),
+        LLMBackend::Mock => None,
+    }
+}
+
+/// Time a request to the backend. Only a successful answer counts, a `4xx` means
+/// that a prompt sent to it would fail too, with a wrong key or url.
+async fn probe(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<String>,
+    timeout: Duration,
+) -> Option<Duration> {
+    let mut request = client.get(url);
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, request.send()).await {
+        Ok(Ok(res)) if res.status().is_success() => Some(start.elapsed()),
+        _ => None,
+    }
+}
+
+pub async fn measure(config: Arc<Config>) -> Vec<BackendStatus> {
+    // No backend is reachable with a wrong network config
+    let client = network::client(&config.network).ok();
+    let timeout = Duration::from_secs(config.network.timeout);
+
+    let mut statuses = Vec::new();
+    for backend in config.backends() {
+        // The key may come from a command
+        let request = {
+            let config = config.clone();
+            tokio::task::spawn_blocking(move || probe_request(&config, &backend))
+                .await
+                .ok()
+                .flatten()
+        };
+        let latency = match (&client, request) {
+            (Some(client), Some((url, api_key))) => probe(client, &url, api_key, timeout).await,
+            _ => None,
+        };
+        statuses.push(BackendStatus { backend, latency });
+    }
+
+    statuses
+}
+
+/// Measure the configured backends periodically in the background
+pub fn spawn(config: Arc<Config>, sender: UnboundedSender<Event>) {
+    let interval = Duration::from_secs(config.auto_select.interval.max(1));
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            let statuses = measure(config.clone()).await;
+            if sender.send(Event::Latency(statuses)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// The healthy backend with the lowest latency
+pub fn fastest(statuses: &[BackendStatus]) -> Option<LLMBackend> {
+    statuses
+        .iter()
+        .filter_map(|status| status.latency.map(|latency| (status.backend, latency)))
+        .min_by_key(|(_, latency)| *latency)
+        .map(|(backend, _)| backend)
+}
@@ -33,3 +33,130 @@ pub mod chat;
 pub mod llamacpp;
 
 pub mod ollama;
+
+pub mod export;
+
+pub mod network;
+
+pub mod context;
+
+pub mod attachment;
+
+pub mod theme;
+
+pub mod state;
+
+pub mod command;
+
+pub mod latency;
+
+pub mod redaction;
+
+pub mod privacy;
+
+pub mod share;
+
+pub mod profile;
+
+pub mod recording;
+
+pub mod scrubber;
+
+pub mod environment;
+
+pub mod picker;
+
+pub mod shell;
+
+pub mod rag;
+
+#[cfg(feature = "voice")]
+pub mod voice;
+
+pub mod split;
+
+pub mod statusbar;
+
+pub mod cmdline;
+
+pub mod undo;
+
+pub mod timestamp;
+
+pub mod streaming;
+
+pub mod math;
+
+pub mod editor;
+
+pub mod alert;
+
+pub mod logging;
+
+pub mod queue;
+
+pub mod compare;
+
+pub mod import;
+
+pub mod headless;
+
+pub mod control;
+
+pub mod clipboard;
+
+pub mod viewport;
+
+pub mod sse;
+
+pub mod archive;
+
+pub mod vault;
+
+pub mod reasoning;
+
+pub mod setup;
+
+pub mod plain;
+
+pub mod i18n;
+
+pub mod authors;
+
+pub mod info;
+
+pub mod diff;
+
+pub mod tee;
+
+pub mod git;
+
+pub mod commit;
+
+pub mod registers;
+
+pub mod completion;
+
+pub mod spellcheck;
+
+pub mod draft;
+
+pub mod quit;
+
+pub mod codeblock;
+
+pub mod runner;
+
+pub mod variables;
+
+pub mod pipeline;
+
+pub mod hooks;
+
+pub mod diagnostics;
+
+pub mod cache;
+
+pub mod mock;
+
+pub mod paths;
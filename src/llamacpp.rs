@@ -1,14 +1,18 @@
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crate::event::Event;
 use async_trait::async_trait;
-use regex::Regex;
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::config::LLamacppConfig;
-use crate::llm::{LLMAnswer, LLMRole, LLM};
+use crate::config::{fetch_api_key_from_cmd, LLMParams, LLamacppConfig, NetworkConfig};
+use crate::llm::{
+    apply_params, openai_messages, parse_openai_chunk, system_message, LLMAnswer, LLMRole, LLM,
+};
+use crate::network;
+use crate::sse::{EventStream, SseEvent, SseParser};
 use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
 use serde_json::{json, Value};
 use std;
 use std::collections::HashMap;
@@ -17,23 +21,48 @@ use std::collections::HashMap;
 pub struct LLamacpp {
     client: reqwest::Client,
     url: String,
-    api_key: Option<String>,
+    api_key: Arc<Mutex<Option<String>>>,
+    api_key_cmd: Option<String>,
     messages: Vec<HashMap<String, String>>,
+    network: NetworkConfig,
+    params: LLMParams,
 }
 
 impl LLamacpp {
-    pub fn new(config: LLamacppConfig) -> Self {
-        let api_key = match std::env::var("LLAMACPP_API_KEY") {
-            Ok(key) => Some(key),
-            Err(_) => config.api_key.clone(),
-        };
-
-        Self {
-            client: reqwest::Client::new(),
+    pub fn new(
+        config: LLamacppConfig,
+        network: NetworkConfig,
+        params: LLMParams,
+    ) -> Result<Self, String> {
+        let api_key = config.api_key();
+
+        Ok(Self {
+            client: network::client(&network)?,
             url: config.url,
-            api_key,
+            api_key: Arc::new(Mutex::new(api_key)),
+            api_key_cmd: config.api_key_cmd,
             messages: Vec::new(),
+            network,
+            params,
+        })
+    }
+
+    async fn send(
+        &self,
+        body: &Value,
+        api_key: Option<&str>,
+        sender: &UnboundedSender<Event>,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", "application/json".parse()?);
+
+        if let Some(api_key) = api_key {
+            headers.insert("Authorization", format!("Bearer {}", api_key).parse()?);
         }
+
+        let request = self.client.post(&self.url).headers(headers).json(body);
+
+        network::send(request, &self.network, sender).await
     }
 }
 
@@ -43,6 +72,14 @@ impl LLM for LLamacpp {
         self.messages = Vec::new();
     }
 
+    fn messages(&self) -> Vec<HashMap<String, String>> {
+        self.messages.clone()
+    }
+
+    fn set_messages(&mut self, messages: Vec<HashMap<String, String>>) {
+        self.messages = messages;
+    }
+
     fn append_chat_msg(&mut self, msg: String, role: LLMRole) {
         let mut conv: HashMap<String, String> = HashMap::new();
         conv.insert("role".to_string(), role.to_string());
@@ -50,71 +87,58 @@ impl LLM for LLamacpp {
         self.messages.push(conv);
     }
 
-    async fn ask(
+    async fn ask_with(
         &self,
+        chat_messages: Vec<HashMap<String, String>>,
         sender: UnboundedSender<Event>,
         terminate_response_signal: Arc<AtomicBool>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut headers = HeaderMap::new();
-        headers.insert("Content-Type", "application/json".parse()?);
+        let mut messages: Vec<HashMap<String, String>> = vec![system_message(&self.params)];
 
-        if let Some(api_key) = &self.api_key {
-            headers.insert("Authorization", format!("Bearer {}", api_key).parse()?);
-        }
+        messages.extend(chat_messages);
 
-        let mut messages: Vec<HashMap<String, String>> = vec![
-            (HashMap::from([
-                ("role".to_string(), "system".to_string()),
-                (
-                    "content".to_string(),
-                    "You are a helpful assistant.".to_string(),
-                ),
-            ])),
-        ];
-
-        messages.extend(self.messages.clone());
-
-        let body: Value = json!({
-            "messages": messages,
+        let mut body: Value = json!({
+            "messages": openai_messages(&messages),
             "stream": true,
         });
+        apply_params(&mut body, &self.params);
+
+        let api_key = self.api_key.lock().unwrap().clone();
+        let mut response = self.send(&body, api_key.as_deref(), &sender).await?;
+
+        // The key may have been rotated, fetch a fresh one and try again
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let api_key = match self.api_key_cmd.as_deref() {
+                Some(cmd) => fetch_api_key_from_cmd(cmd).await,
+                None => None,
+            };
+            if let Some(api_key) = api_key {
+                *self.api_key.lock().unwrap() = Some(api_key.clone());
+                response = self.send(&body, Some(&api_key), &sender).await?;
+            }
+        }
 
-        let response = self
-            .client
-            .post(&self.url)
-            .headers(headers)
-            .json(&body)
-            .send()
-            .await?;
-
-        match response.error_for_status() {
-            Ok(mut res) => {
+        match network::error_for_status(response).await {
+            Ok(res) => {
                 sender.send(Event::LLMEvent(LLMAnswer::StartAnswer))?;
-                while let Some(chunk) = res.chunk().await? {
-                    let chunk = std::str::from_utf8(&chunk)?;
-
-                    let re = Regex::new(r"data:\s(.*)")?;
-
-                    for captures in re.captures_iter(chunk) {
-                        if let Some(data_json) = captures.get(1) {
-                            if terminate_response_signal.load(Ordering::Relaxed) {
-                                sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
-                                return Ok(());
-                            }
+                let mut stream = EventStream::new(res, &self.network, SseParser::default());
+                while let Some(event) = stream.next().await? {
+                    if terminate_response_signal.load(Ordering::Relaxed) {
+                        break;
+                    }
 
-                            let answer: Value = serde_json::from_str(data_json.as_str())?;
+                    let SseEvent::Data(data) = event else {
+                        break;
+                    };
 
-                            if answer["choices"]["finish_reason"] == "stop" {
-                                sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
-                                return Ok(());
-                            }
+                    let answer: Value = serde_json::from_str(&data)?;
 
-                            let msg = answer["choices"][0]["delta"]["content"]
-                                .as_str()
-                                .unwrap_or("\n");
+                    for event in parse_openai_chunk(&answer) {
+                        sender.send(Event::LLMEvent(event))?;
+                    }
 
-                            sender.send(Event::LLMEvent(LLMAnswer::Answer(msg.to_string())))?;
-                        }
+                    if answer["choices"][0]["finish_reason"].is_string() {
+                        break;
                     }
                 }
             }
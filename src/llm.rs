@@ -1,14 +1,23 @@
+use crate::cache::Cached;
 use crate::chatgpt::ChatGPT;
-use crate::config::Config;
+use crate::config::{Config, LLMParams};
 use crate::event::Event;
 use crate::llamacpp::LLamacpp;
+use crate::mock::Mock;
 use crate::ollama::Ollama;
+use crate::reasoning::ThinkTags;
+use crate::redaction::{self, Redacted};
+use crate::variables;
 use async_trait::async_trait;
 use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::atomic::AtomicBool;
 use strum_macros::Display;
 use strum_macros::EnumIter;
-use tokio::sync::mpsc::UnboundedSender;
+use strum_macros::EnumString;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 
 use std::sync::Arc;
 
@@ -18,20 +27,224 @@ pub trait LLM: Send + Sync {
         &self,
         sender: UnboundedSender<Event>,
         terminate_response_signal: Arc<AtomicBool>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (answer_sender, mut receiver) = unbounded_channel();
+
+        // The reasoning written in the answer is told apart from it
+        let forward = async {
+            let mut tags = ThinkTags::default();
+            while let Some(event) = receiver.recv().await {
+                let events = match event {
+                    Event::LLMEvent(answer) => tags.split(answer),
+                    event => {
+                        let _ = sender.send(event);
+                        continue;
+                    }
+                };
+                for answer in events {
+                    let _ = sender.send(Event::LLMEvent(answer));
+                }
+            }
+        };
+        let ask = async {
+            self.ask_with(self.messages(), answer_sender, terminate_response_signal)
+                .await
+                .map_err(LLMError::from)
+        };
+
+        let (res, _) = tokio::join!(ask, forward);
+        res.map_err(|e| e.into())
+    }
+
+    async fn ask_with(
+        &self,
+        messages: Vec<HashMap<String, String>>,
+        sender: UnboundedSender<Event>,
+        terminate_response_signal: Arc<AtomicBool>,
     ) -> Result<(), Box<dyn std::error::Error>>;
 
+    /// Send the messages and wait for the whole answer
+    async fn complete(
+        &self,
+        messages: Vec<HashMap<String, String>>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let (sender, mut receiver) = unbounded_channel();
+        self.ask_with(messages, sender, Arc::new(AtomicBool::new(false)))
+            .await?;
+
+        let mut tags = ThinkTags::default();
+        let mut answer = String::new();
+        while let Ok(event) = receiver.try_recv() {
+            let Event::LLMEvent(event) = event else {
+                continue;
+            };
+            for event in tags.split(event) {
+                if let LLMAnswer::Answer(chunk) = event {
+                    answer.push_str(&chunk);
+                }
+            }
+        }
+
+        Ok(answer)
+    }
+
+    fn messages(&self) -> Vec<HashMap<String, String>>;
+    fn set_messages(&mut self, messages: Vec<HashMap<String, String>>);
     fn append_chat_msg(&mut self, msg: String, role: LLMRole);
     fn clear(&mut self);
 }
 
+pub fn message(role: LLMRole, content: &str) -> HashMap<String, String> {
+    HashMap::from([
+        ("role".to_string(), role.to_string()),
+        ("content".to_string(), content.to_string()),
+    ])
+}
+
+/// A message with images, for vision models.
+/// The data urls of the images are stored one per line.
+pub fn message_with_images(
+    role: LLMRole,
+    content: &str,
+    images: &[String],
+) -> HashMap<String, String> {
+    let mut message = message(role, content);
+    if !images.is_empty() {
+        message.insert("images".to_string(), images.join("\n"));
+    }
+    message
+}
+
+/// The messages in the openai format, where images are content parts
+pub fn openai_messages(messages: &[HashMap<String, String>]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| match message.get("images") {
+            Some(images) => {
+                let mut parts = vec![json!({
+                    "type": "text",
+                    "text": message.get("content").cloned().unwrap_or_default(),
+                })];
+                parts.extend(images.lines().map(|url| {
+                    json!({
+                        "type": "image_url",
+                        "image_url": { "url": url },
+                    })
+                }));
+                json!({ "role": message.get("role"), "content": parts })
+            }
+            None => json!(message),
+        })
+        .collect()
+}
+
+/// The messages in the ollama format, where images are a list of base64 strings
+pub fn ollama_messages(messages: &[HashMap<String, String>]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| match message.get("images") {
+            Some(images) => {
+                let images: Vec<&str> = images
+                    .lines()
+                    .map(|url| url.split_once(',').map(|(_, data)| data).unwrap_or(url))
+                    .collect();
+                json!({
+                    "role": message.get("role"),
+                    "content": message.get("content"),
+                    "images": images,
+                })
+            }
+            None => json!(message),
+        })
+        .collect()
+}
+
+/// The system message sent before the conversation
+pub fn system_message(params: &LLMParams) -> HashMap<String, String> {
+    message(
+        LLMRole::SYSTEM,
+        &params
+            .system_prompt
+            .clone()
+            .unwrap_or_else(LLMParams::default_system_prompt),
+    )
+}
+
+/// The reasoning models of OpenAI, like `o3-mini`, which reject `max_tokens` and `temperature`
+fn is_reasoning_model(model: &str) -> bool {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    ["o1", "o3", "o4"]
+        .iter()
+        .any(|family| model == *family || model.starts_with(&format!("{}-", family)))
+        || model.starts_with("gpt-5")
+}
+
+/// Add the optional parameters to an openai compatible request, after its model
+pub fn apply_params(body: &mut Value, params: &LLMParams) {
+    let reasoning = body["model"].as_str().is_some_and(is_reasoning_model);
+
+    if let Some(temperature) = params.temperature.filter(|_| !reasoning) {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        let name = if reasoning {
+            "max_completion_tokens"
+        } else {
+            "max_tokens"
+        };
+        body[name] = json!(max_tokens);
+    }
+    if let Some(stop) = &params.stop {
+        body["stop"] = json!(stop);
+    }
+    if let Some(effort) = &params.reasoning_effort {
+        body["reasoning_effort"] = json!(effort);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum LLMAnswer {
     StartAnswer,
+    Role(LLMRole),
     Answer(String),
+    /// The chain of thought of a reasoning model, streamed before its answer
+    Reasoning(String),
+    ToolCall(ToolCallDelta),
+    FinishReason(FinishReason),
+    Usage(Usage),
     EndAnswer,
 }
 
-#[derive(EnumIter, Display, Debug)]
+#[derive(Debug, Clone)]
+pub struct LLMError {
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+impl std::fmt::Display for LLMError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{}: {}", status, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for LLMError {}
+
+impl From<Box<dyn std::error::Error>> for LLMError {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        match error.downcast::<LLMError>() {
+            Ok(error) => *error,
+            Err(error) => Self {
+                status: None,
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(EnumIter, EnumString, Display, Debug, Clone, PartialEq)]
 #[strum(serialize_all = "lowercase")]
 pub enum LLMRole {
     ASSISTANT,
@@ -39,22 +252,219 @@ pub enum LLMRole {
     USER,
 }
 
-#[derive(Deserialize, PartialEq, Debug)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinishReason {
+    Stop,
+    Length,
+    ContentFilter,
+    ToolCalls,
+    /// Stopped by tenere, once longer than `max_answer_chars`
+    Truncated,
+    Other(String),
+}
+
+impl From<&str> for FinishReason {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "stop" => Self::Stop,
+            "length" => Self::Length,
+            "content_filter" => Self::ContentFilter,
+            "tool_calls" | "function_call" => Self::ToolCalls,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl FinishReason {
+    pub fn description(&self) -> String {
+        match self {
+            Self::Stop => String::from("the model finished its answer"),
+            Self::Length => String::from("the maximum length was reached"),
+            Self::ContentFilter => String::from("the content was filtered by the provider"),
+            Self::ToolCalls => String::from("the model requested a tool call"),
+            Self::Truncated => String::from("the answer is truncated to `max_answer_chars`"),
+            Self::Other(reason) => reason.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+/// Extract the events from a chunk of an OpenAI compatible stream
+pub fn parse_openai_chunk(chunk: &Value) -> Vec<LLMAnswer> {
+    let mut events = Vec::new();
+    let choice = &chunk["choices"][0];
+    let delta = &choice["delta"];
+
+    if let Some(role) = delta["role"]
+        .as_str()
+        .and_then(|r| LLMRole::from_str(r).ok())
+    {
+        events.push(LLMAnswer::Role(role));
+    }
+
+    // `reasoning_content` for DeepSeek, `reasoning` for OpenRouter and vLLM
+    if let Some(reasoning) = delta["reasoning_content"]
+        .as_str()
+        .or(delta["reasoning"].as_str())
+    {
+        if !reasoning.is_empty() {
+            events.push(LLMAnswer::Reasoning(reasoning.to_string()));
+        }
+    }
+
+    if let Some(content) = delta["content"].as_str() {
+        if !content.is_empty() {
+            events.push(LLMAnswer::Answer(content.to_string()));
+        }
+    }
+
+    if let Some(tool_calls) = delta["tool_calls"].as_array() {
+        for tool_call in tool_calls {
+            events.push(LLMAnswer::ToolCall(ToolCallDelta {
+                index: tool_call["index"].as_u64().unwrap_or(0) as usize,
+                id: tool_call["id"].as_str().map(|s| s.to_string()),
+                name: tool_call["function"]["name"]
+                    .as_str()
+                    .map(|s| s.to_string()),
+                arguments: tool_call["function"]["arguments"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            }));
+        }
+    }
+
+    if let Some(reason) = choice["finish_reason"].as_str() {
+        events.push(LLMAnswer::FinishReason(FinishReason::from(reason)));
+    }
+
+    if chunk["usage"].is_object() {
+        events.push(LLMAnswer::Usage(Usage {
+            prompt_tokens: chunk["usage"]["prompt_tokens"].as_u64().unwrap_or(0),
+            completion_tokens: chunk["usage"]["completion_tokens"].as_u64().unwrap_or(0),
+        }));
+    }
+
+    events
+}
+
+#[derive(Deserialize, PartialEq, Debug, Clone, Copy, Display, EnumString)]
 #[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
 pub enum LLMBackend {
     ChatGPT,
     LLamacpp,
     Ollama,
+    Mock,
 }
 
 pub struct LLMModel;
 
 impl LLMModel {
-    pub async fn init(model: &LLMBackend, config: Arc<Config>) -> Box<dyn LLM> {
-        match model {
-            LLMBackend::ChatGPT => Box::new(ChatGPT::new(config.chatgpt.clone())),
-            LLMBackend::LLamacpp => Box::new(LLamacpp::new(config.llamacpp.clone().unwrap())),
-            LLMBackend::Ollama => Box::new(Ollama::new(config.ollama.clone().unwrap())),
+    /// The backend set up with the config, or why it can not be used
+    pub async fn init(model: &LLMBackend, config: Arc<Config>) -> Result<Box<dyn LLM>, String> {
+        // The variables of the system prompt take their value once, when the model is set up
+        let mut params = config.backend_params(model);
+        params.system_prompt = params
+            .system_prompt
+            .map(|prompt| variables::expand(&prompt, &config.variables));
+
+        // The answers change with the model and the parameters of the requests
+        let scope = format!(
+            "{}\n{}\n{:?}",
+            config.backend_url(model).unwrap_or_default(),
+            config.backend_model_name(model),
+            params
+        );
+
+        let llm: Box<dyn LLM> = match model {
+            LLMBackend::ChatGPT => Box::new(ChatGPT::new(
+                config.chatgpt.clone(),
+                config.network.clone(),
+                params,
+            )?),
+            LLMBackend::LLamacpp => Box::new(LLamacpp::new(
+                config.llamacpp.clone().unwrap(),
+                config.network.clone(),
+                params,
+            )?),
+            LLMBackend::Ollama => Box::new(Ollama::new(
+                config.ollama.clone().unwrap(),
+                config.network.clone(),
+                params,
+            )?),
+            LLMBackend::Mock => Box::new(Mock::new(config.mock.clone().unwrap(), params)),
+        };
+
+        let llm: Box<dyn LLM> = if config.cache.enabled {
+            Box::new(Cached::new(
+                llm,
+                scope,
+                config.cache.clone(),
+                config.history.encrypt,
+            ))
+        } else {
+            llm
+        };
+
+        // Around the cache, the secrets are not kept in it either
+        let rules = redaction::outgoing_rules(&config.redaction)?;
+        if rules.is_empty() {
+            Ok(llm)
+        } else {
+            Ok(Box::new(Redacted::new(llm, rules)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_of_the_reasoning_models() {
+        let params = LLMParams {
+            temperature: Some(0.2),
+            max_tokens: Some(100),
+            ..Default::default()
+        };
+
+        let mut body = json!({ "model": "gpt-4o" });
+        apply_params(&mut body, &params);
+        assert_eq!(
+            body,
+            json!({ "model": "gpt-4o", "temperature": 0.2f32, "max_tokens": 100 })
+        );
+
+        for model in ["o1", "o3-mini", "openai/o4-mini", "gpt-5-nano"] {
+            let mut body = json!({ "model": model });
+            apply_params(&mut body, &params);
+            assert_eq!(
+                body,
+                json!({ "model": model, "max_completion_tokens": 100 }),
+                "{}",
+                model
+            );
         }
+        assert!(!is_reasoning_model("omni-llama"));
     }
 }
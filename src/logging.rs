@@ -0,0 +1,197 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, Paragraph,
+    },
+    Frame,
+};
+use tracing::level_filters::LevelFilter;
+
+use crate::{i18n::t, paths, theme::Theme};
+
+// Only the end of the log is worth showing
+const MAX_LINES: usize = 500;
+
+// Read from the end of the file, enough for the lines above
+const TAIL_BYTES: u64 = 256 * 1024;
+
+// Past this size, the log is moved to `tenere.log.old` when tenere starts
+const MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+pub fn path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join("tenere.log"))
+}
+
+/// Append the events up to `level` to the log file, nothing is logged with `off`
+pub fn init(level: &str) -> Result<(), String> {
+    let level = LevelFilter::from_str(level).map_err(|_| {
+        format!(
+            "Invalid log level `{}`, use one of off, error, warn, info, debug or trace",
+            level
+        )
+    })?;
+    if level == LevelFilter::OFF {
+        return Ok(());
+    }
+
+    let path = path().ok_or("Can not find the state directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_SIZE) {
+        let _ = std::fs::rename(&path, path.with_extension("log.old"));
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Can not open the log file `{}`: {}", path.display(), e))?;
+
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(level)
+        .try_init()
+        .map_err(|e| e.to_string())
+}
+
+/// The last lines of a file, without reading all of it
+fn tail(path: &Path, max_lines: usize) -> std::io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let start = file.metadata()?.len().saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let log = String::from_utf8_lossy(&bytes);
+
+    let mut lines: Vec<&str> = log.lines().collect();
+    // The first line is cut, unless the file is read from its start
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    Ok(lines[lines.len().saturating_sub(max_lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// The last lines of the log file, to diagnose the API errors without leaving the app
+pub struct LogViewer {
+    lines: Vec<String>,
+    pub scroll: usize,
+}
+
+impl LogViewer {
+    pub fn load() -> Self {
+        let lines = path()
+            .and_then(|path| tail(&path, MAX_LINES).ok())
+            .unwrap_or_default();
+
+        Self {
+            scroll: lines.len(),
+            lines,
+        }
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_add(lines).min(self.lines.len());
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    fn styled(line: &str) -> Line<'_> {
+        let color = if line.contains(" ERROR ") {
+            Color::Red
+        } else if line.contains(" WARN ") {
+            Color::Yellow
+        } else if line.contains(" DEBUG ") || line.contains(" TRACE ") {
+            Color::DarkGray
+        } else {
+            Color::Reset
+        };
+        Line::styled(line, Style::default().fg(color))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let block = Block::default()
+            .title(format!(" {} ", t("Log")))
+            .title_style(Style::default().bold())
+            .title_alignment(Alignment::Center)
+            .title(
+                Title::from(format!(
+                    " {} ",
+                    t("j/k: scroll · g/G: top/bottom · Esc: quit")
+                ))
+                .position(Position::Bottom)
+                .alignment(Alignment::Center),
+            )
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme.focused_border));
+
+        let height = block.inner(area).height as usize;
+        // The scroll is the last line shown, the end of the log by default
+        self.scroll = self.scroll.max(height.min(self.lines.len()));
+        let start = self.scroll.saturating_sub(height);
+
+        let text = if self.lines.is_empty() {
+            Text::from(match path() {
+                Some(path) => format!(
+                    "Nothing logged yet in {}, set `log_level = \"debug\"` for the details of the requests",
+                    path.display()
+                ),
+                None => String::from("Can not find the state directory"),
+            })
+        } else {
+            Text::from(
+                self.lines[start..self.scroll]
+                    .iter()
+                    .map(|line| Self::styled(line))
+                    .collect::<Vec<Line>>(),
+            )
+        };
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn tail_of_the_log() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for i in 0..20 {
+            writeln!(file, "line {}", i).unwrap();
+        }
+        assert_eq!(tail(file.path(), 2).unwrap(), vec!["line 18", "line 19"]);
+        assert_eq!(tail(file.path(), 100).unwrap().len(), 20);
+
+        // Longer than what is read, the cut line is dropped
+        let line = "x".repeat(1000);
+        for _ in 0..(TAIL_BYTES / 1000 + 10) {
+            writeln!(file, "{}", line).unwrap();
+        }
+        let lines = tail(file.path(), usize::MAX).unwrap();
+        assert!(lines.len() < (TAIL_BYTES / 1000) as usize + 1);
+        assert!(lines.iter().all(|l| *l == line));
+    }
+}
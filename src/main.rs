@@ -1,14 +1,36 @@
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
-use std::{env, io};
-use tenere::app::{App, AppResult};
+use std::time::{Duration, Instant};
+use std::{env, io, path::PathBuf};
+use tenere::app::{App, AppResult, FocusedBlock};
+use tenere::cache;
 use tenere::cli;
-use tenere::config::Config;
+use tenere::commit;
+use tenere::config::{self, HookRole};
+use tenere::control;
+use tenere::editor;
 use tenere::event::{Event, EventHandler};
 use tenere::formatter::Formatter;
-use tenere::handler::handle_key_events;
+use tenere::handler::{
+    abort_answer, apply_preferred_backend, handle_answer, handle_answer_error, handle_control,
+    handle_key_events, handle_paste, handle_pre_sent, keep_previous_answer, merge_branch,
+    send_pipeline_step, stop_pipeline,
+};
+use tenere::headless;
+use tenere::history::History;
+use tenere::hooks;
+use tenere::i18n;
+use tenere::import;
+use tenere::latency;
 use tenere::llm::{LLMAnswer, LLMRole};
+use tenere::logging;
+use tenere::notification::{Notification, NotificationLevel};
+use tenere::paths;
+use tenere::plain;
+use tenere::rag;
+use tenere::setup;
 use tenere::tui::Tui;
+use tenere::vault;
 
 use tenere::llm::LLMModel;
 
@@ -19,60 +41,384 @@ use clap::crate_version;
 
 #[tokio::main]
 async fn main() -> AppResult<()> {
-    cli::cli().version(crate_version!()).get_matches();
+    let matches = cli::cli().version(crate_version!()).get_matches();
 
-    let config = Arc::new(Config::load());
+    // The first start writes a config, instead of failing without an api key
+    let setup_command = matches.subcommand_name() == Some("setup");
+    if setup_command || setup::needed(&matches) {
+        match setup::run(&matches).await {
+            Ok(Some(path)) if setup_command => {
+                println!("Config written to {}", path.display());
+                return Ok(());
+            }
+            Ok(Some(_)) => {}
+            Ok(None) if setup_command => return Ok(()),
+            Ok(None) => {
+                eprintln!("Setup cancelled, run `tenere setup` or set OPENAI_API_KEY to start");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let config = Arc::new(config::from_args(&matches));
+    paths::init(&config);
+    i18n::init(config.language);
+
+    if let Some(("index", matches)) = matches.subcommand() {
+        let dir = matches.get_one::<PathBuf>("dir").unwrap();
+        if let Err(e) = rag::index(dir, &config).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(("commit", matches)) = matches.subcommand() {
+        if let Err(e) = commit::run(config, matches.get_flag("apply")).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    let (formatter_config, formatter_assets) = Formatter::init();
-    let formatter = Formatter::new(&formatter_config, &formatter_assets);
+    // The history is decrypted before the TUI takes the terminal
+    let uses_history =
+        config.history.persist || matches!(matches.subcommand_name(), Some("import" | "history"));
+    if config.history.encrypt && uses_history && !matches.get_flag("headless") {
+        if let Some(path) = History::encrypted_path() {
+            if let Err(e) = vault::unlock(&path) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(("import", matches)) = matches.subcommand() {
+        let file = matches.get_one::<PathBuf>("file").unwrap();
+        match import::run(file) {
+            Ok(imported) => println!("{} conversations imported into the history", imported),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(("history", matches)) = matches.subcommand() {
+        let tag = matches.get_one::<String>("tag");
+        for line in History::list(tag.map(String::as_str)) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    let log_error = logging::init(&config.log_level).err();
+
+    // The answers of the conversations never sent again are removed once expired
+    if config.cache.enabled {
+        let cache = config.cache.clone();
+        tokio::task::spawn_blocking(move || cache::prune(&cache));
+    }
+
+    if matches.get_flag("headless") {
+        return headless::run(config, matches.get_flag("json")).await;
+    }
+    if matches.get_flag("plain") {
+        return plain::run(config).await;
+    }
+    tracing::info!(
+        version = crate_version!(),
+        model = config.model_name(),
+        "tenere started"
+    );
+
+    let formatter = Formatter::new();
 
     let mut app = App::new(config.clone(), &formatter);
+    if let Some(e) = log_error {
+        app.notifications
+            .push(Notification::new(e, NotificationLevel::Warning));
+    }
 
-    let llm = Arc::new(Mutex::new(
-        LLMModel::init(&config.llm, config.clone()).await,
-    ));
+    // Checked before the TUI takes the terminal
+    let llm = match LLMModel::init(&config.llm, config.clone()).await {
+        Ok(llm) => Arc::new(Mutex::new(llm)),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
-    let events = EventHandler::new(250);
+    let events = EventHandler::new(config.tick_rate.max(1));
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
 
+    let control_socket = config.control_socket
+        && match control::spawn(tui.events.sender.clone()) {
+            Ok(()) => true,
+            Err(e) => {
+                app.notifications
+                    .push(Notification::new(e, NotificationLevel::Warning));
+                false
+            }
+        };
+
+    if config.auto_select.enabled {
+        latency::spawn(config.clone(), tui.events.sender.clone());
+    }
+
+    // The events arriving within a frame, like the chunks of an answer, are drawn together
+    let frame = Duration::from_secs_f64(1.0 / config.max_fps.max(1) as f64);
+    let mut redraw = true;
+    let mut last_draw: Option<Instant> = None;
+
     while app.running {
-        tui.draw(&mut app)?;
-        match tui.events.next().await? {
-            Event::Tick => app.tick(),
+        let wait = last_draw.map_or(Duration::ZERO, |t| frame.saturating_sub(t.elapsed()));
+        if redraw && wait.is_zero() {
+            tui.draw(&mut app)?;
+            last_draw = Some(Instant::now());
+            redraw = false;
+        }
+
+        let event = if redraw {
+            match tokio::time::timeout(wait, tui.events.next()).await {
+                Ok(event) => event?,
+                Err(_) => continue,
+            }
+        } else {
+            tui.events.next().await?
+        };
+
+        // Only the ticks may leave the screen as it is
+        redraw |= !matches!(event, Event::Tick);
+        match event {
+            Event::Tick => redraw |= app.tick(),
             Event::Key(key_event) => {
-                handle_key_events(key_event, &mut app, llm.clone(), tui.events.sender.clone())
-                    .await?;
+                if !app.unlock(Some(key_event)) {
+                    handle_key_events(key_event, &mut app, llm.clone(), tui.events.sender.clone())
+                        .await?;
+                }
+
+                if std::mem::take(&mut app.external_editor) {
+                    tui.suspend().await?;
+                    let result = editor::edit(&app.prompt.editor.lines().join("\n"));
+                    tui.resume()?;
+
+                    match result {
+                        Ok(text) => {
+                            app.prompt.set_text(&text);
+                            app.focused_block = FocusedBlock::Prompt;
+                            app.prompt.update(&app.focused_block);
+                        }
+                        Err(e) => app
+                            .notifications
+                            .push(Notification::new(e, NotificationLevel::Error)),
+                    }
+                }
+            }
+            Event::Mouse(_) => {
+                app.last_activity = std::time::Instant::now();
+            }
+            Event::Paste(text) => {
+                if !app.unlock(None) {
+                    handle_paste(&mut app, text);
+                }
             }
-            Event::Mouse(_) => {}
+            Event::Focus(focused) => app.terminal_focused = focused,
             Event::Resize(_, _) => {}
-            Event::LLMEvent(LLMAnswer::Answer(answer)) => {
-                app.chat
-                    .handle_answer(LLMAnswer::Answer(answer), &formatter);
+            Event::Answer(id, answer) => {
+                handle_answer(&mut app, llm.clone(), tui.events.sender.clone(), id, answer).await;
             }
-            Event::LLMEvent(LLMAnswer::EndAnswer) => {
-                {
-                    let mut llm = llm.lock().await;
-                    llm.append_chat_msg(app.chat.answer.plain_answer.clone(), LLMRole::ASSISTANT);
+            // Only sent to the tasks of the requests, which forward them as answers
+            Event::LLMEvent(_) => {}
+
+            Event::AnswerError(id, error) => {
+                handle_answer_error(&mut app, llm.clone(), tui.events.sender.clone(), id, error)
+                    .await;
+            }
+
+            Event::LLMError(error) => {
+                tracing::error!(status = error.status, "{}", error.message);
+                if app.spinner.active {
+                    app.spinner.active = false;
+                    app.streaming_chat()
+                        .handle_answer(LLMAnswer::StartAnswer, &formatter);
                 }
 
-                app.chat.handle_answer(LLMAnswer::EndAnswer, &formatter);
+                app.streaming_chat().handle_error(&error, &formatter);
+                let chat_id = app.streaming_chat().id;
+                keep_previous_answer(&mut app, llm.clone(), chat_id).await;
                 app.terminate_response_signal
                     .store(false, std::sync::atomic::Ordering::Relaxed);
+
+                if app.branched.is_some() {
+                    merge_branch(&mut app, llm.clone(), tui.events.sender.clone()).await;
+                }
+                stop_pipeline(&mut app);
+
+                app.notifications.push(Notification::new(
+                    error.to_string(),
+                    NotificationLevel::Error,
+                ));
+            }
+
+            Event::CompareAnswer(i, answer) => {
+                let variables = app.variables();
+                if let Some(compare) = app.compare.as_mut() {
+                    if let Some((answer, model)) =
+                        compare.handle_answer(i, answer, &formatter).await
+                    {
+                        hooks::run(
+                            &app.config.hooks,
+                            HookRole::Assistant,
+                            answer.trim(),
+                            &model,
+                            &variables,
+                            &tui.events.sender,
+                        );
+                    }
+                    if !compare.is_streaming() {
+                        app.terminate_response_signal
+                            .store(false, std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
             }
-            Event::LLMEvent(LLMAnswer::StartAnswer) => {
+
+            Event::CompareError(i, error) => {
+                if let Some(compare) = app.compare.as_mut() {
+                    compare.handle_error(i, &error, &formatter);
+                    if !compare.is_streaming() {
+                        app.terminate_response_signal
+                            .store(false, std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
+                app.notifications.push(Notification::new(
+                    error.to_string(),
+                    NotificationLevel::Error,
+                ));
+            }
+
+            Event::Summary(summary) => {
                 app.spinner.active = false;
                 app.chat.handle_answer(LLMAnswer::StartAnswer, &formatter);
+                app.chat.handle_summary(&summary, &formatter);
+            }
+
+            Event::Title(chat_id, title) => {
+                if app.chat.id == chat_id {
+                    app.chat.title = Some(title);
+                } else if let Some(thread) =
+                    app.history.threads.iter_mut().find(|t| t.id == chat_id)
+                {
+                    thread.title = Some(title);
+                    app.save_history();
+                }
+            }
+
+            Event::ShellOutput(output) => {
+                // The model sees the output to help with the next steps
+                if let Some(output) = app.chat.handle_tool_output(output, &formatter) {
+                    let mut llm = llm.lock().await;
+                    llm.append_chat_msg(output, LLMRole::USER);
+                }
+            }
+
+            Event::Transcript(transcript) => {
+                app.transcribing = false;
+                match transcript {
+                    Ok(transcript) => {
+                        app.prompt.editor.insert_str(transcript);
+                    }
+                    Err(e) => app.notifications.push(Notification::new(
+                        format!("Can not transcribe the recording: {}", e),
+                        NotificationLevel::Error,
+                    )),
+                };
+            }
+
+            Event::Attachment(attachment) => match attachment {
+                Ok(attachment) => {
+                    app.prompt
+                        .attachments
+                        .retain(|a| !(a.kind == attachment.kind && a.name == attachment.name));
+                    let marker = attachment.marker();
+                    app.prompt.attachments.push(attachment);
+                    app.notifications.push(Notification::new(
+                        format!("{} attached to the prompt", marker),
+                        NotificationLevel::Info,
+                    ));
+                }
+                Err(e) => app
+                    .notifications
+                    .push(Notification::new(e, NotificationLevel::Error)),
+            },
+
+            Event::Clipboard(text) => match text {
+                Ok(text) if app.focused_block == FocusedBlock::Prompt => {
+                    app.prompt.editor.insert_str(text);
+                }
+                Ok(_) => {}
+                Err(e) => app
+                    .notifications
+                    .push(Notification::new(e, NotificationLevel::Error)),
+            },
+
+            Event::Latency(latencies) => {
+                app.latencies = latencies;
+                if app.chat.plain_chat.is_empty() && !app.spinner.active {
+                    apply_preferred_backend(&mut app, llm.clone()).await;
+                }
             }
 
             Event::Notification(notification) => {
                 app.notifications.push(notification);
             }
+
+            Event::Control(command) => {
+                handle_control(&mut app, command, llm.clone(), tui.events.sender.clone()).await;
+            }
+
+            Event::PreSent(pre_sent) => {
+                handle_pre_sent(&mut app, llm.clone(), tui.events.sender.clone(), pre_sent).await;
+            }
+
+            Event::PipelineStep => {
+                send_pipeline_step(&mut app, llm.clone(), tui.events.sender.clone()).await;
+            }
+
+            Event::Suspend => {
+                #[cfg(unix)]
+                tui.stop().await?;
+            }
         }
     }
 
+    abort_answer(&mut app).await;
+    app.save_draft();
+    let detached = std::mem::take(&mut app.detached);
+    for chat in app.branched.take().into_iter().chain(detached) {
+        app.history.push(chat.to_thread());
+        app.save_history();
+    }
+    if let Some(compare) = app.compare.take() {
+        for chat in compare.into_chats() {
+            app.history.push(chat.to_thread());
+        }
+        app.save_history();
+    }
+    app.archive_chat();
+
+    if control_socket {
+        control::remove();
+    }
+
     tui.exit()?;
     Ok(())
 }
@@ -0,0 +1,312 @@
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+
+const SYMBOLS: [(&str, &str); 83] = [
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("times", "×"),
+    ("cdot", "·"),
+    ("div", "÷"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("sim", "∼"),
+    ("propto", "∝"),
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("oint", "∮"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("emptyset", "∅"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("neg", "¬"),
+    ("land", "∧"),
+    ("lor", "∨"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("iff", "⇔"),
+    ("mapsto", "↦"),
+    ("ldots", "…"),
+    ("cdots", "⋯"),
+    ("circ", "∘"),
+    ("degree", "°"),
+    ("mathbb{R}", "ℝ"),
+    ("mathbb{N}", "ℕ"),
+];
+
+const SUPERSCRIPTS: [(char, char); 17] = [
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('=', '⁼'),
+    ('(', '⁽'),
+    (')', '⁾'),
+    ('n', 'ⁿ'),
+    ('i', 'ⁱ'),
+];
+
+const SUBSCRIPTS: [(char, char); 20] = [
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+    ('=', '₌'),
+    ('(', '₍'),
+    (')', '₎'),
+    ('a', 'ₐ'),
+    ('e', 'ₑ'),
+    ('i', 'ᵢ'),
+    ('j', 'ⱼ'),
+    ('n', 'ₙ'),
+];
+
+/// Map all the characters, or none if one of them has no equivalent
+fn map_chars(text: &str, table: &[(char, char)]) -> Option<String> {
+    text.chars()
+        .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+        .collect()
+}
+
+fn group(text: &str) -> String {
+    if text.chars().count() > 1 {
+        format!("({})", text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Compiled at the first use only, the math is converted at each frame of a streamed answer
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+/// Convert a LaTeX expression to Unicode, as far as plain text allows
+fn to_unicode(expr: &str) -> String {
+    let mut expr = expr.trim().to_string();
+
+    for command in ["left", "right", "displaystyle", "mathrm", "text", "mathbf"] {
+        expr = expr.replace(&format!("\\{}", command), "");
+    }
+    for space in ["\\,", "\\;", "\\!", "\\quad", "\\qquad"] {
+        expr = expr.replace(space, " ");
+    }
+
+    // Innermost first, the arguments of a fraction may contain another one
+    static FRAC: OnceLock<Regex> = OnceLock::new();
+    let frac = regex(&FRAC, r"\\[dt]?frac\{([^{}]*)\}\{([^{}]*)\}");
+    static SQRT: OnceLock<Regex> = OnceLock::new();
+    let sqrt = regex(&SQRT, r"\\sqrt\{([^{}]*)\}");
+    loop {
+        let replaced = frac.replace_all(&expr, |caps: &Captures| {
+            format!("{}/{}", group(&caps[1]), group(&caps[2]))
+        });
+        let replaced = sqrt
+            .replace_all(&replaced, |caps: &Captures| format!("√{}", group(&caps[1])))
+            .to_string();
+        if replaced == expr {
+            break;
+        }
+        expr = replaced;
+    }
+
+    static COMMAND: OnceLock<Regex> = OnceLock::new();
+    let command = regex(&COMMAND, r"\\(mathbb\{[A-Z]\}|[a-zA-Z]+)");
+    expr = command
+        .replace_all(&expr, |caps: &Captures| {
+            SYMBOLS
+                .iter()
+                .find(|(name, _)| *name == &caps[1])
+                .map(|(_, symbol)| symbol.to_string())
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .to_string();
+
+    static SCRIPTS: OnceLock<Regex> = OnceLock::new();
+    let scripts = regex(&SCRIPTS, r"([\^_])(?:\{([^{}]*)\}|(\w))");
+    expr = scripts
+        .replace_all(&expr, |caps: &Captures| {
+            let text = caps.get(2).or(caps.get(3)).map_or("", |m| m.as_str());
+            let table: &[(char, char)] = if &caps[1] == "^" {
+                &SUPERSCRIPTS
+            } else {
+                &SUBSCRIPTS
+            };
+            map_chars(text, table).unwrap_or_else(|| format!("{}{}", &caps[1], group(text)))
+        })
+        .to_string();
+
+    expr.replace(['{', '}'], "")
+}
+
+/// Convert the math of a prose segment, the `$` of prices are left alone
+fn prettify_prose(text: &str) -> String {
+    static DISPLAY: OnceLock<Regex> = OnceLock::new();
+    let display = regex(&DISPLAY, r"(?s)\$\$(.+?)\$\$|\\\[(.+?)\\\]");
+    let text = display.replace_all(text, |caps: &Captures| {
+        let expr = caps.get(1).or(caps.get(2)).map_or("", |m| m.as_str());
+        to_unicode(expr)
+    });
+
+    static INLINE: OnceLock<Regex> = OnceLock::new();
+    let inline = regex(&INLINE, r"\\\((.+?)\\\)|\$([^\s$](?:[^$\n]*[^\s$])?)\$");
+    inline
+        .replace_all(&text, |caps: &Captures| {
+            let end = caps.get(0).map_or(0, |m| m.end());
+            let next = text[end..].chars().next();
+            match (caps.get(1), caps.get(2)) {
+                (Some(expr), _) => to_unicode(expr.as_str()),
+                // Like in `$5 and $10`
+                (_, Some(_)) if next.is_some_and(|c| c.is_ascii_digit()) => caps[0].to_string(),
+                (_, Some(expr)) => to_unicode(expr.as_str()),
+                _ => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Render the LaTeX math of a markdown message with Unicode characters.
+/// The code blocks and the inline code are kept as they are.
+pub fn prettify(message: &str) -> String {
+    let mut output = String::new();
+    let mut prose = String::new();
+    let mut in_fence = false;
+
+    for line in message.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            if !in_fence {
+                output.push_str(&prettify_inline_code(&prose));
+                prose.clear();
+            }
+            in_fence = !in_fence;
+            output.push_str(line);
+        } else if in_fence {
+            output.push_str(line);
+        } else {
+            prose.push_str(line);
+        }
+    }
+    output.push_str(&prettify_inline_code(&prose));
+
+    output
+}
+
+/// Convert the prose between the spans of inline code
+fn prettify_inline_code(prose: &str) -> String {
+    prose
+        .split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 0 {
+                prettify_prose(part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latex_to_unicode() {
+        assert_eq!(to_unicode(r"\alpha + \beta \leq \pi"), "α + β ≤ π");
+        assert_eq!(to_unicode(r"\frac{a+b}{\sqrt{2}}"), "(a+b)/(√2)");
+        assert_eq!(to_unicode(r"\frac{\frac{1}{2}}{3}"), "(1/2)/3");
+        assert_eq!(to_unicode(r"x^2 + x_{i+1}"), "x² + xᵢ₊₁");
+        assert_eq!(to_unicode(r"e^{xy}"), "e^(xy)");
+        assert_eq!(to_unicode(r"x \in \mathbb{R}"), "x ∈ ℝ");
+        assert_eq!(to_unicode(r"\unknown"), r"\unknown");
+    }
+
+    #[test]
+    fn math_of_the_prose() {
+        assert_eq!(prettify("Where $x^2$ and \\(\\pi\\)"), "Where x² and π");
+        assert_eq!(prettify("$$\\sum_i x_i$$"), "∑ᵢ xᵢ");
+        // Prices
+        assert_eq!(prettify("Between $5 and $10"), "Between $5 and $10");
+    }
+
+    #[test]
+    fn code_left_alone() {
+        let message = "Run `echo $x^2$`\n\n```sh\necho $y^2$\n```\nThen $y^2$\n";
+        assert_eq!(
+            prettify(message),
+            "Run `echo $x^2$`\n\n```sh\necho $y^2$\n```\nThen y²\n"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use regex::Regex;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    config::{LLMParams, MockConfig},
+    context::{estimate_messages_tokens, estimate_tokens},
+    event::Event,
+    llm::{system_message, FinishReason, LLMAnswer, LLMRole, Usage, LLM},
+};
+
+/// An answer of the script, to the prompts matching its pattern or to any of them
+#[derive(Debug, PartialEq)]
+struct Response {
+    pattern: Option<String>,
+    text: String,
+}
+
+/// The responses of a script are separated by `---` lines. A response starting with a
+/// `> REGEX` line answers the prompts matching it, the others answer the prompts in turn.
+fn parse(script: &str) -> Vec<Response> {
+    let mut responses = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in script.lines().chain(std::iter::once("---")) {
+        if line.trim_end() != "---" {
+            lines.push(line);
+            continue;
+        }
+
+        let pattern = lines
+            .first()
+            .and_then(|line| line.strip_prefix("> "))
+            .map(|pattern| pattern.trim().to_string());
+        let skip = usize::from(pattern.is_some());
+        let text = lines[skip.min(lines.len())..].join("\n").trim().to_string();
+        if !text.is_empty() {
+            responses.push(Response { pattern, text });
+        }
+        lines.clear();
+    }
+
+    responses
+}
+
+/// The answer when there is no script, to see the rendering of the chat
+fn demo(prompt: &str) -> String {
+    format!(
+        "This is the **mock** backend, answering without the network.\n\n\
+        > {}\n\n\
+        A list:\n\n\
+        - the prompt has {} words\n\
+        - the answer is streamed word by word\n\n\
+        And some code:\n\n\
+        ```rust\n\
+        fn main() {{\n    println!(\"Hello, tenere!\");\n}}\n\
+        ```\n",
+        prompt.lines().next().unwrap_or_default(),
+        prompt.split_whitespace().count()
+    )
+}
+
+/// A backend streaming canned answers, for the tests and the demos
+pub struct Mock {
+    config: MockConfig,
+    messages: Vec<HashMap<String, String>>,
+    params: LLMParams,
+    /// The number of answers given in turn
+    answered: AtomicUsize,
+}
+
+impl Mock {
+    pub fn new(config: MockConfig, params: LLMParams) -> Self {
+        Self {
+            config,
+            messages: Vec::new(),
+            params,
+            answered: AtomicUsize::new(0),
+        }
+    }
+
+    fn answer(&self, prompt: &str) -> Result<String, String> {
+        let Some(path) = &self.config.script else {
+            return Ok(demo(prompt));
+        };
+
+        // Read at each answer, the script can be changed while tenere is running
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| format!("Can not read the mock script `{}`: {}", path, e))?;
+        let responses = parse(&script);
+
+        for response in &responses {
+            if let Some(pattern) = &response.pattern {
+                let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+                if regex.is_match(prompt) {
+                    return Ok(response.text.clone());
+                }
+            }
+        }
+
+        let in_turn: Vec<&Response> = responses.iter().filter(|r| r.pattern.is_none()).collect();
+        if in_turn.is_empty() {
+            return Ok(demo(prompt));
+        }
+        let i = self.answered.fetch_add(1, Ordering::Relaxed);
+        Ok(in_turn[i % in_turn.len()].text.clone())
+    }
+}
+
+#[async_trait]
+impl LLM for Mock {
+    fn clear(&mut self) {
+        self.messages = Vec::new();
+    }
+
+    fn messages(&self) -> Vec<HashMap<String, String>> {
+        self.messages.clone()
+    }
+
+    fn set_messages(&mut self, messages: Vec<HashMap<String, String>>) {
+        self.messages = messages;
+    }
+
+    fn append_chat_msg(&mut self, msg: String, role: LLMRole) {
+        let mut conv: HashMap<String, String> = HashMap::new();
+        conv.insert("role".to_string(), role.to_string());
+        conv.insert("content".to_string(), msg);
+        self.messages.push(conv);
+    }
+
+    async fn ask_with(
+        &self,
+        chat_messages: Vec<HashMap<String, String>>,
+        sender: UnboundedSender<Event>,
+        terminate_response_signal: Arc<AtomicBool>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut messages: Vec<HashMap<String, String>> = vec![system_message(&self.params)];
+        messages.extend(chat_messages);
+
+        let prompt = messages
+            .iter()
+            .rev()
+            .find(|message| message.get("role") == Some(&LLMRole::USER.to_string()))
+            .and_then(|message| message.get("content").cloned())
+            .unwrap_or_default();
+        let answer = self.answer(&prompt)?;
+
+        sender.send(Event::LLMEvent(LLMAnswer::StartAnswer))?;
+
+        let delay = Duration::from_millis(self.config.delay);
+        for token in answer.split_inclusive(char::is_whitespace) {
+            if terminate_response_signal.load(Ordering::Relaxed) {
+                sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
+                return Ok(());
+            }
+
+            tokio::time::sleep(delay).await;
+            sender.send(Event::LLMEvent(LLMAnswer::Answer(token.to_string())))?;
+        }
+
+        sender.send(Event::LLMEvent(LLMAnswer::FinishReason(FinishReason::Stop)))?;
+        sender.send(Event::LLMEvent(LLMAnswer::Usage(Usage {
+            prompt_tokens: estimate_messages_tokens(&messages) as u64,
+            completion_tokens: estimate_tokens(&answer) as u64,
+        })))?;
+        sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_script() {
+        let script =
+            "First answer\n---\n> ^(hi|hello)\nHello!\n\nHow are you?\n---\n\n---\nSecond answer\n";
+
+        assert_eq!(
+            parse(script),
+            vec![
+                Response {
+                    pattern: None,
+                    text: String::from("First answer"),
+                },
+                Response {
+                    pattern: Some(String::from("^(hi|hello)")),
+                    text: String::from("Hello!\n\nHow are you?"),
+                },
+                Response {
+                    pattern: None,
+                    text: String::from("Second answer"),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use reqwest::{Certificate, ClientBuilder, NoProxy, Proxy, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    config::NetworkConfig,
+    event::Event,
+    llm::LLMError,
+    notification::{Notification, NotificationLevel},
+    queue,
+};
+
+/// Client builder with the proxy and the certificates of the config.
+/// Without `proxy`, the `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` variables are used.
+pub fn builder(config: &NetworkConfig) -> Result<ClientBuilder, Box<dyn std::error::Error>> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?.no_proxy(NoProxy::from_env()));
+    }
+
+    if let Some(ca_bundle) = &config.ca_bundle {
+        let pem = std::fs::read(ca_bundle)
+            .map_err(|e| format!("Can not read the ca bundle `{}`: {}", ca_bundle, e))?;
+        for certificate in Certificate::from_pem_bundle(&pem)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder)
+}
+
+/// The client used by the backends, or why the network config is wrong
+pub fn client(config: &NetworkConfig) -> Result<reqwest::Client, String> {
+    builder(config)
+        .and_then(|builder| Ok(builder.build()?))
+        .map_err(|e| format!("Invalid network config: {}", e))
+}
+
+/// Send the request, retrying with an exponential backoff on timeouts,
+/// connection errors, `429` and `5xx` responses. When the provider tells
+/// how long to wait with `Retry-After`, the request is queued until then.
+pub async fn send(
+    request: RequestBuilder,
+    config: &NetworkConfig,
+    sender: &UnboundedSender<Event>,
+) -> Result<Response, Box<dyn std::error::Error>> {
+    let timeout = Duration::from_secs(config.timeout);
+    let mut attempt = 0;
+    let mut rate_limited = false;
+
+    let built = request.try_clone().and_then(|req| req.build().ok());
+    let host = built
+        .as_ref()
+        .and_then(|req| req.url().host_str().map(|host| host.to_string()))
+        .unwrap_or_default();
+    // The url without the query, which may hold an api key
+    let url = built
+        .map(|req| {
+            format!(
+                "{} {}",
+                req.method(),
+                req.url().as_str().split('?').next().unwrap_or_default()
+            )
+        })
+        .unwrap_or_default();
+
+    loop {
+        let req = request
+            .try_clone()
+            .ok_or("The request can not be sent more than once")?;
+
+        queue::wait(&host, rate_limited).await?;
+        rate_limited = false;
+
+        tracing::debug!(attempt, "sending {}", url);
+        let started = std::time::Instant::now();
+
+        let reason = match tokio::time::timeout(timeout, req.send()).await {
+            Ok(Ok(res)) if res.status() == StatusCode::TOO_MANY_REQUESTS => {
+                if let Some(delay) = queue::retry_after(&res) {
+                    queue::limit(&host, delay);
+                    rate_limited = true;
+                    tracing::warn!("{}: rate limited for {}s", url, delay.as_secs());
+
+                    let notif = Notification::new(
+                        format!(
+                            "Rate limited by {}, the request is queued for {}s. `ctrl + t` cancels it",
+                            host,
+                            delay.as_secs()
+                        ),
+                        NotificationLevel::Warning,
+                    );
+                    sender.send(Event::Notification(notif))?;
+                    continue;
+                }
+
+                if attempt >= config.max_retries {
+                    return Ok(res);
+                }
+                format!("The server answered with `{}`", res.status())
+            }
+            Ok(Ok(res)) if res.status().is_server_error() => {
+                if attempt >= config.max_retries {
+                    return Ok(res);
+                }
+                format!("The server answered with `{}`", res.status())
+            }
+            Ok(Ok(res)) => {
+                tracing::debug!(
+                    status = res.status().as_u16(),
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    "response of {}",
+                    url
+                );
+                return Ok(res);
+            }
+            Ok(Err(e)) if e.is_connect() || e.is_timeout() => {
+                if attempt >= config.max_retries {
+                    return Err(Box::new(e));
+                }
+                e.to_string()
+            }
+            Ok(Err(e)) => return Err(Box::new(e)),
+            Err(_) => {
+                if attempt >= config.max_retries {
+                    return Err("The request timed out".into());
+                }
+                String::from("The request timed out")
+            }
+        };
+
+        attempt += 1;
+        let delay = Duration::from_secs(2u64.pow(attempt - 1));
+        tracing::warn!(
+            attempt,
+            "{}: {}, retrying in {}s",
+            url,
+            reason,
+            delay.as_secs()
+        );
+
+        let notif = Notification::new(
+            format!(
+                "{}, retrying in {}s ({}/{})",
+                reason,
+                delay.as_secs(),
+                attempt,
+                config.max_retries
+            ),
+            NotificationLevel::Warning,
+        );
+        sender.send(Event::Notification(notif))?;
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Read the next chunk of a streamed response, failing if the server stalls
+pub async fn next_chunk(
+    response: &mut Response,
+    config: &NetworkConfig,
+) -> std::io::Result<Option<Vec<u8>>> {
+    match tokio::time::timeout(Duration::from_secs(config.timeout), response.chunk()).await {
+        Ok(chunk) => Ok(chunk
+            .map_err(std::io::Error::other)?
+            .map(|chunk| chunk.to_vec())),
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "The stream timed out",
+        )),
+    }
+}
+
+/// Turn an unsuccessful response into an error with the message sent by the provider
+pub async fn error_for_status(response: Response) -> Result<Response, LLMError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let url = response
+        .url()
+        .as_str()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let body = response.text().await.unwrap_or_default();
+    tracing::warn!(status = status.as_u16(), "{} failed: {}", url, body.trim());
+
+    let message = serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|error| {
+            error["error"]["message"]
+                .as_str()
+                .or(error["error"].as_str())
+                .map(|message| message.to_string())
+        })
+        .unwrap_or(body);
+
+    let message = if message.trim().is_empty() {
+        status.canonical_reason().unwrap_or_default().to_string()
+    } else {
+        message.trim().to_string()
+    };
+
+    Err(LLMError {
+        status: Some(status.as_u16()),
+        message,
+    })
+}
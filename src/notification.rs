@@ -1,16 +1,26 @@
 use ratatui::{
     layout::{Alignment, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Text},
     widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
 
+use std::time::{Duration, Instant};
+
+use crate::{
+    i18n::{t, translate},
+    theme::Theme,
+};
+
+// How long a notification stays on the screen
+const DURATION: Duration = Duration::from_secs(2);
+
 #[derive(Debug, Clone)]
 pub struct Notification {
     pub message: String,
     pub level: NotificationLevel,
-    pub ttl: u16,
+    pub shown_until: Instant,
 }
 
 #[derive(Debug, Clone)]
@@ -21,24 +31,29 @@ pub enum NotificationLevel {
 }
 
 impl Notification {
+    /// The message is translated once, not each time it is drawn
     pub fn new(message: String, level: NotificationLevel) -> Self {
         Self {
-            message,
+            message: translate(&message),
             level,
-            ttl: 8,
+            shown_until: Instant::now() + DURATION,
         }
     }
 
-    pub fn render(&mut self, frame: &mut Frame, block: Rect) {
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.shown_until
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, block: Rect, theme: &Theme) {
         let (color, title) = match self.level {
-            NotificationLevel::Info => (Color::Green, "Info"),
-            NotificationLevel::Warning => (Color::Yellow, "Warning"),
-            NotificationLevel::Error => (Color::Red, "Error"),
+            NotificationLevel::Info => (theme.info, "Info"),
+            NotificationLevel::Warning => (theme.warning, "Warning"),
+            NotificationLevel::Error => (theme.error, "Error"),
         };
 
         let text = Text::from(vec![
             Line::styled(
-                title,
+                t(title),
                 Style::default().fg(color).add_modifier(Modifier::BOLD),
             )
             .alignment(Alignment::Center),
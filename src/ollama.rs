@@ -2,12 +2,14 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 use std::sync::Arc;
 
-use crate::config::OllamaConfig;
+use crate::config::{LLMParams, NetworkConfig, OllamaConfig};
 use crate::event::Event;
 use async_trait::async_trait;
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::llm::{LLMAnswer, LLMRole, LLM};
+use crate::llm::{ollama_messages, system_message, FinishReason, LLMAnswer, LLMRole, Usage, LLM};
+use crate::network;
+use crate::sse::{EventStream, LineBuffer};
 use reqwest::header::HeaderMap;
 use serde_json::{json, Value};
 use std;
@@ -19,16 +21,24 @@ pub struct Ollama {
     url: String,
     model: String,
     messages: Vec<HashMap<String, String>>,
+    network: NetworkConfig,
+    params: LLMParams,
 }
 
 impl Ollama {
-    pub fn new(config: OllamaConfig) -> Self {
-        Self {
-            client: reqwest::Client::new(),
+    pub fn new(
+        config: OllamaConfig,
+        network: NetworkConfig,
+        params: LLMParams,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            client: network::client(&network)?,
             url: config.url,
             model: config.model,
             messages: Vec::new(),
-        }
+            network,
+            params,
+        })
     }
 }
 
@@ -38,6 +48,14 @@ impl LLM for Ollama {
         self.messages = Vec::new();
     }
 
+    fn messages(&self) -> Vec<HashMap<String, String>> {
+        self.messages.clone()
+    }
+
+    fn set_messages(&mut self, messages: Vec<HashMap<String, String>>) {
+        self.messages = messages;
+    }
+
     fn append_chat_msg(&mut self, msg: String, role: LLMRole) {
         let mut conv: HashMap<String, String> = HashMap::new();
         conv.insert("role".to_string(), role.to_string());
@@ -45,56 +63,77 @@ impl LLM for Ollama {
         self.messages.push(conv);
     }
 
-    async fn ask(
+    async fn ask_with(
         &self,
+        chat_messages: Vec<HashMap<String, String>>,
         sender: UnboundedSender<Event>,
         terminate_response_signal: Arc<AtomicBool>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let mut headers = HeaderMap::new();
         headers.insert("Content-Type", "application/json".parse()?);
 
-        let mut messages: Vec<HashMap<String, String>> = vec![
-            (HashMap::from([
-                ("role".to_string(), "system".to_string()),
-                (
-                    "content".to_string(),
-                    "You are a helpful assistant.".to_string(),
-                ),
-            ])),
-        ];
+        let mut messages: Vec<HashMap<String, String>> = vec![system_message(&self.params)];
 
-        messages.extend(self.messages.clone());
+        messages.extend(chat_messages);
 
-        let body: Value = json!({
-            "messages": messages,
+        let mut body: Value = json!({
+            "messages": ollama_messages(&messages),
             "model": self.model,
             "stream": true,
         });
+        if let Some(temperature) = self.params.temperature {
+            body["options"]["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = self.params.max_tokens {
+            body["options"]["num_predict"] = json!(max_tokens);
+        }
+        if let Some(stop) = &self.params.stop {
+            body["options"]["stop"] = json!(stop);
+        }
+
+        let request = self.client.post(&self.url).headers(headers).json(&body);
 
-        let response = self
-            .client
-            .post(&self.url)
-            .headers(headers)
-            .json(&body)
-            .send()
-            .await?;
+        let response = network::send(request, &self.network, &sender).await?;
 
-        match response.error_for_status() {
-            Ok(mut res) => {
+        match network::error_for_status(response).await {
+            Ok(res) => {
                 sender.send(Event::LLMEvent(LLMAnswer::StartAnswer))?;
-                while let Some(chunk) = res.chunk().await? {
+                // One json object per line
+                let mut stream = EventStream::new(res, &self.network, LineBuffer::default());
+                while let Some(line) = stream.next().await? {
                     if terminate_response_signal.load(Ordering::Relaxed) {
                         sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
                         return Ok(());
                     }
 
-                    let answer: Value = serde_json::from_slice(chunk.as_ref())?;
-
-                    if answer["done"].as_bool().unwrap() {
+                    let answer: Value = serde_json::from_str(&line)?;
+
+                    if answer["done"].as_bool().unwrap_or(false) {
+                        let reason = answer["done_reason"].as_str().unwrap_or("stop");
+                        sender.send(Event::LLMEvent(LLMAnswer::FinishReason(
+                            FinishReason::from(reason),
+                        )))?;
+                        sender.send(Event::LLMEvent(LLMAnswer::Usage(Usage {
+                            prompt_tokens: answer["prompt_eval_count"].as_u64().unwrap_or(0),
+                            completion_tokens: answer["eval_count"].as_u64().unwrap_or(0),
+                        })))?;
                         sender.send(Event::LLMEvent(LLMAnswer::EndAnswer))?;
                         return Ok(());
                     }
 
+                    // The thinking models stream their reasoning before their answer
+                    let thinking = answer["message"]["thinking"].as_str().unwrap_or_default();
+                    if !thinking.is_empty() {
+                        sender.send(Event::LLMEvent(LLMAnswer::Reasoning(thinking.to_string())))?;
+                        if answer["message"]["content"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .is_empty()
+                        {
+                            continue;
+                        }
+                    }
+
                     let msg = answer["message"]["content"].as_str().unwrap_or("\n");
 
                     sender.send(Event::LLMEvent(LLMAnswer::Answer(msg.to_string())))?;
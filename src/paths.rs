@@ -0,0 +1,46 @@
+use std::{path::PathBuf, sync::OnceLock};
+
+use crate::{attachment, config::Config};
+
+/// The directories set in the config
+struct Dirs {
+    data: Option<PathBuf>,
+    state: Option<PathBuf>,
+}
+
+static DIRS: OnceLock<Dirs> = OnceLock::new();
+
+/// Keep the directories of the config, before the history or the state is read. Only the
+/// first call counts.
+pub fn init(config: &Config) {
+    let _ = DIRS.set(Dirs {
+        data: config.data_dir.as_deref().map(attachment::expand_home),
+        state: config.state_dir.as_deref().map(attachment::expand_home),
+    });
+}
+
+fn configured() -> &'static Dirs {
+    DIRS.get_or_init(|| Dirs {
+        data: None,
+        state: None,
+    })
+}
+
+/// Where the history and the index are kept, `tenere` in the data directory of the platform
+/// by default
+pub fn data_dir() -> Option<PathBuf> {
+    configured()
+        .data
+        .clone()
+        .or_else(|| dirs::data_dir().map(|dir| dir.join("tenere")))
+}
+
+/// Where the state, the drafts and the log are kept, `tenere` in the state directory of the
+/// platform by default
+pub fn state_dir() -> Option<PathBuf> {
+    configured().state.clone().or_else(|| {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("tenere"))
+    })
+}
@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::{attachment, i18n::t, theme::Theme};
+
+/// The paths starting with `partial`, directories end with a `/`
+pub fn completions(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+
+    let search_dir = if dir.is_empty() {
+        Path::new(".").to_path_buf()
+    } else {
+        attachment::expand_home(dir)
+    };
+
+    let Ok(entries) = std::fs::read_dir(search_dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            // Hidden files only show up once a dot is typed
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+
+    paths.sort();
+    paths
+}
+
+/// The longest prefix shared by all the paths
+pub fn common_prefix(paths: &[String]) -> String {
+    let Some(first) = paths.first() else {
+        return String::new();
+    };
+
+    let mut prefix = first.as_str();
+    for path in paths.iter().skip(1) {
+        while !path.starts_with(prefix) {
+            let mut end = prefix.len() - 1;
+            while !prefix.is_char_boundary(end) {
+                end -= 1;
+            }
+            prefix = &prefix[..end];
+        }
+    }
+
+    prefix.to_string()
+}
+
+/// Pop-up listing the paths completing the one typed after `/attach`
+pub struct FilePicker {
+    pub paths: Vec<String>,
+    state: ListState,
+}
+
+impl FilePicker {
+    pub fn new(paths: Vec<String>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self { paths, state }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.paths.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&String> {
+        self.state.selected().and_then(|i| self.paths.get(i))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let items = self
+            .paths
+            .iter()
+            .map(|path| ListItem::new(path.as_str()))
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(" {} ", t("Attach")))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+/// A named sequence of prompts run one after the other in a chat. Each step is sent
+/// once the answer to the previous one ended, its `{{previous}}` is replaced with that answer.
+pub struct Pipeline {
+    pub name: String,
+    /// The id of the chat the pipeline runs in
+    pub chat: usize,
+    steps: Vec<String>,
+    input: String,
+    /// The answers to the steps already sent
+    answers: Vec<String>,
+    /// The number of steps sent
+    sent: usize,
+}
+
+impl Pipeline {
+    pub fn new(name: &str, steps: Vec<String>, input: &str, chat: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            chat,
+            steps,
+            input: input.to_string(),
+            answers: Vec::new(),
+            sent: 0,
+        }
+    }
+
+    /// The prompt of the next step, or None once the last step was answered
+    pub fn next_step(&mut self) -> Option<String> {
+        let step = self.steps.get(self.sent)?.clone();
+        self.sent += 1;
+        Some(step)
+    }
+
+    /// Keep the answer to the step being answered
+    pub fn answered(&mut self, answer: &str) {
+        self.answers.push(answer.to_string());
+    }
+
+    /// The step being answered, and the number of steps
+    pub fn progress(&self) -> (usize, usize) {
+        (self.sent, self.steps.len())
+    }
+
+    /// The variables of the step prompts: `{{input}}`, `{{previous}}` and the answer
+    /// to each step as `{{step1}}`, `{{step2}}`…
+    pub fn variables(&self) -> HashMap<String, String> {
+        let mut variables = HashMap::from([
+            (String::from("input"), self.input.clone()),
+            (
+                String::from("previous"),
+                self.answers.last().cloned().unwrap_or_default(),
+            ),
+        ]);
+        for (i, answer) in self.answers.iter().enumerate() {
+            variables.insert(format!("step{}", i + 1), answer.clone());
+        }
+        variables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_and_their_variables() {
+        let steps = vec![
+            String::from("Summarize {{input}}"),
+            String::from("Translate {{previous}}"),
+        ];
+        let mut pipeline = Pipeline::new("summary", steps, "the text", 1);
+        assert_eq!(pipeline.progress(), (0, 2));
+        assert_eq!(pipeline.variables()["input"], "the text");
+        assert_eq!(pipeline.variables()["previous"], "");
+
+        assert_eq!(pipeline.next_step().as_deref(), Some("Summarize {{input}}"));
+        assert_eq!(pipeline.progress(), (1, 2));
+        pipeline.answered("A summary");
+        assert_eq!(pipeline.variables()["previous"], "A summary");
+        assert_eq!(pipeline.variables()["step1"], "A summary");
+
+        assert_eq!(
+            pipeline.next_step().as_deref(),
+            Some("Translate {{previous}}")
+        );
+        pipeline.answered("Un résumé");
+        let variables = pipeline.variables();
+        assert_eq!(variables["previous"], "Un résumé");
+        assert_eq!(variables["step1"], "A summary");
+        assert_eq!(variables["step2"], "Un résumé");
+        assert_eq!(pipeline.progress(), (2, 2));
+
+        assert_eq!(pipeline.next_step(), None);
+        assert_eq!(pipeline.progress(), (2, 2));
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    sync::mpsc::unbounded_channel,
+};
+
+use crate::{
+    app::AppResult,
+    authors::{self, Authors},
+    chat,
+    config::Config,
+    config::HookRole,
+    context,
+    event::Event,
+    formatter::Formatter,
+    history::{History, Thread},
+    hooks,
+    llm::{LLMAnswer, LLMError, LLMModel, LLMRole},
+    notification::NotificationLevel,
+    reasoning::ReasoningDisplay,
+    redaction,
+    tee::Tee,
+    variables,
+};
+
+const HELP: &str = "Type a message and press Enter to send it. \
+    /new starts a new chat, /help shows this help, /quit or ctrl + d quits. \
+    ctrl + c stops an answer.";
+
+fn print(text: &str) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = write!(stdout, "{}", text);
+    let _ = stdout.flush();
+}
+
+/// Save the chat to the history and start a new one
+fn save(history: &mut Option<History>, plain_chat: &mut Vec<String>) {
+    let Some(history) = history else {
+        plain_chat.clear();
+        return;
+    };
+    if plain_chat.is_empty() {
+        return;
+    }
+
+    history.push(Thread {
+        id: chat::next_id(),
+        plain_chat: std::mem::take(plain_chat),
+        ..Default::default()
+    });
+    if let Err(e) = history.persist() {
+        println!("Error: can not save the history: {}", e);
+    }
+}
+
+/// Chat line by line on the standard input and output, without colors, borders or
+/// spinners, so that screen readers and braille displays read the conversation in order
+pub async fn run(config: Arc<Config>) -> AppResult<()> {
+    let mut llm = match LLMModel::init(&config.llm, config.clone()).await {
+        Ok(llm) => llm,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let model = config.model_name();
+    let redaction_rules = redaction::outgoing_rules(&config.redaction).unwrap_or_default();
+
+    let formatter = Formatter::new();
+    let mut history = config.history.persist.then(|| History::load(&formatter));
+    let mut plain_chat = Vec::new();
+
+    let authors = Authors::new(&config.authors);
+    let mut tee = config
+        .tee
+        .as_deref()
+        .and_then(|path| Tee::open(path).map_err(|e| println!("Warning: {}", e)).ok());
+
+    println!("Tenere, {} with {}.", config.llm, model);
+    println!("{}", HELP);
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut running_hooks = Vec::new();
+    loop {
+        for failure in hooks::failures(&mut running_hooks, false).await {
+            println!("Warning: {}", failure);
+        }
+        print("You: ");
+        let line = tokio::select! {
+            line = lines.next_line() => line?,
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        let Some(line) = line else {
+            println!();
+            break;
+        };
+
+        let prompt = line.trim();
+        match prompt {
+            "" => continue,
+            "/quit" | "/exit" => break,
+            "/help" => {
+                println!("{}", HELP);
+                continue;
+            }
+            "/new" => {
+                save(&mut history, &mut plain_chat);
+                llm.clear();
+                println!("New chat.");
+                continue;
+            }
+            _ => {}
+        }
+
+        let prompt = variables::expand(prompt, &config.variables);
+        let (prompt, redacted) = redaction::redact(&prompt, &redaction_rules);
+        if !redacted.is_empty() {
+            println!(
+                "Warning: redacted before sending: {}",
+                redaction::summary(&redacted)
+            );
+        }
+        running_hooks.push(hooks::spawn(
+            &config.hooks,
+            HookRole::User,
+            &prompt,
+            &model,
+            &config.variables,
+        ));
+        llm.append_chat_msg(prompt.clone(), LLMRole::USER);
+        if let Some(tee) = tee.as_mut() {
+            tee.question(&format!("{} {}", authors.user, prompt));
+        }
+
+        let (sender, mut receiver) = unbounded_channel();
+        context::fit(&mut llm, &config.context, &model, &sender).await;
+
+        let signal = Arc::new(AtomicBool::new(false));
+
+        let printer = async {
+            let mut answer = String::new();
+            let mut reasoning = false;
+            let mut started = false;
+            while let Some(event) = receiver.recv().await {
+                match event {
+                    Event::LLMEvent(LLMAnswer::Reasoning(chunk)) => {
+                        match config.reasoning_display {
+                            ReasoningDisplay::Hidden => {}
+                            ReasoningDisplay::Collapsed if !reasoning => {
+                                println!("Assistant is thinking.")
+                            }
+                            ReasoningDisplay::Collapsed => {}
+                            ReasoningDisplay::Expanded => {
+                                if !reasoning {
+                                    print("Reasoning: ");
+                                }
+                                print(&chunk);
+                            }
+                        }
+                        reasoning = true;
+                    }
+                    Event::LLMEvent(LLMAnswer::Answer(chunk)) => {
+                        if !started {
+                            if reasoning && config.reasoning_display == ReasoningDisplay::Expanded {
+                                println!();
+                            }
+                            print("Assistant: ");
+                            started = true;
+                        }
+                        answer.push_str(&chunk);
+                        print(&chunk);
+                        if let Some(tee) = tee.as_mut() {
+                            tee.chunk(&authors.assistant, &chunk);
+                        }
+                    }
+                    Event::Notification(notification) => {
+                        let level = match notification.level {
+                            NotificationLevel::Error => "Error",
+                            NotificationLevel::Warning => "Warning",
+                            NotificationLevel::Info => "Info",
+                        };
+                        println!("{}: {}", level, notification.message);
+                    }
+                    _ => {}
+                }
+            }
+            if started {
+                println!();
+            }
+            answer
+        };
+
+        let ask = async {
+            llm.ask(sender, signal.clone())
+                .await
+                .map_err(LLMError::from)
+        };
+        let stop = async {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                signal.store(true, Ordering::Relaxed);
+            }
+            std::future::pending::<()>().await
+        };
+        let (res, answer) = tokio::select! {
+            answered = async { tokio::join!(ask, printer) } => answered,
+            _ = stop => unreachable!(),
+        };
+
+        if signal.load(Ordering::Relaxed) {
+            println!("Answer stopped.");
+        }
+        if let Some(tee) = tee.as_mut() {
+            tee.end(res.as_ref().err().map(|error| error.message.as_str()));
+        }
+        match res {
+            Ok(_) if !answer.is_empty() => {
+                plain_chat.push(format!("{}{}\n", authors::USER, prompt));
+                plain_chat.push(format!("{}{}", authors::ASSISTANT, answer));
+                running_hooks.push(hooks::spawn(
+                    &config.hooks,
+                    HookRole::Assistant,
+                    answer.trim(),
+                    &model,
+                    &config.variables,
+                ));
+                llm.append_chat_msg(answer, LLMRole::ASSISTANT);
+            }
+            res => {
+                if let Err(error) = res {
+                    println!("Error: {}", error.message);
+                }
+                // The question can be asked again
+                let mut messages = llm.messages();
+                messages.pop();
+                llm.set_messages(messages);
+            }
+        }
+    }
+
+    save(&mut history, &mut plain_chat);
+    for failure in hooks::failures(&mut running_hooks, true).await {
+        println!("Warning: {}", failure);
+    }
+    Ok(())
+}
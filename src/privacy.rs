@@ -0,0 +1,96 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, List, ListItem, ListState,
+    },
+    Frame,
+};
+
+use crate::{app::FocusedBlock, export::Export, i18n::t, redaction::Finding, theme::Theme};
+
+/// Potential secrets found in a chat about to be exported, waiting for a confirmation
+pub struct PrivacyReport {
+    pub export: Export,
+    pub findings: Vec<Finding>,
+    pub previous_focus: FocusedBlock,
+    state: ListState,
+}
+
+impl PrivacyReport {
+    pub fn new(export: Export, findings: Vec<Finding>, previous_focus: FocusedBlock) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+
+        Self {
+            export,
+            findings,
+            previous_focus,
+            state,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.findings.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&Finding> {
+        self.state.selected().and_then(|i| self.findings.get(i))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let items = self
+            .findings
+            .iter()
+            .map(|finding| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("line {:<6}", finding.line + 1),
+                        Style::default().bold(),
+                    ),
+                    Span::raw(format!("{:<18}", finding.rule)),
+                    Span::raw(finding.excerpt.clone()),
+                ]))
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(
+                        " {} potential secrets found before {} ",
+                        self.findings.len(),
+                        self.export
+                    ))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .title(
+                        Title::from(format!(
+                            " {} ",
+                            t("Enter: jump · y: continue · Esc: cancel")
+                        ))
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.warning)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
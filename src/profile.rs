@@ -0,0 +1,78 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::{config::Config, i18n::t, theme::Theme};
+
+/// Pop-up listing the profiles of the config
+pub struct ProfileSwitcher {
+    pub names: Vec<String>,
+    state: ListState,
+}
+
+impl ProfileSwitcher {
+    pub fn new(config: &Config) -> Self {
+        let names: Vec<String> = config.profiles.keys().cloned().collect();
+
+        let mut state = ListState::default();
+        state.select(Some(
+            config
+                .profile
+                .as_ref()
+                .and_then(|profile| names.iter().position(|name| name == profile))
+                .unwrap_or(0),
+        ));
+
+        Self { names, state }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.names.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&String> {
+        self.state.selected().and_then(|i| self.names.get(i))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, active: Option<&str>, theme: &Theme) {
+        let items = self
+            .names
+            .iter()
+            .map(|name| {
+                if Some(name.as_str()) == active {
+                    ListItem::new(format!("● {}", name))
+                } else {
+                    ListItem::new(format!("  {}", name))
+                }
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(" {} ", t("Profiles")))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
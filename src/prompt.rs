@@ -1,15 +1,27 @@
-use arboard::Clipboard;
 use ratatui::{
-    layout::Rect,
-    style::{Color, Style},
-    text::Text,
-    widgets::{Block, BorderType, Borders},
+    layout::{Alignment, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, Paragraph, Wrap,
+    },
     Frame,
 };
+use tokio::sync::mpsc::UnboundedSender;
 use tui_textarea::{CursorMove, TextArea};
 use unicode_width::UnicodeWidthStr;
 
 use crate::app::FocusedBlock;
+use crate::attachment::{self, Attachment};
+use crate::clipboard::Clipboard;
+use crate::config::PromptConfig;
+use crate::context;
+use crate::event::Event;
+use crate::i18n::{fill, t};
+use crate::registers::Registers;
+use crate::spellcheck::{self, Dictionary};
+use crate::theme::Theme;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 #[derive(Debug, PartialEq)]
@@ -19,11 +31,25 @@ pub enum Mode {
     Visual,
 }
 
+/// How much of the context window of the model the chat takes
+pub struct ContextUsage {
+    pub chat_tokens: usize,
+    pub size: usize,
+    /// Above this part of the window, the oldest messages are dropped or summarized
+    pub threshold: f32,
+}
+
 pub struct Prompt<'a> {
     pub mode: Mode,
     pub formatted_prompt: Text<'a>,
     pub editor: TextArea<'a>,
     pub block: Block<'a>,
+    pub attachments: Vec<Attachment>,
+    pub payload_scroll: u16,
+    pub theme: Theme,
+    pub max_height: u16,
+    /// The text whose misspelled words are underlined, checked again once it changes
+    pub spelling_text: Option<String>,
 }
 
 impl Default for Prompt<'_> {
@@ -43,6 +69,11 @@ impl Default for Prompt<'_> {
             formatted_prompt: Text::raw(""),
             editor,
             block,
+            attachments: Vec::new(),
+            payload_scroll: 0,
+            theme: Theme::default(),
+            max_height: PromptConfig::default_max_height(),
+            spelling_text: None,
         }
     }
 }
@@ -53,25 +84,111 @@ impl Prompt<'_> {
     }
 
     pub fn clear(&mut self) {
+        self.clear_text();
+        self.attachments.clear();
+        self.payload_scroll = 0;
+    }
+
+    /// Clear the text typed, keeping the attachments
+    pub fn clear_text(&mut self) {
         self.formatted_prompt = Text::raw("");
         self.editor.select_all();
         self.editor.cut();
     }
 
+    /// Replace the text typed, the cursor goes to its end
+    pub fn set_text(&mut self, text: &str) {
+        self.clear_text();
+        self.editor.insert_str(text);
+    }
+
+    /// Change the height the prompt grows up to, within 10% and 90% of the screen
+    pub fn resize(&mut self, delta: i16) {
+        self.max_height = self.max_height.saturating_add_signed(delta).clamp(10, 90);
+    }
+
     pub fn height(&self, frame_size: &Rect) -> u16 {
-        let prompt_block_max_height = (0.4 * frame_size.height as f32) as u16;
+        // Counted in usize, a long text or a big screen overflows an u16
+        let prompt_block_max_height = frame_size.height as usize * self.max_height as usize / 100;
+        let width = frame_size.width.max(1) as usize;
 
-        let height: u16 = 1 + self
+        let height: usize = 1 + self
             .editor
             .lines()
             .iter()
-            .map(|line| 1 + line.width() as u16 / frame_size.width)
-            .sum::<u16>();
+            .map(|line| 1 + line.width() / width)
+            .sum::<usize>();
+
+        std::cmp::min(height, prompt_block_max_height) as u16
+    }
+
+    /// Underline the misspelled words, or nothing without a dictionary
+    pub fn check_spelling(&mut self, dictionary: Option<&Dictionary>) {
+        let Some(dictionary) = dictionary else {
+            if self.spelling_text.take().is_some() {
+                let _ = self.editor.set_search_pattern("");
+            }
+            return;
+        };
+
+        let text = self.editor.lines().join("\n");
+        if self.spelling_text.as_ref() == Some(&text) {
+            return;
+        }
+
+        let mut misspelled = dictionary.misspelled(&text);
+        misspelled.sort_unstable();
+        misspelled.dedup();
+        let pattern = if misspelled.is_empty() {
+            String::new()
+        } else {
+            format!(
+                r"\b(?:{})\b",
+                misspelled
+                    .iter()
+                    .map(|word| regex::escape(word))
+                    .collect::<Vec<String>>()
+                    .join("|")
+            )
+        };
+        let _ = self.editor.set_search_pattern(pattern);
+        self.editor
+            .set_search_style(Style::default().fg(self.theme.error).underlined());
+        self.spelling_text = Some(text);
+    }
 
-        std::cmp::min(height, prompt_block_max_height)
+    /// Check the spelling again, after a change of the dictionary
+    pub fn recheck_spelling(&mut self) {
+        self.spelling_text = None;
+    }
+
+    /// The word under the cursor, with its row and the range of its characters
+    pub fn word_at_cursor(&self) -> Option<(String, (usize, usize, usize))> {
+        let (row, col) = self.editor.cursor();
+        let line = self.editor.lines().get(row)?;
+
+        spellcheck::words(line)
+            .into_iter()
+            .find_map(|(start, end)| {
+                let start_col = line[..start].chars().count();
+                let end_col = start_col + line[start..end].chars().count();
+                (start_col <= col && col < end_col)
+                    .then(|| (line[start..end].to_string(), (row, start_col, end_col)))
+            })
+    }
+
+    /// Replace the characters `start..end` of the row
+    pub fn replace(&mut self, (row, start, end): (usize, usize, usize), text: &str) {
+        self.editor
+            .move_cursor(CursorMove::Jump(row as u16, start as u16));
+        self.editor.delete_str(end - start);
+        self.editor.insert_str(text);
     }
 
     pub fn update(&mut self, focused_block: &FocusedBlock) {
+        self.editor
+            .set_selection_style(Style::default().bg(self.theme.selection));
+
         self.block = Block::default()
             .borders(Borders::ALL)
             .style(Style::default())
@@ -81,11 +198,11 @@ impl Prompt<'_> {
             })
             .border_style(match focused_block {
                 FocusedBlock::Prompt => match self.mode {
-                    Mode::Insert => Style::default().fg(Color::Green),
-                    Mode::Normal => Style::default(),
-                    Mode::Visual => Style::default().fg(Color::Yellow),
+                    Mode::Insert => Style::default().fg(self.theme.focused_border),
+                    Mode::Normal => Style::default().fg(self.theme.border),
+                    Mode::Visual => Style::default().fg(self.theme.visual_border),
                 },
-                _ => Style::default(),
+                _ => Style::default().fg(self.theme.border),
             });
     }
 
@@ -93,7 +210,9 @@ impl Prompt<'_> {
         &mut self,
         key_event: KeyEvent,
         previous_key: KeyCode,
-        clipboard: Option<&mut Clipboard>,
+        clipboard: &mut Clipboard,
+        registers: &mut Registers,
+        sender: &UnboundedSender<Event>,
     ) {
         match self.mode {
             Mode::Insert => match key_event.code {
@@ -204,10 +323,8 @@ impl Prompt<'_> {
 
                 KeyCode::Char('G') => self.editor.move_cursor(CursorMove::Bottom),
 
-                KeyCode::Char('g') => {
-                    if previous_key == KeyCode::Char('g') {
-                        self.editor.move_cursor(CursorMove::Jump(0, 0))
-                    }
+                KeyCode::Char('g') if previous_key == KeyCode::Char('g') => {
+                    self.editor.move_cursor(CursorMove::Jump(0, 0))
                 }
 
                 KeyCode::Char('D') => {
@@ -216,20 +333,16 @@ impl Prompt<'_> {
                     self.editor.delete_line_by_head();
                 }
 
-                KeyCode::Char('d') => {
-                    if previous_key == KeyCode::Char('d') {
-                        self.editor.move_cursor(CursorMove::Head);
-                        self.editor.delete_line_by_end();
-                    }
+                KeyCode::Char('d') if previous_key == KeyCode::Char('d') => {
+                    self.editor.move_cursor(CursorMove::Head);
+                    self.editor.delete_line_by_end();
                 }
 
-                KeyCode::Char('c') => {
-                    if previous_key == KeyCode::Char('c') {
-                        self.editor.move_cursor(CursorMove::Head);
-                        self.editor.delete_line_by_end();
-                        self.mode = Mode::Insert;
-                        self.update(&FocusedBlock::Prompt);
-                    }
+                KeyCode::Char('c') if previous_key == KeyCode::Char('c') => {
+                    self.editor.move_cursor(CursorMove::Head);
+                    self.editor.delete_line_by_end();
+                    self.mode = Mode::Insert;
+                    self.update(&FocusedBlock::Prompt);
                 }
 
                 KeyCode::Char('C') => {
@@ -275,21 +388,31 @@ impl Prompt<'_> {
                     self.update(&FocusedBlock::Prompt);
                 }
 
-                KeyCode::Char('y') => {
+                KeyCode::Char('y') if key_event.modifiers == KeyModifiers::NONE => {
                     self.editor.copy();
-                    if let Some(clipboard) = clipboard {
-                        let text = self.editor.yank_text();
-                        let _ = clipboard.set_text(text);
+                    match registers.selected.take() {
+                        Some(name) => registers.yank(name, &self.editor.yank_text()),
+                        None => {
+                            let _ = clipboard.set_text(&self.editor.yank_text());
+                        }
                     }
                 }
 
-                KeyCode::Char('p') => {
-                    if !self.editor.paste() {
-                        if let Some(clipboard) = clipboard {
-                            if let Ok(text) = clipboard.get_text() {
+                KeyCode::Char('p') if key_event.modifiers == KeyModifiers::NONE => {
+                    match registers.selected.take() {
+                        Some(name) => {
+                            if let Some(text) = registers.get(name) {
                                 self.editor.insert_str(text);
                             }
                         }
+                        None => {
+                            let pasted = self.editor.paste();
+                            if !pasted {
+                                if let Ok(Some(text)) = clipboard.get_text(sender) {
+                                    self.editor.insert_str(text);
+                                }
+                            }
+                        }
                     }
                 }
 
@@ -302,8 +425,103 @@ impl Prompt<'_> {
         }
     }
 
-    pub fn render(&mut self, frame: &mut Frame, block: Rect) {
-        self.editor.set_block(self.block.clone());
+    /// Estimated tokens of the message, with its attachments
+    pub fn estimated_tokens(&self) -> usize {
+        let text = self.editor.lines().join("\n");
+        if self.attachments.is_empty() {
+            context::estimate_tokens(text.trim())
+        } else {
+            context::estimate_tokens(&attachment::payload(&self.attachments, text.trim()))
+        }
+    }
+
+    /// The tokens of the message, and the part of the context window it would fill with the chat
+    fn tokens_title(&self, usage: &ContextUsage) -> Option<Line<'static>> {
+        let tokens = self.estimated_tokens();
+        if tokens == 0 {
+            return None;
+        }
+
+        let filled = (usage.chat_tokens + tokens) as f32 / usage.size.max(1) as f32;
+        let color = if filled > 1.0 {
+            self.theme.error
+        } else if filled > usage.threshold {
+            self.theme.warning
+        } else {
+            self.theme.muted
+        };
+
+        Some(Line::from(Span::styled(
+            format!(
+                " ~{} tokens · {:.0}% of the context ",
+                tokens,
+                filled * 100.0
+            ),
+            Style::default().fg(color),
+        )))
+    }
+
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        block: Rect,
+        indicator: Option<&'static str>,
+        usage: &ContextUsage,
+    ) {
+        let mut prompt_block = self.block.clone();
+
+        if let Some(title) = self.tokens_title(usage) {
+            prompt_block = prompt_block.title(
+                Title::from(title)
+                    .position(Position::Top)
+                    .alignment(Alignment::Right),
+            );
+        }
+
+        if let Some(indicator) = indicator {
+            prompt_block = prompt_block.title(
+                Title::from(indicator)
+                    .position(Position::Top)
+                    .alignment(Alignment::Left),
+            );
+            prompt_block = prompt_block.title_style(Style::default().fg(self.theme.warning));
+        }
+
+        if !self.attachments.is_empty() {
+            let text = self.editor.lines().join("\n");
+            prompt_block = prompt_block.title(
+                Title::from(format!(
+                    " {} ",
+                    fill(
+                        "{} · P to preview",
+                        &[&attachment::summary(&self.attachments, &text)]
+                    )
+                ))
+                .position(Position::Bottom)
+                .alignment(Alignment::Right),
+            );
+        }
+
+        self.editor.set_block(prompt_block);
         frame.render_widget(self.editor.widget(), block);
     }
+
+    pub fn render_payload(&self, frame: &mut Frame, area: Rect) {
+        let text = self.editor.lines().join("\n");
+
+        let payload = Paragraph::new(attachment::payload(&self.attachments, text.trim()))
+            .wrap(Wrap { trim: false })
+            .scroll((self.payload_scroll, 0))
+            .block(
+                Block::default()
+                    .title(format!(" {} ", t("Payload")))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(payload, area);
+    }
 }
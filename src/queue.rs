@@ -0,0 +1,144 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use reqwest::{header::HeaderMap, Response};
+
+/// The requests to a provider waiting for its rate limit to pass, in order
+#[derive(Default)]
+struct Queue {
+    until: Option<Instant>,
+    waiting: VecDeque<u64>,
+}
+
+// The rate limits apply to all the requests to a host, whatever sends them
+static QUEUES: OnceLock<Mutex<HashMap<String, Queue>>> = OnceLock::new();
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// Incremented to cancel the requests waiting at that time
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn queues() -> std::sync::MutexGuard<'static, HashMap<String, Queue>> {
+    QUEUES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// The place of a request in the queue, left even if the task of the request is aborted
+struct Place<'a> {
+    host: &'a str,
+    id: u64,
+}
+
+impl Drop for Place<'_> {
+    fn drop(&mut self) {
+        if let Some(queue) = queues().get_mut(self.host) {
+            queue.waiting.retain(|waiting| *waiting != self.id);
+        }
+    }
+}
+
+/// Wait for the turn of a request to the host. A request sent again after
+/// a rate limit goes first, it was the first to be sent.
+pub async fn wait(host: &str, retry: bool) -> Result<(), String> {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let generation = GENERATION.load(Ordering::Relaxed);
+    let mut position_set = false;
+    let _place = Place { host, id };
+
+    loop {
+        {
+            let mut queues = queues();
+            let queue = queues.entry(host.to_string()).or_default();
+            let limited = queue.until.is_some_and(|until| until > Instant::now());
+
+            if !limited && queue.waiting.front().is_none_or(|first| *first == id) {
+                return Ok(());
+            }
+
+            if !position_set {
+                if retry {
+                    queue.waiting.push_front(id);
+                } else {
+                    queue.waiting.push_back(id);
+                }
+                position_set = true;
+            }
+
+            if GENERATION.load(Ordering::Relaxed) != generation {
+                return Err(String::from("The queued request was cancelled"));
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Hold the requests to the host for a while
+pub fn limit(host: &str, delay: Duration) {
+    let mut queues = queues();
+    let queue = queues.entry(host.to_string()).or_default();
+    let until = Instant::now() + delay;
+    queue.until = Some(queue.until.map_or(until, |previous| previous.max(until)));
+}
+
+/// Cancel all the requests waiting for a rate limit
+pub fn cancel() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The number of requests waiting, and how long before the first is sent
+pub fn status() -> Option<(usize, Duration)> {
+    let queues = queues();
+    let now = Instant::now();
+
+    let waiting: usize = queues.values().map(|queue| queue.waiting.len()).sum();
+    if waiting == 0 {
+        return None;
+    }
+
+    let remaining = queues
+        .values()
+        .filter(|queue| !queue.waiting.is_empty())
+        .filter_map(|queue| {
+            queue
+                .until
+                .map(|until| until.saturating_duration_since(now))
+        })
+        .min()
+        .unwrap_or_default();
+
+    Some((waiting, remaining))
+}
+
+/// The delay asked by the provider, in the `retry-after-ms` header of OpenAI
+/// or in the standard `Retry-After` header, in seconds or as a date
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    parse_retry_after(response.headers())
+}
+
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(ms) = headers
+        .get("retry-after-ms")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<f64>().ok())
+    {
+        return Some(Duration::from_secs_f64(ms.max(0.0) / 1000.0));
+    }
+
+    let value = headers.get("retry-after")?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Some(Duration::from_secs_f64(seconds.max(0.0)));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = date.timestamp() - chrono::Utc::now().timestamp();
+    Some(Duration::from_secs(seconds.max(0) as u64))
+}
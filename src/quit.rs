@@ -0,0 +1,54 @@
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::Style,
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{app::FocusedBlock, i18n::t, theme::Theme};
+
+/// What would be lost by quitting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuitReason {
+    Streaming,
+    UnsavedChat,
+}
+
+impl QuitReason {
+    fn message(&self) -> &'static str {
+        match self {
+            Self::Streaming => "An answer is being streamed, quit and lose it? (y/n)",
+            Self::UnsavedChat => "The history is not saved, quit and lose the chats? (y/n)",
+        }
+    }
+}
+
+/// Pop-up asking to confirm the quit
+pub struct QuitConfirmation {
+    pub reason: QuitReason,
+    pub previous_focus: FocusedBlock,
+}
+
+impl QuitConfirmation {
+    pub fn new(reason: QuitReason, previous_focus: FocusedBlock) -> Self {
+        Self {
+            reason,
+            previous_focus,
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let confirmation = Paragraph::new(t(self.reason.message()))
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.warning)),
+            );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(confirmation, area);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    config::{Config, RagConfig},
+    event::Event,
+    llm::{LLMBackend, LLM},
+    network,
+    notification::{Notification, NotificationLevel},
+    paths,
+};
+
+// Marks the questions already augmented, a retry should not add the context twice
+const CONTEXT_HEADER: &str = "Context retrieved from local files:";
+
+// Bigger files are rarely worth indexing
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+const BATCH_SIZE: usize = 32;
+
+const IGNORED_DIRS: [&str; 4] = ["target", "node_modules", "__pycache__", "venv"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    pub path: String,
+    pub start_line: usize,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+/// Embeddings of the chunks of the files of a directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub root: PathBuf,
+    pub model: String,
+    pub chunks: Vec<Chunk>,
+}
+
+impl Index {
+    fn path() -> Option<PathBuf> {
+        paths::data_dir().map(|dir| dir.join("index.json"))
+    }
+
+    /// The index built by `tenere index`, if any
+    pub fn load() -> Option<Self> {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|index| serde_json::from_str(&index).ok())
+    }
+
+    pub fn persist(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("Can not find the data directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, serde_json::to_string(self)?)?;
+        Ok(path)
+    }
+
+    /// The chunks closest to the embedding, the most relevant first
+    pub fn search(&self, embedding: &[f32], top_k: usize) -> Vec<&Chunk> {
+        let mut scored: Vec<(f32, &Chunk)> = self
+            .chunks
+            .iter()
+            .map(|chunk| (cosine_similarity(&chunk.embedding, embedding), chunk))
+            .collect();
+
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(top_k)
+            .map(|(_, chunk)| chunk)
+            .collect()
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Compute the embeddings of the inputs with the configured backend
+async fn embed(
+    client: &reqwest::Client,
+    rag: &RagConfig,
+    config: &Config,
+    inputs: &[String],
+) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+    let request = match rag.backend {
+        LLMBackend::ChatGPT => {
+            let api_key = config
+                .chatgpt
+                .fetch_api_key()
+                .await
+                .ok_or("Can not find the openai api key")?;
+            let url = config.chatgpt.url.replace("chat/completions", "embeddings");
+            client
+                .post(url)
+                .bearer_auth(api_key)
+                .json(&json!({ "model": rag.model(), "input": inputs }))
+        }
+        LLMBackend::Ollama => {
+            let ollama = config
+                .ollama
+                .as_ref()
+                .ok_or("Config for Ollama is not provided")?;
+            let url = ollama.url.replace("/api/chat", "/api/embed");
+            client
+                .post(url)
+                .json(&json!({ "model": rag.model(), "input": inputs }))
+        }
+        LLMBackend::LLamacpp | LLMBackend::Mock => {
+            return Err("Embeddings are computed with chatgpt or ollama".into());
+        }
+    };
+
+    let response = network::error_for_status(request.send().await?).await?;
+    let body: Value = response.json().await?;
+
+    let embeddings: Vec<Vec<f32>> = match rag.backend {
+        LLMBackend::Ollama => serde_json::from_value(body["embeddings"].clone())?,
+        _ => body["data"]
+            .as_array()
+            .ok_or("Unexpected response for the embeddings")?
+            .iter()
+            .map(|data| serde_json::from_value(data["embedding"].clone()))
+            .collect::<Result<_, _>>()?,
+    };
+
+    if embeddings.len() != inputs.len() {
+        return Err("Unexpected number of embeddings".into());
+    }
+
+    Ok(embeddings)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        match entry.file_type() {
+            Ok(t) if t.is_dir() && !IGNORED_DIRS.contains(&name.as_str()) => {
+                collect_files(&path, files)
+            }
+            Ok(t)
+                if t.is_file()
+                    && entry.metadata().map(|m| m.len()).unwrap_or(0) <= MAX_FILE_SIZE =>
+            {
+                files.push(path)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Split the text files of the directory into chunks of lines
+fn chunks(root: &Path, chunk_lines: usize) -> Vec<Chunk> {
+    let mut files = Vec::new();
+    collect_files(root, &mut files);
+    files.sort();
+
+    let mut chunks = Vec::new();
+    for file in files {
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        if content.contains('\0') {
+            continue;
+        }
+
+        let path = file
+            .strip_prefix(root)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+
+        let lines: Vec<&str> = content.lines().collect();
+        for (i, lines) in lines.chunks(chunk_lines.max(1)).enumerate() {
+            let text = lines.join("\n");
+            if text.trim().is_empty() {
+                continue;
+            }
+            chunks.push(Chunk {
+                path: path.clone(),
+                start_line: i * chunk_lines.max(1) + 1,
+                text,
+                embedding: Vec::new(),
+            });
+        }
+    }
+
+    chunks
+}
+
+/// Build the index of a directory, used by `tenere index <dir>`
+pub async fn index(dir: &Path, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let rag = config
+        .rag
+        .as_ref()
+        .ok_or("Configure the embeddings in the `[rag]` section first")?;
+
+    let root = dir
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let mut chunks = chunks(&root, rag.chunk_lines);
+    if chunks.is_empty() {
+        return Err(format!("No text file found in {}", root.display()).into());
+    }
+
+    let client = network::client(&config.network)?;
+
+    let total = chunks.len();
+    for (i, batch) in chunks.chunks_mut(BATCH_SIZE).enumerate() {
+        let inputs: Vec<String> = batch
+            .iter()
+            .map(|chunk| format!("{}\n{}", chunk.path, chunk.text))
+            .collect();
+
+        let embeddings = embed(&client, rag, config, &inputs).await?;
+        for (chunk, embedding) in batch.iter_mut().zip(embeddings) {
+            chunk.embedding = embedding;
+        }
+
+        println!(
+            "Indexed {}/{} chunks",
+            (i * BATCH_SIZE + batch.len()).min(total),
+            total
+        );
+    }
+
+    let index = Index {
+        root,
+        model: rag.model(),
+        chunks,
+    };
+    let path = index.persist()?;
+    println!("Index saved to {}", path.display());
+
+    Ok(())
+}
+
+/// Prepend the chunks relevant to the last question, and list the files they come from
+async fn retrieve(
+    question: &str,
+    index: &Index,
+    config: &Config,
+) -> Result<(String, Vec<String>), Box<dyn std::error::Error>> {
+    let rag = config.rag.as_ref().ok_or("Retrieval is not configured")?;
+    if rag.model() != index.model {
+        return Err(format!(
+            "The index was built with `{}`, run `tenere index` again",
+            index.model
+        )
+        .into());
+    }
+
+    let client = network::builder(&config.network)?.build()?;
+    let embedding = embed(&client, rag, config, &[question.to_string()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+
+    let chunks = index.search(&embedding, rag.top_k);
+
+    let mut files: Vec<String> = Vec::new();
+    let mut context = String::from(CONTEXT_HEADER);
+    for chunk in chunks {
+        context.push_str(&format!(
+            "\n\n{}:{}\n```\n{}\n```",
+            chunk.path, chunk.start_line, chunk.text
+        ));
+        if !files.contains(&chunk.path) {
+            files.push(chunk.path.clone());
+        }
+    }
+
+    Ok((format!("{}\n\nQuestion:\n{}", context, question), files))
+}
+
+/// Add the relevant chunks of the index to the last question sent to the llm
+pub async fn augment(
+    llm: &mut Box<dyn LLM + 'static>,
+    index: &Index,
+    config: &Config,
+    sender: &UnboundedSender<Event>,
+) {
+    let mut messages: Vec<HashMap<String, String>> = llm.messages();
+
+    let Some(question) = messages
+        .last_mut()
+        .filter(|message| message.get("role").map(|r| r.as_str()) == Some("user"))
+        .and_then(|message| message.get_mut("content"))
+    else {
+        return;
+    };
+
+    if question.starts_with(CONTEXT_HEADER) {
+        return;
+    }
+
+    let notif = match retrieve(question, index, config).await {
+        Ok((augmented, files)) => {
+            *question = augmented;
+            llm.set_messages(messages);
+            Notification::new(
+                format!("📚 Context from {}", files.join(", ")),
+                NotificationLevel::Info,
+            )
+        }
+        Err(e) => Notification::new(
+            format!("Can not retrieve the context: {}", e),
+            NotificationLevel::Warning,
+        ),
+    };
+
+    let _ = sender.send(Event::Notification(notif));
+}
@@ -0,0 +1,239 @@
+use ratatui::{
+    style::{Color, Style, Stylize},
+    text::{Line, Text},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::llm::LLMAnswer;
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// How the chain of thought of the reasoning models is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningDisplay {
+    /// A single line, unfolded with `z`
+    #[default]
+    Collapsed,
+    Expanded,
+    /// Not shown at all
+    Hidden,
+}
+
+impl ReasoningDisplay {
+    /// Fold or unfold the reasoning, it stays hidden if the config hides it
+    pub fn toggle(self) -> Self {
+        match self {
+            ReasoningDisplay::Collapsed => ReasoningDisplay::Expanded,
+            ReasoningDisplay::Expanded => ReasoningDisplay::Collapsed,
+            ReasoningDisplay::Hidden => ReasoningDisplay::Hidden,
+        }
+    }
+}
+
+/// The reasoning of an answer, never sent back to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reasoning {
+    /// The position of the answer in the chat
+    pub index: usize,
+    pub text: String,
+}
+
+pub fn find(reasoning: &[Reasoning], index: usize) -> Option<&Reasoning> {
+    reasoning.iter().find(|reasoning| reasoning.index == index)
+}
+
+/// The section shown above the answer, in the muted color of the theme
+pub fn format(
+    text: &str,
+    display: ReasoningDisplay,
+    streaming: bool,
+    color: Color,
+) -> Text<'static> {
+    let text = text.trim();
+    if text.is_empty() || display == ReasoningDisplay::Hidden {
+        return Text::default();
+    }
+
+    let style = Style::default().fg(color).italic();
+    let words = text.split_whitespace().count();
+    let header = if streaming {
+        format!("💭 Thinking… {} words", words)
+    } else {
+        format!("💭 Thought for {} words", words)
+    };
+
+    let mut lines = match display {
+        ReasoningDisplay::Expanded => {
+            let mut lines = vec![Line::styled(header, style)];
+            lines.extend(
+                text.lines()
+                    .map(|line| Line::styled(format!("│ {}", line), style)),
+            );
+            lines
+        }
+        _ => vec![Line::styled(format!("{} · z to unfold", header), style)],
+    };
+    lines.push(Line::raw(""));
+    Text::from(lines)
+}
+
+#[derive(Debug, Default)]
+enum State {
+    /// The answer may open with the tag
+    #[default]
+    Start,
+    Thinking,
+    /// The blank lines after the closing tag are skipped
+    AfterThinking,
+    Answer,
+}
+
+/// Split the `<think>` block opening the answers of the models served without a separate
+/// reasoning field, like DeepSeek-R1 on older servers. A tag split between two chunks is
+/// kept until its end arrives.
+#[derive(Debug, Default)]
+pub struct ThinkTags {
+    state: State,
+    pending: String,
+}
+
+impl ThinkTags {
+    pub fn split(&mut self, event: LLMAnswer) -> Vec<LLMAnswer> {
+        match event {
+            LLMAnswer::Answer(chunk) => self.feed(&chunk),
+            LLMAnswer::EndAnswer => {
+                let mut events = self.finish();
+                events.push(LLMAnswer::EndAnswer);
+                events
+            }
+            event => vec![event],
+        }
+    }
+
+    fn feed(&mut self, chunk: &str) -> Vec<LLMAnswer> {
+        self.pending.push_str(chunk);
+
+        let mut events = Vec::new();
+        loop {
+            match self.state {
+                State::Start => {
+                    let start = self.pending.trim_start();
+                    if let Some(rest) = start.strip_prefix(OPEN_TAG) {
+                        self.pending = rest.to_string();
+                        self.state = State::Thinking;
+                    } else if OPEN_TAG.starts_with(start) {
+                        return events;
+                    } else {
+                        self.state = State::Answer;
+                    }
+                }
+                State::Thinking => match self.pending.find(CLOSE_TAG) {
+                    Some(end) => {
+                        let rest = self.pending.split_off(end + CLOSE_TAG.len());
+                        self.pending.truncate(end);
+                        events.extend(self.take(LLMAnswer::Reasoning));
+                        self.pending = rest;
+                        self.state = State::AfterThinking;
+                    }
+                    None => {
+                        // The end may be the start of the closing tag
+                        let keep = (1..CLOSE_TAG.len())
+                            .rev()
+                            .find(|n| self.pending.ends_with(&CLOSE_TAG[..*n]))
+                            .unwrap_or(0);
+                        let rest = self.pending.split_off(self.pending.len() - keep);
+                        events.extend(self.take(LLMAnswer::Reasoning));
+                        self.pending = rest;
+                        return events;
+                    }
+                },
+                State::AfterThinking => {
+                    self.pending = self.pending.trim_start().to_string();
+                    if self.pending.is_empty() {
+                        return events;
+                    }
+                    self.state = State::Answer;
+                }
+                State::Answer => {
+                    events.extend(self.take(LLMAnswer::Answer));
+                    return events;
+                }
+            }
+        }
+    }
+
+    /// What is left once the answer ends
+    fn finish(&mut self) -> Vec<LLMAnswer> {
+        let events = match self.state {
+            State::Thinking => self.take(LLMAnswer::Reasoning),
+            State::AfterThinking => None,
+            State::Start | State::Answer => self.take(LLMAnswer::Answer),
+        };
+        self.pending.clear();
+        events.into_iter().collect()
+    }
+
+    fn take(&mut self, event: fn(String) -> LLMAnswer) -> Option<LLMAnswer> {
+        let text = std::mem::take(&mut self.pending);
+        (!text.is_empty()).then(|| event(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reasoning and the answer, once the chunks are split
+    fn split(chunks: &[&str]) -> (String, String) {
+        let mut tags = ThinkTags::default();
+        let mut events: Vec<LLMAnswer> = chunks
+            .iter()
+            .flat_map(|chunk| tags.split(LLMAnswer::Answer(chunk.to_string())))
+            .collect();
+        events.extend(tags.split(LLMAnswer::EndAnswer));
+
+        let (mut reasoning, mut answer) = (String::new(), String::new());
+        for event in events {
+            match event {
+                LLMAnswer::Reasoning(text) => reasoning.push_str(&text),
+                LLMAnswer::Answer(text) => answer.push_str(&text),
+                _ => {}
+            }
+        }
+        (reasoning, answer)
+    }
+
+    #[test]
+    fn think_block() {
+        assert_eq!(
+            split(&["<think>\nhmm</think>\n\nHello"]),
+            ("\nhmm".to_string(), "Hello".to_string())
+        );
+    }
+
+    #[test]
+    fn tags_split_between_chunks() {
+        assert_eq!(
+            split(&["\n<th", "ink>a b", "</thi", "nk>", "\n", "\nHel", "lo"]),
+            ("a b".to_string(), "Hello".to_string())
+        );
+    }
+
+    #[test]
+    fn answer_without_tags() {
+        assert_eq!(
+            split(&["<", "b>bold</b> <think>"]),
+            (String::new(), "<b>bold</b> <think>".to_string())
+        );
+    }
+
+    #[test]
+    fn stream_ending_while_thinking() {
+        assert_eq!(
+            split(&["<think>a</th"]),
+            ("a</th".to_string(), String::new())
+        );
+    }
+}
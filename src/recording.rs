@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+/// Timeline of a streamed answer: each chunk with the time it was received
+#[derive(Debug, Clone)]
+pub struct Recording {
+    start: Instant,
+    pub chunks: Vec<(Duration, String)>,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            chunks: Vec::new(),
+        }
+    }
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, chunk: &str) {
+        self.chunks.push((self.start.elapsed(), chunk.to_string()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.chunks.last().map(|(t, _)| *t).unwrap_or_default()
+    }
+
+    /// Time at which the first `n` chunks were received
+    pub fn time_at(&self, n: usize) -> Duration {
+        match n {
+            0 => Duration::ZERO,
+            n => self.chunks[n.min(self.chunks.len()) - 1].0,
+        }
+    }
+
+    /// The mean time between two chunks
+    pub fn inter_chunk(&self) -> Option<Duration> {
+        let (first, last) = (self.chunks.first()?.0, self.chunks.last()?.0);
+        let gaps = self.chunks.len().checked_sub(1).filter(|gaps| *gaps > 0)?;
+        Some((last - first) / gaps as u32)
+    }
+
+    /// Number of chunks received after `elapsed`
+    pub fn position_at(&self, elapsed: Duration) -> usize {
+        self.chunks.partition_point(|(t, _)| *t <= elapsed)
+    }
+
+    /// The answer as it was after the first `n` chunks
+    pub fn text_at(&self, n: usize) -> String {
+        self.chunks
+            .iter()
+            .take(n)
+            .map(|(_, chunk)| chunk.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(times: &[u64]) -> Recording {
+        Recording {
+            start: Instant::now(),
+            chunks: times
+                .iter()
+                .map(|ms| (Duration::from_millis(*ms), String::from("chunk ")))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn mean_time_between_the_chunks() {
+        // Counted from the first chunk, the wait for it is the time to the first token
+        assert_eq!(
+            recording(&[500, 600, 800, 1100]).inter_chunk(),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(recording(&[500]).inter_chunk(), None);
+        assert_eq!(recording(&[]).inter_chunk(), None);
+    }
+}
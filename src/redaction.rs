@@ -0,0 +1,303 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::AtomicBool, Arc, Mutex, OnceLock},
+};
+
+use async_trait::async_trait;
+use ratatui::text::Text;
+use regex::Regex;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    config::RedactionConfig,
+    event::Event,
+    llm::{LLMRole, LLM},
+    notification::{Notification, NotificationLevel},
+};
+
+#[derive(Clone)]
+pub struct Rule {
+    pub name: String,
+    pub pattern: Regex,
+    /// Replaces the matches in the messages sent
+    pub placeholder: String,
+}
+
+impl Rule {
+    fn new(name: &str, pattern: Regex, placeholder: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            pattern,
+            placeholder: placeholder
+                .unwrap_or_else(|| format!("[redacted {}]", name.to_lowercase())),
+        }
+    }
+}
+
+static DEFAULT_RULES: [(&str, &str); 10] = [
+    ("OpenAI api key", r"sk-[A-Za-z0-9_-]{20,}"),
+    ("AWS access key", r"\b(AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("GitHub token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+    ("Slack token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}"),
+    ("Private key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----"),
+    (
+        "JSON web token",
+        r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+    ),
+    ("Bearer token", r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]{20,}=*"),
+    (
+        "Password",
+        r"(?i)\b(password|passwd|pwd|secret|api_key|apikey)\s*[:=]\s*\S+",
+    ),
+    (
+        "Email address",
+        r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+    ),
+    (
+        "IP address",
+        r"\b(?:(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\b",
+    ),
+];
+
+/// Compiled once, they are used to scan the chat while it is drawn
+pub fn default_rules() -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        DEFAULT_RULES
+            .iter()
+            .map(|(name, pattern)| Rule::new(name, Regex::new(pattern).unwrap(), None))
+            .collect()
+    })
+}
+
+/// The rules applied to the messages before they are sent, none if the redaction is disabled
+pub fn outgoing_rules(config: &RedactionConfig) -> Result<Vec<Rule>, String> {
+    if !config.enabled {
+        return Ok(Vec::new());
+    }
+
+    let mut rules = if config.builtin_rules {
+        default_rules().to_vec()
+    } else {
+        Vec::new()
+    };
+    for rule in config.rules.iter() {
+        let pattern = Regex::new(&rule.pattern)
+            .map_err(|e| format!("Invalid redaction rule `{}`: {}", rule.name, e))?;
+        rules.push(Rule::new(&rule.name, pattern, rule.placeholder.clone()));
+    }
+    Ok(rules)
+}
+
+/// Replace the matches of the rules with their placeholders.
+/// Returns the names of the rules that matched, once per match.
+pub fn redact(text: &str, rules: &[Rule]) -> (String, Vec<String>) {
+    let mut text = text.to_string();
+    let mut redacted = Vec::new();
+
+    for rule in rules.iter() {
+        let matches = rule.pattern.find_iter(&text).count();
+        if matches > 0 {
+            text = rule
+                .pattern
+                .replace_all(&text, regex::NoExpand(&rule.placeholder))
+                .to_string();
+            redacted.extend(std::iter::repeat_n(rule.name.clone(), matches));
+        }
+    }
+
+    (text, redacted)
+}
+
+/// What was redacted, like `2 Email address, OpenAI api key`
+pub fn summary(redacted: &[String]) -> String {
+    let mut names: Vec<&String> = redacted.iter().collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .iter()
+        .map(|name| match redacted.iter().filter(|r| r == name).count() {
+            1 => name.to_string(),
+            count => format!("{} {}", count, name),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// A backend getting the messages once redacted, whatever put them in the conversation:
+/// the prompt, the output of the commands or the context of the files
+pub struct Redacted {
+    llm: Box<dyn LLM>,
+    rules: Vec<Rule>,
+    /// The messages already redacted in a request, not to warn about them at each request
+    reported: Mutex<HashSet<String>>,
+}
+
+impl Redacted {
+    pub fn new(llm: Box<dyn LLM>, rules: Vec<Rule>) -> Self {
+        Self {
+            llm,
+            rules,
+            reported: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl LLM for Redacted {
+    async fn ask_with(
+        &self,
+        mut messages: Vec<HashMap<String, String>>,
+        sender: UnboundedSender<Event>,
+        terminate_response_signal: Arc<AtomicBool>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut new_redactions = Vec::new();
+        for content in messages.iter_mut().filter_map(|m| m.get_mut("content")) {
+            let (text, redacted) = redact(content, &self.rules);
+            if redacted.is_empty() {
+                continue;
+            }
+            if let Ok(mut reported) = self.reported.lock() {
+                if reported.insert(std::mem::take(content)) {
+                    new_redactions.extend(redacted);
+                }
+            }
+            *content = text;
+        }
+
+        if !new_redactions.is_empty() {
+            let _ = sender.send(Event::Notification(Notification::new(
+                format!("Redacted before sending: {}", summary(&new_redactions)),
+                NotificationLevel::Warning,
+            )));
+        }
+
+        self.llm
+            .ask_with(messages, sender, terminate_response_signal)
+            .await
+    }
+
+    fn messages(&self) -> Vec<HashMap<String, String>> {
+        self.llm.messages()
+    }
+
+    fn set_messages(&mut self, messages: Vec<HashMap<String, String>>) {
+        self.llm.set_messages(messages);
+    }
+
+    fn append_chat_msg(&mut self, msg: String, role: LLMRole) {
+        self.llm.append_chat_msg(msg, role);
+    }
+
+    fn clear(&mut self) {
+        self.llm.clear();
+    }
+}
+
+/// A potential secret, located by its line in the formatted chat
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub rule: String,
+    pub line: usize,
+    pub excerpt: String,
+}
+
+/// Hide most of a secret so the report does not leak it
+fn mask(secret: &str) -> String {
+    let count = secret.chars().count();
+    let visible = count.min(4);
+    format!(
+        "{}{}",
+        secret.chars().take(visible).collect::<String>(),
+        "*".repeat(count.min(12) - visible)
+    )
+}
+
+pub fn scan(text: &Text, rules: &[Rule]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for (i, line) in text.lines.iter().enumerate() {
+        let content: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+
+        for rule in rules.iter() {
+            for m in rule.pattern.find_iter(&content) {
+                findings.push(Finding {
+                    rule: rule.name.clone(),
+                    line: i,
+                    excerpt: mask(m.as_str()),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{config::MockConfig, llm::LLMAnswer, mock::Mock};
+
+    #[test]
+    fn redact_the_secrets() {
+        let text = "Mail bob@example.com or alice@example.org, password: hunter2";
+        let (text, redacted) = redact(text, default_rules());
+        assert_eq!(
+            text,
+            "Mail [redacted email address] or [redacted email address], [redacted password]"
+        );
+        assert_eq!(summary(&redacted), "2 Email address, Password");
+
+        let (text, redacted) = redact("Nothing to hide", default_rules());
+        assert_eq!(text, "Nothing to hide");
+        assert!(redacted.is_empty());
+    }
+
+    #[tokio::test]
+    async fn redacted_before_the_backend() {
+        let config = MockConfig {
+            script: None,
+            delay: 0,
+            model: String::from("mock"),
+        };
+        let mut llm = Redacted::new(
+            Box::new(Mock::new(config, Default::default())),
+            default_rules().to_vec(),
+        );
+        // Like the output of a command
+        llm.append_chat_msg(
+            String::from("token sk-abcdefghijklmnopqrstuvwx"),
+            LLMRole::USER,
+        );
+
+        let (sender, mut receiver) = unbounded_channel();
+        for _ in 0..2 {
+            llm.ask(sender.clone(), Arc::new(AtomicBool::new(false)))
+                .await
+                .unwrap();
+        }
+        drop(sender);
+
+        let mut answers = String::new();
+        let mut warnings = 0;
+        while let Some(event) = receiver.recv().await {
+            match event {
+                Event::LLMEvent(LLMAnswer::Answer(chunk)) => answers.push_str(&chunk),
+                Event::Notification(_) => warnings += 1,
+                _ => {}
+            }
+        }
+        // The mock quotes the prompt
+        assert!(answers.contains("token [redacted openai api key]"));
+        assert!(!answers.contains("sk-"));
+        // Once for the message
+        assert_eq!(warnings, 1);
+    }
+}
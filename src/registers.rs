@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, List, ListItem, ListState,
+    },
+    Frame,
+};
+
+use crate::{app::FocusedBlock, i18n::t, theme::Theme};
+
+/// Named registers holding yanked texts, like the ones of vim: `"ay` yanks into `a`,
+/// `"Ay` appends to it and `"ap` pastes it
+#[derive(Debug, Default)]
+pub struct Registers {
+    texts: BTreeMap<char, String>,
+    /// The register chosen with `"` for the next yank or paste
+    pub selected: Option<char>,
+}
+
+impl Registers {
+    pub fn is_name(c: char) -> bool {
+        c.is_ascii_alphabetic()
+    }
+
+    /// Store the text, appended to the register if its name is uppercase
+    pub fn yank(&mut self, name: char, text: &str) {
+        let register = self.texts.entry(name.to_ascii_lowercase()).or_default();
+        if name.is_ascii_uppercase() && !register.is_empty() {
+            register.push('\n');
+            register.push_str(text);
+        } else {
+            *register = text.to_string();
+        }
+    }
+
+    pub fn get(&self, name: char) -> Option<&str> {
+        self.texts
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+}
+
+/// Pop-up listing the registers, the selected one is pasted in the prompt
+pub struct RegisterViewer {
+    registers: Vec<(char, String)>,
+    state: ListState,
+    pub previous_focus: FocusedBlock,
+}
+
+impl RegisterViewer {
+    pub fn new(registers: &Registers, previous_focus: FocusedBlock) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            registers: registers
+                .texts
+                .iter()
+                .map(|(name, text)| (*name, text.clone()))
+                .collect(),
+            state,
+            previous_focus,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.registers.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.state
+            .selected()
+            .and_then(|i| self.registers.get(i))
+            .map(|(_, text)| text.as_str())
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        // The first line of each text, its other lines are counted
+        let width = area.width.saturating_sub(8) as usize;
+        let items = self
+            .registers
+            .iter()
+            .map(|(name, text)| {
+                let mut lines = text.lines();
+                let first: String = lines
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(width)
+                    .collect();
+                let mut spans = vec![
+                    Span::styled(format!("\"{}  ", name), Style::default().bold()),
+                    Span::raw(first),
+                ];
+                let more = lines.count();
+                if more > 0 {
+                    spans.push(Span::styled(
+                        format!(" +{}", more),
+                        Style::default().fg(theme.muted),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(" {} ", t("Registers")))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .title(
+                        Title::from(format!(
+                            " {} ",
+                            t("Enter: paste in the prompt · Esc: close")
+                        ))
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
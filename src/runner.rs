@@ -0,0 +1,196 @@
+use std::{collections::HashMap, io::Write, path::Path, time::Duration};
+
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+
+use crate::{config::RunnerConfig, event::Event, shell};
+
+/// The environment variables the programs get besides the ones of the config,
+/// the others are not passed to them
+const ALLOWED_ENV: [&str; 10] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "TERM",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+];
+
+/// The language the interpreters are configured for, from the name after the fence
+fn language(name: &str) -> &str {
+    match name {
+        "py" | "python3" => "python",
+        "sh" | "shell" => "bash",
+        "rs" => "rust",
+        name => name,
+    }
+}
+
+/// The command running the code of the language, if one is configured
+pub fn interpreter<'a>(config: &'a RunnerConfig, name: &str) -> Option<&'a str> {
+    config
+        .interpreters
+        .get(language(&name.to_lowercase()))
+        .map(String::as_str)
+        .filter(|command| !command.trim().is_empty())
+}
+
+/// The command running the file of the code in the directory, with only the allowed
+/// environment variables and the variables of the chat
+fn command(
+    interpreter: &str,
+    file: &Path,
+    dir: &Path,
+    env: &[String],
+    variables: &HashMap<String, String>,
+) -> std::process::Command {
+    let mut words = interpreter.split_whitespace();
+    let mut command = std::process::Command::new(words.next().unwrap_or_default());
+    command.args(words).arg(file).current_dir(dir).env_clear();
+
+    for name in ALLOWED_ENV
+        .iter()
+        .copied()
+        .chain(env.iter().map(String::as_str))
+    {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+    command.envs(variables);
+    command
+}
+
+/// Run the code with the interpreter from a file of a new temporary directory, which is its
+/// working directory and is removed once it exits. Its output is streamed like the output of
+/// a shell command. Returns the sender stopping it.
+pub fn run(
+    code: String,
+    interpreter: String,
+    extension: String,
+    config: &RunnerConfig,
+    variables: HashMap<String, String>,
+    sender: UnboundedSender<Event>,
+) -> oneshot::Sender<()> {
+    let (stop, stopped) = oneshot::channel();
+    let timeout = (config.timeout > 0).then(|| Duration::from_secs(config.timeout));
+    let env = config.env.clone();
+
+    tokio::spawn(async move {
+        // Only readable by the user, the file is created in it and can not be a link
+        let prepared = tempfile::Builder::new()
+            .prefix("tenere-run-")
+            .tempdir()
+            .and_then(|dir| {
+                let file = dir.path().join(format!("snippet.{}", extension));
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&file)?
+                    .write_all(code.as_bytes())?;
+                Ok((dir, file))
+            });
+        let (dir, file) = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                let _ = sender.send(Event::ShellOutput(shell::ShellOutput::Exit(format!(
+                    "failed to start: {}",
+                    e
+                ))));
+                return;
+            }
+        };
+
+        let command = command(&interpreter, &file, dir.path(), &env, &variables);
+        shell::execute(command, timeout, stopped, sender).await;
+        drop(dir);
+    });
+    stop
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_of_the_fence() {
+        assert_eq!(language("py"), "python");
+        assert_eq!(language("sh"), "bash");
+        assert_eq!(language("rs"), "rust");
+        assert_eq!(language("ruby"), "ruby");
+
+        let mut config = RunnerConfig::default();
+        assert_eq!(interpreter(&config, "Python3"), Some("python3"));
+        assert_eq!(interpreter(&config, "shell"), Some("bash"));
+        assert_eq!(interpreter(&config, "ruby"), None);
+
+        config
+            .interpreters
+            .insert(String::from("ruby"), String::from(" "));
+        assert_eq!(interpreter(&config, "ruby"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn run_in_a_temporary_directory() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let _stop = run(
+            String::from("pwd"),
+            String::from("sh"),
+            String::from("sh"),
+            &RunnerConfig::default(),
+            HashMap::new(),
+            sender,
+        );
+
+        let mut lines = Vec::new();
+        while let Some(Event::ShellOutput(output)) = receiver.recv().await {
+            match output {
+                shell::ShellOutput::Line(line) => lines.push(line),
+                shell::ShellOutput::Exit(status) => {
+                    assert_eq!(status, "exited with status 0");
+                    break;
+                }
+            }
+        }
+
+        assert!(lines[0].contains("tenere-run-"), "{:?}", lines);
+        // Removed once the program exited
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!Path::new(&lines[0]).exists());
+    }
+
+    #[test]
+    fn only_the_allowed_environment() {
+        let command = command(
+            "python3 -u",
+            Path::new("/tmp/run/snippet.py"),
+            Path::new("/tmp/run"),
+            &[String::from("CARGO_HOME")],
+            &HashMap::from([(String::from("project"), String::from("tenere"))]),
+        );
+
+        assert_eq!(command.get_program(), "python3");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["-u", "/tmp/run/snippet.py"]
+        );
+        assert_eq!(command.get_current_dir(), Some(Path::new("/tmp/run")));
+        // Set from the allowed variables of the environment of the tests only, and from the
+        // variables of the chat
+        for (name, _) in command.get_envs() {
+            let name = name.to_str().unwrap();
+            assert!(
+                ALLOWED_ENV.contains(&name) || name == "CARGO_HOME" || name == "project",
+                "{}",
+                name
+            );
+        }
+        assert!(command
+            .get_envs()
+            .any(|(name, value)| name == "project" && value == Some("tenere".as_ref())));
+    }
+}
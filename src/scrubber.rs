@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, Gauge, Paragraph, Wrap,
+    },
+    Frame,
+};
+
+use crate::{
+    authors,
+    formatter::{Formatter, RenderProfile},
+    recording::Recording,
+    theme::Theme,
+};
+
+/// Replay how the answers of a chat were streamed
+pub struct Scrubber {
+    /// Index of the recording in the chat
+    pub recording: usize,
+    /// Number of chunks displayed
+    pub position: usize,
+    /// When the playback started and from which time
+    playing: Option<(Instant, Duration)>,
+}
+
+impl Scrubber {
+    pub fn new(recordings: &[Recording]) -> Self {
+        let recording = recordings.len().saturating_sub(1);
+        Self {
+            recording,
+            position: recordings.get(recording).map(|r| r.len()).unwrap_or(0),
+            playing: None,
+        }
+    }
+
+    pub fn step(&mut self, recordings: &[Recording], delta: isize) {
+        self.playing = None;
+        let len = recordings[self.recording].len();
+        self.position = self.position.saturating_add_signed(delta).min(len);
+    }
+
+    pub fn go_to(&mut self, position: usize) {
+        self.playing = None;
+        self.position = position;
+    }
+
+    /// Switch to the recording of another answer
+    pub fn switch(&mut self, recordings: &[Recording], delta: isize) {
+        self.playing = None;
+        self.recording = self
+            .recording
+            .saturating_add_signed(delta)
+            .min(recordings.len().saturating_sub(1));
+        self.position = recordings[self.recording].len();
+    }
+
+    pub fn toggle_play(&mut self, recordings: &[Recording]) {
+        let recording = &recordings[self.recording];
+        self.playing = match self.playing {
+            Some(_) => None,
+            None if self.position >= recording.len() => {
+                self.position = 0;
+                Some((Instant::now(), Duration::ZERO))
+            }
+            None => Some((Instant::now(), recording.time_at(self.position))),
+        };
+    }
+
+    /// Move forward while playing, returns whether it was playing
+    pub fn tick(&mut self, recordings: &[Recording]) -> bool {
+        let Some((started, offset)) = self.playing else {
+            return false;
+        };
+
+        let recording = &recordings[self.recording];
+        self.position = recording.position_at(offset + started.elapsed());
+        if self.position >= recording.len() {
+            self.playing = None;
+        }
+        true
+    }
+
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        recordings: &[Recording],
+        formatter: &Formatter,
+        profile: &RenderProfile,
+        theme: &Theme,
+    ) {
+        let recording = &recordings[self.recording];
+
+        let block = Block::default()
+            .title(format!(
+                " Replay of answer {}/{} ",
+                self.recording + 1,
+                recordings.len()
+            ))
+            .title_style(Style::default().bold())
+            .title_alignment(Alignment::Center)
+            .title(
+                Title::from(
+                    " h/l: step · H/L: 10 steps · Space: play · n/p: next/previous answer · Esc: quit ",
+                )
+                .position(Position::Bottom)
+                .alignment(Alignment::Center),
+            )
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme.focused_border));
+
+        let inner = block.inner(area);
+        let (text_area, gauge_area) = {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(1)])
+                .split(inner);
+            (chunks[0], chunks[1])
+        };
+
+        let text = formatter.format_answer(
+            &format!("{}{}", authors::ASSISTANT, recording.text_at(self.position)),
+            profile,
+        );
+        let height = text.height() as u16;
+
+        let answer = Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .scroll((height.saturating_sub(text_area.height), 0));
+
+        let ratio = if recording.is_empty() {
+            1.0
+        } else {
+            self.position as f64 / recording.len() as f64
+        };
+
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(theme.focused_border))
+            .ratio(ratio)
+            .label(format!(
+                "{:.2}s / {:.2}s · chunk {}/{}",
+                recording.time_at(self.position).as_secs_f64(),
+                recording.duration().as_secs_f64(),
+                self.position,
+                recording.len()
+            ));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(answer, text_area);
+        frame.render_widget(gauge, gauge_area);
+    }
+}
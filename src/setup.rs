@@ -0,0 +1,711 @@
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use clap::ArgMatches;
+use crossterm::{
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures::StreamExt;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
+    Frame, Terminal,
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    config::{ChatGPTConfig, Config, MockConfig},
+    llm::{message, LLMBackend, LLMModel, LLMRole},
+    theme::Theme,
+};
+
+const BACKENDS: [LLMBackend; 3] = [
+    LLMBackend::ChatGPT,
+    LLMBackend::LLamacpp,
+    LLMBackend::Ollama,
+];
+
+/// The test request gives up after this delay, a local model may be loading
+const TEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the api key of the backend is read from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeySource {
+    None,
+    Env,
+    Command,
+    Keyring,
+    Config,
+}
+
+impl KeySource {
+    fn label(self, backend: LLMBackend) -> String {
+        match self {
+            KeySource::None => String::from("No api key"),
+            KeySource::Env => format!("The {} environment variable", env_var(backend)),
+            KeySource::Command => {
+                String::from("A command printing the key, like `pass show openai`")
+            }
+            KeySource::Keyring => String::from("The system keyring"),
+            KeySource::Config => String::from("Written in the config file"),
+        }
+    }
+
+    fn question(self) -> &'static str {
+        match self {
+            KeySource::Command => "Command printing the api key",
+            KeySource::Keyring => "User name of the keyring entry, under the service `tenere`",
+            _ => "Api key",
+        }
+    }
+}
+
+fn env_var(backend: LLMBackend) -> &'static str {
+    match backend {
+        LLMBackend::LLamacpp => "LLAMACPP_API_KEY",
+        _ => "OPENAI_API_KEY",
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    Backend,
+    Url,
+    Model,
+    KeySource,
+    Key,
+    Test,
+}
+
+/// The answers given so far
+struct Setup {
+    first_run: bool,
+    path: PathBuf,
+    /// Where the config file already there is moved before the new one is written
+    backup: Option<PathBuf>,
+    step: Step,
+    backend: usize,
+    url: String,
+    model: String,
+    source: usize,
+    key: String,
+    /// The answer to the test request, or why it failed
+    test: Option<Result<String, String>>,
+    error: Option<String>,
+}
+
+impl Setup {
+    fn new(path: PathBuf, first_run: bool) -> Self {
+        let mut setup = Self {
+            first_run,
+            backup: path.exists().then(|| backup_path(&path)),
+            path,
+            step: Step::Backend,
+            backend: 0,
+            url: String::new(),
+            model: String::new(),
+            source: 0,
+            key: String::new(),
+            test: None,
+            error: None,
+        };
+        setup.select_backend(0);
+        setup
+    }
+
+    fn backend(&self) -> LLMBackend {
+        BACKENDS[self.backend]
+    }
+
+    fn select_backend(&mut self, i: usize) {
+        self.backend = i;
+        self.source = 0;
+        self.key.clear();
+        (self.url, self.model) = match self.backend() {
+            LLMBackend::ChatGPT => (ChatGPTConfig::default_url(), ChatGPTConfig::default_model()),
+            LLMBackend::LLamacpp => (
+                String::from("http://localhost:8080/v1/chat/completions"),
+                String::new(),
+            ),
+            LLMBackend::Ollama => (
+                String::from("http://localhost:11434/api/chat"),
+                String::from("llama3"),
+            ),
+            LLMBackend::Mock => (String::new(), MockConfig::default_model()),
+        };
+    }
+
+    fn sources(&self) -> Vec<KeySource> {
+        match self.backend() {
+            LLMBackend::ChatGPT => vec![
+                KeySource::Env,
+                KeySource::Command,
+                KeySource::Keyring,
+                KeySource::Config,
+            ],
+            LLMBackend::LLamacpp => vec![
+                KeySource::None,
+                KeySource::Env,
+                KeySource::Command,
+                KeySource::Keyring,
+                KeySource::Config,
+            ],
+            LLMBackend::Ollama | LLMBackend::Mock => Vec::new(),
+        }
+    }
+
+    fn source(&self) -> KeySource {
+        self.sources()
+            .get(self.source)
+            .copied()
+            .unwrap_or(KeySource::None)
+    }
+
+    /// The steps of the chosen backend
+    fn steps(&self) -> Vec<Step> {
+        let mut steps = vec![Step::Backend, Step::Url];
+        if self.backend() != LLMBackend::LLamacpp {
+            steps.push(Step::Model);
+        }
+        if self.backend() != LLMBackend::Ollama {
+            steps.push(Step::KeySource);
+            if matches!(
+                self.source(),
+                KeySource::Command | KeySource::Keyring | KeySource::Config
+            ) {
+                steps.push(Step::Key);
+            }
+        }
+        steps.push(Step::Test);
+        steps
+    }
+
+    fn position(&self) -> usize {
+        self.steps()
+            .iter()
+            .position(|step| *step == self.step)
+            .unwrap_or(0)
+    }
+
+    fn next(&mut self) {
+        let required = match self.step {
+            Step::Url => Some(&self.url),
+            Step::Key => Some(&self.key),
+            Step::Model if self.backend() == LLMBackend::Ollama => Some(&self.model),
+            _ => None,
+        };
+        if required.is_some_and(|answer| answer.trim().is_empty()) {
+            self.error = Some(String::from("The answer can not be empty"));
+            return;
+        }
+        // The default model of openai
+        if self.step == Step::Model && self.model.trim().is_empty() {
+            self.model = ChatGPTConfig::default_model();
+        }
+
+        self.error = None;
+        self.test = None;
+        if let Some(step) = self.steps().get(self.position() + 1) {
+            self.step = *step;
+        }
+    }
+
+    /// Returns false on the first step
+    fn back(&mut self) -> bool {
+        self.error = None;
+        self.test = None;
+        match self.position().checked_sub(1) {
+            Some(i) => {
+                self.step = self.steps()[i];
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The text being typed, on the steps that ask for one
+    fn input(&mut self) -> Option<&mut String> {
+        match self.step {
+            Step::Url => Some(&mut self.url),
+            Step::Model => Some(&mut self.model),
+            Step::Key => Some(&mut self.key),
+            _ => None,
+        }
+    }
+
+    fn select(&mut self, down: bool) {
+        let (selected, len) = match self.step {
+            Step::Backend => (self.backend, BACKENDS.len()),
+            Step::KeySource => (self.source, self.sources().len()),
+            _ => return,
+        };
+        let selected = if down {
+            (selected + 1).min(len.saturating_sub(1))
+        } else {
+            selected.saturating_sub(1)
+        };
+        match self.step {
+            Step::Backend => self.select_backend(selected),
+            _ => self.source = selected,
+        }
+    }
+
+    /// The config file written once the setup is done
+    fn toml(&self) -> String {
+        let string = |value: &str| toml::Value::String(value.trim().to_string()).to_string();
+        let backend = self.backend();
+
+        let mut lines = vec![
+            String::from("# Written by `tenere setup`, the other settings are in the README"),
+            format!("llm = {}", string(&backend.to_string())),
+            String::new(),
+            format!("[{}]", backend),
+            format!("url = {}", string(&self.url)),
+        ];
+        if backend != LLMBackend::LLamacpp {
+            lines.push(format!("model = {}", string(&self.model)));
+        }
+
+        let key_name = match backend {
+            LLMBackend::ChatGPT => "openai_api_key",
+            _ => "api_key",
+        };
+        match self.source() {
+            KeySource::Command => lines.push(format!("api_key_cmd = {}", string(&self.key))),
+            KeySource::Keyring => lines.push(format!("keyring = {}", string(&self.key))),
+            KeySource::Config => lines.push(format!("{} = {}", key_name, string(&self.key))),
+            KeySource::None | KeySource::Env => {}
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn config(&self) -> Result<Config, String> {
+        let config: Config = toml::from_str(&self.toml()).map_err(|e| e.to_string())?;
+        config.check()?;
+        if config.llm == LLMBackend::ChatGPT && config.chatgpt.api_key().is_none() {
+            return Err(match self.source() {
+                KeySource::Env => format!("{} is not set", env_var(LLMBackend::ChatGPT)),
+                _ => String::from("The api key can not be found"),
+            });
+        }
+        Ok(config)
+    }
+
+    fn render(&self, frame: &mut Frame, theme: &Theme) {
+        let area = frame.size();
+        let width = area.width.min(76);
+        let height = area.height.min(18);
+        let area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let muted = Style::default().fg(theme.muted);
+        let mut lines = Vec::new();
+        if self.first_run {
+            lines.push(Line::raw(
+                "Welcome to tenere! There is no config yet, let's write one.",
+            ));
+            lines.push(Line::raw(""));
+        }
+
+        let steps = self.steps();
+        let counter = format!("{}/{} ", self.position() + 1, steps.len());
+        let question = match self.step {
+            Step::Backend => "Which backend do you use?",
+            Step::Url => "Url of the chat completions endpoint",
+            Step::Model => "Model",
+            Step::KeySource => "Where is the api key?",
+            Step::Key => self.source().question(),
+            Step::Test => "Test request",
+        };
+        lines.push(Line::from(vec![counter.fg(theme.muted), question.bold()]));
+
+        let list = |items: Vec<String>, selected: usize| {
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    if i == selected {
+                        Line::styled(format!("> {}", item), Style::default().bg(theme.selection))
+                    } else {
+                        Line::raw(format!("  {}", item))
+                    }
+                })
+                .collect::<Vec<Line>>()
+        };
+
+        let hint = match self.step {
+            Step::Backend => {
+                lines.extend(list(
+                    BACKENDS.iter().map(|backend| backend.to_string()).collect(),
+                    self.backend,
+                ));
+                "j/k to choose, Enter to continue, Esc to quit"
+            }
+            Step::KeySource => {
+                let backend = self.backend();
+                lines.extend(list(
+                    self.sources()
+                        .iter()
+                        .map(|source| source.label(backend))
+                        .collect(),
+                    self.source,
+                ));
+                "j/k to choose, Enter to continue, Esc to go back"
+            }
+            Step::Url | Step::Model | Step::Key => {
+                let text = match self.step {
+                    Step::Url => self.url.clone(),
+                    Step::Model => self.model.clone(),
+                    // The key is not shown on the screen
+                    _ if self.source() == KeySource::Config => "•".repeat(self.key.chars().count()),
+                    _ => self.key.clone(),
+                };
+                lines.push(Line::raw(format!("> {}▏", text)));
+                "Enter to continue, Esc to go back"
+            }
+            Step::Test => {
+                match &self.test {
+                    None => lines.push(Line::styled("Waiting for the answer…", muted)),
+                    Some(Ok(answer)) => {
+                        lines.push(Line::styled(
+                            format!("✓ The backend answered: {}", answer),
+                            Style::default().fg(theme.info),
+                        ));
+                    }
+                    Some(Err(e)) => {
+                        lines.push(Line::styled(
+                            format!("✗ {}", e),
+                            Style::default().fg(theme.error),
+                        ));
+                    }
+                }
+                lines.push(Line::raw(""));
+                lines.push(Line::raw(format!(
+                    "The config is saved to {}",
+                    self.path.display()
+                )));
+                if let Some(backup) = &self.backup {
+                    lines.push(Line::raw(format!(
+                        "The current one is kept in {}",
+                        backup.display()
+                    )));
+                }
+                match &self.test {
+                    None => "Enter to save without waiting, Esc to cancel the test",
+                    Some(Err(_)) => "Enter to save anyway, Esc to go back",
+                    Some(Ok(_)) => "Enter to save, Esc to go back",
+                }
+            }
+        };
+
+        if let Some(e) = &self.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::styled(e.clone(), Style::default().fg(theme.error)));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(hint, muted));
+
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title(" Setup ")
+                .title_style(Style::default().bold())
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(theme.focused_border)),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+/// Send a short question with the config, to check the url, the model and the key
+async fn test(config: Config) -> Result<String, String> {
+    let backend = config.llm;
+    let llm = LLMModel::init(&backend, Arc::new(config)).await?;
+    let question = vec![message(LLMRole::USER, "Reply with OK")];
+
+    match tokio::time::timeout(TEST_TIMEOUT, llm.complete(question)).await {
+        Ok(Ok(answer)) => Ok(answer.lines().next().unwrap_or_default().trim().to_string()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!(
+            "No answer after {} seconds",
+            TEST_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+/// The config file used without `--config`
+fn config_path(matches: &ArgMatches) -> Option<PathBuf> {
+    matches
+        .get_one::<PathBuf>("config")
+        .cloned()
+        .or_else(Config::default_path)
+}
+
+/// The first start, when there is no config file and the default backend has no api key
+pub fn needed(matches: &ArgMatches) -> bool {
+    matches.subcommand().is_none()
+        && !matches.get_flag("headless")
+        && !matches.get_flag("plain")
+        && matches.get_one::<String>("backend").is_none()
+        && config_path(matches).is_some_and(|path| !path.exists())
+        && ChatGPTConfig::default().api_key().is_none()
+        && io::stdin().is_terminal()
+}
+
+/// `config.toml.bak`, or `config.toml.bak.N` for the first free N
+fn backup_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (0..)
+        .map(|i| match i {
+            0 => path.with_file_name(format!("{}.bak", name)),
+            i => path.with_file_name(format!("{}.bak.{}", name, i)),
+        })
+        .find(|backup| !backup.exists())
+        .unwrap_or_default()
+}
+
+/// Write the config, after moving the one already there to the backup.
+/// Only the user can read it, it may contain the api key.
+fn write(path: &Path, backup: Option<&Path>, content: &str) -> Result<(), String> {
+    use std::io::Write;
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    if let Some(backup) = backup.filter(|_| path.exists()) {
+        std::fs::rename(path, backup)
+            .map_err(|e| format!("Can not move `{}` aside: {}", path.display(), e))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| format!("Can not write `{}`: {}", path.display(), e))
+}
+
+/// Ask for the backend, the model and the api key, then write the config file.
+/// Returns the path of the file, `None` if the setup is cancelled.
+pub async fn run(matches: &ArgMatches) -> Result<Option<PathBuf>, String> {
+    let path = config_path(matches).ok_or("The config directory can not be found")?;
+    let first_run = matches.subcommand().is_none();
+
+    terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+    crossterm::execute!(io::stderr(), EnterAlternateScreen).map_err(|e| e.to_string())?;
+
+    let result = wizard(Setup::new(path, first_run)).await;
+
+    let _ = terminal::disable_raw_mode();
+    let _ = crossterm::execute!(io::stderr(), LeaveAlternateScreen);
+    result
+}
+
+async fn wizard(mut setup: Setup) -> Result<Option<PathBuf>, String> {
+    let mut terminal =
+        Terminal::new(CrosstermBackend::new(io::stderr())).map_err(|e| e.to_string())?;
+    let theme = Theme::default();
+    let mut events = EventStream::new();
+    // The test request, the keys are still read while it runs
+    let mut testing: Option<JoinHandle<Result<String, String>>> = None;
+
+    loop {
+        if setup.step != Step::Test {
+            if let Some(task) = testing.take() {
+                task.abort();
+            }
+        }
+
+        terminal
+            .draw(|frame| setup.render(frame, &theme))
+            .map_err(|e| e.to_string())?;
+
+        if setup.step == Step::Test && setup.test.is_none() && testing.is_none() {
+            match setup.config() {
+                Ok(config) => testing = Some(tokio::spawn(test(config))),
+                Err(e) => {
+                    setup.test = Some(Err(e));
+                    continue;
+                }
+            }
+        }
+
+        let event = match testing.as_mut() {
+            Some(task) => tokio::select! {
+                result = task => {
+                    testing = None;
+                    setup.test = Some(result.unwrap_or_else(|e| Err(e.to_string())));
+                    continue;
+                }
+                event = events.next() => event,
+            },
+            None => events.next().await,
+        };
+        let Event::Key(key) = event
+            .ok_or("The terminal is closed")?
+            .map_err(|e| e.to_string())?
+        else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL {
+            return Ok(None);
+        }
+
+        match handle_key(&mut setup, key) {
+            Action::Continue => {}
+            Action::Cancel => return Ok(None),
+            Action::Save => {
+                if let Some(task) = testing.take() {
+                    task.abort();
+                }
+                write(&setup.path, setup.backup.as_deref(), &setup.toml())?;
+                return Ok(Some(setup.path));
+            }
+        }
+    }
+}
+
+enum Action {
+    Continue,
+    Cancel,
+    Save,
+}
+
+fn handle_key(setup: &mut Setup, key: KeyEvent) -> Action {
+    match key.code {
+        KeyCode::Esc if !setup.back() => return Action::Cancel,
+        KeyCode::Enter if setup.step == Step::Test => return Action::Save,
+        KeyCode::Enter => setup.next(),
+        KeyCode::Backspace => {
+            if let Some(input) = setup.input() {
+                input.pop();
+            }
+        }
+        KeyCode::Char(c) => match setup.input() {
+            Some(input) => input.push(c),
+            None => match c {
+                'j' => setup.select(true),
+                'k' => setup.select(false),
+                _ => {}
+            },
+        },
+        KeyCode::Down => setup.select(true),
+        KeyCode::Up => setup.select(false),
+        _ => {}
+    }
+    Action::Continue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(backend: LLMBackend) -> Setup {
+        let mut setup = Setup::new(PathBuf::from("config.toml"), false);
+        let i = BACKENDS.iter().position(|b| *b == backend).unwrap();
+        setup.select_backend(i);
+        setup
+    }
+
+    #[test]
+    fn steps_of_the_backends() {
+        let mut chatgpt = setup(LLMBackend::ChatGPT);
+        assert_eq!(
+            chatgpt.steps(),
+            vec![
+                Step::Backend,
+                Step::Url,
+                Step::Model,
+                Step::KeySource,
+                Step::Test
+            ]
+        );
+        chatgpt.source = 1;
+        assert_eq!(chatgpt.source(), KeySource::Command);
+        assert!(chatgpt.steps().contains(&Step::Key));
+
+        let llamacpp = setup(LLMBackend::LLamacpp);
+        assert_eq!(
+            llamacpp.steps(),
+            vec![Step::Backend, Step::Url, Step::KeySource, Step::Test]
+        );
+
+        let ollama = setup(LLMBackend::Ollama);
+        assert_eq!(
+            ollama.steps(),
+            vec![Step::Backend, Step::Url, Step::Model, Step::Test]
+        );
+    }
+
+    #[test]
+    fn config_written() {
+        let mut chatgpt = setup(LLMBackend::ChatGPT);
+        chatgpt.source = 3;
+        chatgpt.key = String::from(" sk-\"quoted\" ");
+        assert_eq!(
+            chatgpt.toml(),
+            format!(
+                "# Written by `tenere setup`, the other settings are in the README\n\
+                llm = \"chatgpt\"\n\n[chatgpt]\nurl = \"{}\"\nmodel = \"{}\"\n\
+                openai_api_key = \"sk-\\\"quoted\\\"\"\n",
+                ChatGPTConfig::default_url(),
+                ChatGPTConfig::default_model()
+            )
+        );
+        let config = chatgpt.config().unwrap();
+        assert_eq!(
+            config.chatgpt.openai_api_key.as_deref(),
+            Some("sk-\"quoted\"")
+        );
+
+        let mut llamacpp = setup(LLMBackend::LLamacpp);
+        llamacpp.source = 1;
+        let config: Config = toml::from_str(&llamacpp.toml()).unwrap();
+        assert_eq!(config.llm, LLMBackend::LLamacpp);
+        assert!(!llamacpp.toml().contains("api_key"));
+    }
+
+    #[test]
+    fn existing_config_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::write(dir.path().join("config.toml.bak"), "older").unwrap();
+
+        let backup = backup_path(&path);
+        assert_eq!(backup, dir.path().join("config.toml.bak.1"));
+        write(&path, Some(&backup), "new").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "old");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::{fmt, time::Duration};
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::Text,
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap,
+    },
+    Frame,
+};
+use serde_json::json;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    authors::{self, Authors},
+    config::{api_key_from_cmd, MatrixConfig, NetworkConfig, ShareConfig, SlackConfig},
+    event::Event,
+    i18n::{fill, t},
+    network,
+    notification::{Notification, NotificationLevel},
+    redaction,
+    theme::Theme,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShareTarget {
+    Slack,
+    Matrix,
+}
+
+impl fmt::Display for ShareTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShareTarget::Slack => write!(f, "Slack"),
+            ShareTarget::Matrix => write!(f, "Matrix"),
+        }
+    }
+}
+
+/// A message or a code block of a chat
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub label: String,
+    pub content: String,
+    /// An answer, the last one is selected first
+    pub answer: bool,
+}
+
+fn label(prefix: &str, content: &str) -> String {
+    let first_line = content
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default()
+        .trim();
+
+    if first_line.chars().count() > 60 {
+        format!(
+            "{}{}…",
+            prefix,
+            first_line.chars().take(60).collect::<String>()
+        )
+    } else {
+        format!("{}{}", prefix, first_line)
+    }
+}
+
+/// The fenced code blocks of a message, with their language
+pub fn code_blocks(content: &str) -> Vec<(String, String)> {
+    let mut blocks = Vec::new();
+
+    let mut code_block: Option<(String, Vec<&str>)> = None;
+    for line in content.lines() {
+        match (line.trim_start().strip_prefix("```"), code_block.take()) {
+            (Some(lang), None) => code_block = Some((lang.trim().to_string(), Vec::new())),
+            (Some(_), Some((lang, lines))) => blocks.push((lang, lines.join("\n"))),
+            (None, Some((lang, mut lines))) => {
+                lines.push(line);
+                code_block = Some((lang, lines));
+            }
+            (None, None) => {}
+        }
+    }
+
+    blocks
+}
+
+/// The messages of the chat, each one followed by its code blocks
+pub fn snippets(plain_chat: &[String], authors: &Authors) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+
+    for message in plain_chat.iter() {
+        let (prefix, content, answer) = if let Some(question) = message.strip_prefix(authors::USER)
+        {
+            (format!("{} ", authors.user), question, false)
+        } else if let Some(answer) = message.strip_prefix(authors::ASSISTANT) {
+            (format!("{} ", authors.assistant), answer, true)
+        } else {
+            // The notes and the outputs of the commands keep their marker
+            let content = message
+                .strip_prefix("📝: ")
+                .or_else(|| message.strip_prefix("🔧: "))
+                .unwrap_or(message);
+            (message.chars().take(3).collect(), content, false)
+        };
+        let content = content.trim();
+
+        snippets.push(Snippet {
+            label: label(&prefix, content),
+            content: content.to_string(),
+            answer,
+        });
+
+        for (lang, code) in code_blocks(content) {
+            let lang = if lang.is_empty() {
+                "code".to_string()
+            } else {
+                lang
+            };
+            snippets.push(Snippet {
+                label: label(&format!("    {}: ", lang), &code),
+                content: code,
+                answer: false,
+            });
+        }
+    }
+
+    snippets
+}
+
+/// Percent encode a path segment, for the matrix room ids
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+async fn send_to_slack(
+    client: &reqwest::Client,
+    config: &SlackConfig,
+    content: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    client
+        .post(&config.webhook_url)
+        .json(&json!({ "text": content }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+async fn send_to_matrix(
+    client: &reqwest::Client,
+    config: &MatrixConfig,
+    content: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let access_token = config
+        .access_token
+        .clone()
+        .or_else(|| {
+            config
+                .access_token_cmd
+                .as_deref()
+                .and_then(api_key_from_cmd)
+        })
+        .ok_or("No access token for Matrix")?;
+
+    let txn_id = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis();
+
+    let url = format!(
+        "{}/_matrix/client/v3/rooms/{}/send/m.room.message/tenere{}",
+        config.homeserver.trim_end_matches('/'),
+        encode(&config.room_id),
+        txn_id
+    );
+
+    client
+        .put(url)
+        .bearer_auth(access_token)
+        .json(&json!({ "msgtype": "m.text", "body": content }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Send the snippet in the background and report the result as a notification
+pub fn send(
+    target: ShareTarget,
+    config: ShareConfig,
+    network: NetworkConfig,
+    content: String,
+    sender: UnboundedSender<Event>,
+) {
+    let client = network::builder(&network).and_then(|builder| {
+        Ok(builder
+            .timeout(Duration::from_secs(network.timeout))
+            .build()?)
+    });
+
+    let client = match client {
+        Ok(client) => client,
+        Err(e) => {
+            let notif = Notification::new(
+                format!("Can not send to {}: {}", target, e),
+                NotificationLevel::Error,
+            );
+            let _ = sender.send(Event::Notification(notif));
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        let result = match (target, &config.slack, &config.matrix) {
+            (ShareTarget::Slack, Some(slack), _) => send_to_slack(&client, slack, &content).await,
+            (ShareTarget::Matrix, _, Some(matrix)) => {
+                send_to_matrix(&client, matrix, &content).await
+            }
+            _ => Err(format!("{} is not configured", target).into()),
+        };
+
+        let notif = match result {
+            Ok(_) => Notification::new(format!("Sent to {}", target), NotificationLevel::Info),
+            Err(e) => Notification::new(
+                format!("Can not send to {}: {}", target, e),
+                NotificationLevel::Error,
+            ),
+        };
+        let _ = sender.send(Event::Notification(notif));
+    });
+}
+
+/// Pick a part of the chat and send it to a team chat
+pub struct Share {
+    pub snippets: Vec<Snippet>,
+    pub targets: Vec<ShareTarget>,
+    pub target: usize,
+    pub confirming: bool,
+    state: ListState,
+}
+
+impl Share {
+    pub fn new(plain_chat: &[String], config: &ShareConfig, authors: &Authors) -> Self {
+        let mut targets = Vec::new();
+        if config.slack.is_some() {
+            targets.push(ShareTarget::Slack);
+        }
+        if config.matrix.is_some() {
+            targets.push(ShareTarget::Matrix);
+        }
+
+        let snippets = snippets(plain_chat, authors);
+        let mut state = ListState::default();
+        // Select the last answer by default
+        state.select(snippets.iter().rposition(|s| s.answer).or(Some(0)));
+
+        Self {
+            snippets,
+            targets,
+            target: 0,
+            confirming: false,
+            state,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.snippets.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn next_target(&mut self) {
+        if !self.targets.is_empty() {
+            self.target = (self.target + 1) % self.targets.len();
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Snippet> {
+        self.state.selected().and_then(|i| self.snippets.get(i))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let target = self.targets[self.target];
+
+        let items = self
+            .snippets
+            .iter()
+            .map(|snippet| ListItem::new(snippet.label.clone()))
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(" {} ", fill("Share to {}", &[&target.to_string()])))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .title(
+                        Title::from(format!(
+                            " {} ",
+                            t("Enter: send · Tab: switch target · Esc: cancel")
+                        ))
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+
+        if !self.confirming {
+            return;
+        }
+
+        let Some(snippet) = self.selected() else {
+            return;
+        };
+
+        let findings = redaction::scan(
+            &Text::raw(snippet.content.as_str()),
+            redaction::default_rules(),
+        );
+
+        let mut message = fill(
+            "Send `{}` to {} ? (y/n)",
+            &[snippet.label.trim(), &target.to_string()],
+        );
+        if !findings.is_empty() {
+            message = format!(
+                "{}\n{}",
+                fill(
+                    "⚠ {} potential secrets found",
+                    &[&findings.len().to_string()]
+                ),
+                message
+            );
+        }
+
+        let confirmation = Paragraph::new(message)
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.warning)),
+            );
+
+        let confirmation_area = Rect {
+            x: area.x + area.width / 4,
+            y: area.y + (area.height / 2).saturating_sub(2),
+            width: area.width / 2,
+            height: 5,
+        };
+
+        frame.render_widget(Clear, confirmation_area);
+        frame.render_widget(confirmation, confirmation_area);
+    }
+}
@@ -0,0 +1,290 @@
+use std::{collections::HashMap, process::Stdio, time::Duration};
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap,
+    },
+    Frame,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Command,
+    sync::{mpsc::UnboundedSender, oneshot},
+};
+
+use crate::{authors, event::Event, i18n::t, share, theme::Theme};
+
+/// Lines after which the output is not added to the chat anymore
+pub const MAX_OUTPUT_LINES: usize = 500;
+
+#[derive(Debug, Clone)]
+pub enum ShellOutput {
+    Line(String),
+    Exit(String),
+}
+
+fn is_shell(lang: &str) -> bool {
+    matches!(
+        lang,
+        "sh" | "bash" | "zsh" | "fish" | "shell" | "console" | "powershell" | "cmd"
+    )
+}
+
+/// The shell code blocks proposed in the answers
+pub fn commands(plain_chat: &[String]) -> Vec<String> {
+    plain_chat
+        .iter()
+        .filter_map(|message| message.strip_prefix(authors::ASSISTANT))
+        .flat_map(share::code_blocks)
+        .filter(|(lang, _)| is_shell(lang))
+        .map(|(_, code)| {
+            // Drop the prompt sign of console blocks
+            code.lines()
+                .map(|line| line.strip_prefix("$ ").unwrap_or(line))
+                .collect::<Vec<&str>>()
+                .join("\n")
+        })
+        .filter(|code| !code.trim().is_empty())
+        .collect()
+}
+
+async fn forward_lines<R: AsyncRead + Unpin>(reader: R, sender: UnboundedSender<Event>) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if sender
+            .send(Event::ShellOutput(ShellOutput::Line(line)))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Run the command in a subshell, with the variables in its environment. Its output is
+/// streamed line by line. Returns the sender stopping it.
+pub fn run(
+    cmd: String,
+    variables: HashMap<String, String>,
+    sender: UnboundedSender<Event>,
+) -> oneshot::Sender<()> {
+    let (stop, stopped) = oneshot::channel();
+    tokio::spawn(async move {
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = std::process::Command::new("cmd");
+            command.args(["/C", &cmd]);
+            command
+        } else {
+            let mut command = std::process::Command::new("sh");
+            command.args(["-c", &cmd]);
+            command
+        };
+        command.envs(variables);
+        execute(command, None, stopped, sender).await;
+    });
+    stop
+}
+
+/// Kill the processes the command started too, in the group of the command
+#[cfg(unix)]
+fn kill_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        // The id of the group is the pid of the command
+        unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: Option<u32>) {}
+
+/// Stream the output of the command, then how it exited. It is killed after the timeout,
+/// or once `stop` receives, with the processes it started.
+pub async fn execute(
+    mut command: std::process::Command,
+    timeout: Option<Duration>,
+    stop: oneshot::Receiver<()>,
+    sender: UnboundedSender<Event>,
+) {
+    // A group of its own, to kill its children with it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let child = Command::from(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = sender.send(Event::ShellOutput(ShellOutput::Exit(format!(
+                "failed to start: {}",
+                e
+            ))));
+            return;
+        }
+    };
+
+    let pid = child.id();
+    let stdout = child.stdout.take().map(|stdout| {
+        let sender = sender.clone();
+        tokio::spawn(forward_lines(stdout, sender))
+    });
+    let stderr = child.stderr.take().map(|stderr| {
+        let sender = sender.clone();
+        tokio::spawn(forward_lines(stderr, sender))
+    });
+
+    let mut readers: Vec<_> = [stdout, stderr].into_iter().flatten().collect();
+
+    let run = async {
+        for reader in readers.iter_mut() {
+            let _ = reader.await;
+        }
+        child.wait().await
+    };
+    let killed = async {
+        match timeout {
+            Some(timeout) => {
+                tokio::time::sleep(timeout).await;
+                format!("was killed after {}s", timeout.as_secs())
+            }
+            None => std::future::pending().await,
+        }
+    };
+    let stopped = async {
+        // Without a sender left, the command goes on up to its end
+        if stop.await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        String::from("was stopped")
+    };
+
+    let result = tokio::select! {
+        status = run => Ok(status),
+        reason = killed => Err(reason),
+        reason = stopped => Err(reason),
+    };
+
+    let status = match result {
+        Ok(Ok(status)) => match status.code() {
+            Some(code) => format!("exited with status {}", code),
+            None => String::from("killed by a signal"),
+        },
+        Ok(Err(e)) => e.to_string(),
+        Err(reason) => {
+            kill_group(pid);
+            let _ = child.kill().await;
+            // No line is added to the output once it is closed
+            for reader in readers {
+                reader.abort();
+            }
+            reason
+        }
+    };
+
+    let _ = sender.send(Event::ShellOutput(ShellOutput::Exit(status)));
+}
+
+/// Pick a command proposed by the assistant and run it
+pub struct CommandRunner {
+    pub commands: Vec<String>,
+    pub confirming: bool,
+    state: ListState,
+}
+
+impl CommandRunner {
+    pub fn new(plain_chat: &[String]) -> Self {
+        let commands = commands(plain_chat);
+
+        // Select the last command by default
+        let mut state = ListState::default();
+        state.select(commands.len().checked_sub(1));
+
+        Self {
+            commands,
+            confirming: false,
+            state,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.commands.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&String> {
+        self.state.selected().and_then(|i| self.commands.get(i))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let items = self
+            .commands
+            .iter()
+            .map(|command| ListItem::new(command.as_str()))
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(" {} ", t("Run a command")))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .title(
+                        Title::from(format!(" {} ", t("Enter: run · Esc: cancel")))
+                            .position(Position::Bottom)
+                            .alignment(Alignment::Center),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+
+        if !self.confirming {
+            return;
+        }
+
+        let Some(command) = self.selected() else {
+            return;
+        };
+
+        let confirmation =
+            Paragraph::new(format!("{}\n\n{}", t("Run in a subshell ? (y/n)"), command))
+                .wrap(Wrap { trim: false })
+                .alignment(Alignment::Center)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(theme.warning)),
+                );
+
+        let height = (command.lines().count() as u16 + 4).min(area.height);
+        let confirmation_area = Rect {
+            x: area.x + area.width / 8,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width: area.width * 3 / 4,
+            height,
+        };
+
+        frame.render_widget(Clear, confirmation_area);
+        frame.render_widget(confirmation, confirmation_area);
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    widgets::{
+        block::{Position, Title},
+        Block, BorderType, Borders, Clear, List, ListItem, ListState,
+    },
+    Frame,
+};
+
+use crate::{
+    app::FocusedBlock, attachment::expand_home, config::SpellcheckConfig, i18n::t, theme::Theme,
+};
+
+/// The corrections offered for a word
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Endings tried on the words missing from the dictionary, since the affixes
+/// of the hunspell dictionaries are not applied
+const SUFFIXES: [&str; 10] = ["'s", "s", "es", "ed", "d", "ing", "ly", "er", "est", "ies"];
+
+/// The words of the dictionaries, in lowercase
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Read the dictionaries that exist, one word per line like `/usr/share/dict/words`,
+    /// or hunspell `.dic` files whose flags after `/` are dropped
+    pub fn load(config: &SpellcheckConfig) -> Result<Self, String> {
+        let mut words: HashSet<String> = config.words.iter().map(|w| w.to_lowercase()).collect();
+
+        let mut found = false;
+        for path in &config.dictionaries {
+            let Ok(content) = std::fs::read_to_string(expand_home(path)) else {
+                continue;
+            };
+            found = true;
+            let is_hunspell = path.ends_with(".dic");
+            for (i, line) in content.lines().enumerate() {
+                // The first line of a hunspell dictionary is the number of words
+                if is_hunspell && i == 0 {
+                    continue;
+                }
+                let word = line.split('/').next().unwrap_or_default().trim();
+                if !word.is_empty() {
+                    words.insert(word.to_lowercase());
+                }
+            }
+        }
+
+        if !found {
+            return Err(format!(
+                "No dictionary found for the spellcheck in {}",
+                config.dictionaries.join(", ")
+            ));
+        }
+        Ok(Self { words })
+    }
+
+    pub fn add(&mut self, word: &str) {
+        self.words.insert(word.to_lowercase());
+    }
+
+    pub fn knows(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        if self.words.contains(&word) {
+            return true;
+        }
+
+        SUFFIXES.iter().any(|suffix| {
+            word.strip_suffix(suffix)
+                .is_some_and(|stem| stem.chars().count() > 1 && self.knows_stem(stem))
+        })
+    }
+
+    /// making → make, studies → study, stopped → stop
+    fn knows_stem(&self, stem: &str) -> bool {
+        if self.words.contains(stem)
+            || self.words.contains(&format!("{}e", stem))
+            || self.words.contains(&format!("{}y", stem))
+        {
+            return true;
+        }
+
+        let mut last = stem.chars().rev();
+        match (last.next(), last.next()) {
+            (Some(a), Some(b)) if a == b => self.words.contains(&stem[..stem.len() - a.len_utf8()]),
+            _ => false,
+        }
+    }
+
+    /// The misspelled words of the text
+    pub fn misspelled<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        words(text)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .filter(|word| checked(word) && !self.knows(word))
+            .collect()
+    }
+
+    /// The closest words of the dictionary, with the capital of the word if it has one
+    pub fn suggestions(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let length = lower.chars().count();
+
+        let mut candidates: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .filter(|candidate| candidate.chars().count().abs_diff(length) <= 2)
+            .filter_map(|candidate| {
+                let distance = distance(&lower, candidate);
+                (distance <= 2).then_some((distance, candidate))
+            })
+            .collect();
+        candidates.sort();
+
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        candidates
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| {
+                if capitalized {
+                    let mut chars = candidate.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                } else {
+                    candidate.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+/// The byte ranges of the words of the text, letters with the apostrophes inside them
+pub fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let inside = c.is_alphabetic()
+            || (c == '\''
+                && start.is_some()
+                && chars.peek().is_some_and(|(_, n)| n.is_alphabetic()));
+        match (inside, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, text.len()));
+    }
+    words
+}
+
+/// Single letters, acronyms and identifiers like `camelCase` are not checked
+fn checked(word: &str) -> bool {
+    word.chars().count() > 1 && !word.chars().skip(1).any(char::is_uppercase)
+}
+
+/// The edits turning a word into the other, a swap of two letters counting as one
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Pop-up offering the corrections of the misspelled word under the cursor of the prompt
+pub struct Corrections {
+    pub word: String,
+    /// The row of the word and the range of its characters
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+    suggestions: Vec<String>,
+    state: ListState,
+    pub previous_focus: FocusedBlock,
+}
+
+impl Corrections {
+    pub fn new(
+        word: String,
+        (row, start, end): (usize, usize, usize),
+        suggestions: Vec<String>,
+        previous_focus: FocusedBlock,
+    ) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            word,
+            row,
+            start,
+            end,
+            suggestions,
+            state,
+            previous_focus,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let i = match self.state.selected() {
+            Some(i) if i + 1 < self.suggestions.len() => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    pub fn scroll_up(&mut self) {
+        let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(i));
+    }
+
+    pub fn selected(&self) -> Option<&String> {
+        self.state.selected().and_then(|i| self.suggestions.get(i))
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let items: Vec<ListItem> = if self.suggestions.is_empty() {
+            vec![ListItem::new(t("No suggestion")).style(Style::default().fg(theme.muted))]
+        } else {
+            self.suggestions
+                .iter()
+                .map(|suggestion| ListItem::new(suggestion.as_str()))
+                .collect()
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(" {} ", self.word))
+                    .title_style(Style::default().bold())
+                    .title_alignment(Alignment::Center)
+                    .title(
+                        Title::from(format!(
+                            " {} ",
+                            t("Enter: replace · a: add to the dictionary · Esc: close")
+                        ))
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Center),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.focused_border)),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misspelled_words() {
+        let dictionary = Dictionary {
+            words: ["the", "make", "study", "stop", "cat", "it"]
+                .iter()
+                .map(|w| w.to_string())
+                .collect(),
+        };
+
+        assert_eq!(
+            dictionary.misspelled("The cat's making it, studies stopped teh HTTP parseJson x"),
+            vec!["teh"]
+        );
+        assert_eq!(dictionary.suggestions("Teh"), vec!["The"]);
+        assert_eq!(distance("teh", "the"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,220 @@
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::Text,
+    widgets::{block::Title, Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    authors,
+    chat::{accent, format_chat, Chat},
+    formatter::Formatter,
+    i18n::t,
+    reasoning::Reasoning,
+    theme::Theme,
+    timestamp::Timestamp,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatLayout {
+    /// The whole conversation in one pane
+    #[default]
+    Stacked,
+    /// The questions on the left, the selected question and its answer on the right
+    Split,
+}
+
+impl ChatLayout {
+    pub fn toggle(self) -> Self {
+        match self {
+            ChatLayout::Stacked => ChatLayout::Split,
+            ChatLayout::Split => ChatLayout::Stacked,
+        }
+    }
+}
+
+/// The position of the questions in the messages of the chat
+fn questions(plain_chat: &[String]) -> Vec<usize> {
+    plain_chat
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.starts_with(authors::USER))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[derive(Default)]
+pub struct SplitView {
+    // None follows the last question
+    selected: Option<usize>,
+    state: ListState,
+    pub scroll: u16,
+    // The formatted pair is cached, highlighting it on each frame is too slow
+    cache: Option<((usize, usize, usize), Text<'static>)>,
+}
+
+impl SplitView {
+    pub fn scroll_down(&mut self, plain_chat: &[String]) {
+        let count = questions(plain_chat).len();
+        if let Some(i) = self.selected {
+            self.selected = if i + 1 >= count { None } else { Some(i + 1) };
+        }
+        self.scroll = 0;
+    }
+
+    pub fn scroll_up(&mut self, plain_chat: &[String]) {
+        let count = questions(plain_chat).len();
+        self.selected = match self.selected {
+            Some(i) => Some(i.saturating_sub(1)),
+            None => Some(count.saturating_sub(2)),
+        };
+        self.scroll = 0;
+    }
+
+    pub fn move_to_top(&mut self) {
+        self.selected = Some(0);
+        self.scroll = 0;
+    }
+
+    pub fn move_to_bottom(&mut self) {
+        self.selected = None;
+        self.scroll = 0;
+    }
+
+    /// Format the pair again, after a theme change for instance
+    pub fn invalidate(&mut self) {
+        self.cache = None;
+    }
+
+    /// The question and the messages following it, until the next question
+    fn pair(&mut self, chat: &Chat, index: usize, formatter: &Formatter) -> Text<'static> {
+        let questions = questions(&chat.plain_chat);
+        let start = questions[index];
+        let end = questions
+            .get(index + 1)
+            .copied()
+            .unwrap_or(chat.plain_chat.len());
+
+        let key = (chat.id, start, end);
+        if let Some((cached_key, text)) = &self.cache {
+            if *cached_key == key {
+                return text.clone();
+            }
+        }
+
+        // The positions of the messages start at the question
+        let timestamps: Vec<Timestamp> = chat
+            .timestamps
+            .iter()
+            .filter(|t| (start..end).contains(&t.index))
+            .map(|t| Timestamp {
+                index: t.index - start,
+                ..t.clone()
+            })
+            .collect();
+        let reasoning: Vec<Reasoning> = chat
+            .reasoning
+            .iter()
+            .filter(|r| (start..end).contains(&r.index))
+            .map(|r| Reasoning {
+                index: r.index - start,
+                ..r.clone()
+            })
+            .collect();
+
+        let text = format_chat(
+            &chat.plain_chat[start..end],
+            &timestamps,
+            &reasoning,
+            formatter,
+            &chat.render,
+        );
+        self.cache = Some((key, text.clone()));
+        text
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        chat: &Chat,
+        formatter: &Formatter,
+        focused: bool,
+        indicator: Option<String>,
+        theme: &Theme,
+    ) {
+        let (list_block, pair_block) = {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+                .split(area);
+            (chunks[0], chunks[1])
+        };
+
+        let questions = questions(&chat.plain_chat);
+        let selected = match self.selected {
+            Some(i) if i < questions.len() => Some(i),
+            _ => questions.len().checked_sub(1),
+        };
+        self.state.select(selected);
+
+        let items = questions
+            .iter()
+            .map(|&i| {
+                let question = chat.plain_chat[i].trim_start_matches(authors::USER);
+                ListItem::new(question.lines().next().unwrap_or_default().to_string())
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::RIGHT)
+                    .border_style(Style::default().fg(theme.border))
+                    .title(format!(" {} ", t("Questions")))
+                    .title_alignment(Alignment::Center)
+                    .title_style(if focused {
+                        Style::default().bold()
+                    } else {
+                        Style::default()
+                    }),
+            )
+            .highlight_style(Style::default().bg(theme.selection));
+
+        frame.render_stateful_widget(list, list_block, &mut self.state);
+
+        // The titles of the stacked chat, and whether an answer is on its way to the last
+        // question while another one is read
+        let mut block = Block::default().title_style(Style::default().fg(theme.muted));
+        if let Some(indicator) = indicator {
+            block = block.title(Title::from(indicator).alignment(Alignment::Left));
+        }
+        if chat.request.is_some() {
+            block = block
+                .title(Title::from(format!(" {} ", t("Answering…"))).alignment(Alignment::Right));
+        }
+
+        let Some(index) = selected else {
+            frame.render_widget(block, pair_block);
+            return;
+        };
+
+        let mut text = self.pair(chat, index, formatter);
+
+        // The answer being streamed and the output of a command belong to the last question
+        if index + 1 == questions.len() {
+            text.lines.extend(chat.tail().cloned());
+        }
+
+        let pair = Paragraph::new(accent(text, theme, &formatter.authors()))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .block(block);
+
+        frame.render_widget(pair, pair_block);
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::VecDeque;
+
+use reqwest::Response;
+
+use crate::{config::NetworkConfig, network};
+
+/// An event of a server-sent events stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum SseEvent {
+    /// The data lines of an event, joined by new lines
+    Data(String),
+    /// The `[DONE]` ending the streams of the OpenAI compatible APIs
+    Done,
+}
+
+/// Turn the chunks of a streamed response into items, whatever the way they are split
+pub trait StreamParser {
+    type Item;
+
+    fn feed(&mut self, chunk: &str) -> Vec<Self::Item>;
+
+    /// The items left once the stream ends
+    fn finish(&mut self) -> Vec<Self::Item>;
+}
+
+/// Decode the bytes of a stream, a character split between two chunks is kept until its end
+/// arrives. The invalid bytes are replaced with `�`.
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+
+        let mut text = String::new();
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    self.pending.clear();
+                    return text;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    text.push_str(&String::from_utf8_lossy(&self.pending[..valid]));
+                    match e.error_len() {
+                        // The end of the character is in the next chunk
+                        None => {
+                            self.pending.drain(..valid);
+                            return text;
+                        }
+                        Some(invalid) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid + invalid);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The incomplete character left when the stream ends
+    pub fn finish(&mut self) -> String {
+        let rest = String::from_utf8_lossy(&self.pending).to_string();
+        self.pending.clear();
+        rest
+    }
+}
+
+/// Split a stream in lines, a line split between two chunks is kept until its end arrives.
+/// The blank lines are skipped, as the json lines of ollama.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    buffer: String,
+}
+
+impl LineBuffer {
+    /// All the lines, the blank ones included
+    fn lines(&mut self, chunk: &str) -> Vec<String> {
+        self.buffer.push_str(chunk);
+        let Some(end) = self.buffer.rfind('\n') else {
+            return Vec::new();
+        };
+
+        let rest = self.buffer.split_off(end + 1);
+        std::mem::replace(&mut self.buffer, rest)
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    /// The last line, when the stream does not end with a new line
+    fn rest(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.buffer);
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+impl StreamParser for LineBuffer {
+    type Item = String;
+
+    fn feed(&mut self, chunk: &str) -> Vec<String> {
+        self.lines(chunk)
+            .into_iter()
+            .filter(|line| !line.trim().is_empty())
+            .collect()
+    }
+
+    fn finish(&mut self) -> Vec<String> {
+        self.rest()
+            .filter(|line| !line.trim().is_empty())
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Parse the server-sent events of the OpenAI compatible APIs.
+/// The comments, like the keep-alives, and the fields other than `data` are skipped.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    lines: LineBuffer,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        // An empty line ends the event
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        if field == "data" {
+            self.data
+                .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let data = std::mem::take(&mut self.data).join("\n");
+        if data.trim() == "[DONE]" {
+            Some(SseEvent::Done)
+        } else {
+            Some(SseEvent::Data(data))
+        }
+    }
+}
+
+impl StreamParser for SseParser {
+    type Item = SseEvent;
+
+    fn feed(&mut self, chunk: &str) -> Vec<SseEvent> {
+        self.lines
+            .lines(chunk)
+            .iter()
+            .filter_map(|line| self.line(line))
+            .collect()
+    }
+
+    fn finish(&mut self) -> Vec<SseEvent> {
+        let mut events: Vec<SseEvent> = self
+            .lines
+            .rest()
+            .and_then(|line| self.line(&line))
+            .into_iter()
+            .collect();
+        events.extend(self.dispatch());
+        events
+    }
+}
+
+/// The items of a streamed response, read as the backend asks for them.
+/// The answer is not read faster than its events are sent to the app.
+pub struct EventStream<P: StreamParser> {
+    response: Response,
+    network: NetworkConfig,
+    parser: P,
+    decoder: Utf8Decoder,
+    pending: VecDeque<P::Item>,
+    ended: bool,
+}
+
+impl<P: StreamParser> EventStream<P> {
+    pub fn new(response: Response, network: &NetworkConfig, parser: P) -> Self {
+        Self {
+            response,
+            network: network.clone(),
+            parser,
+            decoder: Utf8Decoder::default(),
+            pending: VecDeque::new(),
+            ended: false,
+        }
+    }
+
+    pub async fn next(&mut self) -> Result<Option<P::Item>, Box<dyn std::error::Error>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Ok(Some(item));
+            }
+            if self.ended {
+                return Ok(None);
+            }
+
+            match network::next_chunk(&mut self.response, &self.network).await? {
+                Some(chunk) => {
+                    let chunk = self.decoder.decode(&chunk);
+                    self.pending.extend(self.parser.feed(&chunk));
+                }
+                None => {
+                    self.ended = true;
+                    let rest = self.decoder.finish();
+                    self.pending.extend(self.parser.feed(&rest));
+                    self.pending.extend(self.parser.finish());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&str]) -> Vec<SseEvent> {
+        let mut parser = SseParser::default();
+        let mut events: Vec<SseEvent> =
+            chunks.iter().flat_map(|chunk| parser.feed(chunk)).collect();
+        events.extend(parser.finish());
+        events
+    }
+
+    fn data(data: &str) -> SseEvent {
+        SseEvent::Data(data.to_string())
+    }
+
+    #[test]
+    fn events() {
+        assert_eq!(
+            parse(&["data: {\"a\":1}\n\ndata: {\"a\":2}\n\ndata: [DONE]\n\n"]),
+            vec![data("{\"a\":1}"), data("{\"a\":2}"), SseEvent::Done]
+        );
+    }
+
+    #[test]
+    fn event_split_between_chunks() {
+        assert_eq!(
+            parse(&[
+                "da",
+                "ta: {\"content\":",
+                "\"hello\"}\n",
+                "\n",
+                "data: [DO",
+                "NE]\n\n"
+            ]),
+            vec![data("{\"content\":\"hello\"}"), SseEvent::Done]
+        );
+    }
+
+    #[test]
+    fn keep_alives_and_other_fields() {
+        assert_eq!(
+            parse(&[
+                ": keep-alive\n\n",
+                "event: message\nid: 3\nretry: 1000\ndata: x\n\n"
+            ]),
+            vec![data("x")]
+        );
+    }
+
+    #[test]
+    fn multiline_data() {
+        assert_eq!(parse(&["data: a\ndata:b\n\n"]), vec![data("a\nb")]);
+    }
+
+    #[test]
+    fn crlf() {
+        assert_eq!(
+            parse(&["data: a\r\n\r\ndata: [DONE]\r\n\r\n"]),
+            vec![data("a"), SseEvent::Done]
+        );
+    }
+
+    #[test]
+    fn stream_ending_without_blank_line() {
+        assert_eq!(parse(&["data: a\n\ndata: b"]), vec![data("a"), data("b")]);
+    }
+
+    /// Decode the text cut at every position
+    fn decode_split(text: &str) {
+        let bytes = text.as_bytes();
+        for cut in 0..=bytes.len() {
+            let mut decoder = Utf8Decoder::default();
+            let mut decoded = decoder.decode(&bytes[..cut]);
+            decoded.push_str(&decoder.decode(&bytes[cut..]));
+            decoded.push_str(&decoder.finish());
+            assert_eq!(decoded, text, "cut at {}", cut);
+        }
+    }
+
+    #[test]
+    fn emoji_split_between_chunks() {
+        decode_split("ok 👍🏽 done 🤖");
+    }
+
+    #[test]
+    fn cjk_split_between_chunks() {
+        decode_split("你好，世界 こんにちは 안녕하세요");
+    }
+
+    #[test]
+    fn utf8_byte_by_byte() {
+        let text = "data: {\"content\":\"日本語 🎉\"}\n\n";
+        let mut decoder = Utf8Decoder::default();
+        let mut parser = SseParser::default();
+        let events: Vec<SseEvent> = text
+            .as_bytes()
+            .iter()
+            .flat_map(|byte| parser.feed(&decoder.decode(&[*byte])))
+            .collect();
+        assert_eq!(events, vec![data("{\"content\":\"日本語 🎉\"}")]);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut decoder = Utf8Decoder::default();
+        assert_eq!(decoder.decode(b"a\xffb\xe6\x97"), "a\u{fffd}b");
+        // The stream ends in the middle of a character
+        assert_eq!(decoder.finish(), "\u{fffd}");
+    }
+
+    #[test]
+    fn json_lines() {
+        let mut lines = LineBuffer::default();
+        let mut items = lines.feed("{\"a\":1}\n{\"a\"");
+        items.extend(lines.feed(":2}\n\n{\"a\":3}"));
+        items.extend(lines.finish());
+        assert_eq!(items, vec!["{\"a\":1}", "{\"a\":2}", "{\"a\":3}"]);
+    }
+}
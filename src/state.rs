@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+use crate::split::ChatLayout;
+
+/// UI preferences changed at runtime and restored on the next start
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct State {
+    pub theme: Option<String>,
+    pub layout: Option<ChatLayout>,
+}
+
+impl State {
+    fn path() -> Option<PathBuf> {
+        paths::state_dir().map(|dir| dir.join("state.toml"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|state| toml::from_str(&state).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("Can not find the state directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
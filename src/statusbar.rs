@@ -0,0 +1,142 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::{
+    app::{App, FocusedBlock},
+    prompt::Mode,
+    queue,
+};
+
+/// The mode of the prompt, with the color of its border
+fn mode(app: &App) -> (&'static str, Color) {
+    if app.focused_block != FocusedBlock::Prompt {
+        return ("NORMAL", app.theme.border);
+    }
+    match app.prompt.mode {
+        Mode::Normal => ("NORMAL", app.theme.border),
+        Mode::Insert => ("INSERT", app.theme.focused_border),
+        Mode::Visual => ("VISUAL", app.theme.visual_border),
+    }
+}
+
+/// The first key of a chord like `gg` or `dd`, waiting for the second one,
+/// after the register chosen with `"`
+fn pending_keys(app: &App) -> Option<String> {
+    if app.focused_block == FocusedBlock::Prompt && app.prompt.mode == Mode::Insert {
+        return None;
+    }
+    let register = app.registers.selected.map(|name| format!("\"{}", name));
+    let key = match app.previous_key {
+        KeyCode::Char(c) if matches!(c, 'g' | 'd' | 'c' | '"') => Some(c.to_string()),
+        KeyCode::Char('z') if app.focused_block == FocusedBlock::Prompt => Some(String::from("z")),
+        _ => None,
+    };
+    match (register, key) {
+        (None, None) => None,
+        (register, key) => Some(format!(
+            "{}{}",
+            register.unwrap_or_default(),
+            key.unwrap_or_default()
+        )),
+    }
+}
+
+/// How the backend was chosen: by hand, or the fastest one with its latency
+fn backend_selection(app: &App) -> Option<String> {
+    if app.backend_override.is_some() {
+        return Some(String::from(" · manual"));
+    }
+
+    if !app.config.auto_select.enabled {
+        return None;
+    }
+
+    let status = match app.latencies.iter().find(|s| s.backend == app.backend) {
+        Some(status) => match status.latency {
+            Some(latency) => format!("{}ms", latency.as_millis()),
+            None => String::from("unreachable"),
+        },
+        None => String::from("measuring…"),
+    };
+    Some(format!(" · ⚡ {}", status))
+}
+
+/// The tokens reported by the backend, or an estimation when it does not report them
+fn tokens(app: &App) -> String {
+    let total = app.chat.usage.total_tokens();
+    if total > 0 {
+        return format!("{} tokens", total);
+    }
+
+    format!("~{} tokens", app.chat.estimated_tokens())
+}
+
+/// One line at the bottom of the screen, like the statusline of vim
+pub fn render(app: &App, frame: &mut Frame, area: Rect) {
+    let streaming = app.spinner.active || !app.chat.answer.plain_answer().is_empty();
+
+    let (mode, color) = mode(app);
+
+    let mut left = vec![
+        Span::styled(
+            format!(" {} ", mode),
+            // Reversed, the label stays readable when the color is the one of the terminal
+            Style::default().fg(color).reversed().bold(),
+        ),
+        Span::raw(" "),
+        Span::raw(format!(
+            "{} · {}",
+            app.backend,
+            app.config.backend_model_name(&app.backend)
+        )),
+    ];
+    if let Some(selection) = backend_selection(app) {
+        left.push(Span::styled(
+            selection,
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+    if let Some((waiting, remaining)) = queue::status() {
+        left.push(Span::styled(
+            format!(
+                " · ⏳ {} queued, {}s left",
+                waiting,
+                remaining.as_secs_f64().ceil()
+            ),
+            Style::default().fg(app.theme.warning),
+        ));
+    } else if streaming {
+        left.push(Span::styled(
+            " · ● streaming",
+            Style::default().fg(app.theme.warning),
+        ));
+    }
+    if let Some(pipeline) = &app.pipeline {
+        let (current, total) = pipeline.progress();
+        left.push(Span::styled(
+            format!(" · ⛓ {} {}/{}", pipeline.name, current, total),
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+
+    let mut right = Vec::new();
+    if let Some(key) = pending_keys(app) {
+        right.push(Span::styled(
+            format!("{}  ", key),
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+    right.push(Span::raw(format!("{} ", tokens(app))));
+
+    frame.render_widget(Paragraph::new(Line::from(left)), area);
+    frame.render_widget(
+        Paragraph::new(Line::from(right)).alignment(Alignment::Right),
+        area,
+    );
+}
@@ -0,0 +1,136 @@
+use ratatui::text::Text;
+
+use crate::formatter::{Formatter, RenderProfile};
+
+pub fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// The position after the last blank line outside of a code block.
+/// The markdown before it is highlighted the same way whatever follows.
+fn stable_split(message: &str) -> usize {
+    let mut in_fence = false;
+    let mut split = 0;
+    let mut offset = 0;
+
+    for line in message.split_inclusive('\n') {
+        offset += line.len();
+        // The last line may still grow
+        if !line.ends_with('\n') {
+            break;
+        }
+
+        if is_fence(line) {
+            in_fence = !in_fence;
+        } else if !in_fence && line.trim().is_empty() {
+            split = offset;
+        }
+    }
+
+    split
+}
+
+/// Close the code block left open by a partial answer, for display only
+fn close_fences(tail: &str) -> String {
+    let mut lines: Vec<&str> = tail.split_inclusive('\n').collect();
+
+    // A fence being streamed, like "``" or "```ru", would open a block too early
+    if let Some(last) = lines.last() {
+        let trimmed = last.trim_start();
+        let backticks = trimmed.chars().take_while(|c| *c == '`').count();
+        if !last.ends_with('\n')
+            && (1..=3).contains(&backticks)
+            && trimmed[backticks..]
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '+' || c == '-' || c == '_')
+        {
+            lines.pop();
+        }
+    }
+
+    let open = lines.iter().filter(|line| is_fence(line)).count() % 2 == 1;
+
+    let mut text = lines.concat();
+    if open {
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str("```");
+    }
+    text
+}
+
+/// Highlight an answer while it is streamed. The part of the message that can not
+/// change anymore is highlighted once, only the rest is highlighted on each chunk.
+#[derive(Debug, Clone, Default)]
+pub struct StreamingRenderer {
+    stable_len: usize,
+    stable: Text<'static>,
+}
+
+impl StreamingRenderer {
+    pub fn format(
+        &mut self,
+        message: &str,
+        formatter: &Formatter,
+        profile: &RenderProfile,
+    ) -> Text<'static> {
+        let split = stable_split(message);
+        // Only the part that became stable is highlighted, it starts after a blank line
+        // outside of a code block so it does not depend on what is before
+        if split > self.stable_len {
+            let stable = formatter.format_answer(&message[self.stable_len..split], profile);
+            self.stable.extend(stable);
+            self.stable_len = split;
+        }
+
+        let mut text = self.stable.clone();
+        let tail = &message[self.stable_len..];
+        if !tail.is_empty() {
+            text.extend(formatter.format_answer(&close_fences(tail), profile));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_after_the_last_blank_line() {
+        assert_eq!(stable_split("Hello"), 0);
+        assert_eq!(stable_split("Hello\n\nWorld"), 7);
+        // The blank lines of a code block do not count
+        assert_eq!(stable_split("Code:\n\n```\na\n\nb"), 7);
+        assert_eq!(stable_split("```\na\n\nb\n```\n\nDone"), 14);
+        // The last line may still become a fence
+        assert_eq!(stable_split("Hello\n\n"), 7);
+    }
+
+    #[test]
+    fn close_the_open_fences() {
+        assert_eq!(close_fences("Text"), "Text");
+        assert_eq!(close_fences("```rust\nfn main"), "```rust\nfn main\n```");
+        assert_eq!(close_fences("```\na\n```\n"), "```\na\n```\n");
+        // A fence being streamed is left out
+        assert_eq!(close_fences("Code:\n``"), "Code:\n");
+        assert_eq!(close_fences("Code:\n```ru"), "Code:\n");
+    }
+
+    #[test]
+    fn same_lines_as_the_whole_message() {
+        let formatter = Formatter::new();
+        let profile = RenderProfile::default();
+        let message = "Intro\n\n```rust\nfn main() {}\n\n```\n\nDone\n\nEnd";
+
+        let mut renderer = StreamingRenderer::default();
+        let mut text = Text::default();
+        for end in (1..=message.len()).filter(|end| message.is_char_boundary(*end)) {
+            text = renderer.format(&message[..end], &formatter, &profile);
+        }
+
+        assert_eq!(text, formatter.format_answer(message, &profile));
+    }
+}
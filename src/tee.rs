@@ -0,0 +1,72 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use chrono::Local;
+
+use crate::attachment::expand_home;
+
+/// Appends the messages to a file as they arrive, the chunks of the answers included,
+/// so that the session is kept even if tenere is killed in the middle of an answer
+pub struct Tee {
+    pub path: PathBuf,
+    file: File,
+    /// The label of the answer being streamed is written
+    answering: bool,
+}
+
+impl Tee {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let path = expand_home(path);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Can not open `{}`: {}", path.display(), e))?;
+
+        // Each write goes straight to the file, nothing waits in a buffer
+        writeln!(
+            file,
+            "--- {} ---\n",
+            Local::now().format("%Y-%m-%d %H:%M:%S")
+        )
+        .map_err(|e| format!("Can not write to `{}`: {}", path.display(), e))?;
+
+        Ok(Self {
+            path,
+            file,
+            answering: false,
+        })
+    }
+
+    fn write(&mut self, text: &str) {
+        if let Err(e) = self.file.write_all(text.as_bytes()) {
+            tracing::warn!("can not write to {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// A question, with the label of the user
+    pub fn question(&mut self, question: &str) {
+        self.write(&format!("{}\n", question.trim_end()));
+    }
+
+    /// A chunk of the answer, after the label of the assistant for the first one
+    pub fn chunk(&mut self, label: &str, chunk: &str) {
+        if !self.answering {
+            self.answering = true;
+            self.write(&format!("{} ", label));
+        }
+        self.write(chunk);
+    }
+
+    /// The end of the answer, or the reason it has none
+    pub fn end(&mut self, error: Option<&str>) {
+        if let Some(error) = error {
+            self.write(&format!("\n[error: {}]", error));
+        }
+        self.write("\n\n");
+        self.answering = false;
+    }
+}
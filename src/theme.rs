@@ -0,0 +1,126 @@
+use std::str::FromStr;
+
+use ratatui::style::Color;
+
+use crate::config::ThemeColors;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub bat_theme: &'static str,
+    pub focused_border: Color,
+    pub visual_border: Color,
+    pub selection: Color,
+    pub info: Color,
+    pub warning: Color,
+    pub error: Color,
+    pub border: Color,
+    pub muted: Color,
+    pub user: Color,
+    pub assistant: Color,
+}
+
+pub static THEMES: &[Theme] = &[
+    Theme {
+        name: "dark",
+        bat_theme: "Monokai Extended",
+        focused_border: Color::Green,
+        visual_border: Color::Yellow,
+        selection: Color::DarkGray,
+        info: Color::Green,
+        warning: Color::Yellow,
+        error: Color::Red,
+        border: Color::Reset,
+        muted: Color::DarkGray,
+        user: Color::Cyan,
+        assistant: Color::Green,
+    },
+    Theme {
+        name: "light",
+        bat_theme: "Monokai Extended Light",
+        focused_border: Color::Blue,
+        visual_border: Color::Magenta,
+        selection: Color::Gray,
+        info: Color::Blue,
+        warning: Color::Magenta,
+        error: Color::Red,
+        border: Color::Reset,
+        muted: Color::DarkGray,
+        user: Color::Blue,
+        assistant: Color::Magenta,
+    },
+    Theme {
+        name: "gruvbox-dark",
+        bat_theme: "gruvbox-dark",
+        focused_border: Color::Rgb(184, 187, 38),
+        visual_border: Color::Rgb(250, 189, 47),
+        selection: Color::Rgb(80, 73, 69),
+        info: Color::Rgb(184, 187, 38),
+        warning: Color::Rgb(250, 189, 47),
+        error: Color::Rgb(251, 73, 52),
+        border: Color::Rgb(146, 131, 116),
+        muted: Color::Rgb(146, 131, 116),
+        user: Color::Rgb(131, 165, 152),
+        assistant: Color::Rgb(184, 187, 38),
+    },
+    Theme {
+        name: "gruvbox-light",
+        bat_theme: "gruvbox-light",
+        focused_border: Color::Rgb(121, 116, 14),
+        visual_border: Color::Rgb(181, 118, 20),
+        selection: Color::Rgb(213, 196, 161),
+        info: Color::Rgb(121, 116, 14),
+        warning: Color::Rgb(181, 118, 20),
+        error: Color::Rgb(157, 0, 6),
+        border: Color::Rgb(124, 111, 100),
+        muted: Color::Rgb(124, 111, 100),
+        user: Color::Rgb(7, 102, 120),
+        assistant: Color::Rgb(121, 116, 14),
+    },
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        THEMES[0]
+    }
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Self> {
+        THEMES.iter().find(|theme| theme.name == name).copied()
+    }
+
+    /// Replace the colors defined in the `[theme.colors]` section of the config,
+    /// or tell the first one that is not valid
+    pub fn with_overrides(mut self, colors: &ThemeColors) -> Result<Self, String> {
+        let overrides = [
+            (&colors.focused_border, &mut self.focused_border),
+            (&colors.visual_border, &mut self.visual_border),
+            (&colors.selection, &mut self.selection),
+            (&colors.info, &mut self.info),
+            (&colors.warning, &mut self.warning),
+            (&colors.error, &mut self.error),
+            (&colors.border, &mut self.border),
+            (&colors.muted, &mut self.muted),
+            (&colors.user, &mut self.user),
+            (&colors.assistant, &mut self.assistant),
+        ];
+
+        for (value, color) in overrides {
+            if let Some(value) = value {
+                *color = Color::from_str(value)
+                    .map_err(|_| format!("`{}` is not a valid color", value))?;
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn next(&self) -> Self {
+        let index = THEMES
+            .iter()
+            .position(|theme| theme.name == self.name)
+            .unwrap_or(0);
+        THEMES[(index + 1) % THEMES.len()]
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Local};
+use ratatui::{
+    style::{Color, Style},
+    text::Line,
+};
+use serde::{Deserialize, Serialize};
+
+/// When a message was added to the chat, and how long its answer took
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timestamp {
+    /// The position of the message in the chat
+    pub index: usize,
+    pub time: DateTime<Local>,
+    /// Seconds spent streaming the answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Tokens of the answer, reported by the backend or estimated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u64>,
+    /// Tokens of the question and its context, when the backend reports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    /// The model that answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Seconds between the request and the first token of the answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_token: Option<f64>,
+    /// Mean seconds between two chunks of the answer, a chunk has one token or more
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inter_chunk: Option<f64>,
+}
+
+impl Timestamp {
+    pub fn now(index: usize) -> Self {
+        Self {
+            index,
+            time: Local::now(),
+            duration: None,
+            tokens: None,
+            prompt_tokens: None,
+            model: None,
+            first_token: None,
+            inter_chunk: None,
+        }
+    }
+
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        match (self.tokens, self.duration) {
+            (Some(tokens), Some(duration)) if duration > 0.0 => Some(tokens as f64 / duration),
+            _ => None,
+        }
+    }
+
+    /// Like `14:03:05 · 4.2s · 38 tokens/s`, with the date for the exports
+    pub fn describe(&self, with_date: bool) -> String {
+        let mut parts = vec![if with_date {
+            self.time.format("%Y-%m-%d %H:%M:%S").to_string()
+        } else {
+            self.time.format("%H:%M:%S").to_string()
+        }];
+
+        if let Some(duration) = self.duration {
+            parts.push(format!("{:.1}s", duration));
+        }
+        if let Some(speed) = self.tokens_per_second() {
+            parts.push(format!("{:.0} tokens/s", speed));
+        }
+
+        parts.join(" · ")
+    }
+
+    /// The line shown under the answers, in the muted color of the theme
+    pub fn line(&self, color: Color) -> Line<'static> {
+        Line::styled(
+            format!("🕑 {}", self.describe(false)),
+            Style::default().fg(color),
+        )
+    }
+}
+
+pub fn find(timestamps: &[Timestamp], index: usize) -> Option<&Timestamp> {
+    timestamps.iter().find(|t| t.index == index)
+}
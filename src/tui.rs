@@ -1,7 +1,10 @@
 use crate::app::{App, AppResult};
 use crate::event::EventHandler;
 use crate::ui;
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::event::{
+    DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+    EnableFocusChange, EnableMouseCapture,
+};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::Backend;
 use ratatui::Terminal;
@@ -20,8 +23,7 @@ impl<B: Backend> Tui<B> {
     }
 
     pub fn init(&mut self) -> AppResult<()> {
-        terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+        Self::enter()?;
 
         let panic_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic| {
@@ -39,12 +41,57 @@ impl<B: Backend> Tui<B> {
         Ok(())
     }
 
+    fn enter() -> AppResult<()> {
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(
+            io::stderr(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste,
+            EnableFocusChange
+        )?;
+        Ok(())
+    }
+
     fn reset() -> AppResult<()> {
         terminal::disable_raw_mode()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste,
+            DisableFocusChange
+        )?;
         Ok(())
     }
 
+    /// Give the terminal back, to run an interactive program
+    pub async fn suspend(&mut self) -> AppResult<()> {
+        self.events.pause().await;
+        Self::reset()?;
+        self.terminal.show_cursor()?;
+        Ok(())
+    }
+
+    pub fn resume(&mut self) -> AppResult<()> {
+        Self::enter()?;
+        self.terminal.hide_cursor()?;
+        self.terminal.clear()?;
+        self.events.resume();
+        Ok(())
+    }
+
+    /// Stop the process with the terminal restored, the TUI is back once it is continued
+    #[cfg(unix)]
+    pub async fn stop(&mut self) -> AppResult<()> {
+        self.suspend().await?;
+        // SIGSTOP can not be caught, the call returns on SIGCONT (`fg`)
+        unsafe {
+            libc::raise(libc::SIGSTOP);
+        }
+        self.resume()
+    }
+
     pub fn exit(&mut self) -> AppResult<()> {
         Self::reset()?;
         self.terminal.show_cursor()?;
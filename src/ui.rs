@@ -1,8 +1,17 @@
 use std;
 
 use crate::app::{App, FocusedBlock};
+use crate::config::PromptPosition;
+use crate::export::ExportSource;
+use crate::prompt::ContextUsage;
+use crate::split::ChatLayout;
+use crate::statusbar;
+use crate::vault;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
     Frame,
 };
 
@@ -86,41 +95,319 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
+fn render_lock_screen(app: &App, frame: &mut Frame) {
+    let frame_size = frame.size();
+    let area = centered_rect(50, 30, frame_size);
+
+    let mut text = Text::from(vec![
+        Line::raw(""),
+        Line::styled("🔒 Locked", Style::default().bold()),
+        Line::raw(""),
+        Line::raw(format!(
+            "Locked after {} minutes of inactivity",
+            app.config.idle_lock_timeout.unwrap_or_default()
+        )),
+    ]);
+    if app.config.history.encrypt && vault::get().is_some() {
+        text.extend([
+            Line::raw("Type the passphrase of the history and press Enter"),
+            Line::raw(""),
+            Line::raw("•".repeat(app.lock_passphrase.chars().count())),
+        ]);
+        if let Some(error) = &app.lock_error {
+            text.extend([Line::styled(
+                error.clone(),
+                Style::default().fg(app.theme.error),
+            )]);
+        }
+    } else {
+        text.extend([Line::raw("Press any key to resume")]);
+    }
+
+    let lock = Paragraph::new(text).alignment(Alignment::Center).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+
+    frame.render_widget(Clear, frame_size);
+    frame.render_widget(lock, area);
+}
+
 pub fn render(app: &mut App, frame: &mut Frame) {
     let frame_size = frame.size();
 
-    let prompt_block_height = app.prompt.height(&frame_size) + 3;
+    if app.locked {
+        render_lock_screen(app, frame);
+        return;
+    }
+
+    let (main_block, status_block) = if app.config.status_bar {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame_size);
+        (chunks[0], Some(chunks[1]))
+    } else {
+        (frame_size, None)
+    };
+
+    let prompt_block_height = app.prompt.height(&main_block).saturating_add(3);
 
     let (chat_block, prompt_block) = {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(1), Constraint::Length(prompt_block_height)].as_ref())
-            .split(frame.size());
-        (chunks[0], chunks[1])
+            .constraints(match app.config.prompt.position {
+                PromptPosition::Top => {
+                    [Constraint::Length(prompt_block_height), Constraint::Min(1)]
+                }
+                PromptPosition::Bottom => {
+                    [Constraint::Min(1), Constraint::Length(prompt_block_height)]
+                }
+            })
+            .split(main_block);
+        match app.config.prompt.position {
+            PromptPosition::Top => (chunks[1], chunks[0]),
+            PromptPosition::Bottom => (chunks[0], chunks[1]),
+        }
     };
 
     // Chat
-    app.chat.render(frame, chat_block);
+    match app.layout {
+        _ if app.compare.is_some() => {
+            if let Some(compare) = app.compare.as_mut() {
+                compare.render(frame, chat_block, &app.theme, &app.formatter.authors());
+            }
+        }
+        ChatLayout::Stacked => {
+            let indicator = app.status_indicator();
+            app.chat.render(
+                frame,
+                chat_block,
+                indicator,
+                &app.theme,
+                &app.formatter.authors(),
+            );
+        }
+        ChatLayout::Split => {
+            let indicator = app.status_indicator();
+            app.split.render(
+                frame,
+                chat_block,
+                &app.chat,
+                app.formatter,
+                app.focused_block == FocusedBlock::Chat,
+                indicator,
+                &app.theme,
+            );
+        }
+    }
 
     // Prompt
-    app.prompt.render(frame, prompt_block);
+    let indicator = app.voice_indicator();
+    let usage = ContextUsage {
+        chat_tokens: match app.chat.usage.total_tokens() {
+            0 => app.chat.estimated_tokens(),
+            total => total as usize,
+        },
+        size: app
+            .config
+            .context
+            .size(&app.config.backend_model_name(&app.backend)),
+        threshold: app.config.context.threshold,
+    };
+    let dictionary = app.dictionary.as_ref().filter(|_| app.spellcheck);
+    app.prompt.check_spelling(dictionary);
+    app.prompt.render(frame, prompt_block, indicator, &usage);
+
+    // Status bar, replaced by the command line while typing a command
+    match (app.command_line.as_ref(), status_block) {
+        (Some(command_line), area) => {
+            let area = area.unwrap_or(Rect {
+                y: frame_size.height.saturating_sub(1),
+                height: 1,
+                ..frame_size
+            });
+            command_line.render(frame, area, &app.theme);
+        }
+        (None, Some(area)) => statusbar::render(app, frame, area),
+        (None, None) => {}
+    }
+
+    // Completions of the slash command or of the command line
+    if let Some(completion) = app.completion.as_mut() {
+        match (&app.focused_block, status_block) {
+            (FocusedBlock::CommandLine, area) => {
+                let area = area.unwrap_or(Rect {
+                    y: frame_size.height.saturating_sub(1),
+                    height: 1,
+                    ..frame_size
+                });
+                completion.render(frame, area, true, &app.theme);
+            }
+            (FocusedBlock::Prompt, _) => completion.render(
+                frame,
+                Rect {
+                    x: prompt_block.x + 1,
+                    ..prompt_block
+                },
+                app.config.prompt.position == PromptPosition::Bottom,
+                &app.theme,
+            ),
+            _ => {}
+        }
+    }
 
     // History
+    let exporting_history = matches!(
+        &app.privacy_report,
+        Some(report) if matches!(report.export.source, ExportSource::History(_))
+    );
     if let FocusedBlock::History | FocusedBlock::Preview = app.focused_block {
         let area = centered_rect(80, 80, frame_size);
-        app.history.render(frame, area, app.focused_block.clone());
+        app.history.render(
+            frame,
+            area,
+            app.focused_block.clone(),
+            &app.theme,
+            &app.formatter.authors(),
+        );
+    } else if exporting_history {
+        let area = centered_rect(80, 80, frame_size);
+        app.history.render(
+            frame,
+            area,
+            FocusedBlock::Preview,
+            &app.theme,
+            &app.formatter.authors(),
+        );
+    } else if let Some(focus @ (FocusedBlock::History | FocusedBlock::Preview)) =
+        app.command_line.as_ref().map(|c| c.previous_focus.clone())
+    {
+        // A command typed from the history, like `:tag`
+        let area = centered_rect(80, 80, frame_size);
+        app.history
+            .render(frame, area, focus, &app.theme, &app.formatter.authors());
+    }
+
+    // Replay
+    if let Some(scrubber) = app.scrubber.as_ref() {
+        let area = centered_rect(80, 80, frame_size);
+        scrubber.render(
+            frame,
+            area,
+            &app.chat.recordings,
+            app.formatter,
+            &app.chat.render,
+            &app.theme,
+        );
+    }
+
+    // Log
+    if let Some(viewer) = app.log_viewer.as_mut() {
+        let area = centered_rect(90, 80, frame_size);
+        viewer.render(frame, area, &app.theme);
+    }
+
+    // Chat info
+    if let Some(info) = app.chat_info.as_ref() {
+        let area = centered_rect(50, 50, frame_size);
+        info.render(frame, area, &app.theme);
+    }
+
+    // Latency of the models
+    if let Some(diagnostics) = app.diagnostics.as_ref() {
+        let area = centered_rect(70, 50, frame_size);
+        diagnostics.render(frame, area, &app.theme);
+    }
+
+    // Regenerated answer
+    if let Some(diff) = app.answer_diff.as_mut() {
+        let area = centered_rect(80, 80, frame_size);
+        diff.render(frame, area, &app.theme);
+    }
+
+    // Corrections of a misspelled word
+    if let Some(corrections) = app.corrections.as_mut() {
+        let area = centered_rect(30, 40, frame_size);
+        corrections.render(frame, area, &app.theme);
+    }
+
+    // Registers
+    if let Some(viewer) = app.register_viewer.as_mut() {
+        let area = centered_rect(60, 50, frame_size);
+        viewer.render(frame, area, &app.theme);
+    }
+
+    // Files to attach
+    if let Some(file_picker) = app.file_picker.as_mut() {
+        let area = centered_rect(50, 40, frame_size);
+        file_picker.render(frame, area, &app.theme);
+    }
+
+    // Code blocks to copy or save
+    if let Some(picker) = app.code_blocks.as_mut() {
+        let area = centered_rect(80, 50, frame_size);
+        picker.render(frame, area, &app.theme);
+    }
+
+    // Commands to run
+    if let Some(runner) = app.command_runner.as_mut() {
+        let area = centered_rect(80, 60, frame_size);
+        runner.render(frame, area, &app.theme);
+    }
+
+    // Profiles
+    if let Some(switcher) = app.profile_switcher.as_mut() {
+        let area = centered_rect(40, 40, frame_size);
+        switcher.render(frame, area, app.config.profile.as_deref(), &app.theme);
+    }
+
+    // Share
+    if let Some(popup) = app.share.as_mut() {
+        let area = centered_rect(80, 60, frame_size);
+        popup.render(frame, area, &app.theme);
+    }
+
+    // Privacy report, at the bottom to keep the chat visible
+    if let Some(report) = app.privacy_report.as_mut() {
+        let area = Rect {
+            x: frame_size.width / 10,
+            y: frame_size.height * 6 / 10,
+            width: frame_size.width * 8 / 10,
+            height: frame_size.height * 3 / 10,
+        };
+        report.render(frame, area, &app.theme);
+    }
+
+    // Payload
+    if let FocusedBlock::Payload = app.focused_block {
+        let area = centered_rect(80, 80, frame_size);
+        app.prompt.render_payload(frame, area);
     }
 
     // Help
     if let FocusedBlock::Help = app.focused_block {
         app.prompt.update(&FocusedBlock::Help);
         let area = help_rect(frame_size);
-        app.help.render(frame, area);
+        app.help.render(frame, area, &app.config.key_bindings);
+    }
+
+    // Over everything else, the notifications aside
+    if let Some(confirmation) = app.quit_confirmation.as_ref() {
+        let width = (frame_size.width / 2).max(40).min(frame_size.width);
+        let area = Rect {
+            x: (frame_size.width - width) / 2,
+            y: (frame_size.height / 2).saturating_sub(2),
+            width,
+            height: 4.min(frame_size.height),
+        };
+        confirmation.render(frame, area, &app.theme);
     }
 
     // Notifications
     for (i, notif) in app.notifications.iter_mut().enumerate() {
         let area = notification_rect(i as u16, frame_size);
-        notif.render(frame, area);
+        notif.render(frame, area, &app.theme);
     }
 }
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::{
+    app::App,
+    attachment::Attachment,
+    chat::Chat,
+    history::Thread,
+    llm::LLM,
+    notification::{Notification, NotificationLevel},
+};
+
+// Older operations are forgotten
+const MAX_OPERATIONS: usize = 50;
+
+/// A destructive operation, with what is needed to revert it
+pub enum Operation<'a> {
+    /// A chat replaced by a new one, with the prompt written at that time
+    Chat {
+        chat: Box<Chat<'a>>,
+        text: String,
+        attachments: Vec<Attachment>,
+    },
+    /// A thread deleted from the history, at its position
+    DeletedThread(usize, Thread<'a>),
+    /// The id of a thread put back in the history by an undo
+    RestoredThread(usize),
+    /// The text and the attachments of a cleared prompt
+    Prompt {
+        text: String,
+        attachments: Vec<Attachment>,
+    },
+}
+
+impl Operation<'_> {
+    fn description(&self) -> &'static str {
+        match self {
+            Operation::Chat { .. } => "chat",
+            Operation::DeletedThread(..) | Operation::RestoredThread(_) => "history",
+            Operation::Prompt { .. } => "prompt",
+        }
+    }
+}
+
+/// The operations to undo and to redo, separate from the undo of the prompt editor
+#[derive(Default)]
+pub struct UndoStack<'a> {
+    undo: Vec<Operation<'a>>,
+    redo: Vec<Operation<'a>>,
+}
+
+impl<'a> UndoStack<'a> {
+    pub fn push(&mut self, operation: Operation<'a>) {
+        self.undo.push(operation);
+        if self.undo.len() > MAX_OPERATIONS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+/// Swap the text and the attachments of the prompt, returns the previous ones
+fn swap_prompt(
+    app: &mut App,
+    text: String,
+    attachments: Vec<Attachment>,
+) -> (String, Vec<Attachment>) {
+    let previous_text = app.prompt.editor.lines().join("\n");
+    let previous_attachments = std::mem::replace(&mut app.prompt.attachments, attachments);
+    app.prompt.set_text(&text);
+    (previous_text, previous_attachments)
+}
+
+/// Revert the operation, returns the operation reverting it back
+async fn revert<'a>(
+    app: &mut App<'a>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    operation: Operation<'a>,
+) -> Operation<'a> {
+    match operation {
+        Operation::Chat {
+            chat,
+            text,
+            attachments,
+        } => {
+            let current = std::mem::replace(&mut app.chat, *chat);
+
+            // The chat was archived when it was replaced
+            if let Some(i) = app.history.threads.iter().position(|t| t.id == app.chat.id) {
+                app.history.delete(i);
+            }
+            if !current.plain_chat.is_empty() {
+                app.history.push(current.to_thread());
+            }
+            app.save_history();
+
+            {
+                let mut llm = llm.lock().await;
+                llm.set_messages(app.chat.to_thread().messages());
+            }
+
+            let (text, attachments) = swap_prompt(app, text, attachments);
+            Operation::Chat {
+                chat: Box::new(current),
+                text,
+                attachments,
+            }
+        }
+
+        Operation::DeletedThread(i, thread) => {
+            let id = thread.id;
+            app.history.insert(i, thread);
+            app.save_history();
+            Operation::RestoredThread(id)
+        }
+
+        Operation::RestoredThread(id) => {
+            // The thread may have moved since, when pinning another one for instance
+            let Some(i) = app.history.threads.iter().position(|t| t.id == id) else {
+                return Operation::RestoredThread(id);
+            };
+            let thread = app.history.threads[i].clone();
+            app.history.delete(i);
+            app.save_history();
+            Operation::DeletedThread(i, thread)
+        }
+
+        Operation::Prompt { text, attachments } => {
+            let (text, attachments) = swap_prompt(app, text, attachments);
+            Operation::Prompt { text, attachments }
+        }
+    }
+}
+
+fn is_busy(app: &mut App) -> bool {
+    let busy = app.spinner.active
+        || app.chat.request.is_some()
+        || app.branched.is_some()
+        || !app.detached.is_empty();
+    if busy {
+        app.notifications.push(Notification::new(
+            "Wait for the answer to end".to_string(),
+            NotificationLevel::Warning,
+        ));
+    }
+    busy
+}
+
+/// Revert the last destructive operation
+pub async fn undo(app: &mut App<'_>, llm: Arc<Mutex<Box<dyn LLM + 'static>>>) {
+    if is_busy(app) {
+        return;
+    }
+
+    let Some(operation) = app.undo.undo.pop() else {
+        app.notifications.push(Notification::new(
+            "Nothing to undo".to_string(),
+            NotificationLevel::Info,
+        ));
+        return;
+    };
+
+    let description = operation.description();
+    let inverse = revert(app, llm, operation).await;
+    app.undo.redo.push(inverse);
+
+    app.notifications.push(Notification::new(
+        format!("Undo: {}", description),
+        NotificationLevel::Info,
+    ));
+}
+
+/// Apply again the last undone operation
+pub async fn redo(app: &mut App<'_>, llm: Arc<Mutex<Box<dyn LLM + 'static>>>) {
+    if is_busy(app) {
+        return;
+    }
+
+    let Some(operation) = app.undo.redo.pop() else {
+        app.notifications.push(Notification::new(
+            "Nothing to redo".to_string(),
+            NotificationLevel::Info,
+        ));
+        return;
+    };
+
+    let description = operation.description();
+    let inverse = revert(app, llm, operation).await;
+    app.undo.undo.push(inverse);
+
+    app.notifications.push(Notification::new(
+        format!("Redo: {}", description),
+        NotificationLevel::Info,
+    ));
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use chrono::Local;
+use regex::{Captures, Regex};
+
+/// The `HEAD` file of the git repository of the directory, or of the closest of its parents
+fn git_head(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        let git = dir.join(".git");
+        if git.is_dir() {
+            return Some(git.join("HEAD"));
+        }
+        // A worktree or a submodule names its git directory
+        let gitdir = std::fs::read_to_string(&git).ok()?;
+        let gitdir = Path::new(gitdir.strip_prefix("gitdir:")?.trim());
+        Some(dir.join(gitdir).join("HEAD"))
+    })
+}
+
+/// The branch checked out, read from the `HEAD` file rather than by running git, the prompts
+/// are expanded in the event loop. `HEAD` when it is detached, like git says.
+fn git_branch(dir: &Path) -> Option<String> {
+    let head = std::fs::read_to_string(git_head(dir)?).ok()?;
+    match head.trim().strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(
+                reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string(),
+            )
+        }
+        None => Some(String::from("HEAD")),
+    }
+}
+
+/// The value of a built-in variable
+fn builtin(name: &str) -> Option<String> {
+    if let Some(variable) = name.strip_prefix("env:") {
+        return std::env::var(variable).ok();
+    }
+
+    match name {
+        "today" => Some(Local::now().format("%Y-%m-%d").to_string()),
+        "now" => Some(Local::now().format("%H:%M").to_string()),
+        "cwd" => std::env::current_dir()
+            .ok()
+            .map(|dir| dir.display().to_string()),
+        "git_branch" => git_branch(&std::env::current_dir().ok()?),
+        _ => None,
+    }
+}
+
+/// Replace the `{{name}}` of the text with the value of the variable, the ones defined
+/// before the built-in ones. `\{{name}}` is kept as `{{name}}`, and so are the unknown names.
+pub fn expand(text: &str, variables: &HashMap<String, String>) -> String {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"(\\)?\{\{([A-Za-z_][A-Za-z0-9_]*|env:[A-Za-z0-9_]+)\}\}").unwrap()
+    });
+
+    pattern
+        .replace_all(text, |captures: &Captures| {
+            let placeholder = &captures[0];
+            if captures.get(1).is_some() {
+                return placeholder[1..].to_string();
+            }
+
+            let name = &captures[2];
+            variables
+                .get(name)
+                .cloned()
+                .or_else(|| builtin(name))
+                .unwrap_or_else(|| placeholder.to_string())
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_variables() {
+        let variables = HashMap::from([(String::from("lang"), String::from("Rust"))]);
+
+        assert_eq!(
+            expand("{{lang}} \\{{lang}} {{unknown}} {{ lang }}", &variables),
+            "Rust {{lang}} {{unknown}} {{ lang }}"
+        );
+        assert_eq!(
+            expand("{{env:PATH}}", &variables),
+            std::env::var("PATH").unwrap_or_else(|_| String::from("{{env:PATH}}"))
+        );
+
+        // Around midnight, the date may change while it is expanded
+        let before = Local::now().format("%Y-%m-%d").to_string();
+        let today = expand("{{today}}", &variables);
+        let after = Local::now().format("%Y-%m-%d").to_string();
+        assert!(today == before || today == after, "{}", today);
+    }
+
+    #[test]
+    fn branch_of_the_repository() {
+        let repository = tempfile::tempdir().unwrap();
+        let git = repository.path().join(".git");
+        let dir = repository.path().join("src");
+        std::fs::create_dir_all(&git).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(git.join("HEAD"), "ref: refs/heads/feature/x\n").unwrap();
+        assert_eq!(git_branch(&dir).as_deref(), Some("feature/x"));
+
+        std::fs::write(git.join("HEAD"), "0123456789abcdef\n").unwrap();
+        assert_eq!(git_branch(&dir).as_deref(), Some("HEAD"));
+
+        // A worktree
+        let worktree = tempfile::tempdir().unwrap();
+        std::fs::write(
+            worktree.path().join(".git"),
+            format!("gitdir: {}\n", git.display()),
+        )
+        .unwrap();
+        std::fs::write(git.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        assert_eq!(git_branch(worktree.path()).as_deref(), Some("main"));
+    }
+}
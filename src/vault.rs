@@ -0,0 +1,177 @@
+use std::{path::Path, sync::OnceLock};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use zeroize::Zeroizing;
+
+/// The start of the encrypted files, followed by the salt, the nonce and the encrypted data
+const MAGIC: &[u8] = b"tenere-vault-1\n";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const ATTEMPTS: usize = 3;
+
+/// Read instead of asking the passphrase, to start tenere from a script
+pub const PASSPHRASE_VAR: &str = "TENERE_HISTORY_PASSPHRASE";
+
+static VAULT: OnceLock<Vault> = OnceLock::new();
+
+/// The key derived from the passphrase, kept in memory for the whole session
+/// to save the history without asking the passphrase again
+pub struct Vault {
+    key: Zeroizing<[u8; 32]>,
+    salt: [u8; SALT_LENGTH],
+}
+
+impl std::fmt::Debug for Vault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Vault")
+    }
+}
+
+impl Vault {
+    fn new(passphrase: &str, salt: [u8; SALT_LENGTH]) -> Result<Self, String> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| format!("Can not derive the key: {}", e))?;
+        Ok(Self { key, salt })
+    }
+
+    /// Whether the key is the one of the passphrase, to unlock the idle lock
+    pub fn matches(&self, passphrase: &str) -> bool {
+        Self::new(passphrase, self.salt).is_ok_and(|vault| {
+            // Compared in constant time
+            vault
+                .key
+                .iter()
+                .zip(self.key.iter())
+                .fold(0, |difference, (a, b)| difference | (a ^ b))
+                == 0
+        })
+    }
+
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let cipher = XChaCha20Poly1305::new(self.key.as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let encrypted = cipher
+            .encrypt(&nonce, data)
+            .map_err(|_| String::from("Can not encrypt the history"))?;
+
+        let mut file =
+            Vec::with_capacity(MAGIC.len() + SALT_LENGTH + NONCE_LENGTH + encrypted.len());
+        file.extend_from_slice(MAGIC);
+        file.extend_from_slice(&self.salt);
+        file.extend_from_slice(&nonce);
+        file.extend_from_slice(&encrypted);
+        Ok(file)
+    }
+
+    pub fn decrypt(&self, file: &[u8]) -> Result<Vec<u8>, String> {
+        let (_, nonce, encrypted) = split(file)?;
+        XChaCha20Poly1305::new(self.key.as_ref().into())
+            .decrypt(XNonce::from_slice(nonce), encrypted)
+            .map_err(|_| String::from("Wrong passphrase"))
+    }
+}
+
+/// The salt, the nonce and the encrypted data of a file
+type Parts<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+fn split(file: &[u8]) -> Result<Parts<'_>, String> {
+    let rest = file
+        .strip_prefix(MAGIC)
+        .filter(|rest| rest.len() >= SALT_LENGTH + NONCE_LENGTH)
+        .ok_or("The encrypted history is not valid")?;
+    let (salt, rest) = rest.split_at(SALT_LENGTH);
+    let (nonce, encrypted) = rest.split_at(NONCE_LENGTH);
+    Ok((salt, nonce, encrypted))
+}
+
+/// The vault once unlocked, the history is stored in clear without it
+pub fn get() -> Option<&'static Vault> {
+    VAULT.get()
+}
+
+fn ask(prompt: &str) -> Result<Zeroizing<String>, String> {
+    rpassword::prompt_password(prompt)
+        .map(Zeroizing::new)
+        .map_err(|e| format!("Can not read the passphrase: {}", e))
+}
+
+/// Ask the passphrase of the encrypted history at `path`, before the TUI starts.
+/// A new passphrase is asked twice if the history is not encrypted yet.
+pub fn unlock(path: &Path) -> Result<(), String> {
+    if VAULT.get().is_some() {
+        return Ok(());
+    }
+
+    let from_env = std::env::var(PASSPHRASE_VAR).ok().map(Zeroizing::new);
+
+    let vault = match std::fs::read(path) {
+        Ok(file) => {
+            let (salt, _, _) = split(&file)?;
+            let salt: [u8; SALT_LENGTH] = salt.try_into().map_err(|_| "Invalid salt")?;
+
+            let mut attempt = 0;
+            loop {
+                let passphrase = match &from_env {
+                    Some(passphrase) => passphrase.clone(),
+                    None => ask("History passphrase: ")?,
+                };
+                let vault = Vault::new(&passphrase, salt)?;
+                match vault.decrypt(&file) {
+                    Ok(_) => break vault,
+                    Err(e) => {
+                        attempt += 1;
+                        if from_env.is_some() || attempt == ATTEMPTS {
+                            return Err(e);
+                        }
+                        eprintln!("{}, try again", e);
+                    }
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let passphrase = match from_env {
+                Some(passphrase) => passphrase,
+                None => {
+                    let passphrase = ask("New passphrase for the history: ")?;
+                    if *passphrase != *ask("Confirm the passphrase: ")? {
+                        return Err(String::from("The passphrases do not match"));
+                    }
+                    passphrase
+                }
+            };
+            if passphrase.is_empty() {
+                return Err(String::from("The passphrase can not be empty"));
+            }
+
+            let mut salt = [0u8; SALT_LENGTH];
+            OsRng.fill_bytes(&mut salt);
+            Vault::new(&passphrase, salt)?
+        }
+        Err(e) => return Err(format!("Can not read `{}`: {}", path.display(), e)),
+    };
+
+    let _ = VAULT.set(vault);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passphrase_of_the_vault() {
+        let vault = Vault::new("correct horse", [7; SALT_LENGTH]).unwrap();
+        assert!(vault.matches("correct horse"));
+        assert!(!vault.matches("correct horse "));
+        assert!(!vault.matches(""));
+
+        let file = vault.encrypt(b"history").unwrap();
+        assert_eq!(vault.decrypt(&file).unwrap(), b"history");
+    }
+}
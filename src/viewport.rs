@@ -0,0 +1,284 @@
+use ratatui::{
+    text::{Line, Span, Text},
+    widgets::{Paragraph, Wrap},
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{formatter::gutter_width, streaming::is_fence};
+
+/// The rows taken by a line once wrapped, like the chat paragraph wraps it
+pub fn wrapped_height(line: &Line, width: u16) -> usize {
+    Paragraph::new(Text::from(line.clone()))
+        .wrap(Wrap { trim: false })
+        .line_count(width)
+        .max(1)
+}
+
+/// The rows taken by a line, a single one for the code lines when they are not wrapped
+fn height(line: &Line, width: u16, code: bool, wrap_code: bool) -> usize {
+    if code && !wrap_code {
+        1
+    } else {
+        wrapped_height(line, width)
+    }
+}
+
+/// Whether each line is inside a code block, the fences aside, given whether a block is
+/// open before the first one. The last state tells whether a block is open after them.
+pub fn code_lines<'b>(
+    lines: impl Iterator<Item = &'b Line<'b>>,
+    mut open: bool,
+) -> Vec<(bool, bool)> {
+    lines
+        .map(|line| {
+            let text: String = line
+                .spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect();
+            if is_fence(&text) {
+                open = !open;
+                (false, open)
+            } else {
+                (open, open)
+            }
+        })
+        .collect()
+}
+
+/// The heights of the lines following the ones of the viewport, with whether they are code
+pub fn tail_heights<'b>(
+    lines: impl Iterator<Item = &'b Line<'b>> + Clone,
+    width: u16,
+    wrap_code: bool,
+) -> Vec<(usize, bool)> {
+    lines
+        .clone()
+        .zip(code_lines(lines, false))
+        .map(|(line, (code, _))| (height(line, width, code, wrap_code), code))
+        .collect()
+}
+
+/// The columns of the line from `offset`, cut to the width.
+/// The gutter of the line numbers stays in place, the code after it is scrolled.
+pub fn clip<'b>(line: &Line<'b>, offset: usize, width: usize) -> Line<'b> {
+    let gutter = gutter_width(line);
+    let mut column = 0;
+    let mut spans = Vec::new();
+    for span in &line.spans {
+        let mut content = String::new();
+        for c in span.content.chars() {
+            let c_width = c.width().unwrap_or_default();
+            let in_gutter = column < gutter && column + c_width <= width;
+            if in_gutter || (column >= gutter + offset && column + c_width <= offset + width) {
+                content.push(c);
+            }
+            column += c_width;
+        }
+        if !content.is_empty() {
+            spans.push(Span::styled(content, span.style));
+        }
+    }
+    Line::from(spans)
+}
+
+/// Where the lines of a long text start once wrapped, to lay out only the lines in view.
+/// The text is expected to grow at its end, the last line may change.
+#[derive(Debug, Clone, Default)]
+pub struct Viewport {
+    width: u16,
+    wrap_code: bool,
+    /// The row after each line
+    ends: Vec<usize>,
+    /// Whether each line is code, and whether a code block is open after it
+    code: Vec<(bool, bool)>,
+}
+
+impl Viewport {
+    /// Wrap the lines added since the last update, and the last line again.
+    /// The code lines take a single row unless `wrap_code` is set.
+    pub fn update(&mut self, lines: &[Line], width: u16, wrap_code: bool) {
+        if width != self.width || wrap_code != self.wrap_code {
+            self.width = width;
+            self.wrap_code = wrap_code;
+            self.invalidate();
+        }
+
+        let keep = self.ends.len().min(lines.len()).saturating_sub(1);
+        self.ends.truncate(keep);
+        self.code.truncate(keep);
+
+        let open = self.code.last().is_some_and(|(_, open)| *open);
+        let code = code_lines(lines[keep..].iter(), open);
+        let mut end = self.height();
+        for (line, (is_code, open)) in lines[keep..].iter().zip(code) {
+            end += height(line, width, is_code, wrap_code);
+            self.ends.push(end);
+            self.code.push((is_code, open));
+        }
+    }
+
+    /// Wrap all the lines again on the next update, after they are formatted again
+    pub fn invalidate(&mut self) {
+        self.ends.clear();
+        self.code.clear();
+    }
+
+    /// The line is inside a code block
+    pub fn is_code(&self, line: usize) -> bool {
+        self.code.get(line).is_some_and(|(code, _)| *code)
+    }
+
+    pub fn height(&self) -> usize {
+        self.ends.last().copied().unwrap_or_default()
+    }
+
+    /// The first row of the line
+    pub fn start(&self, line: usize) -> usize {
+        match line.checked_sub(1) {
+            Some(previous) => self.ends.get(previous).copied().unwrap_or(self.height()),
+            None => 0,
+        }
+    }
+
+    /// The line shown at the row, the number of lines when the row is below them
+    pub fn line_at(&self, row: usize) -> usize {
+        self.ends.partition_point(|end| *end <= row)
+    }
+
+    /// The rows taken by each line, from the given one
+    pub fn heights_from(&self, line: usize) -> impl Iterator<Item = usize> + '_ {
+        self.ends
+            .iter()
+            .skip(line)
+            .scan(self.start(line), |start, end| {
+                Some(end - std::mem::replace(start, *end))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Style, Stylize};
+
+    use super::*;
+
+    fn lines(text: &str) -> Vec<Line<'static>> {
+        text.lines()
+            .map(|line| Line::raw(line.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn lines_of_the_code_blocks() {
+        let text = lines("prose\n```rust\nlet a = 1;\n\n```\nprose");
+        assert_eq!(
+            code_lines(text.iter(), false),
+            vec![
+                (false, false),
+                (false, true),
+                (true, true),
+                (true, true),
+                (false, false),
+                (false, false)
+            ]
+        );
+
+        // A block left open by the previous lines
+        assert_eq!(
+            code_lines(lines("code\n```\nprose").iter(), true),
+            vec![(true, true), (false, false), (false, false)]
+        );
+    }
+
+    #[test]
+    fn heights_of_the_tail() {
+        let text = lines("0123456789abcdef\n```\n0123456789abcdef\n```");
+        assert_eq!(
+            tail_heights(text.iter(), 10, false),
+            vec![(2, false), (1, false), (1, true), (1, false)]
+        );
+        assert_eq!(
+            tail_heights(text.iter(), 10, true),
+            vec![(2, false), (1, false), (2, true), (1, false)]
+        );
+    }
+
+    #[test]
+    fn clip_the_columns() {
+        let line = Line::from(vec![
+            Span::raw("let "),
+            Span::styled("name", Style::default().bold()),
+            Span::raw(" = \"日本\";"),
+        ]);
+        let text = |line: Line| -> String {
+            line.spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        };
+
+        assert_eq!(text(clip(&line, 0, 6)), "let na");
+        assert_eq!(text(clip(&line, 4, 4)), "name");
+        assert_eq!(clip(&line, 4, 4).spans[0].style, Style::default().bold());
+        // The wide characters are not cut in half
+        assert_eq!(text(clip(&line, 12, 3)), "日");
+        assert_eq!(text(clip(&line, 13, 5)), "本\";");
+        assert_eq!(text(clip(&line, 40, 10)), "");
+
+        // The gutter of the line numbers is not scrolled
+        let numbered = Line::from(vec![Span::raw("12 │ "), Span::raw("let name = 1;")]);
+        assert_eq!(text(clip(&numbered, 4, 10)), "12 │ name ");
+    }
+
+    #[test]
+    fn rows_of_the_lines() {
+        let mut viewport = Viewport::default();
+        let text = lines("short\n0123456789abcdef\n```\n0123456789abcdef\n```\nend");
+        viewport.update(&text, 10, false);
+
+        // The long code line is not wrapped
+        assert_eq!(viewport.height(), 7);
+        assert_eq!(
+            viewport.heights_from(0).collect::<Vec<usize>>(),
+            vec![1, 2, 1, 1, 1, 1]
+        );
+        assert_eq!(viewport.start(2), 3);
+        assert_eq!(viewport.line_at(0), 0);
+        assert_eq!(viewport.line_at(2), 1);
+        assert_eq!(viewport.line_at(3), 2);
+        assert_eq!(viewport.line_at(100), 6);
+        assert!(viewport.is_code(3));
+        assert!(!viewport.is_code(2) && !viewport.is_code(5));
+
+        viewport.update(&text, 10, true);
+        assert_eq!(viewport.height(), 8);
+        viewport.update(&text, 20, true);
+        assert_eq!(viewport.height(), 6);
+    }
+
+    #[test]
+    fn growing_text() {
+        let mut viewport = Viewport::default();
+        viewport.update(&lines("first\n```\ncode"), 10, false);
+        assert_eq!(viewport.height(), 3);
+
+        // The last line changed and lines were added, in the open code block
+        viewport.update(&lines("first\n```\ncode that is long\nmore"), 10, false);
+        assert_eq!(viewport.height(), 4);
+        assert!(viewport.is_code(3));
+
+        let text = lines("first\n```\ncode that is long\nmore\n```\nthe end of the answer");
+        viewport.update(&text, 10, false);
+        assert_eq!(viewport.height(), 7);
+        assert!(!viewport.is_code(5));
+
+        // The same as when all the lines are wrapped at once
+        let mut whole = Viewport::default();
+        whole.update(&text, 10, false);
+        assert_eq!(
+            viewport.heights_from(0).collect::<Vec<usize>>(),
+            whole.heights_from(0).collect::<Vec<usize>>()
+        );
+    }
+}
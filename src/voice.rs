@@ -0,0 +1,231 @@
+use std::{path::Path, process::Stdio, sync::Arc};
+
+use reqwest::multipart::{Form, Part};
+use tempfile::TempDir;
+use tokio::{
+    process::{Child, Command},
+    sync::mpsc::UnboundedSender,
+};
+
+use crate::{
+    app::App,
+    config::{Config, VoiceConfig},
+    event::Event,
+    network,
+    notification::{Notification, NotificationLevel},
+};
+
+/// The name of the recording, in its temporary directory
+const RECORDING: &str = "recording.wav";
+
+/// The command recording the microphone, until it is stopped
+pub struct Recorder {
+    child: Child,
+    /// Only readable by the user, removed when dropped
+    dir: TempDir,
+}
+
+/// The path quoted for the shell, it replaces `{file}` in the commands
+fn quote(path: &Path) -> String {
+    let path = path.display().to_string();
+    if cfg!(target_os = "windows") {
+        format!("\"{}\"", path)
+    } else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
+
+fn shell(cmd: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        // exec so that the signal stopping the recording reaches the recorder
+        let mut command = Command::new("sh");
+        command.args(["-c", &format!("exec {}", cmd)]);
+        command
+    }
+}
+
+impl Recorder {
+    pub fn start(config: &VoiceConfig) -> Result<Self, String> {
+        // With a random name, the recording can not be replaced by a link
+        let dir = tempfile::Builder::new()
+            .prefix("tenere-voice-")
+            .tempdir()
+            .map_err(|e| format!("Can not start the recording: {}", e))?;
+
+        let cmd = config
+            .record_cmd
+            .replace("{file}", &quote(&dir.path().join(RECORDING)));
+
+        let child = shell(&cmd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Can not start the recording: {}", e))?;
+
+        Ok(Self { child, dir })
+    }
+
+    /// Stop the recording and return the directory of the audio file
+    async fn stop(mut self) -> Result<TempDir, String> {
+        // Interrupt the recorder so that it writes a valid file
+        #[cfg(unix)]
+        if let Some(pid) = self.child.id() {
+            unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) };
+        }
+        #[cfg(not(unix))]
+        let _ = self.child.start_kill();
+        let _ = self.child.wait().await;
+
+        if self.dir.path().join(RECORDING).exists() {
+            Ok(self.dir)
+        } else {
+            Err(String::from("Nothing was recorded, check `record_cmd`"))
+        }
+    }
+}
+
+/// Transcribe the audio file with a local command, like whisper.cpp
+async fn transcribe_with_command(cmd: &str, path: &Path) -> Result<String, String> {
+    let cmd = cmd.replace("{file}", &quote(path));
+    let output = shell(&cmd)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Transcribe the audio file with the OpenAI Whisper API
+async fn transcribe_with_openai(
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    config: VoiceConfig,
+    path: &Path,
+) -> Result<String, String> {
+    let audio = tokio::fs::read(path).await.map_err(|e| e.to_string())?;
+
+    let file = Part::bytes(audio)
+        .file_name("audio.wav")
+        .mime_str("audio/wav")
+        .map_err(|e| e.to_string())?;
+
+    let mut form = Form::new()
+        .text("model", config.model)
+        .text("response_format", "text")
+        .part("file", file);
+    if let Some(language) = config.language {
+        form = form.text("language", language);
+    }
+
+    let response = client
+        .post(url)
+        .bearer_auth(api_key)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let response = network::error_for_status(response)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let transcript = response.text().await.map_err(|e| e.to_string())?;
+    Ok(transcript.trim().to_string())
+}
+
+/// Start recording, or stop it and insert the transcript into the prompt
+pub fn toggle(app: &mut App, sender: UnboundedSender<Event>) {
+    let config: Arc<Config> = app.config.clone();
+
+    let Some(recorder) = app.recorder.take() else {
+        match Recorder::start(&config.voice) {
+            Ok(recorder) => app.recorder = Some(recorder),
+            Err(e) => app
+                .notifications
+                .push(Notification::new(e, NotificationLevel::Error)),
+        }
+        return;
+    };
+
+    let voice = config.voice.clone();
+
+    // Built before spawning, the errors of the config are not Send
+    let openai = match &voice.transcribe_cmd {
+        Some(_) => None,
+        None => {
+            let client = match network::client(&config.network) {
+                Ok(client) => client,
+                Err(e) => {
+                    app.notifications
+                        .push(Notification::new(e, NotificationLevel::Error));
+                    return;
+                }
+            };
+            let url = config
+                .chatgpt
+                .url
+                .replace("chat/completions", "audio/transcriptions");
+            Some((client, url))
+        }
+    };
+
+    app.transcribing = true;
+
+    tokio::spawn(async move {
+        let transcript = match recorder.stop().await {
+            Ok(dir) => {
+                let path = dir.path().join(RECORDING);
+                let transcript = match (voice.transcribe_cmd.clone(), openai) {
+                    (Some(cmd), _) => transcribe_with_command(&cmd, &path).await,
+                    // The key may come from a command, it is not fetched on the UI thread
+                    (None, Some((client, url))) => match config.chatgpt.fetch_api_key().await {
+                        Some(api_key) => {
+                            transcribe_with_openai(client, url, api_key, voice, &path).await
+                        }
+                        None => Err(String::from("Can not find the openai api key")),
+                    },
+                    (None, None) => Err(String::from("No way to transcribe the recording")),
+                };
+                // The recording is removed with its directory
+                drop(dir);
+                transcript
+            }
+            Err(e) => Err(e),
+        };
+
+        let _ = sender.send(Event::Transcript(transcript));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn path_quoted_for_the_shell() {
+        let path = Path::new("/tmp/it's $HOME/recording.wav");
+
+        let output = std::process::Command::new("sh")
+            .args(["-c", &format!("printf %s {}", quote(path))])
+            .output()
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "/tmp/it's $HOME/recording.wav"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Snapshots of the screen after synthetic key events, answered by the mock backend.
+//!
+//! Update them with `INSTA_UPDATE=always cargo test --test tui`, or review them with
+//! `cargo insta review`.
+
+use std::{path::PathBuf, sync::Arc};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{backend::TestBackend, Terminal};
+use regex::Regex;
+use tenere::{
+    app::App,
+    config::Config,
+    event::Event,
+    formatter::Formatter,
+    handler::{handle_answer, handle_key_events},
+    llm::{LLMAnswer, LLMModel, LLM},
+    ui,
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    Mutex,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// The directory of the history, the state and the drafts of the tests, out of the ones of
+/// the user. The directories of the first config are kept for the whole process, so the
+/// harnesses share it, and the last one alive removes it.
+struct TestDir;
+
+/// The directory and the number of harnesses using it
+static TEST_DIR: std::sync::Mutex<(Option<PathBuf>, usize)> = std::sync::Mutex::new((None, 0));
+
+impl TestDir {
+    fn acquire() -> (Self, PathBuf) {
+        let mut test_dir = TEST_DIR.lock().unwrap();
+        let dir = test_dir
+            .0
+            .get_or_insert_with(|| tempfile::tempdir().unwrap().keep())
+            .clone();
+        // Created again when a harness used it before and removed it
+        std::fs::create_dir_all(&dir).unwrap();
+        test_dir.1 += 1;
+        (Self, dir)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let mut test_dir = TEST_DIR.lock().unwrap();
+        test_dir.1 -= 1;
+        if let (Some(dir), 0) = &*test_dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+fn config(dir: PathBuf) -> Config {
+    toml::from_str(&format!(
+        r#"
+        llm = "mock"
+        generate_titles = false
+        save_draft = false
+        data_dir = {:?}
+        state_dir = {:?}
+
+        [mock]
+        delay = 0
+        "#,
+        dir.join("data"),
+        dir.join("state"),
+    ))
+    .unwrap()
+}
+
+/// The app drawn on a test terminal, with the events of the answers applied like the
+/// event loop of tenere does
+struct Harness {
+    app: App<'static>,
+    llm: Arc<Mutex<Box<dyn LLM + 'static>>>,
+    sender: UnboundedSender<Event>,
+    receiver: UnboundedReceiver<Event>,
+    terminal: Terminal<TestBackend>,
+    _dir: TestDir,
+}
+
+impl Harness {
+    async fn new() -> Self {
+        let (test_dir, dir) = TestDir::acquire();
+        let config = Arc::new(config(dir));
+        // Lives as long as the app, until the end of the test
+        let formatter: &'static Formatter = Box::leak(Box::new(Formatter::new()));
+        let app = App::new(config.clone(), formatter);
+        let llm = Arc::new(Mutex::new(
+            LLMModel::init(&config.llm, config.clone()).await.unwrap(),
+        ));
+        let (sender, receiver) = unbounded_channel();
+
+        Self {
+            app,
+            llm,
+            sender,
+            receiver,
+            terminal: Terminal::new(TestBackend::new(80, 30)).unwrap(),
+            _dir: test_dir,
+        }
+    }
+
+    async fn press(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        handle_key_events(
+            KeyEvent::new(code, modifiers),
+            &mut self.app,
+            self.llm.clone(),
+            self.sender.clone(),
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn key(&mut self, code: KeyCode) {
+        self.press(code, KeyModifiers::NONE).await;
+    }
+
+    async fn ctrl(&mut self, c: char) {
+        self.press(KeyCode::Char(c), KeyModifiers::CONTROL).await;
+    }
+
+    /// Type the text in the prompt, and go back to the Normal mode
+    async fn type_text(&mut self, text: &str) {
+        self.key(KeyCode::Char('i')).await;
+        for c in text.chars() {
+            self.key(KeyCode::Char(c)).await;
+        }
+        self.key(KeyCode::Esc).await;
+    }
+
+    /// Apply the events of the answer, up to its end or up to the chunk `chunks`
+    async fn receive(&mut self, chunks: Option<usize>) {
+        let mut received = 0;
+        while let Some(event) = self.receiver.recv().await {
+            let Event::Answer(id, answer) = event else {
+                continue;
+            };
+            let end = matches!(answer, LLMAnswer::EndAnswer);
+            received += usize::from(matches!(answer, LLMAnswer::Answer(_)));
+
+            handle_answer(
+                &mut self.app,
+                self.llm.clone(),
+                self.sender.clone(),
+                id,
+                answer,
+            )
+            .await;
+            if end || chunks.is_some_and(|chunks| received >= chunks) {
+                return;
+            }
+        }
+    }
+
+    /// The text of the screen, without the trailing spaces and with the times masked
+    fn screen(&mut self) -> String {
+        self.terminal
+            .draw(|frame| ui::render(&mut self.app, frame))
+            .unwrap();
+
+        let buffer = self.terminal.backend().buffer();
+        let mut lines = Vec::new();
+        for y in 0..buffer.area.height {
+            let mut line = String::new();
+            // The cells covered by a wide character are skipped
+            let mut skip = 0;
+            for x in 0..buffer.area.width {
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                let symbol = buffer.get(x, y).symbol();
+                skip = symbol.width().saturating_sub(1);
+                line.push_str(symbol);
+            }
+            lines.push(line.trim_end().to_string());
+        }
+
+        // Cut by the borders of the pop-ups, the end of the line may be missing
+        let times = Regex::new(r"🕑 \d\d:\d\d:\d\d[0-9. ·s]*(tokens/s)?").unwrap();
+        times
+            .replace_all(&lines.join("\n"), "🕑 <time>")
+            .to_string()
+    }
+}
+
+#[tokio::test]
+async fn main_view() {
+    let mut tui = Harness::new().await;
+
+    insta::assert_snapshot!(tui.screen());
+}
+
+#[tokio::test]
+async fn prompt_typed() {
+    let mut tui = Harness::new().await;
+    tui.type_text("How do I read a file in Rust?").await;
+
+    insta::assert_snapshot!(tui.screen());
+}
+
+#[tokio::test]
+async fn streaming_answer() {
+    let mut tui = Harness::new().await;
+    tui.type_text("Show me some code").await;
+    tui.key(KeyCode::Enter).await;
+
+    insta::assert_snapshot!("waiting_for_answer", tui.screen());
+
+    tui.receive(Some(12)).await;
+    insta::assert_snapshot!("answer_streamed", tui.screen());
+
+    tui.receive(None).await;
+    insta::assert_snapshot!("answer_ended", tui.screen());
+}
+
+#[tokio::test]
+async fn history_popup() {
+    let mut tui = Harness::new().await;
+    tui.type_text("First chat").await;
+    tui.key(KeyCode::Enter).await;
+    tui.receive(None).await;
+    tui.ctrl('n').await;
+    tui.ctrl('h').await;
+
+    insta::assert_snapshot!(tui.screen());
+}
+
+#[tokio::test]
+async fn help_popup() {
+    let mut tui = Harness::new().await;
+    tui.key(KeyCode::Char('?')).await;
+
+    insta::assert_snapshot!(tui.screen());
+}
+
+#[tokio::test]
+async fn control_keys_not_typed() {
+    let mut tui = Harness::new().await;
+    tui.type_text("Hello").await;
+
+    for c in ['x', 'v', 'w', 'l', 'p', 'b', 'f', 'y'] {
+        // In the Insert mode, then in the Normal mode
+        tui.key(KeyCode::Char('i')).await;
+        tui.ctrl(c).await;
+        tui.key(KeyCode::Esc).await;
+        tui.ctrl(c).await;
+        tui.app.external_editor = false;
+    }
+
+    assert_eq!(tui.app.prompt.editor.lines(), ["Hello"]);
+}